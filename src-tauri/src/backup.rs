@@ -0,0 +1,815 @@
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, UNIX_EPOCH};
+use tauri::async_runtime::JoinHandle;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::sync::crypto::{KeyCache, SyncCipher};
+
+const BACKUP_DIR: &str = "backups";
+const FILE_PREFIX: &str = "workplan-";
+const FILE_EXTENSION: &str = ".wpbak";
+const FORMAT: &str = "workplan-backup";
+const VERSION: u32 = 1;
+const MAX_KEEP: usize = 365;
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+/// An unanswered `backup-due` event is repeated after this long.
+const REQUEST_RETRY_MS: u64 = 10 * 60 * 1000;
+
+/// The backup settings saved by the frontend.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase", default)]
+pub struct BackupConfig {
+    enabled: bool,
+    /// Target directory; the app data directory when empty.
+    directory: String,
+    interval_hours: u64,
+    /// Number of snapshots kept; older ones are deleted after each backup.
+    keep: usize,
+    encrypt: bool,
+    passphrase: String,
+}
+
+impl Default for BackupConfig {
+    fn default() -> Self {
+        BackupConfig {
+            enabled: false,
+            directory: String::new(),
+            interval_hours: 24,
+            keep: 7,
+            encrypt: false,
+            passphrase: String::new(),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct BackupState {
+    config: Mutex<BackupConfig>,
+    scheduler: Mutex<Option<JoinHandle<()>>>,
+    requested_at: AtomicU64,
+    keys: KeyCache,
+}
+
+/// A snapshot on disk: a readable manifest around the gzip-compressed data,
+/// which is sealed with the backup passphrase when encryption is on.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BackupFile {
+    format: String,
+    version: u32,
+    created_at: u64,
+    reason: String,
+    app_version: String,
+    encrypted: bool,
+    /// Entries per collection, e.g. `tasks.tasks`; left out of encrypted backups.
+    #[serde(default)]
+    counts: BTreeMap<String, usize>,
+    /// SHA-256 of the serialized `content`, checkable without the passphrase.
+    checksum: String,
+    /// SHA-256 of the uncompressed data, checked after decoding.
+    data_checksum: String,
+    /// Base64 of the compressed data, or an encryption envelope around it.
+    content: Value,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupInfo {
+    id: String,
+    path: String,
+    created_at: u64,
+    reason: String,
+    size: u64,
+    encrypted: bool,
+    counts: BTreeMap<String, usize>,
+    /// The file parsed and its checksum matched.
+    valid: bool,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupStatus {
+    enabled: bool,
+    directory: String,
+    last_backup_at: Option<u64>,
+    next_backup_at: Option<u64>,
+    count: usize,
+}
+
+/// What restoring one collection would do to the current data.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CollectionDiff {
+    section: String,
+    collection: String,
+    added: usize,
+    removed: usize,
+    changed: usize,
+    unchanged: usize,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RestorePlan {
+    backup: BackupInfo,
+    changes: Vec<CollectionDiff>,
+    /// The backed-up sections to adopt; only set when not a dry run.
+    data: Option<Value>,
+    /// Snapshot of the current data taken before restoring.
+    safety_backup: Option<BackupInfo>,
+}
+
+fn now_millis() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+fn hex_digest(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+fn backup_dir(app: &AppHandle, config: &BackupConfig) -> Result<PathBuf, String> {
+    let dir = if config.directory.trim().is_empty() {
+        crate::app_data_file(app, BACKUP_DIR)?
+    } else {
+        PathBuf::from(config.directory.trim())
+    };
+    fs::create_dir_all(&dir).map_err(|e| format!("创建备份目录失败: {}", e))?;
+    Ok(dir)
+}
+
+fn is_backup_name(name: &str) -> bool {
+    name.starts_with(FILE_PREFIX) && name.ends_with(FILE_EXTENSION)
+}
+
+/// Splits a backup name into its UTC time part and the counter that tells apart
+/// backups written within the same second.
+fn name_order(name: &str) -> (&str, u32) {
+    let stem = name.trim_end_matches(FILE_EXTENSION);
+    let time_len = FILE_PREFIX.len() + "YYYYMMDD-HHMMSS".len();
+    match (stem.get(..time_len), stem.get(time_len..)) {
+        (Some(time), Some(rest)) => (
+            time,
+            rest.strip_prefix('-')
+                .and_then(|counter| counter.parse().ok())
+                .unwrap_or(1),
+        ),
+        _ => (stem, 1),
+    }
+}
+
+/// Backup file names in the directory, oldest first. Names embed the UTC time,
+/// so they sort chronologically.
+fn backup_names(dir: &Path) -> Vec<String> {
+    let mut names: Vec<String> = fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .flatten()
+                .filter_map(|entry| entry.file_name().into_string().ok())
+                .filter(|name| is_backup_name(name))
+                .collect()
+        })
+        .unwrap_or_default();
+    names.sort_by(|a, b| name_order(a).cmp(&name_order(b)));
+    names
+}
+
+fn last_backup_at(dir: &Path) -> Option<u64> {
+    let name = backup_names(dir).pop()?;
+    let modified = fs::metadata(dir.join(name)).ok()?.modified().ok()?;
+    modified
+        .duration_since(UNIX_EPOCH)
+        .ok()
+        .map(|d| d.as_millis() as u64)
+}
+
+fn file_name(dir: &Path, created_at: u64) -> String {
+    let (year, month, day, hour, minute, second) = crate::utc_date_time(created_at);
+    let stem = format!(
+        "{}{:04}{:02}{:02}-{:02}{:02}{:02}",
+        FILE_PREFIX, year, month, day, hour, minute, second
+    );
+    // Counted up from the newest same-second backup rather than filling gaps,
+    // so that names keep sorting by age after older ones are pruned.
+    let latest = backup_names(dir)
+        .iter()
+        .filter_map(|name| {
+            let (time, counter) = name_order(name);
+            (time == stem).then_some(counter)
+        })
+        .max();
+    match latest {
+        Some(counter) => format!("{}-{}{}", stem, counter + 1, FILE_EXTENSION),
+        None => format!("{}{}", stem, FILE_EXTENSION),
+    }
+}
+
+/// Entry counts of the array collections in each section.
+fn count_entries(data: &Value) -> BTreeMap<String, usize> {
+    let mut counts = BTreeMap::new();
+    for (section, value) in data.as_object().into_iter().flatten() {
+        for (collection, items) in value.as_object().into_iter().flatten() {
+            if let Some(items) = items.as_array() {
+                counts.insert(format!("{}.{}", section, collection), items.len());
+            }
+        }
+    }
+    counts
+}
+
+fn gzip(bytes: &[u8]) -> Result<Vec<u8>, String> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder
+        .write_all(bytes)
+        .and_then(|_| encoder.finish())
+        .map_err(|e| format!("压缩备份失败: {}", e))
+}
+
+fn gunzip(bytes: &[u8]) -> Result<Vec<u8>, String> {
+    let mut data = Vec::new();
+    GzDecoder::new(bytes)
+        .read_to_end(&mut data)
+        .map_err(|e| format!("解压备份失败: {}", e))?;
+    Ok(data)
+}
+
+fn content_checksum(content: &Value) -> Result<String, String> {
+    Ok(hex_digest(
+        &serde_json::to_vec(content).map_err(|e| e.to_string())?,
+    ))
+}
+
+fn info(dir: &Path, name: &str, file: Option<&BackupFile>) -> BackupInfo {
+    let path = dir.join(name);
+    let size = fs::metadata(&path).map(|meta| meta.len()).unwrap_or(0);
+    match file {
+        Some(file) => BackupInfo {
+            id: name.to_string(),
+            path: path.to_string_lossy().to_string(),
+            created_at: file.created_at,
+            reason: file.reason.clone(),
+            size,
+            encrypted: file.encrypted,
+            counts: file.counts.clone(),
+            valid: file.format == FORMAT
+                && file.version <= VERSION
+                && content_checksum(&file.content).ok().as_ref() == Some(&file.checksum),
+        },
+        None => BackupInfo {
+            id: name.to_string(),
+            path: path.to_string_lossy().to_string(),
+            created_at: 0,
+            reason: String::new(),
+            size,
+            encrypted: false,
+            counts: BTreeMap::new(),
+            valid: false,
+        },
+    }
+}
+
+fn read_file(path: &Path) -> Option<BackupFile> {
+    let raw = fs::read(path).ok()?;
+    serde_json::from_slice(&raw).ok()
+}
+
+fn encode_backup(
+    state: &BackupState,
+    config: &BackupConfig,
+    data: &Value,
+    reason: &str,
+) -> Result<BackupFile, String> {
+    let plain = serde_json::to_vec(data).map_err(|e| e.to_string())?;
+    let compressed = gzip(&plain)?;
+    let content = if config.encrypt {
+        if config.passphrase.is_empty() {
+            return Err("启用备份加密时需要设置口令".to_string());
+        }
+        SyncCipher::from_passphrase(&config.passphrase, &state.keys)
+            .seal_bytes(&compressed, None)?
+    } else {
+        Value::String(BASE64.encode(&compressed))
+    };
+    Ok(BackupFile {
+        format: FORMAT.to_string(),
+        version: VERSION,
+        created_at: now_millis(),
+        reason: reason.to_string(),
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        encrypted: config.encrypt,
+        counts: if config.encrypt {
+            BTreeMap::new()
+        } else {
+            count_entries(data)
+        },
+        checksum: content_checksum(&content)?,
+        data_checksum: hex_digest(&plain),
+        content,
+    })
+}
+
+/// Writes a snapshot into `dir` and deletes the oldest ones beyond
+/// `config.keep`, leaving `protected` (a backup being restored) in place.
+fn store_backup(
+    dir: &Path,
+    state: &BackupState,
+    config: &BackupConfig,
+    data: &Value,
+    reason: &str,
+    protected: Option<&str>,
+) -> Result<BackupInfo, String> {
+    let file = encode_backup(state, config, data, reason)?;
+
+    let name = file_name(dir, file.created_at);
+    // Written under a temporary name so a partial file never looks like a backup.
+    let temp = dir.join(format!(".{}.tmp", name));
+    let bytes = serde_json::to_vec(&file).map_err(|e| e.to_string())?;
+    fs::write(&temp, bytes).map_err(|e| format!("写入备份失败: {}", e))?;
+    fs::rename(&temp, dir.join(&name)).map_err(|e| format!("写入备份失败: {}", e))?;
+
+    let names = backup_names(dir);
+    let excess = names.len().saturating_sub(config.keep.max(1));
+    for old in names
+        .iter()
+        .take(excess)
+        .filter(|old| **old != name && Some(old.as_str()) != protected)
+    {
+        let _ = fs::remove_file(dir.join(old));
+    }
+    Ok(info(dir, &name, Some(&file)))
+}
+
+fn write_backup(
+    app: &AppHandle,
+    state: &BackupState,
+    data: &Value,
+    reason: &str,
+    protected: Option<&str>,
+) -> Result<BackupInfo, String> {
+    let config = state.config.lock().map_err(|e| e.to_string())?.clone();
+    let dir = backup_dir(app, &config)?;
+    let info = store_backup(&dir, state, &config, data, reason, protected)?;
+    state.requested_at.store(0, Ordering::Relaxed);
+    Ok(info)
+}
+
+fn decode_backup(
+    state: &BackupState,
+    file: &BackupFile,
+    passphrase: &str,
+) -> Result<Value, String> {
+    if file.format != FORMAT || file.version > VERSION {
+        return Err("不支持的备份格式".to_string());
+    }
+    if content_checksum(&file.content)? != file.checksum {
+        return Err("备份文件已损坏（校验和不匹配）".to_string());
+    }
+    let compressed = if file.encrypted {
+        if passphrase.is_empty() {
+            return Err("该备份已加密，请输入备份口令".to_string());
+        }
+        SyncCipher::from_passphrase(passphrase, &state.keys)
+            .open_bytes(&file.content)
+            .map_err(|_| "无法解密备份，请检查备份口令".to_string())?
+            .value
+    } else {
+        let encoded = file
+            .content
+            .as_str()
+            .ok_or_else(|| "备份内容格式无效".to_string())?;
+        BASE64
+            .decode(encoded)
+            .map_err(|_| "备份内容格式无效".to_string())?
+    };
+    let plain = gunzip(&compressed)?;
+    if hex_digest(&plain) != file.data_checksum {
+        return Err("备份数据校验失败".to_string());
+    }
+    serde_json::from_slice(&plain).map_err(|e| format!("备份数据无效: {}", e))
+}
+
+/// Keys a collection's entries by `id`, or by their content when they have none.
+/// A non-array value counts as a single entry.
+fn keyed_entries(value: Option<&Value>) -> HashMap<String, &Value> {
+    match value {
+        None | Some(Value::Null) => HashMap::new(),
+        Some(Value::Array(items)) => items
+            .iter()
+            .map(|item| {
+                let key = match item.get("id") {
+                    Some(Value::String(id)) => id.clone(),
+                    Some(id) => id.to_string(),
+                    None => item.to_string(),
+                };
+                (key, item)
+            })
+            .collect(),
+        Some(other) => HashMap::from([(String::new(), other)]),
+    }
+}
+
+fn diff_collection(
+    section: &str,
+    collection: &str,
+    backup: &Value,
+    current: Option<&Value>,
+) -> CollectionDiff {
+    let backup = keyed_entries(Some(backup));
+    let current = keyed_entries(current);
+    let mut diff = CollectionDiff {
+        section: section.to_string(),
+        collection: collection.to_string(),
+        added: 0,
+        removed: current
+            .keys()
+            .filter(|key| !backup.contains_key(*key))
+            .count(),
+        changed: 0,
+        unchanged: 0,
+    };
+    for (key, item) in &backup {
+        match current.get(key) {
+            None => diff.added += 1,
+            Some(existing) if existing == item => diff.unchanged += 1,
+            Some(_) => diff.changed += 1,
+        }
+    }
+    diff
+}
+
+fn diff_sections(current: &Value, backup: &Value) -> Vec<CollectionDiff> {
+    let mut changes = Vec::new();
+    for (section, value) in backup.as_object().into_iter().flatten() {
+        let current_section = current.get(section);
+        match value.as_object() {
+            Some(collections) => {
+                for (collection, items) in collections {
+                    changes.push(diff_collection(
+                        section,
+                        collection,
+                        items,
+                        current_section.and_then(|current| current.get(collection)),
+                    ));
+                }
+            }
+            None => changes.push(diff_collection(section, "", value, current_section)),
+        }
+    }
+    changes
+}
+
+fn check_due(app: &AppHandle) {
+    let state = app.state::<BackupState>();
+    let Ok(config) = state.config.lock().map(|config| config.clone()) else {
+        return;
+    };
+    if !config.enabled {
+        return;
+    }
+    let Ok(dir) = backup_dir(app, &config) else {
+        return;
+    };
+    let now = now_millis();
+    let due_at = last_backup_at(&dir).unwrap_or(0) + config.interval_hours * 3_600_000;
+    if now < due_at {
+        return;
+    }
+    let requested_at = state.requested_at.load(Ordering::Relaxed);
+    if requested_at != 0 && now < requested_at + REQUEST_RETRY_MS {
+        return;
+    }
+    state.requested_at.store(now, Ordering::Relaxed);
+    // The data lives in the frontend, which answers with `create_backup`.
+    let _ = app.emit("backup-due", json!({ "reason": "scheduled" }));
+}
+
+fn status(app: &AppHandle, state: &BackupState) -> Result<BackupStatus, String> {
+    let config = state.config.lock().map_err(|e| e.to_string())?.clone();
+    let dir = backup_dir(app, &config)?;
+    let last_backup_at = last_backup_at(&dir);
+    Ok(BackupStatus {
+        enabled: config.enabled,
+        directory: dir.to_string_lossy().to_string(),
+        last_backup_at,
+        next_backup_at: config.enabled.then(|| {
+            last_backup_at.map_or_else(now_millis, |last| last + config.interval_hours * 3_600_000)
+        }),
+        count: backup_names(&dir).len(),
+    })
+}
+
+/// Applies the backup settings and starts or stops the schedule.
+#[tauri::command]
+pub fn configure_backups(
+    app: AppHandle,
+    state: State<'_, BackupState>,
+    config: BackupConfig,
+) -> Result<BackupStatus, String> {
+    let mut config = config;
+    config.interval_hours = config.interval_hours.clamp(1, 24 * 30);
+    config.keep = config.keep.clamp(1, MAX_KEEP);
+    let directory = config.directory.trim();
+    if !directory.is_empty() && !Path::new(directory).is_absolute() {
+        return Err("备份目录必须是绝对路径".to_string());
+    }
+    if config.encrypt && config.passphrase.is_empty() {
+        return Err("启用备份加密时需要设置口令".to_string());
+    }
+    backup_dir(&app, &config)?;
+    let enabled = config.enabled;
+    *state.config.lock().map_err(|e| e.to_string())? = config;
+
+    let mut scheduler = state.scheduler.lock().map_err(|e| e.to_string())?;
+    if let Some(task) = scheduler.take() {
+        task.abort();
+    }
+    if enabled {
+        let app = app.clone();
+        *scheduler = Some(tauri::async_runtime::spawn(async move {
+            loop {
+                check_due(&app);
+                tokio::time::sleep(CHECK_INTERVAL).await;
+            }
+        }));
+    }
+    drop(scheduler);
+    status(&app, &state)
+}
+
+#[tauri::command]
+pub fn get_backup_status(
+    app: AppHandle,
+    state: State<'_, BackupState>,
+) -> Result<BackupStatus, String> {
+    status(&app, &state)
+}
+
+/// Writes a snapshot of `data` (the frontend's sections) and prunes old ones.
+#[tauri::command]
+pub async fn create_backup(
+    app: AppHandle,
+    data: Value,
+    reason: Option<String>,
+) -> Result<BackupInfo, String> {
+    if !data.is_object() {
+        return Err("备份数据无效".to_string());
+    }
+    let reason = match reason.as_deref() {
+        Some("scheduled") => "scheduled",
+        _ => "manual",
+    };
+    tauri::async_runtime::spawn_blocking(move || {
+        write_backup(&app, &app.state::<BackupState>(), &data, reason, None)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn list_backups(app: AppHandle) -> Result<Vec<BackupInfo>, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let state = app.state::<BackupState>();
+        let config = state.config.lock().map_err(|e| e.to_string())?.clone();
+        let dir = backup_dir(&app, &config)?;
+        let mut backups: Vec<BackupInfo> = backup_names(&dir)
+            .iter()
+            .map(|name| info(&dir, name, read_file(&dir.join(name)).as_ref()))
+            .collect();
+        backups.reverse();
+        Ok(backups)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+fn restore(
+    app: &AppHandle,
+    state: &BackupState,
+    id: String,
+    current: Value,
+    dry_run: bool,
+    passphrase: Option<String>,
+    sections: Option<Vec<String>>,
+) -> Result<RestorePlan, String> {
+    if !is_backup_name(&id) || id.contains(['/', '\\']) {
+        return Err("备份不存在".to_string());
+    }
+    let config = state.config.lock().map_err(|e| e.to_string())?.clone();
+    let dir = backup_dir(app, &config)?;
+    let path = dir.join(&id);
+    if !path.is_file() {
+        return Err("备份不存在".to_string());
+    }
+    let file = read_file(&path).ok_or_else(|| "备份文件无法读取".to_string())?;
+    let passphrase = passphrase
+        .filter(|passphrase| !passphrase.is_empty())
+        .unwrap_or(config.passphrase);
+    let mut data = decode_backup(state, &file, &passphrase)?;
+    if let (Some(sections), Some(object)) = (sections, data.as_object_mut()) {
+        object.retain(|section, _| sections.contains(section));
+    }
+    if data.as_object().is_none_or(|object| object.is_empty()) {
+        return Err("备份中没有可恢复的数据".to_string());
+    }
+
+    let changes = diff_sections(&current, &data);
+    let backup = info(&dir, &id, Some(&file));
+    if dry_run {
+        return Ok(RestorePlan {
+            backup,
+            changes,
+            data: None,
+            safety_backup: None,
+        });
+    }
+    // The snapshot must not rotate out the backup that is about to be restored.
+    let safety_backup = write_backup(app, state, &current, "pre-restore", Some(&id))?;
+    Ok(RestorePlan {
+        backup,
+        changes,
+        data: Some(data),
+        safety_backup: Some(safety_backup),
+    })
+}
+
+/// Compares a backup with `current` and, unless `dry_run`, returns its data for
+/// the frontend to adopt after snapshotting `current`. `sections` limits the
+/// restore to some of the backed-up sections.
+#[tauri::command]
+pub async fn restore_backup(
+    app: AppHandle,
+    id: String,
+    current: Value,
+    dry_run: bool,
+    passphrase: Option<String>,
+    sections: Option<Vec<String>>,
+) -> Result<RestorePlan, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let state = app.state::<BackupState>();
+        restore(&app, &state, id, current, dry_run, passphrase, sections)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+
+    fn sample() -> Value {
+        json!({
+            "tasks": {
+                "tasks": [{ "id": "a", "title": "写周报" }, { "id": "b", "title": "Review" }],
+                "tags": ["work"],
+            },
+            "settings": { "theme": "dark" },
+        })
+    }
+
+    fn store(dir: &TempDir, config: &BackupConfig, protected: Option<&str>) -> BackupInfo {
+        store_backup(
+            dir.path(),
+            &BackupState::default(),
+            config,
+            &sample(),
+            "manual",
+            protected,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn plain_backups_round_trip() {
+        let dir = TempDir::new("backup-plain");
+        let stored = store(&dir, &BackupConfig::default(), None);
+        assert!(stored.valid && !stored.encrypted);
+        assert_eq!(stored.counts.get("tasks.tasks"), Some(&2));
+        assert_eq!(stored.counts.get("tasks.tags"), Some(&1));
+
+        let file = read_file(Path::new(&stored.path)).unwrap();
+        let data = decode_backup(&BackupState::default(), &file, "").unwrap();
+        assert_eq!(data, sample());
+    }
+
+    #[test]
+    fn encrypted_backups_need_the_passphrase() {
+        let dir = TempDir::new("backup-encrypted");
+        let config = BackupConfig {
+            encrypt: true,
+            passphrase: "correct horse".to_string(),
+            ..BackupConfig::default()
+        };
+        let stored = store(&dir, &config, None);
+        assert!(stored.valid && stored.encrypted);
+        assert!(stored.counts.is_empty());
+
+        let file = read_file(Path::new(&stored.path)).unwrap();
+        assert!(!serde_json::to_string(&file).unwrap().contains("写周报"));
+        let state = BackupState::default();
+        assert_eq!(
+            decode_backup(&state, &file, "").unwrap_err(),
+            "该备份已加密，请输入备份口令"
+        );
+        assert_eq!(
+            decode_backup(&state, &file, "wrong").unwrap_err(),
+            "无法解密备份，请检查备份口令"
+        );
+        assert_eq!(
+            decode_backup(&state, &file, "correct horse").unwrap(),
+            sample()
+        );
+    }
+
+    #[test]
+    fn corrupted_backups_fail_their_checksums() {
+        let dir = TempDir::new("backup-corrupted");
+        let stored = store(&dir, &BackupConfig::default(), None);
+        let state = BackupState::default();
+
+        let mut file = read_file(Path::new(&stored.path)).unwrap();
+        let encoded = file.content.as_str().unwrap().to_string();
+        file.content = Value::String(encoded.replacen('A', "B", 1));
+        assert_eq!(
+            decode_backup(&state, &file, "").unwrap_err(),
+            "备份文件已损坏（校验和不匹配）"
+        );
+        assert!(!info(dir.path(), &stored.id, Some(&file)).valid);
+
+        let mut file = read_file(Path::new(&stored.path)).unwrap();
+        file.data_checksum = hex_digest(b"other data");
+        assert_eq!(
+            decode_backup(&state, &file, "").unwrap_err(),
+            "备份数据校验失败"
+        );
+    }
+
+    #[test]
+    fn diff_sections_counts_changes_per_collection() {
+        let current = json!({
+            "tasks": {
+                "tasks": [{ "id": "a", "title": "写周报" }, { "id": "b", "title": "Old" }, { "id": "c" }],
+                "tags": ["work", "home"],
+            },
+        });
+        let changes = diff_sections(&current, &sample());
+        let find = |section: &str, collection: &str| {
+            let diff = changes
+                .iter()
+                .find(|diff| diff.section == section && diff.collection == collection)
+                .unwrap();
+            (diff.added, diff.removed, diff.changed, diff.unchanged)
+        };
+        assert_eq!(changes.len(), 3);
+        assert_eq!(find("tasks", "tasks"), (0, 1, 1, 1));
+        // Entries without an id are keyed by their content.
+        assert_eq!(find("tasks", "tags"), (0, 1, 0, 1));
+        // A section missing from the current data is added wholesale.
+        assert_eq!(find("settings", "theme"), (1, 0, 0, 0));
+    }
+
+    #[test]
+    fn rotation_keeps_the_newest_backups_and_the_one_being_restored() {
+        let dir = TempDir::new("backup-rotation");
+        let config = BackupConfig {
+            keep: 2,
+            ..BackupConfig::default()
+        };
+        let first = store(&dir, &config, None);
+        let second = store(&dir, &config, None);
+        let third = store(&dir, &config, None);
+        assert_eq!(
+            backup_names(dir.path()),
+            vec![second.id.clone(), third.id.clone()]
+        );
+        assert!(!Path::new(&first.path).exists());
+
+        // Restoring the oldest kept backup snapshots the current data first.
+        let safety = store(&dir, &config, Some(&second.id));
+        assert_eq!(
+            backup_names(dir.path()),
+            vec![second.id, third.id.clone(), safety.id.clone()]
+        );
+        // The next regular backup prunes back down to `keep`.
+        let fifth = store(&dir, &config, None);
+        assert_eq!(backup_names(dir.path()), vec![safety.id, fifth.id]);
+    }
+}
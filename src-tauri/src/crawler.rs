@@ -0,0 +1,417 @@
+use serde::Serialize;
+use std::collections::{HashSet, VecDeque};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, State};
+use url::Url;
+
+use crate::http_cache::{self, HttpCacheState};
+use crate::network::NetworkState;
+use crate::web_content;
+
+const ROBOTS_AGENT: &str = "workplan";
+const MAX_CRAWL_DELAY: Duration = Duration::from_secs(10);
+const SKIPPED_EXTENSIONS: [&str; 24] = [
+    "png", "jpg", "jpeg", "gif", "webp", "svg", "ico", "bmp", "css", "js", "mjs", "woff", "woff2",
+    "ttf", "eot", "zip", "gz", "tar", "rar", "7z", "exe", "dmg", "mp3", "mp4",
+];
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CrawledPage {
+    url: String,
+    canonical_url: String,
+    title: String,
+    depth: usize,
+    content_type: String,
+    content: String,
+    truncated: bool,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CrawlSkip {
+    url: String,
+    reason: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CrawlSiteResult {
+    start_url: String,
+    pages: Vec<CrawledPage>,
+    skipped: Vec<CrawlSkip>,
+    /// True when the page budget ran out before the frontier was exhausted.
+    budget_exhausted: bool,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CrawlProgress {
+    start_url: String,
+    url: String,
+    fetched: usize,
+    queued: usize,
+}
+
+/// Whether a `User-agent` value names us: its product token, ignoring any
+/// version, must equal ours case-insensitively.
+fn names_our_agent(agent: &str) -> bool {
+    agent
+        .split('/')
+        .next()
+        .is_some_and(|token| token.trim().eq_ignore_ascii_case(ROBOTS_AGENT))
+}
+
+#[derive(Default)]
+struct RobotsRules {
+    rules: Vec<(bool, String)>,
+    crawl_delay: Option<Duration>,
+}
+
+impl RobotsRules {
+    /// Picks the group naming our agent, falling back to `*`. Within a group the
+    /// longest matching pattern wins and `Allow` wins ties.
+    fn parse(text: &str) -> Self {
+        let mut specific = RobotsRules::default();
+        let mut wildcard = RobotsRules::default();
+        let mut specific_seen = false;
+        let mut agents: Vec<String> = Vec::new();
+        let mut in_rules = false;
+
+        for line in text.lines() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            let key = key.trim().to_ascii_lowercase();
+            let value = value.trim();
+
+            if key == "user-agent" {
+                if in_rules {
+                    agents.clear();
+                    in_rules = false;
+                }
+                let agent = value.to_ascii_lowercase();
+                specific_seen |= names_our_agent(&agent);
+                agents.push(agent);
+                continue;
+            }
+
+            in_rules = true;
+            let matches_specific = agents.iter().any(|agent| names_our_agent(agent));
+            let matches_wildcard = agents.iter().any(|agent| agent == "*");
+            let targets = [
+                (matches_specific, &mut specific),
+                (matches_wildcard, &mut wildcard),
+            ];
+            for (applies, rules) in targets {
+                if !applies {
+                    continue;
+                }
+                match key.as_str() {
+                    "allow" if !value.is_empty() => rules.rules.push((true, value.to_string())),
+                    "disallow" if !value.is_empty() => rules.rules.push((false, value.to_string())),
+                    "crawl-delay" => {
+                        rules.crawl_delay = value
+                            .parse::<f64>()
+                            .ok()
+                            .filter(|secs| secs.is_finite() && *secs > 0.0)
+                            .map(|secs| Duration::from_secs_f64(secs).min(MAX_CRAWL_DELAY));
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        if specific_seen {
+            specific
+        } else {
+            wildcard
+        }
+    }
+
+    fn allows(&self, url: &Url) -> bool {
+        let mut path = url.path().to_string();
+        if let Some(query) = url.query() {
+            path.push('?');
+            path.push_str(query);
+        }
+        self.rules
+            .iter()
+            .filter(|(_, pattern)| robots_match(pattern, &path))
+            .max_by_key(|(allow, pattern)| (pattern.len(), *allow))
+            .is_none_or(|(allow, _)| *allow)
+    }
+}
+
+fn robots_match(pattern: &str, path: &str) -> bool {
+    let (pattern, anchored) = match pattern.strip_suffix('$') {
+        Some(stripped) => (stripped, true),
+        None => (pattern, false),
+    };
+    let parts: Vec<&str> = pattern.split('*').collect();
+    if !path.starts_with(parts[0]) {
+        return false;
+    }
+    let mut pos = parts[0].len();
+    for (index, part) in parts.iter().enumerate().skip(1) {
+        if anchored && index == parts.len() - 1 {
+            return path.len() >= pos + part.len() && path.ends_with(part);
+        }
+        match path[pos..].find(part) {
+            Some(offset) => pos += offset + part.len(),
+            None => return false,
+        }
+    }
+    !anchored || pos == path.len()
+}
+
+fn same_origin(a: &Url, b: &Url) -> bool {
+    a.scheme() == b.scheme()
+        && a.host_str() == b.host_str()
+        && a.port_or_known_default() == b.port_or_known_default()
+}
+
+fn is_skipped_extension(url: &Url) -> bool {
+    url.path_segments()
+        .and_then(|mut segments| segments.next_back())
+        .and_then(|name| name.rsplit_once('.'))
+        .is_some_and(|(_, ext)| SKIPPED_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()))
+}
+
+async fn load_robots(
+    app: &AppHandle,
+    cache: &HttpCacheState,
+    network: &NetworkState,
+    origin: &Url,
+) -> RobotsRules {
+    let Ok(robots_url) = origin.join("/robots.txt") else {
+        return RobotsRules::default();
+    };
+    match crate::fetch_web_bytes(app, cache, network, robots_url.as_str()).await {
+        Ok(response) if response.is_success() => {
+            RobotsRules::parse(&String::from_utf8_lossy(&response.body))
+        }
+        // A missing or unreachable robots.txt places no restrictions.
+        _ => RobotsRules::default(),
+    }
+}
+
+/// Breadth-first crawl of one site: same-origin links only, bounded by depth and
+/// page budget, filtered by robots.txt and deduplicated by canonical URL.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn crawl_site(
+    app: AppHandle,
+    cache: State<'_, HttpCacheState>,
+    network: State<'_, NetworkState>,
+    url: String,
+    max_depth: Option<usize>,
+    max_pages: Option<usize>,
+    max_chars_per_page: Option<usize>,
+    path_prefix: Option<String>,
+) -> Result<CrawlSiteResult, String> {
+    let start = Url::parse(url.trim()).map_err(|e| format!("URL 无效: {}", e))?;
+    let max_depth = max_depth.unwrap_or(2).min(5);
+    let max_pages = max_pages.unwrap_or(20).clamp(1, 50);
+    let limit = max_chars_per_page.unwrap_or(3000).clamp(200, 12000);
+    let path_prefix = path_prefix
+        .map(|prefix| prefix.trim().to_string())
+        .filter(|prefix| !prefix.is_empty());
+
+    let robots = load_robots(&app, &cache, &network, &start).await;
+    let mut queue: VecDeque<(String, usize)> = VecDeque::from([(start.to_string(), 0)]);
+    let mut queued: HashSet<String> = HashSet::from([http_cache::normalize_url(start.as_str())]);
+    let mut canonical_seen: HashSet<String> = HashSet::new();
+    let mut pages = Vec::new();
+    let mut skipped = Vec::new();
+
+    while let Some((page_url, depth)) = queue.pop_front() {
+        if pages.len() >= max_pages {
+            queue.push_front((page_url, depth));
+            break;
+        }
+        let Ok(parsed) = Url::parse(&page_url) else {
+            continue;
+        };
+        if !robots.allows(&parsed) {
+            skipped.push(CrawlSkip {
+                url: page_url,
+                reason: "robots".to_string(),
+            });
+            continue;
+        }
+        if let Some(delay) = robots.crawl_delay.filter(|_| !pages.is_empty()) {
+            tokio::time::sleep(delay).await;
+        }
+
+        let _ = app.emit(
+            "crawl-site-progress",
+            CrawlProgress {
+                start_url: start.to_string(),
+                url: page_url.clone(),
+                fetched: pages.len(),
+                queued: queue.len(),
+            },
+        );
+
+        let response = match crate::fetch_web_bytes(&app, &cache, &network, &page_url).await {
+            Ok(response) if response.is_success() => response,
+            Ok(response) => {
+                skipped.push(CrawlSkip {
+                    url: page_url,
+                    reason: format!("HTTP {}", response.status),
+                });
+                continue;
+            }
+            Err(e) => {
+                skipped.push(CrawlSkip {
+                    url: page_url,
+                    reason: e,
+                });
+                continue;
+            }
+        };
+
+        let final_url = Url::parse(&response.final_url).unwrap_or_else(|_| parsed.clone());
+        if !same_origin(&start, &final_url) {
+            skipped.push(CrawlSkip {
+                url: page_url,
+                reason: "offsite".to_string(),
+            });
+            continue;
+        }
+        // A redirect can land on a disallowed path of the same site.
+        if !robots.allows(&final_url) {
+            skipped.push(CrawlSkip {
+                url: page_url,
+                reason: "robots".to_string(),
+            });
+            continue;
+        }
+
+        let result = match web_content::build_result(
+            page_url.clone(),
+            response.final_url.clone(),
+            &response.content_type,
+            &response.body,
+            limit,
+        ) {
+            Ok(result) => result,
+            Err(e) => {
+                skipped.push(CrawlSkip {
+                    url: page_url,
+                    reason: e,
+                });
+                continue;
+            }
+        };
+
+        let canonical = result
+            .canonical_url
+            .as_deref()
+            .and_then(|href| Url::parse(href).ok())
+            .filter(|canonical| same_origin(&start, canonical))
+            .unwrap_or_else(|| final_url.clone());
+        let canonical = http_cache::normalize_url(canonical.as_str());
+        if !canonical_seen.insert(canonical.clone()) {
+            skipped.push(CrawlSkip {
+                url: page_url,
+                reason: "duplicate".to_string(),
+            });
+            continue;
+        }
+
+        let links = if result.content_type == "html" {
+            Some(web_content::extract_links(
+                &String::from_utf8_lossy(&response.body),
+                &response.final_url,
+            ))
+        } else {
+            None
+        };
+
+        if let Some(links) = links.as_ref().filter(|links| !links.nofollow) {
+            if depth < max_depth {
+                for link in &links.links {
+                    let Ok(mut next) = Url::parse(link) else {
+                        continue;
+                    };
+                    next.set_fragment(None);
+                    if !same_origin(&start, &next) || is_skipped_extension(&next) {
+                        continue;
+                    }
+                    if path_prefix
+                        .as_deref()
+                        .is_some_and(|prefix| !next.path().starts_with(prefix))
+                    {
+                        continue;
+                    }
+                    if queued.insert(http_cache::normalize_url(next.as_str())) {
+                        queue.push_back((next.to_string(), depth + 1));
+                    }
+                }
+            }
+        }
+
+        if links.as_ref().is_some_and(|links| links.noindex) {
+            continue;
+        }
+        pages.push(CrawledPage {
+            url: result.final_url,
+            canonical_url: canonical,
+            title: result.title,
+            depth,
+            content_type: result.content_type,
+            content: result.content,
+            truncated: result.truncated,
+        });
+    }
+
+    Ok(CrawlSiteResult {
+        start_url: start.to_string(),
+        pages,
+        skipped,
+        budget_exhausted: !queue.is_empty(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn allows(robots: &str, path: &str) -> bool {
+        RobotsRules::parse(robots)
+            .allows(&Url::parse(&format!("https://example.com{}", path)).unwrap())
+    }
+
+    #[test]
+    fn only_our_product_token_selects_a_group() {
+        let robots = "User-agent: *\nDisallow: /private\n\n\
+                      User-agent: w\nDisallow: /\n\n\
+                      User-agent: work\nDisallow: /\n\n\
+                      User-agent: workplanner\nDisallow: /";
+        assert!(allows(robots, "/docs"));
+        assert!(!allows(robots, "/private/a"));
+
+        let ours = "User-agent: *\nDisallow: /\n\nUser-agent: WorkPlan/2.1\nDisallow: /drafts";
+        assert!(allows(ours, "/docs"));
+        assert!(!allows(ours, "/drafts/1"));
+    }
+
+    #[test]
+    fn an_empty_agent_does_not_match_us() {
+        let robots = "User-agent:\nDisallow: /\n\nUser-agent: *\nAllow: /";
+        assert!(allows(robots, "/anything"));
+    }
+
+    #[test]
+    fn longest_pattern_wins_and_allow_wins_ties() {
+        let robots = "User-agent: workplan\nDisallow: /docs\nAllow: /docs/public\nDisallow: /*.pdf$\nAllow: /a\nDisallow: /a";
+        assert!(!allows(robots, "/docs/internal"));
+        assert!(allows(robots, "/docs/public/page"));
+        assert!(!allows(robots, "/files/report.pdf"));
+        assert!(allows(robots, "/files/report.pdf?download=1"));
+        assert!(allows(robots, "/a"));
+    }
+}
@@ -0,0 +1,971 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, State};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::sync::crypto::{KeyCache, SyncCipher};
+use crate::web_archive::{self, WebArchiveEntry, WebArchiveState};
+
+const FORMAT: &str = "workplan-archive";
+const VERSION: u32 = 1;
+const EXTENSION: &str = "workplan";
+const MANIFEST: &str = "manifest.json";
+const VAULT_FILE: &str = "vault.enc";
+const ATTACHMENT_INDEX: &str = "attachments/index.json";
+const ATTACHMENT_DIR: &str = "attachments/";
+/// Limits for one unpacked member, all of them together and their number, so a
+/// crafted archive cannot exhaust memory.
+const MAX_MEMBER_BYTES: u64 = 256 * 1024 * 1024;
+const MAX_ARCHIVE_BYTES: u64 = 1024 * 1024 * 1024;
+const MAX_MEMBERS: usize = 50_000;
+
+/// Archive members holding the frontend's sections, by section key.
+const SECTIONS: [(&str, &str); 5] = [
+    ("tasks", "tasks.json"),
+    ("templates", "templates.json"),
+    ("scheduledTasks", "scheduled_tasks.json"),
+    ("notes", "notes.json"),
+    ("settings", "settings.json"),
+];
+
+/// Archive member names with their contents.
+type Members = Vec<(String, Vec<u8>)>;
+
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+struct FileDigest {
+    sha256: String,
+    size: u64,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Manifest {
+    format: String,
+    version: u32,
+    created_at: u64,
+    app_version: String,
+    /// Every other member with its checksum; members not listed are ignored.
+    files: BTreeMap<String, FileDigest>,
+    counts: BTreeMap<String, usize>,
+    #[serde(default)]
+    vault: bool,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ExportOptions {
+    include_attachments: bool,
+    include_vault: bool,
+    /// Encrypts the vault member; required when the vault is included.
+    passphrase: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveSummary {
+    path: String,
+    size: u64,
+    counts: BTreeMap<String, usize>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchivePreview {
+    version: u32,
+    created_at: u64,
+    app_version: String,
+    counts: BTreeMap<String, usize>,
+    has_vault: bool,
+    /// Whether the vault opened with the given passphrase; `None` without one.
+    vault_unlocked: Option<bool>,
+    /// Schema problems that would stop the import.
+    errors: Vec<String>,
+}
+
+/// What an import did to one collection.
+#[derive(Serialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct CollectionStats {
+    collection: String,
+    added: usize,
+    updated: usize,
+    unchanged: usize,
+    /// Entries in both where the current one was newer and stayed (merge only).
+    kept: usize,
+    /// Current entries missing from the archive (replace only).
+    removed: usize,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportResult {
+    mode: String,
+    /// The resulting sections for the frontend to adopt; sections the archive
+    /// does not contain are absent.
+    data: Value,
+    stats: Vec<CollectionStats>,
+    attachments: usize,
+    warnings: Vec<String>,
+}
+
+fn now_millis() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+fn digest(bytes: &[u8]) -> FileDigest {
+    FileDigest {
+        sha256: Sha256::digest(bytes)
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect(),
+        size: bytes.len() as u64,
+    }
+}
+
+fn entry_key(item: &Value) -> Option<String> {
+    match item.get("id")? {
+        Value::String(id) if !id.is_empty() => Some(id.clone()),
+        Value::Number(id) => Some(id.to_string()),
+        _ => None,
+    }
+}
+
+/// Checks that `value` is an array of objects with unique ids.
+fn validate_items(file: &str, value: &Value, errors: &mut Vec<String>) -> usize {
+    let Some(items) = value.as_array() else {
+        errors.push(format!("{}: 应为数组", file));
+        return 0;
+    };
+    let mut seen = HashSet::new();
+    for (index, item) in items.iter().enumerate() {
+        if !item.is_object() {
+            errors.push(format!("{}: 第 {} 项不是对象", file, index + 1));
+            continue;
+        }
+        match entry_key(item) {
+            None => errors.push(format!("{}: 第 {} 项缺少 id", file, index + 1)),
+            Some(id) if !seen.insert(id.clone()) => {
+                errors.push(format!("{}: id {} 重复", file, id))
+            }
+            Some(_) => {}
+        }
+    }
+    items.len()
+}
+
+/// Validates one section against the shape the frontend stores and returns
+/// its entry count.
+fn validate_section(section: &str, file: &str, value: &Value, errors: &mut Vec<String>) -> usize {
+    let list = match section {
+        "tasks" | "templates" | "scheduledTasks" => return validate_items(file, value, errors),
+        "notes" => "notes",
+        "vault" => "passwords",
+        _ => {
+            if !value.is_object() {
+                errors.push(format!("{}: 应为对象", file));
+            }
+            return 0;
+        }
+    };
+    let Some(object) = value.as_object() else {
+        errors.push(format!("{}: 应为对象", file));
+        return 0;
+    };
+    if section == "notes" {
+        if let Some(prompts) = object.get("aiPrompts") {
+            validate_items(&format!("{}#aiPrompts", file), prompts, errors);
+        }
+        let categories = object.get("categories");
+        if categories.is_some_and(|categories| {
+            !categories
+                .as_array()
+                .is_some_and(|items| items.iter().all(Value::is_string))
+        }) {
+            errors.push(format!("{}#categories: 应为字符串数组", file));
+        }
+    } else if !object.get("masterHash").is_none_or(Value::is_string) {
+        errors.push(format!("{}#masterHash: 应为字符串", file));
+    }
+    validate_items(
+        &format!("{}#{}", file, list),
+        object.get(list).unwrap_or(&Value::Null),
+        errors,
+    )
+}
+
+fn archive_target(app: &AppHandle, path: Option<String>) -> Result<PathBuf, String> {
+    match path.filter(|path| !path.trim().is_empty()) {
+        Some(path) => {
+            let mut path = PathBuf::from(path.trim());
+            if path
+                .extension()
+                .is_none_or(|extension| extension != EXTENSION)
+            {
+                path.as_mut_os_string().push(format!(".{}", EXTENSION));
+            }
+            Ok(path)
+        }
+        None => {
+            let (year, month, day, ..) = crate::utc_date_time(now_millis());
+            let name = format!("workplan-{:04}{:02}{:02}.{}", year, month, day, EXTENSION);
+            crate::download_target(app, &name)
+        }
+    }
+}
+
+fn write_error(error: impl std::fmt::Display) -> String {
+    format!("写入归档失败: {}", error)
+}
+
+fn zip_members(file: File, members: &[(String, Vec<u8>)]) -> Result<(), String> {
+    let mut zip = ZipWriter::new(file);
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    for (name, bytes) in members {
+        zip.start_file(name.as_str(), options)
+            .map_err(write_error)?;
+        zip.write_all(bytes).map_err(write_error)?;
+    }
+    zip.finish().map_err(write_error)?;
+    Ok(())
+}
+
+fn write_archive(path: &Path, members: &[(String, Vec<u8>)]) -> Result<u64, String> {
+    // Written under a temporary name so a failed export leaves nothing half-written.
+    let mut temp = path.as_os_str().to_owned();
+    temp.push(".tmp");
+    let temp = PathBuf::from(temp);
+    let file = File::create(&temp).map_err(write_error)?;
+    if let Err(error) = zip_members(file, members) {
+        let _ = fs::remove_file(&temp);
+        return Err(error);
+    }
+    fs::rename(&temp, path).map_err(write_error)?;
+    Ok(fs::metadata(path).map(|meta| meta.len()).unwrap_or(0))
+}
+
+/// Packs the frontend's sections and, when asked, the sealed vault and the
+/// archived web pages into archive members, manifest first. `attachments` is
+/// only read once the sections are known to be valid.
+fn pack(
+    data: &Value,
+    options: &ExportOptions,
+    attachments: impl FnOnce() -> Result<Vec<(WebArchiveEntry, Vec<u8>)>, String>,
+) -> Result<(Members, BTreeMap<String, usize>), String> {
+    let mut errors = Vec::new();
+    let mut members = Vec::new();
+    let mut counts = BTreeMap::new();
+    for (section, file) in SECTIONS {
+        let Some(value) = data.get(section) else {
+            continue;
+        };
+        let count = validate_section(section, file, value, &mut errors);
+        if section != "settings" {
+            counts.insert(section.to_string(), count);
+        }
+        members.push((
+            file.to_string(),
+            serde_json::to_vec_pretty(value).map_err(|e| e.to_string())?,
+        ));
+    }
+
+    let vault = options.include_vault && data.get("vault").is_some();
+    if let Some(value) = data.get("vault").filter(|_| vault) {
+        if options.passphrase.is_empty() {
+            return Err("导出密码库时需要设置口令".to_string());
+        }
+        validate_section("vault", VAULT_FILE, value, &mut errors);
+        let plain = serde_json::to_vec(value).map_err(|e| e.to_string())?;
+        let sealed = SyncCipher::from_passphrase(&options.passphrase, &KeyCache::default())
+            .seal_bytes(&plain, None)?;
+        members.push((
+            VAULT_FILE.to_string(),
+            serde_json::to_vec(&sealed).map_err(|e| e.to_string())?,
+        ));
+    }
+    if !errors.is_empty() {
+        return Err(format!("导出数据无效:\n{}", errors.join("\n")));
+    }
+
+    if options.include_attachments {
+        let archives = attachments()?;
+        let entries: Vec<&WebArchiveEntry> = archives.iter().map(|(entry, _)| entry).collect();
+        members.push((
+            ATTACHMENT_INDEX.to_string(),
+            serde_json::to_vec_pretty(&entries).map_err(|e| e.to_string())?,
+        ));
+        counts.insert("attachments".to_string(), archives.len());
+        for (entry, bytes) in archives {
+            members.push((format!("{}{}", ATTACHMENT_DIR, entry.file), bytes));
+        }
+    }
+
+    let manifest = Manifest {
+        format: FORMAT.to_string(),
+        version: VERSION,
+        created_at: now_millis(),
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        files: members
+            .iter()
+            .map(|(name, bytes)| (name.clone(), digest(bytes)))
+            .collect(),
+        counts: counts.clone(),
+        vault,
+    };
+    members.insert(
+        0,
+        (
+            MANIFEST.to_string(),
+            serde_json::to_vec_pretty(&manifest).map_err(|e| e.to_string())?,
+        ),
+    );
+    Ok((members, counts))
+}
+
+/// Writes `data` (the frontend's sections) and, when asked, the archived web
+/// pages and the vault into a `.workplan` zip. Without `path` the archive goes
+/// to the downloads directory.
+#[tauri::command]
+pub fn export_workplan_archive(
+    app: AppHandle,
+    web_archives: State<'_, WebArchiveState>,
+    path: Option<String>,
+    data: Value,
+    options: ExportOptions,
+) -> Result<ArchiveSummary, String> {
+    let (members, counts) = pack(&data, &options, || {
+        web_archive::export_archives(&app, &web_archives)
+    })?;
+    let target = archive_target(&app, path)?;
+    let size = write_archive(&target, &members)?;
+    Ok(ArchiveSummary {
+        path: target.to_string_lossy().to_string(),
+        size,
+        counts,
+    })
+}
+
+/// A read archive whose members all matched the manifest checksums.
+struct Unpacked {
+    manifest: Manifest,
+    members: HashMap<String, Vec<u8>>,
+}
+
+/// Reads at most `limit + 1` bytes of a member, enough to tell it is too long.
+fn read_member(zip: &mut ZipArchive<File>, name: &str, limit: u64) -> Result<Vec<u8>, String> {
+    let member = zip
+        .by_name(name)
+        .map_err(|_| format!("归档缺少 {}", name))?;
+    let mut bytes = Vec::new();
+    member
+        .take(limit + 1)
+        .read_to_end(&mut bytes)
+        .map_err(|e| format!("读取 {} 失败: {}", name, e))?;
+    Ok(bytes)
+}
+
+fn read_archive(path: &str) -> Result<Unpacked, String> {
+    unpack(path, MAX_ARCHIVE_BYTES)
+}
+
+/// Reads and checks every member the manifest lists, refusing archives whose
+/// members would unpack to more than `budget` bytes in total.
+fn unpack(path: &str, budget: u64) -> Result<Unpacked, String> {
+    let file = File::open(path.trim()).map_err(|e| format!("无法打开归档: {}", e))?;
+    let mut zip = ZipArchive::new(file).map_err(|_| "不是有效的 WorkPlan 归档".to_string())?;
+    if zip.len() > MAX_MEMBERS {
+        return Err("归档包含的文件过多".to_string());
+    }
+    let manifest = read_member(&mut zip, MANIFEST, MAX_MEMBER_BYTES)?;
+    if manifest.len() as u64 > MAX_MEMBER_BYTES {
+        return Err(format!("{} 过大", MANIFEST));
+    }
+    let manifest: Manifest =
+        serde_json::from_slice(&manifest).map_err(|e| format!("归档清单无效: {}", e))?;
+    if manifest.format != FORMAT {
+        return Err("不是有效的 WorkPlan 归档".to_string());
+    }
+    if manifest.version > VERSION {
+        return Err("归档由更新版本的应用创建，请先升级".to_string());
+    }
+    let total = manifest
+        .files
+        .values()
+        .fold(0u64, |total, file| total.saturating_add(file.size));
+    if total > budget {
+        return Err("归档解压后过大".to_string());
+    }
+    let mut members = HashMap::new();
+    for (name, expected) in &manifest.files {
+        if expected.size > MAX_MEMBER_BYTES {
+            return Err(format!("{} 过大", name));
+        }
+        // Never read past the declared size; a longer member fails the checksum.
+        let bytes = read_member(&mut zip, name, expected.size)?;
+        if digest(&bytes) != *expected {
+            return Err(format!("归档已损坏（{} 校验和不匹配）", name));
+        }
+        members.insert(name.clone(), bytes);
+    }
+    Ok(Unpacked { manifest, members })
+}
+
+/// The archive's sections and web pages, checked against the expected shapes.
+struct Contents {
+    sections: Map<String, Value>,
+    attachments: Vec<(WebArchiveEntry, Vec<u8>)>,
+    vault_unlocked: Option<bool>,
+    errors: Vec<String>,
+}
+
+fn parse_json(name: &str, bytes: &[u8], errors: &mut Vec<String>) -> Option<Value> {
+    serde_json::from_slice(bytes)
+        .map_err(|e| errors.push(format!("{}: JSON 无效 ({})", name, e)))
+        .ok()
+}
+
+fn read_contents(unpacked: &Unpacked, passphrase: &str) -> Contents {
+    let mut contents = Contents {
+        sections: Map::new(),
+        attachments: Vec::new(),
+        vault_unlocked: None,
+        errors: Vec::new(),
+    };
+    let errors = &mut contents.errors;
+    for (section, file) in SECTIONS {
+        let Some(bytes) = unpacked.members.get(file) else {
+            continue;
+        };
+        if let Some(value) = parse_json(file, bytes, errors) {
+            validate_section(section, file, &value, errors);
+            contents.sections.insert(section.to_string(), value);
+        }
+    }
+
+    if let Some(bytes) = unpacked
+        .members
+        .get(VAULT_FILE)
+        .filter(|_| !passphrase.is_empty())
+    {
+        let opened = parse_json(VAULT_FILE, bytes, errors).and_then(|sealed| {
+            SyncCipher::from_passphrase(passphrase, &KeyCache::default())
+                .open_bytes(&sealed)
+                .ok()
+        });
+        contents.vault_unlocked = Some(opened.is_some());
+        if let Some(value) = opened.and_then(|opened| parse_json(VAULT_FILE, &opened.value, errors))
+        {
+            validate_section("vault", VAULT_FILE, &value, errors);
+            contents.sections.insert("vault".to_string(), value);
+        }
+    }
+
+    if let Some(bytes) = unpacked.members.get(ATTACHMENT_INDEX) {
+        match serde_json::from_slice::<Vec<WebArchiveEntry>>(bytes) {
+            Ok(entries) => {
+                for entry in entries {
+                    let name = format!("{}{}", ATTACHMENT_DIR, entry.file);
+                    match unpacked.members.get(&name) {
+                        Some(data) => contents.attachments.push((entry, data.clone())),
+                        None => errors.push(format!("归档缺少 {}", name)),
+                    }
+                }
+            }
+            Err(e) => errors.push(format!("{}: 格式无效 ({})", ATTACHMENT_INDEX, e)),
+        }
+    }
+    contents
+}
+
+/// Validates an archive without importing it.
+#[tauri::command]
+pub fn inspect_workplan_archive(
+    path: String,
+    passphrase: Option<String>,
+) -> Result<ArchivePreview, String> {
+    let unpacked = read_archive(&path)?;
+    let contents = read_contents(&unpacked, passphrase.as_deref().unwrap_or_default());
+    Ok(ArchivePreview {
+        version: unpacked.manifest.version,
+        created_at: unpacked.manifest.created_at,
+        app_version: unpacked.manifest.app_version,
+        counts: unpacked.manifest.counts,
+        has_vault: unpacked.manifest.vault,
+        vault_unlocked: contents.vault_unlocked,
+        errors: contents.errors,
+    })
+}
+
+/// Whether `incoming` carries a later `updatedAt` than `existing`. Entries
+/// without comparable timestamps leave the current one in place.
+fn is_newer(incoming: &Value, existing: &Value) -> bool {
+    match (incoming.get("updatedAt"), existing.get("updatedAt")) {
+        (Some(Value::String(a)), Some(Value::String(b))) => a > b,
+        (Some(Value::Number(a)), Some(Value::Number(b))) => {
+            a.as_f64().unwrap_or(0.0) > b.as_f64().unwrap_or(0.0)
+        }
+        (Some(_), None) => true,
+        _ => false,
+    }
+}
+
+fn merge_items(
+    collection: &str,
+    current: Option<&Value>,
+    incoming: &Value,
+    replace: bool,
+) -> (Value, CollectionStats) {
+    let current = current
+        .and_then(Value::as_array)
+        .cloned()
+        .unwrap_or_default();
+    let incoming = incoming.as_array().cloned().unwrap_or_default();
+    let mut stats = CollectionStats {
+        collection: collection.to_string(),
+        ..Default::default()
+    };
+    let positions: HashMap<String, usize> = current
+        .iter()
+        .enumerate()
+        .filter_map(|(index, item)| Some((entry_key(item)?, index)))
+        .collect();
+
+    if replace {
+        let incoming_keys: HashSet<String> = incoming.iter().filter_map(entry_key).collect();
+        stats.removed = positions
+            .keys()
+            .filter(|key| !incoming_keys.contains(*key))
+            .count();
+        for item in &incoming {
+            match entry_key(item).and_then(|key| positions.get(&key)) {
+                None => stats.added += 1,
+                Some(&index) if current[index] == *item => stats.unchanged += 1,
+                Some(_) => stats.updated += 1,
+            }
+        }
+        return (Value::Array(incoming), stats);
+    }
+
+    let mut merged = current;
+    for item in incoming {
+        match entry_key(&item).and_then(|key| positions.get(&key)) {
+            None => {
+                merged.push(item);
+                stats.added += 1;
+            }
+            Some(&index) if merged[index] == item => stats.unchanged += 1,
+            Some(&index) if is_newer(&item, &merged[index]) => {
+                merged[index] = item;
+                stats.updated += 1;
+            }
+            Some(_) => stats.kept += 1,
+        }
+    }
+    (Value::Array(merged), stats)
+}
+
+/// Merges an object section's id-keyed lists and keeps the current value of
+/// every other field, adding only fields it lacks.
+fn merge_object(
+    section: &str,
+    lists: &[&str],
+    current: Option<&Value>,
+    incoming: &Value,
+    replace: bool,
+    stats: &mut Vec<CollectionStats>,
+) -> Value {
+    let current = current.and_then(Value::as_object);
+    let incoming = incoming.as_object().cloned().unwrap_or_default();
+    let mut result = if replace {
+        incoming.clone()
+    } else {
+        let mut result = incoming.clone();
+        if let Some(current) = current {
+            result.extend(current.clone());
+        }
+        result
+    };
+    for list in lists {
+        let Some(items) = incoming.get(*list) else {
+            continue;
+        };
+        let (merged, list_stats) = merge_items(
+            &format!("{}.{}", section, list),
+            current.and_then(|current| current.get(*list)),
+            items,
+            replace,
+        );
+        result.insert(list.to_string(), merged);
+        stats.push(list_stats);
+    }
+    Value::Object(result)
+}
+
+/// Merges or replaces each archived section against `current`.
+fn merge_sections(
+    sections: &Map<String, Value>,
+    current: &Value,
+    replace: bool,
+    warnings: &mut Vec<String>,
+) -> (Map<String, Value>, Vec<CollectionStats>) {
+    let mut data = Map::new();
+    let mut stats = Vec::new();
+    for (section, incoming) in sections {
+        let existing = current.get(section);
+        let value = match section.as_str() {
+            "tasks" | "templates" | "scheduledTasks" => {
+                let (merged, section_stats) = merge_items(section, existing, incoming, replace);
+                stats.push(section_stats);
+                merged
+            }
+            "notes" => {
+                let mut notes = merge_object(
+                    section,
+                    &["notes", "aiPrompts"],
+                    existing,
+                    incoming,
+                    replace,
+                    &mut stats,
+                );
+                if !replace {
+                    let mut categories: Vec<Value> = existing
+                        .and_then(|notes| notes.get("categories"))
+                        .and_then(Value::as_array)
+                        .cloned()
+                        .unwrap_or_default();
+                    for category in incoming
+                        .get("categories")
+                        .and_then(Value::as_array)
+                        .into_iter()
+                        .flatten()
+                    {
+                        if !categories.contains(category) {
+                            categories.push(category.clone());
+                        }
+                    }
+                    notes["categories"] = Value::Array(categories);
+                }
+                notes
+            }
+            "vault" => {
+                let current_hash = existing
+                    .and_then(|vault| vault.get("masterHash"))
+                    .and_then(Value::as_str)
+                    .unwrap_or_default();
+                let incoming_hash = incoming
+                    .get("masterHash")
+                    .and_then(Value::as_str)
+                    .unwrap_or_default();
+                // Entries are encrypted under the master password, so they only
+                // mix with a vault that uses the same one.
+                if !replace && !current_hash.is_empty() && current_hash != incoming_hash {
+                    warnings.push("密码库的主密码与当前不同，已跳过合并".to_string());
+                    continue;
+                }
+                let mut vault = merge_object(
+                    section,
+                    &["passwords"],
+                    existing,
+                    incoming,
+                    replace,
+                    &mut stats,
+                );
+                vault["masterHash"] = Value::String(incoming_hash.to_string());
+                vault
+            }
+            _ => merge_object(section, &[], existing, incoming, replace, &mut stats),
+        };
+        data.insert(section.clone(), value);
+    }
+    (data, stats)
+}
+
+/// Imports an archive. `merge` adds missing entries and takes newer ones by
+/// `updatedAt`; `replace` swaps each section the archive contains. The result
+/// is computed against `current` and returned for the frontend to adopt; only
+/// archived web pages are written here.
+#[tauri::command]
+pub fn import_workplan_archive(
+    app: AppHandle,
+    web_archives: State<'_, WebArchiveState>,
+    path: String,
+    mode: String,
+    current: Value,
+    passphrase: Option<String>,
+) -> Result<ImportResult, String> {
+    let replace = match mode.as_str() {
+        "merge" => false,
+        "replace" => true,
+        _ => return Err("未知的导入方式".to_string()),
+    };
+    let unpacked = read_archive(&path)?;
+    let passphrase = passphrase.unwrap_or_default();
+    let contents = read_contents(&unpacked, &passphrase);
+    if !contents.errors.is_empty() {
+        return Err(format!("归档内容无效:\n{}", contents.errors.join("\n")));
+    }
+    let mut warnings = Vec::new();
+    match contents.vault_unlocked {
+        Some(false) => return Err("无法解密密码库，请检查口令".to_string()),
+        None if unpacked.manifest.vault => {
+            warnings.push("未提供口令，已跳过密码库".to_string());
+        }
+        _ => {}
+    }
+
+    let (data, stats) = merge_sections(&contents.sections, &current, replace, &mut warnings);
+    let attachments = if unpacked.members.contains_key(ATTACHMENT_INDEX) {
+        web_archive::import_archives(&app, &web_archives, contents.attachments, replace)?
+    } else {
+        0
+    };
+
+    Ok(ImportResult {
+        mode,
+        data: Value::Object(data),
+        stats,
+        attachments,
+        warnings,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+    use serde_json::json;
+
+    fn stats_of(stats: &CollectionStats) -> [usize; 5] {
+        [
+            stats.added,
+            stats.updated,
+            stats.unchanged,
+            stats.kept,
+            stats.removed,
+        ]
+    }
+
+    fn options(passphrase: &str) -> ExportOptions {
+        ExportOptions {
+            include_attachments: true,
+            include_vault: true,
+            passphrase: passphrase.to_string(),
+        }
+    }
+
+    fn sample() -> Value {
+        json!({
+            "tasks": [{ "id": "t1", "title": "Write report", "updatedAt": 2 }],
+            "notes": {
+                "notes": [{ "id": 7, "title": "Ideas" }],
+                "categories": ["work"],
+            },
+            "settings": { "theme": "dark" },
+            "vault": {
+                "masterHash": "hash-a",
+                "passwords": [{ "id": "p1", "site": "example.com" }],
+            },
+        })
+    }
+
+    fn page() -> (WebArchiveEntry, Vec<u8>) {
+        let entry = serde_json::from_value(json!({
+            "id": "w1",
+            "url": "https://example.com/",
+            "finalUrl": "https://example.com/",
+            "title": "Example",
+            "format": "html",
+            "ownerKind": "task",
+            "ownerId": "t1",
+            "createdAt": 1,
+            "size": 5,
+            "file": "w1.html",
+        }))
+        .unwrap();
+        (entry, b"<p>hi".to_vec())
+    }
+
+    fn write(dir: &TempDir, members: &[(String, Vec<u8>)]) -> String {
+        let path = dir.path().join("backup.workplan");
+        write_archive(&path, members).unwrap();
+        path.to_string_lossy().to_string()
+    }
+
+    fn error_of<T>(result: Result<T, String>) -> String {
+        match result {
+            Ok(_) => panic!("expected an error"),
+            Err(error) => error,
+        }
+    }
+
+    #[test]
+    fn validate_section_reports_schema_problems() {
+        let mut errors = Vec::new();
+        let tasks = json!([{ "id": "a" }, { "id": "a" }, { "title": "no id" }, 3]);
+        assert_eq!(
+            validate_section("tasks", "tasks.json", &tasks, &mut errors),
+            4
+        );
+        assert_eq!(
+            errors,
+            [
+                "tasks.json: id a 重复",
+                "tasks.json: 第 3 项缺少 id",
+                "tasks.json: 第 4 项不是对象",
+            ]
+        );
+
+        let mut errors = Vec::new();
+        let notes = json!({ "notes": [{ "id": 1 }, { "id": 2 }], "categories": ["a", 1] });
+        assert_eq!(
+            validate_section("notes", "notes.json", &notes, &mut errors),
+            2
+        );
+        assert_eq!(errors, ["notes.json#categories: 应为字符串数组"]);
+
+        let mut errors = Vec::new();
+        let vault = json!({ "masterHash": 1, "passwords": {} });
+        validate_section("vault", VAULT_FILE, &vault, &mut errors);
+        validate_section("settings", "settings.json", &json!([]), &mut errors);
+        validate_section("templates", "templates.json", &json!({}), &mut errors);
+        assert_eq!(
+            errors,
+            [
+                "vault.enc#masterHash: 应为字符串",
+                "vault.enc#passwords: 应为数组",
+                "settings.json: 应为对象",
+                "templates.json: 应为数组",
+            ]
+        );
+    }
+
+    #[test]
+    fn merge_takes_newer_entries_and_replace_mirrors_the_archive() {
+        let current = json!([
+            { "id": "same", "updatedAt": 1 },
+            { "id": "older", "updatedAt": "2024-01-01" },
+            { "id": "newer", "updatedAt": 9 },
+            { "id": "local" },
+        ]);
+        let incoming = json!([
+            { "id": "same", "updatedAt": 1 },
+            { "id": "older", "updatedAt": "2024-06-01" },
+            { "id": "newer", "updatedAt": 3 },
+            { "id": "fresh" },
+        ]);
+
+        let (merged, stats) = merge_items("tasks", Some(&current), &incoming, false);
+        assert_eq!(stats_of(&stats), [1, 1, 1, 1, 0]);
+        let merged = merged.as_array().unwrap();
+        assert_eq!(merged.len(), 5);
+        assert_eq!(merged[1]["updatedAt"], "2024-06-01");
+        assert_eq!(merged[2]["updatedAt"], 9);
+
+        let (replaced, stats) = merge_items("tasks", Some(&current), &incoming, true);
+        assert_eq!(stats_of(&stats), [1, 2, 1, 0, 1]);
+        assert_eq!(replaced, incoming);
+
+        assert!(is_newer(&json!({ "updatedAt": 2 }), &json!({})));
+        assert!(!is_newer(&json!({}), &json!({ "updatedAt": 2 })));
+        assert!(!is_newer(
+            &json!({ "updatedAt": "3" }),
+            &json!({ "updatedAt": 2 })
+        ));
+    }
+
+    #[test]
+    fn a_vault_under_another_master_password_is_not_merged() {
+        let current = json!({
+            "vault": { "masterHash": "hash-b", "passwords": [{ "id": "p9" }] },
+        });
+        let sections = sample().as_object().unwrap().clone();
+
+        let mut warnings = Vec::new();
+        let (data, _) = merge_sections(&sections, &current, false, &mut warnings);
+        assert!(!data.contains_key("vault"));
+        assert_eq!(warnings, ["密码库的主密码与当前不同，已跳过合并"]);
+
+        // Replacing swaps the whole vault, master password included.
+        let mut warnings = Vec::new();
+        let (data, _) = merge_sections(&sections, &current, true, &mut warnings);
+        assert!(warnings.is_empty());
+        assert_eq!(data["vault"], sections["vault"]);
+
+        let current = json!({
+            "vault": { "masterHash": "hash-a", "passwords": [{ "id": "p9" }] },
+        });
+        let (data, stats) = merge_sections(&sections, &current, false, &mut warnings);
+        assert!(warnings.is_empty());
+        assert_eq!(data["vault"]["passwords"].as_array().unwrap().len(), 2);
+        assert!(stats
+            .iter()
+            .any(|stats| stats.collection == "vault.passwords" && stats.added == 1));
+    }
+
+    #[test]
+    fn an_exported_archive_imports_unchanged() {
+        let dir = TempDir::new("archive-round-trip");
+        let (members, counts) = pack(&sample(), &options("correct horse"), || Ok(vec![page()]))
+            .unwrap_or_else(|e| panic!("{}", e));
+        assert_eq!(counts["tasks"], 1);
+        assert_eq!(counts["notes"], 1);
+        assert_eq!(counts["attachments"], 1);
+        let path = write(&dir, &members);
+
+        let unpacked = read_archive(&path).unwrap_or_else(|e| panic!("{}", e));
+        assert!(unpacked.manifest.vault);
+        let contents = read_contents(&unpacked, "correct horse");
+        assert!(contents.errors.is_empty(), "{:?}", contents.errors);
+        assert_eq!(contents.vault_unlocked, Some(true));
+        assert_eq!(Value::Object(contents.sections.clone()), sample());
+        assert_eq!(contents.attachments.len(), 1);
+        assert_eq!(contents.attachments[0].1, b"<p>hi");
+
+        let mut warnings = Vec::new();
+        let (data, _) = merge_sections(&contents.sections, &json!({}), false, &mut warnings);
+        assert_eq!(Value::Object(data), sample());
+
+        let contents = read_contents(&unpacked, "wrong");
+        assert_eq!(contents.vault_unlocked, Some(false));
+        assert!(!contents.sections.contains_key("vault"));
+    }
+
+    #[test]
+    fn export_refuses_invalid_data_before_reading_attachments() {
+        let data = json!({ "tasks": [{ "title": "no id" }] });
+        let error = error_of(pack(&data, &options(""), || panic!("attachments read")));
+        assert_eq!(error, "导出数据无效:\ntasks.json: 第 1 项缺少 id");
+        let error = error_of(pack(&sample(), &options(""), || Ok(Vec::new())));
+        assert_eq!(error, "导出密码库时需要设置口令");
+    }
+
+    #[test]
+    fn oversized_or_altered_archives_are_refused() {
+        let dir = TempDir::new("archive-limits");
+        let (mut members, _) = pack(&sample(), &options("pass"), || Ok(vec![page()]))
+            .unwrap_or_else(|e| panic!("{}", e));
+        let path = write(&dir, &members);
+        let total: usize = members[1..].iter().map(|(_, bytes)| bytes.len()).sum();
+        assert!(unpack(&path, total as u64).is_ok());
+        assert_eq!(error_of(unpack(&path, total as u64 - 1)), "归档解压后过大");
+
+        let member = members
+            .iter_mut()
+            .find(|(name, _)| name == "tasks.json")
+            .unwrap();
+        member.1.extend_from_slice(b"\n");
+        let path = write(&dir, &members);
+        assert_eq!(
+            error_of(read_archive(&path)),
+            "归档已损坏（tasks.json 校验和不匹配）"
+        );
+    }
+}
@@ -0,0 +1,200 @@
+//! Saving binary exports (PDFs, images, archives) produced by either side.
+//! The frontend sends raw bytes as the invoke body instead of a UTF-8 string;
+//! Rust producers call [`save_bytes`] directly. Both get a sensible extension,
+//! an optional native save dialog and an optional "show in folder".
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use percent_encoding::percent_decode_str;
+use serde::{Deserialize, Serialize};
+use tauri::ipc::{InvokeBody, Request};
+use tauri::AppHandle;
+
+/// Extensions for the MIME types exports use.
+const MIME_EXTENSIONS: &[(&str, &str)] = &[
+    ("application/pdf", "pdf"),
+    ("application/zip", "zip"),
+    ("application/json", "json"),
+    (
+        "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+        "xlsx",
+    ),
+    (
+        "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+        "docx",
+    ),
+    ("image/png", "png"),
+    ("image/jpeg", "jpg"),
+    ("image/gif", "gif"),
+    ("image/webp", "webp"),
+    ("image/svg+xml", "svg"),
+    ("text/csv", "csv"),
+    ("text/markdown", "md"),
+    ("text/html", "html"),
+    ("text/plain", "txt"),
+];
+
+#[derive(Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct SaveOptions {
+    /// MIME type of the content, used when the name lacks an extension.
+    pub mime: String,
+    /// Ask for the location with a native save dialog (desktop only).
+    pub dialog: bool,
+    /// Show the saved file in the system file manager (desktop only).
+    pub reveal: bool,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SavedFile {
+    pub path: String,
+    pub size: u64,
+}
+
+/// Extension implied by the leading bytes. Zip containers are left out on
+/// purpose: docx, xlsx and `.workplan` archives share the signature.
+fn sniff_extension(bytes: &[u8]) -> Option<&'static str> {
+    if bytes.starts_with(b"%PDF-") {
+        Some("pdf")
+    } else if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        Some("png")
+    } else if bytes.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        Some("jpg")
+    } else if bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a") {
+        Some("gif")
+    } else if bytes.len() >= 12 && bytes.starts_with(b"RIFF") && &bytes[8..12] == b"WEBP" {
+        Some("webp")
+    } else {
+        None
+    }
+}
+
+fn mime_extension(mime: &str) -> Option<&'static str> {
+    let mime = mime.split(';').next().unwrap_or_default().trim();
+    MIME_EXTENSIONS
+        .iter()
+        .find(|(known, _)| known.eq_ignore_ascii_case(mime))
+        .map(|(_, extension)| *extension)
+}
+
+/// Reduces `filename` to a bare file name and makes its extension match the
+/// content: a missing one is added from the signature or MIME type, and one
+/// that contradicts an unambiguous signature is replaced.
+fn file_name_for(filename: &str, mime: &str, bytes: &[u8]) -> Result<String, String> {
+    let name = Path::new(filename.trim())
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .filter(|name| !name.trim().is_empty())
+        .ok_or_else(|| "文件名不能为空".to_string())?;
+    let path = Path::new(&name);
+    let current = path
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase());
+    let sniffed = sniff_extension(bytes);
+    let wanted = sniffed.or_else(|| mime_extension(mime));
+    let same = |a: &str, b: &str| a == b || matches!((a, b), ("jpg", "jpeg") | ("jpeg", "jpg"));
+
+    Ok(match (current, wanted) {
+        (None, Some(wanted)) => format!("{}.{}", name, wanted),
+        (Some(current), Some(wanted)) if sniffed.is_some() && !same(&current, wanted) => {
+            let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+            format!("{}.{}", stem, wanted)
+        }
+        _ => name,
+    })
+}
+
+#[cfg(not(any(target_os = "android", target_os = "ios")))]
+async fn ask_save_path(app: &AppHandle, filename: &str) -> Result<Option<PathBuf>, String> {
+    use tauri_plugin_dialog::DialogExt;
+
+    let (sender, receiver) = tokio::sync::oneshot::channel();
+    let mut dialog = app.dialog().file().set_file_name(filename);
+    if let Some(extension) = Path::new(filename).extension() {
+        let extension = extension.to_string_lossy().to_string();
+        dialog = dialog.add_filter(extension.to_uppercase(), &[extension.as_str()]);
+    }
+    dialog.save_file(move |path| {
+        let _ = sender.send(path);
+    });
+    match receiver.await.map_err(|e| e.to_string())? {
+        Some(path) => path
+            .into_path()
+            .map(Some)
+            .map_err(|e| format!("无效的保存路径: {}", e)),
+        None => Ok(None),
+    }
+}
+
+/// Writes `bytes` as `filename` and returns where it went, or `None` when the
+/// save dialog was cancelled. Without the dialog the file lands in the
+/// downloads directory under a free name.
+pub async fn save_bytes(
+    app: &AppHandle,
+    filename: &str,
+    bytes: &[u8],
+    options: &SaveOptions,
+) -> Result<Option<SavedFile>, String> {
+    let filename = file_name_for(filename, &options.mime, bytes)?;
+
+    #[cfg(not(any(target_os = "android", target_os = "ios")))]
+    let chosen = if options.dialog {
+        match ask_save_path(app, &filename).await? {
+            Some(path) => Some(path),
+            None => return Ok(None),
+        }
+    } else {
+        None
+    };
+    #[cfg(any(target_os = "android", target_os = "ios"))]
+    let chosen: Option<PathBuf> = None;
+
+    let path = match chosen {
+        Some(path) => {
+            fs::write(&path, bytes).map_err(|e| format!("写入文件失败: {}", e))?;
+            path.to_string_lossy().to_string()
+        }
+        None => crate::save_to_downloads(app, &filename, bytes)?,
+    };
+
+    #[cfg(not(any(target_os = "android", target_os = "ios")))]
+    if options.reveal {
+        use tauri_plugin_opener::OpenerExt;
+        let _ = app.opener().reveal_item_in_dir(&path);
+    }
+
+    Ok(Some(SavedFile {
+        path,
+        size: bytes.len() as u64,
+    }))
+}
+
+/// Reads a percent-encoded header; header values are ASCII only.
+fn header(request: &Request<'_>, name: &str) -> String {
+    request
+        .headers()
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| percent_decode_str(value).decode_utf8_lossy().to_string())
+        .unwrap_or_default()
+}
+
+/// Saves the raw invoke body. The name and options travel in headers:
+/// `x-filename`, `x-mime-type`, `x-save-dialog` and `x-reveal`.
+#[tauri::command]
+pub async fn save_bytes_to_downloads(
+    app: AppHandle,
+    request: Request<'_>,
+) -> Result<Option<SavedFile>, String> {
+    let InvokeBody::Raw(bytes) = request.body() else {
+        return Err("需要以二进制形式传入文件内容".to_string());
+    };
+    let options = SaveOptions {
+        mime: header(&request, "x-mime-type"),
+        dialog: header(&request, "x-save-dialog") == "1",
+        reveal: header(&request, "x-reveal") == "1",
+    };
+    save_bytes(&app, &header(&request, "x-filename"), bytes, &options).await
+}
@@ -0,0 +1,488 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tauri::AppHandle;
+use url::Url;
+
+const CACHE_DIR: &str = "http_cache";
+const MIN_HOST_INTERVAL: Duration = Duration::from_millis(1000);
+const MAX_ATTEMPTS: u32 = 3;
+const BASE_BACKOFF: Duration = Duration::from_millis(500);
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(10);
+const TRACKING_PARAMS: [&str; 4] = ["fbclid", "gclid", "msclkid", "spm"];
+
+pub const SEARCH_TTL: Duration = Duration::from_secs(30 * 60);
+pub const PAGE_TTL: Duration = Duration::from_secs(6 * 60 * 60);
+
+#[derive(Default)]
+pub struct HttpCacheState {
+    next_slot: Mutex<HashMap<String, Instant>>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct CacheEntry {
+    key: String,
+    final_url: String,
+    status: u16,
+    content_type: String,
+    etag: Option<String>,
+    last_modified: Option<String>,
+    stored_at: u64,
+    size: usize,
+}
+
+pub struct CachedResponse {
+    pub status: u16,
+    pub final_url: String,
+    pub content_type: String,
+    pub body: Vec<u8>,
+}
+
+impl CachedResponse {
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HttpCacheItem {
+    key: String,
+    url: String,
+    content_type: String,
+    stored_at: u64,
+    size: usize,
+    revalidatable: bool,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HttpCacheSummary {
+    entries: usize,
+    total_bytes: u64,
+    items: Vec<HttpCacheItem>,
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+fn cache_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    let dir = crate::app_data_file(app, CACHE_DIR)?;
+    fs::create_dir_all(&dir).map_err(|e| format!("创建缓存目录失败: {}", e))?;
+    Ok(dir)
+}
+
+fn key_hash(key: &str) -> String {
+    let digest = Sha256::digest(key.as_bytes());
+    digest.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Cache key for a page URL: fragment and tracking parameters dropped, query sorted.
+pub fn normalize_url(raw: &str) -> String {
+    let Ok(mut url) = Url::parse(raw.trim()) else {
+        return raw.trim().to_string();
+    };
+    url.set_fragment(None);
+    let mut pairs: Vec<(String, String)> = url
+        .query_pairs()
+        .filter(|(key, _)| {
+            let key = key.to_ascii_lowercase();
+            !key.starts_with("utm_") && !TRACKING_PARAMS.contains(&key.as_str())
+        })
+        .map(|(key, value)| (key.into_owned(), value.into_owned()))
+        .collect();
+    pairs.sort();
+    if pairs.is_empty() {
+        url.set_query(None);
+    } else {
+        url.query_pairs_mut().clear().extend_pairs(pairs);
+    }
+    url.to_string()
+}
+
+/// Cache key for a search query: case and whitespace differences map to the same entry.
+pub fn normalize_query(query: &str) -> String {
+    query
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+fn load_entry(dir: &Path, hash: &str) -> Option<(CacheEntry, Vec<u8>)> {
+    let meta = fs::read_to_string(dir.join(format!("{}.json", hash))).ok()?;
+    let entry: CacheEntry = serde_json::from_str(&meta).ok()?;
+    let body = fs::read(dir.join(format!("{}.bin", hash))).ok()?;
+    Some((entry, body))
+}
+
+fn store_entry(dir: &Path, hash: &str, entry: &CacheEntry, body: &[u8]) -> Result<(), String> {
+    fs::write(dir.join(format!("{}.bin", hash)), body)
+        .map_err(|e| format!("写入缓存失败: {}", e))?;
+    let meta = serde_json::to_string(entry).map_err(|e| e.to_string())?;
+    fs::write(dir.join(format!("{}.json", hash)), meta).map_err(|e| format!("写入缓存失败: {}", e))
+}
+
+fn header_value(response: &reqwest::Response, name: &str) -> Option<String> {
+    response
+        .headers()
+        .get(name)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.to_string())
+}
+
+fn retry_after(response: &reqwest::Response) -> Option<Duration> {
+    header_value(response, "retry-after")
+        .and_then(|v| v.trim().parse::<u64>().ok())
+        .map(|secs| Duration::from_secs(secs).min(MAX_RETRY_AFTER))
+}
+
+fn is_retryable(status: reqwest::StatusCode) -> bool {
+    status == reqwest::StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+}
+
+impl HttpCacheState {
+    /// Spaces requests to the same host at least `MIN_HOST_INTERVAL` apart.
+    async fn wait_for_host(&self, host: &str) {
+        let wait = {
+            let Ok(mut slots) = self.next_slot.lock() else {
+                return;
+            };
+            let now = Instant::now();
+            let slot = slots
+                .get(host)
+                .copied()
+                .filter(|slot| *slot > now)
+                .unwrap_or(now);
+            slots.insert(host.to_string(), slot + MIN_HOST_INTERVAL);
+            slot - now
+        };
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    async fn send_with_retry(
+        &self,
+        request: reqwest::RequestBuilder,
+    ) -> Result<reqwest::Response, String> {
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+            let current = request
+                .try_clone()
+                .ok_or_else(|| "请求无法重试".to_string())?;
+            let (client, built) = current.build_split();
+            let built = built.map_err(|e| e.to_string())?;
+            if let Some(host) = built.url().host_str() {
+                self.wait_for_host(host).await;
+            }
+            let backoff = BASE_BACKOFF * 2u32.pow(attempt - 1);
+
+            match client.execute(built).await {
+                Ok(response) if is_retryable(response.status()) && attempt < MAX_ATTEMPTS => {
+                    tokio::time::sleep(retry_after(&response).unwrap_or(backoff)).await;
+                }
+                Ok(response) => return Ok(response),
+                Err(e) if (e.is_timeout() || e.is_connect()) && attempt < MAX_ATTEMPTS => {
+                    tokio::time::sleep(backoff).await;
+                }
+                Err(e) => return Err(format!("请求失败: {}", e)),
+            }
+        }
+    }
+
+    /// Serves `key` from the on-disk cache while it is younger than `ttl`, otherwise
+    /// revalidates with ETag/Last-Modified or refetches. Only 2xx responses are stored.
+    pub async fn fetch(
+        &self,
+        app: &AppHandle,
+        key: &str,
+        ttl: Duration,
+        max_bytes: u64,
+        request: reqwest::RequestBuilder,
+    ) -> Result<CachedResponse, String> {
+        self.fetch_in(&cache_dir(app)?, key, ttl, max_bytes, request)
+            .await
+    }
+
+    async fn fetch_in(
+        &self,
+        dir: &Path,
+        key: &str,
+        ttl: Duration,
+        max_bytes: u64,
+        request: reqwest::RequestBuilder,
+    ) -> Result<CachedResponse, String> {
+        let hash = key_hash(key);
+        let cached = load_entry(dir, &hash);
+
+        if let Some((entry, body)) = &cached {
+            if now_secs().saturating_sub(entry.stored_at) < ttl.as_secs() {
+                return Ok(CachedResponse {
+                    status: entry.status,
+                    final_url: entry.final_url.clone(),
+                    content_type: entry.content_type.clone(),
+                    body: body.clone(),
+                });
+            }
+        }
+
+        let mut request = request;
+        if let Some((entry, _)) = &cached {
+            if let Some(etag) = &entry.etag {
+                request = request.header("If-None-Match", etag);
+            }
+            if let Some(last_modified) = &entry.last_modified {
+                request = request.header("If-Modified-Since", last_modified);
+            }
+        }
+
+        let response = self.send_with_retry(request).await?;
+        let status = response.status();
+
+        if status == reqwest::StatusCode::NOT_MODIFIED {
+            if let Some((mut entry, body)) = cached {
+                entry.stored_at = now_secs();
+                store_entry(dir, &hash, &entry, &body)?;
+                return Ok(CachedResponse {
+                    status: entry.status,
+                    final_url: entry.final_url,
+                    content_type: entry.content_type,
+                    body,
+                });
+            }
+        }
+
+        let final_url = response.url().to_string();
+        let content_type = header_value(&response, "content-type")
+            .unwrap_or_default()
+            .to_lowercase();
+        let etag = header_value(&response, "etag");
+        let last_modified = header_value(&response, "last-modified");
+        let no_store = header_value(&response, "cache-control")
+            .map(|v| v.to_lowercase().contains("no-store"))
+            .unwrap_or(false);
+        let body = crate::network::read_limited(response, max_bytes).await?;
+
+        if status.is_success() && !no_store {
+            let entry = CacheEntry {
+                key: key.to_string(),
+                final_url: final_url.clone(),
+                status: status.as_u16(),
+                content_type: content_type.clone(),
+                etag,
+                last_modified,
+                stored_at: now_secs(),
+                size: body.len(),
+            };
+            // A failed cache write should not fail the request itself.
+            let _ = store_entry(dir, &hash, &entry, &body);
+        }
+
+        Ok(CachedResponse {
+            status: status.as_u16(),
+            final_url,
+            content_type,
+            body,
+        })
+    }
+}
+
+fn cache_entries(dir: &Path) -> Vec<(String, CacheEntry)> {
+    let Ok(read_dir) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    read_dir
+        .flatten()
+        .filter_map(|item| {
+            let path = item.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some("json") {
+                return None;
+            }
+            let hash = path.file_stem()?.to_str()?.to_string();
+            let entry = serde_json::from_str(&fs::read_to_string(&path).ok()?).ok()?;
+            Some((hash, entry))
+        })
+        .collect()
+}
+
+#[tauri::command]
+pub fn inspect_http_cache(app: AppHandle) -> Result<HttpCacheSummary, String> {
+    let dir = cache_dir(&app)?;
+    let mut items: Vec<HttpCacheItem> = cache_entries(&dir)
+        .into_iter()
+        .map(|(_, entry)| HttpCacheItem {
+            revalidatable: entry.etag.is_some() || entry.last_modified.is_some(),
+            key: entry.key,
+            url: entry.final_url,
+            content_type: entry.content_type,
+            stored_at: entry.stored_at,
+            size: entry.size,
+        })
+        .collect();
+    items.sort_by_key(|item| std::cmp::Reverse(item.stored_at));
+
+    Ok(HttpCacheSummary {
+        entries: items.len(),
+        total_bytes: items.iter().map(|item| item.size as u64).sum(),
+        items,
+    })
+}
+
+/// Removes cached responses whose key contains `pattern`, or everything when omitted.
+#[tauri::command]
+pub fn clear_http_cache(app: AppHandle, pattern: Option<String>) -> Result<usize, String> {
+    let dir = cache_dir(&app)?;
+    let pattern = pattern.unwrap_or_default();
+    let mut removed = 0;
+    for (hash, entry) in cache_entries(&dir) {
+        if !pattern.is_empty() && !entry.key.contains(&pattern) {
+            continue;
+        }
+        let _ = fs::remove_file(dir.join(format!("{}.bin", hash)));
+        fs::remove_file(dir.join(format!("{}.json", hash)))
+            .map_err(|e| format!("删除缓存失败: {}", e))?;
+        removed += 1;
+    }
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{serve, Request, Response, TempDir};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    /// Serves `handler` and counts the requests that reach it.
+    fn counted<F>(handler: F) -> (String, Arc<AtomicUsize>)
+    where
+        F: Fn(&Request) -> Response + Send + Sync + 'static,
+    {
+        let hits = Arc::new(AtomicUsize::new(0));
+        let counter = hits.clone();
+        let base = serve(move |request| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            handler(request)
+        });
+        (base, hits)
+    }
+
+    async fn fetch(
+        state: &HttpCacheState,
+        dir: &TempDir,
+        url: &str,
+        ttl: Duration,
+    ) -> CachedResponse {
+        let client = reqwest::Client::builder().no_proxy().build().unwrap();
+        state
+            .fetch_in(dir.path(), &normalize_url(url), ttl, 1024, client.get(url))
+            .await
+            .unwrap()
+    }
+
+    #[test]
+    fn normalize_url_drops_tracking_and_sorts_the_query() {
+        assert_eq!(
+            normalize_url(" https://example.com/a?b=2&utm_source=x&UTM_Medium=y&a=1&fbclid=z#top "),
+            "https://example.com/a?a=1&b=2"
+        );
+        assert_eq!(
+            normalize_url("https://example.com/a?gclid=1&spm=2"),
+            "https://example.com/a"
+        );
+        // Repeated keys keep both values, ordered by value.
+        assert_eq!(
+            normalize_url("https://example.com/?tag=b&tag=a"),
+            "https://example.com/?tag=a&tag=b"
+        );
+        assert_eq!(normalize_url(" not a url "), "not a url");
+        assert_eq!(normalize_query("  Rust   Async\tIO "), "rust async io");
+    }
+
+    #[tokio::test]
+    async fn fresh_entries_are_served_without_a_request() {
+        let (base, hits) =
+            counted(|_| Response::new(200, "page").header("Content-Type", "Text/HTML"));
+        let (state, dir) = (HttpCacheState::default(), TempDir::new("http-cache-ttl"));
+        let url = format!("{}/page?utm_source=feed", base);
+
+        let first = fetch(&state, &dir, &url, PAGE_TTL).await;
+        assert!(first.is_success());
+        assert_eq!(first.content_type, "text/html");
+        // The tracking parameter maps to the same cache key.
+        let second = fetch(&state, &dir, &format!("{}/page", base), PAGE_TTL).await;
+        assert_eq!(second.body, b"page");
+        assert_eq!(hits.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn stale_entries_are_revalidated_with_etag_and_last_modified() {
+        let not_modified = Arc::new(AtomicUsize::new(0));
+        let counter = not_modified.clone();
+        let (base, hits) = counted(move |request| {
+            match (
+                request.header("if-none-match"),
+                request.header("if-modified-since"),
+            ) {
+                (Some("\"v1\""), Some("Mon, 01 Jan 2024 00:00:00 GMT")) => {
+                    counter.fetch_add(1, Ordering::SeqCst);
+                    Response::new(304, "")
+                }
+                _ => Response::new(200, "original")
+                    .header("ETag", "\"v1\"")
+                    .header("Last-Modified", "Mon, 01 Jan 2024 00:00:00 GMT"),
+            }
+        });
+        let (state, dir) = (HttpCacheState::default(), TempDir::new("http-cache-304"));
+        let url = format!("{}/doc", base);
+
+        fetch(&state, &dir, &url, Duration::ZERO).await;
+        let revalidated = fetch(&state, &dir, &url, Duration::ZERO).await;
+        assert_eq!(hits.load(Ordering::SeqCst), 2);
+        assert_eq!(not_modified.load(Ordering::SeqCst), 1);
+        // The 304 is answered with the cached response.
+        assert_eq!(revalidated.status, 200);
+        assert_eq!(revalidated.body, b"original");
+    }
+
+    #[tokio::test]
+    async fn failures_and_no_store_responses_are_not_cached() {
+        let (base, hits) = counted(|request| match request.path() {
+            "/private" => Response::new(200, "secret").header("Cache-Control", "private, no-store"),
+            _ => Response::new(404, "missing"),
+        });
+        let (state, dir) = (HttpCacheState::default(), TempDir::new("http-cache-skip"));
+
+        for path in ["/private", "/missing"] {
+            let url = format!("{}{}", base, path);
+            fetch(&state, &dir, &url, PAGE_TTL).await;
+            fetch(&state, &dir, &url, PAGE_TTL).await;
+        }
+        assert_eq!(hits.load(Ordering::SeqCst), 4);
+        assert!(cache_entries(dir.path()).is_empty());
+    }
+
+    #[tokio::test]
+    async fn requests_to_one_host_are_spaced_apart() {
+        let state = HttpCacheState::default();
+        let started = Instant::now();
+        state.wait_for_host("example.com").await;
+        state.wait_for_host("example.org").await;
+        assert!(started.elapsed() < MIN_HOST_INTERVAL / 2);
+
+        state.wait_for_host("example.com").await;
+        assert!(started.elapsed() >= MIN_HOST_INTERVAL);
+        assert!(started.elapsed() < MIN_HOST_INTERVAL * 2);
+    }
+}
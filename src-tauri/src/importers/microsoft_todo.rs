@@ -0,0 +1,171 @@
+use serde_json::Value;
+
+use super::{Context, ImportedTask, Recurrence, Status};
+
+const DAY_NAMES: [&str; 7] = [
+    "sunday",
+    "monday",
+    "tuesday",
+    "wednesday",
+    "thursday",
+    "friday",
+    "saturday",
+];
+
+/// Microsoft Graph `todoTask` JSON: a `value` page of tasks, a list of
+/// `{ displayName, tasks }` lists, or a bare task array.
+pub fn parse(content: &str, context: &mut Context) -> Result<Vec<ImportedTask>, String> {
+    let value: Value =
+        serde_json::from_str(content).map_err(|e| format!("Microsoft To Do JSON 无效: {}", e))?;
+    let mut tasks = Vec::new();
+    collect(&value, None, context, &mut tasks);
+    Ok(tasks)
+}
+
+fn collect(
+    value: &Value,
+    list: Option<&str>,
+    context: &mut Context,
+    tasks: &mut Vec<ImportedTask>,
+) {
+    match value {
+        Value::Array(items) => {
+            for item in items {
+                collect(item, list, context, tasks);
+            }
+        }
+        Value::Object(object) => {
+            if let Some(children) = object.get("tasks").or_else(|| object.get("lists")) {
+                let name = object.get("displayName").and_then(Value::as_str).or(list);
+                collect(children, name, context, tasks);
+            } else if let Some(page) = object.get("value") {
+                collect(page, list, context, tasks);
+            } else if object.contains_key("title") {
+                tasks.push(task(value, list, context));
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Keeps the text of an HTML body, which Graph returns for notes edited in
+/// Outlook.
+fn strip_html(body: &str) -> String {
+    let mut text = String::new();
+    let mut in_tag = false;
+    for c in body.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => in_tag = false,
+            _ if !in_tag => text.push(c),
+            _ => {}
+        }
+    }
+    text.replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&amp;", "&")
+        .trim()
+        .to_string()
+}
+
+fn task(item: &Value, list: Option<&str>, context: &mut Context) -> ImportedTask {
+    let text = |value: Option<&Value>| {
+        value
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_string()
+    };
+    let mut task = ImportedTask::new(&text(item.get("title")));
+    if let Some(body) = item.get("body") {
+        let content = text(body.get("content"));
+        task.note = if text(body.get("contentType")).eq_ignore_ascii_case("html") {
+            strip_html(&content)
+        } else {
+            content.trim().to_string()
+        };
+    }
+    if text(item.get("importance")) == "high" {
+        task.priority = "urgent";
+    }
+    task.status = match text(item.get("status")).as_str() {
+        "completed" => Status::Done,
+        "inProgress" => Status::Doing,
+        _ => Status::Todo,
+    };
+    // To Do due dates are whole days, stored as midnight in the list's zone.
+    let due = text(item.get("dueDateTime").and_then(|due| due.get("dateTime")));
+    task.date = context
+        .stamp_or_warn(&due, &task.title)
+        .map(|date| date.date_only());
+    if let Some(pattern) = item.get("recurrence").and_then(|r| r.get("pattern")) {
+        task.recurrence = Some(recurrence(pattern, task.date.map(|date| date.weekday())));
+    }
+    task.subtasks = item
+        .get("checklistItems")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .map(|step| {
+            (
+                text(step.get("displayName")).trim().to_string(),
+                step.get("isChecked")
+                    .and_then(Value::as_bool)
+                    .unwrap_or(false),
+            )
+        })
+        .collect();
+    task.labels.extend(list.map(str::to_string));
+    task.labels.extend(
+        item.get("categories")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(|category| category.as_str().map(str::to_string)),
+    );
+    task
+}
+
+/// Reads a Graph `recurrencePattern`; only daily and weekly patterns with an
+/// interval of one map onto `repeatDays`.
+fn recurrence(pattern: &Value, weekday: Option<u8>) -> Recurrence {
+    let kind = pattern
+        .get("type")
+        .and_then(Value::as_str)
+        .unwrap_or_default();
+    let interval = pattern.get("interval").and_then(Value::as_i64).unwrap_or(1);
+    let unsupported = || Recurrence::Unsupported(format!("{} / {}", kind, interval));
+    if interval != 1 {
+        return unsupported();
+    }
+    match kind {
+        "daily" => Recurrence::Days((0..7).collect()),
+        "weekly" => {
+            let mut days: Vec<u8> = pattern
+                .get("daysOfWeek")
+                .and_then(Value::as_array)
+                .into_iter()
+                .flatten()
+                .filter_map(Value::as_str)
+                .filter_map(|name| {
+                    DAY_NAMES
+                        .iter()
+                        .position(|day| day.eq_ignore_ascii_case(name))
+                })
+                .map(|day| day as u8)
+                .collect();
+            days.sort_unstable();
+            days.dedup();
+            if days.is_empty() {
+                days.extend(weekday);
+            }
+            if days.is_empty() {
+                unsupported()
+            } else {
+                Recurrence::Days(days)
+            }
+        }
+        _ => unsupported(),
+    }
+}
@@ -0,0 +1,982 @@
+mod microsoft_todo;
+mod taskwarrior;
+mod ticktick;
+mod todoist;
+mod todotxt;
+
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+/// Warnings beyond this are summarized, so a bad file cannot flood the preview.
+const MAX_WARNINGS: usize = 50;
+const DAY_MINUTES: i64 = 24 * 60;
+const ALL_DAYS: [u8; 7] = [0, 1, 2, 3, 4, 5, 6];
+const WORKDAYS: [u8; 5] = [1, 2, 3, 4, 5];
+
+/// A local date with an optional time of day.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Stamp {
+    year: i64,
+    month: i64,
+    day: i64,
+    time: Option<(i64, i64)>,
+}
+
+impl Stamp {
+    /// Formats as the `datetime-local` value tasks store, using `default_time`
+    /// for dates without one.
+    fn format(&self, default_time: &str) -> String {
+        let date = format!("{:04}-{:02}-{:02}", self.year, self.month, self.day);
+        match self.time {
+            Some((hour, minute)) => format!("{}T{:02}:{:02}", date, hour, minute),
+            None => format!("{}T{}", date, default_time),
+        }
+    }
+
+    /// Drops the time, for sources that store all-day dates as midnight.
+    pub fn date_only(self) -> Self {
+        Stamp { time: None, ..self }
+    }
+
+    /// 0 is Sunday, matching `Date.getDay()` and `repeatDays`.
+    pub fn weekday(&self) -> u8 {
+        (days_from_civil(self.year, self.month, self.day) + 4).rem_euclid(7) as u8
+    }
+}
+
+pub enum Recurrence {
+    /// Weekdays a scheduled task repeats on.
+    Days(Vec<u8>),
+    /// A rule `repeatDays` cannot express, kept as text in the note.
+    Unsupported(String),
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Status {
+    Todo,
+    Doing,
+    Done,
+}
+
+/// A task as read from another app, before it becomes a WorkPlan task.
+pub struct ImportedTask {
+    pub title: String,
+    pub note: String,
+    /// `normal`, `urgent` or `critical`.
+    pub priority: &'static str,
+    pub status: Status,
+    pub date: Option<Stamp>,
+    pub deadline: Option<Stamp>,
+    pub recurrence: Option<Recurrence>,
+    pub subtasks: Vec<(String, bool)>,
+    /// The source's id and parent id, for folding subtasks into their parents.
+    pub source_id: Option<String>,
+    pub parent_id: Option<String>,
+    /// Project, list or folder names, kept as tags in the note.
+    pub labels: Vec<String>,
+}
+
+impl ImportedTask {
+    pub fn new(title: &str) -> Self {
+        ImportedTask {
+            title: title.trim().to_string(),
+            note: String::new(),
+            priority: "normal",
+            status: Status::Todo,
+            date: None,
+            deadline: None,
+            recurrence: None,
+            subtasks: Vec::new(),
+            source_id: None,
+            parent_id: None,
+            labels: Vec::new(),
+        }
+    }
+}
+
+/// Shared state for one parse.
+pub struct Context {
+    /// Local offset from UTC, used for timestamps that carry a zone.
+    utc_offset_minutes: i64,
+    warnings: Vec<String>,
+    pub skipped: usize,
+}
+
+impl Context {
+    pub fn warn(&mut self, warning: String) {
+        self.warnings.push(warning);
+    }
+
+    pub fn skip(&mut self, reason: String) {
+        self.skipped += 1;
+        self.warn(reason);
+    }
+
+    /// Parses `YYYY-MM-DD`, `YYYY/MM/DD` or `YYYYMMDD`, optionally followed by a
+    /// time and a `Z` or `±HH[:]MM` zone. Zoned times are converted to local
+    /// time; floating ones are kept as written.
+    pub fn parse_stamp(&self, value: &str) -> Option<Stamp> {
+        let value = value.trim();
+        let (date, rest) = split_date(value)?;
+        let (year, month, day) = date;
+        if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+            return None;
+        }
+        let rest = rest.trim_start_matches(['T', ' ']);
+        if rest.is_empty() {
+            return Some(Stamp {
+                year,
+                month,
+                day,
+                time: None,
+            });
+        }
+        let digits: String = rest
+            .chars()
+            .take_while(|c| c.is_ascii_digit() || *c == ':' || *c == '.')
+            .collect();
+        let zone = rest[digits.len()..].trim();
+        let clock: Vec<&str> = if digits.contains(':') {
+            digits.split(':').collect()
+        } else {
+            // Compact `HHMMSS`.
+            digits
+                .as_bytes()
+                .chunks(2)
+                .map(|pair| std::str::from_utf8(pair).unwrap_or_default())
+                .collect()
+        };
+        let hour: i64 = clock.first()?.parse().ok()?;
+        let minute: i64 = clock.get(1).and_then(|m| m.parse().ok()).unwrap_or(0);
+        if hour > 23 || minute > 59 {
+            return None;
+        }
+        let offset = match zone {
+            "" => None,
+            "Z" | "z" | "UTC" => Some(0),
+            zone => parse_zone(zone),
+        };
+        let Some(offset) = offset else {
+            return Some(Stamp {
+                year,
+                month,
+                day,
+                time: Some((hour, minute)),
+            });
+        };
+        let minutes = days_from_civil(year, month, day) * DAY_MINUTES + hour * 60 + minute - offset
+            + self.utc_offset_minutes;
+        let (year, month, day) = civil_from_days(minutes.div_euclid(DAY_MINUTES));
+        let minute_of_day = minutes.rem_euclid(DAY_MINUTES);
+        Some(Stamp {
+            year,
+            month,
+            day,
+            time: Some((minute_of_day / 60, minute_of_day % 60)),
+        })
+    }
+
+    /// Like `parse_stamp`, warning about values that are present but unreadable.
+    pub fn stamp_or_warn(&mut self, value: &str, title: &str) -> Option<Stamp> {
+        if value.trim().is_empty() {
+            return None;
+        }
+        let stamp = self.parse_stamp(value);
+        if stamp.is_none() {
+            self.warn(format!("「{}」的日期无法识别: {}", title, value.trim()));
+        }
+        stamp
+    }
+}
+
+fn split_date(value: &str) -> Option<((i64, i64, i64), &str)> {
+    let number = |s: &str| s.parse::<i64>().ok();
+    // `get` rather than slicing: a non-ASCII character may straddle the cut.
+    if let Some(&separator @ (b'-' | b'/')) = value.as_bytes().get(4) {
+        let mut parts = value.get(..10)?.split(separator as char);
+        let date = (
+            number(parts.next()?)?,
+            number(parts.next()?)?,
+            number(parts.next()?)?,
+        );
+        return Some((date, value.get(10..)?));
+    }
+    if !value.get(..8)?.bytes().all(|byte| byte.is_ascii_digit()) {
+        return None;
+    }
+    let date = (
+        number(value.get(..4)?)?,
+        number(value.get(4..6)?)?,
+        number(value.get(6..8)?)?,
+    );
+    Some((date, value.get(8..)?))
+}
+
+fn parse_zone(zone: &str) -> Option<i64> {
+    let sign = match zone.chars().next()? {
+        '+' => 1,
+        '-' => -1,
+        _ => return None,
+    };
+    let digits: String = zone[1..].chars().filter(char::is_ascii_digit).collect();
+    if digits.len() != 4 && digits.len() != 2 {
+        return None;
+    }
+    let hours: i64 = digits[..2].parse().ok()?;
+    let minutes: i64 = digits.get(2..).unwrap_or("0").parse().ok()?;
+    Some(sign * (hours * 60 + minutes))
+}
+
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year - era * 400;
+    let mp = (month + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    (yoe + era * 400 + i64::from(month <= 2), month, day)
+}
+
+/// Weekday numbers for English day names (or their abbreviations) in `text`,
+/// and for Chinese ones following 周 or 星期, as in 每周一、三.
+fn weekdays_in(text: &str) -> Vec<u8> {
+    const ENGLISH: [&str; 7] = ["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+    const CHINESE: [char; 7] = ['日', '一', '二', '三', '四', '五', '六'];
+    let mut days: Vec<u8> = text
+        .split(|c: char| !c.is_ascii_alphabetic())
+        .filter_map(|word| ENGLISH.iter().position(|name| word.starts_with(name)))
+        .map(|day| day as u8)
+        .collect();
+    if let Some(start) = text.find('周').or_else(|| text.find("星期")) {
+        days.extend(
+            text[start..]
+                .chars()
+                .filter_map(|c| CHINESE.iter().position(|name| *name == c))
+                .map(|day| day as u8),
+        );
+    }
+    days.sort_unstable();
+    days.dedup();
+    days
+}
+
+/// Reads a recurrence described in words, as Todoist writes it
+/// (`every day`, `every mon, fri`, `每周一`).
+pub fn recurrence_from_text(text: &str, date: Option<Stamp>) -> Recurrence {
+    let lower = text.trim().to_lowercase();
+    let unsupported = || Recurrence::Unsupported(text.trim().to_string());
+    if lower.contains("other")
+        || lower.contains("month")
+        || lower.contains("year")
+        || lower.contains('月')
+        || lower.contains('年')
+        || lower
+            .split_whitespace()
+            .any(|word| word.chars().all(|c| c.is_ascii_digit()) && word != "1")
+    {
+        return unsupported();
+    }
+    if lower.contains("weekday") || lower.contains("workday") || lower.contains("工作日") {
+        return Recurrence::Days(WORKDAYS.to_vec());
+    }
+    if lower.contains("weekend") || lower.contains("周末") {
+        return Recurrence::Days(vec![0, 6]);
+    }
+    if lower.contains("every day") || lower.contains("daily") || lower.contains("每天") {
+        return Recurrence::Days(ALL_DAYS.to_vec());
+    }
+    let days = weekdays_in(&lower);
+    if !days.is_empty() {
+        return Recurrence::Days(days);
+    }
+    if lower.contains("week") || lower.contains('周') {
+        if let Some(date) = date {
+            return Recurrence::Days(vec![date.weekday()]);
+        }
+    }
+    unsupported()
+}
+
+/// Reads an iCalendar RRULE, as TickTick stores it.
+pub fn recurrence_from_rrule(rule: &str, date: Option<Stamp>) -> Recurrence {
+    let rule = rule.trim().trim_start_matches("RRULE:");
+    let parts: HashMap<String, String> = rule
+        .split(';')
+        .filter_map(|part| part.split_once('='))
+        .map(|(key, value)| (key.to_ascii_uppercase(), value.to_ascii_uppercase()))
+        .collect();
+    let interval = parts.get("INTERVAL").map(String::as_str).unwrap_or("1");
+    // Limited series and filters other than weekdays cannot be repeated faithfully.
+    let limited = parts.contains_key("COUNT") || parts.contains_key("UNTIL");
+    let filtered = parts
+        .keys()
+        .any(|key| key.starts_with("BY") && key != "BYDAY");
+    if interval != "1" || limited || filtered {
+        return Recurrence::Unsupported(rule.to_string());
+    }
+    match parts.get("FREQ").map(String::as_str) {
+        Some("DAILY") => Recurrence::Days(ALL_DAYS.to_vec()),
+        Some("WEEKLY") => {
+            const CODES: [&str; 7] = ["SU", "MO", "TU", "WE", "TH", "FR", "SA"];
+            let mut days: Vec<u8> = parts
+                .get("BYDAY")
+                .map(|days| {
+                    days.split(',')
+                        .filter_map(|code| CODES.iter().position(|c| code.ends_with(c)))
+                        .map(|day| day as u8)
+                        .collect()
+                })
+                .unwrap_or_default();
+            days.sort_unstable();
+            days.dedup();
+            if !days.is_empty() {
+                Recurrence::Days(days)
+            } else if let Some(date) = date {
+                Recurrence::Days(vec![date.weekday()])
+            } else {
+                Recurrence::Unsupported(rule.to_string())
+            }
+        }
+        _ => Recurrence::Unsupported(rule.to_string()),
+    }
+}
+
+/// Reads a period such as `1d`, `+1w`, `daily` or `weekdays`, as Taskwarrior
+/// and todo.txt write it.
+pub fn recurrence_from_period(period: &str, date: Option<Stamp>) -> Recurrence {
+    let normalized = period.trim().trim_start_matches('+').to_lowercase();
+    match normalized.as_str() {
+        "d" | "1d" | "day" | "1day" | "daily" => Recurrence::Days(ALL_DAYS.to_vec()),
+        "b" | "1b" | "weekdays" => Recurrence::Days(WORKDAYS.to_vec()),
+        "w" | "1w" | "wk" | "1wk" | "week" | "1week" | "weekly" | "7d" => match date {
+            Some(date) => Recurrence::Days(vec![date.weekday()]),
+            None => Recurrence::Unsupported(period.trim().to_string()),
+        },
+        _ => Recurrence::Unsupported(period.trim().to_string()),
+    }
+}
+
+/// A CSV export with columns looked up by header name, case-insensitively.
+pub struct CsvTable {
+    columns: HashMap<String, usize>,
+    pub rows: Vec<csv::StringRecord>,
+}
+
+impl CsvTable {
+    pub fn parse(content: &str) -> Result<Self, String> {
+        let mut reader = csv::ReaderBuilder::new()
+            .flexible(true)
+            .from_reader(content.as_bytes());
+        let columns = reader
+            .headers()
+            .map_err(|e| format!("CSV 表头无效: {}", e))?
+            .iter()
+            .enumerate()
+            .map(|(i, name)| (name.trim().to_uppercase(), i))
+            .collect();
+        let rows = reader
+            .records()
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("CSV 内容无效: {}", e))?;
+        Ok(CsvTable { columns, rows })
+    }
+
+    pub fn has(&self, column: &str) -> bool {
+        self.columns.contains_key(&column.to_uppercase())
+    }
+
+    pub fn get<'a>(&self, row: &'a csv::StringRecord, column: &str) -> &'a str {
+        self.columns
+            .get(&column.to_uppercase())
+            .and_then(|&i| row.get(i))
+            .unwrap_or_default()
+            .trim()
+    }
+}
+
+/// Moves tasks with a known parent into their top-level ancestor's subtasks.
+/// WorkPlan subtasks are flat, so deeper levels are flattened.
+fn fold_subtasks(tasks: Vec<ImportedTask>, context: &mut Context) -> Vec<ImportedTask> {
+    let index: HashMap<String, usize> = tasks
+        .iter()
+        .enumerate()
+        .filter_map(|(i, task)| Some((task.source_id.clone()?, i)))
+        .collect();
+    let root_of = |mut i: usize| {
+        // Bounded walk, so a cycle in the source cannot hang the import.
+        for _ in 0..tasks.len() {
+            match tasks[i]
+                .parent_id
+                .as_ref()
+                .and_then(|parent| index.get(parent))
+            {
+                Some(&parent) if parent != i => i = parent,
+                _ => break,
+            }
+        }
+        i
+    };
+    let roots: Vec<usize> = (0..tasks.len()).map(root_of).collect();
+
+    let mut children: HashMap<usize, Vec<(String, bool)>> = HashMap::new();
+    let mut kept = Vec::new();
+    for (i, task) in tasks.into_iter().enumerate() {
+        if roots[i] == i {
+            if let Some(parent) = task.parent_id.as_ref().filter(|p| !index.contains_key(*p)) {
+                context.warn(format!(
+                    "「{}」的上级任务 {} 不在导入文件中",
+                    task.title, parent
+                ));
+            }
+            kept.push((i, task));
+        } else {
+            let entry = children.entry(roots[i]).or_default();
+            entry.push((task.title.clone(), task.status == Status::Done));
+            entry.extend(task.subtasks);
+        }
+    }
+    kept.into_iter()
+        .map(|(i, mut task)| {
+            task.subtasks
+                .extend(children.remove(&i).unwrap_or_default());
+            task
+        })
+        .collect()
+}
+
+#[derive(Serialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportReport {
+    format: String,
+    tasks: usize,
+    scheduled: usize,
+    subtasks: usize,
+    completed: usize,
+    skipped: usize,
+    warnings: Vec<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportPreview {
+    report: ImportReport,
+    /// WorkPlan tasks and scheduled tasks, ready to be added as they are.
+    tasks: Vec<Value>,
+    scheduled_tasks: Vec<Value>,
+}
+
+/// Guesses the source from the content.
+fn detect_format(content: &str) -> &'static str {
+    let trimmed = content.trim_start_matches('\u{feff}').trim_start();
+    if trimmed.starts_with('[') || trimmed.starts_with('{') {
+        // `task export` can also write one object per line.
+        let value: Value = serde_json::from_str(trimmed)
+            .or_else(|_| serde_json::from_str(trimmed.lines().next().unwrap_or_default()))
+            .unwrap_or(Value::Null);
+        let sample = match &value {
+            Value::Array(items) => items.first().cloned().unwrap_or(Value::Null),
+            other => other.clone(),
+        };
+        if sample.get("uuid").is_some() && sample.get("description").is_some() {
+            return "taskwarrior";
+        }
+        let text = sample.to_string();
+        if ["importance", "dueDateTime", "displayName", "@odata"]
+            .iter()
+            .any(|key| text.contains(key))
+        {
+            return "microsoft-todo";
+        }
+        return "todoist";
+    }
+    let header = trimmed.lines().take(10).collect::<Vec<_>>().join("\n");
+    if header.contains("List Name") || header.contains("Is Check list") {
+        "ticktick"
+    } else if header.starts_with("TYPE,CONTENT") || header.contains("\"TYPE\",\"CONTENT\"") {
+        "todoist"
+    } else {
+        "todotxt"
+    }
+}
+
+fn new_id(seed: u64, n: usize) -> String {
+    format!("{}{:04}", seed, n)
+}
+
+fn subtask_values(subtasks: &[(String, bool)]) -> Vec<Value> {
+    subtasks
+        .iter()
+        .filter(|(title, _)| !title.is_empty())
+        .map(|(title, done)| json!({ "title": title, "status": if *done { "done" } else { "todo" } }))
+        .collect()
+}
+
+fn build_preview(format: &str, parsed: Vec<ImportedTask>, mut context: Context) -> ImportPreview {
+    let parsed = fold_subtasks(parsed, &mut context);
+    let seed = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0);
+    let mut report = ImportReport {
+        format: format.to_string(),
+        skipped: context.skipped,
+        ..Default::default()
+    };
+    let mut tasks = Vec::new();
+    let mut scheduled_tasks = Vec::new();
+    for (n, mut task) in parsed.into_iter().enumerate() {
+        if task.title.is_empty() {
+            report.skipped += 1;
+            continue;
+        }
+        for label in &task.labels {
+            let tag = format!(
+                "#{}",
+                label.split_whitespace().collect::<Vec<_>>().join("_")
+            );
+            if !task.note.contains(&tag) {
+                if !task.note.is_empty() {
+                    task.note.push_str("\n\n");
+                }
+                task.note.push_str(&tag);
+            }
+        }
+        let subtasks = subtask_values(&task.subtasks);
+        report.subtasks += subtasks.len();
+        match task.recurrence.take() {
+            // A finished series has nothing left to schedule.
+            Some(Recurrence::Days(days)) if task.status != Status::Done => {
+                report.scheduled += 1;
+                scheduled_tasks.push(json!({
+                    "id": new_id(seed, n),
+                    "title": task.title,
+                    "status": "todo",
+                    "priority": task.priority,
+                    "date": "",
+                    "deadline": "",
+                    "note": task.note,
+                    "repeatDays": days,
+                    "enabled": true,
+                    "subtasks": subtasks,
+                }));
+                continue;
+            }
+            Some(Recurrence::Unsupported(rule)) => {
+                context.warn(format!(
+                    "「{}」的重复规则无法转换，已作为普通任务导入: {}",
+                    task.title, rule
+                ));
+                if !task.note.is_empty() {
+                    task.note.push_str("\n\n");
+                }
+                task.note.push_str(&format!("重复: {}", rule));
+            }
+            _ => {}
+        }
+        if task.status == Status::Done {
+            report.completed += 1;
+        }
+        report.tasks += 1;
+        tasks.push(json!({
+            "id": new_id(seed, n),
+            "title": task.title,
+            "status": match task.status {
+                Status::Todo => "todo",
+                Status::Doing => "doing",
+                Status::Done => "done",
+            },
+            "priority": task.priority,
+            "date": task.date.map(|date| date.format("09:00")).unwrap_or_default(),
+            "deadline": task.deadline.map(|date| date.format("23:59")).unwrap_or_default(),
+            "note": task.note,
+            "subtasks": subtasks,
+            "expanded": false,
+        }));
+    }
+    let total = context.warnings.len();
+    context.warnings.truncate(MAX_WARNINGS);
+    if total > MAX_WARNINGS {
+        context
+            .warnings
+            .push(format!("另有 {} 条提示未显示", total - MAX_WARNINGS));
+    }
+    report.warnings = context.warnings;
+    ImportPreview {
+        report,
+        tasks,
+        scheduled_tasks,
+    }
+}
+
+/// Parses an export from another task app into WorkPlan tasks without adding
+/// them. `format` is `todoist`, `microsoft-todo`, `ticktick`, `taskwarrior`,
+/// `todotxt` or `auto`; `utc_offset_minutes` is the local offset (east positive).
+#[tauri::command]
+pub fn preview_task_import(
+    content: String,
+    format: Option<String>,
+    utc_offset_minutes: i64,
+) -> Result<ImportPreview, String> {
+    let content = content.trim_start_matches('\u{feff}');
+    if content.trim().is_empty() {
+        return Err("导入文件为空".to_string());
+    }
+    let format = match format.as_deref() {
+        None | Some("") | Some("auto") => detect_format(content),
+        Some("todoist") => "todoist",
+        Some("microsoft-todo") => "microsoft-todo",
+        Some("ticktick") => "ticktick",
+        Some("taskwarrior") => "taskwarrior",
+        Some("todotxt") => "todotxt",
+        Some(other) => return Err(format!("不支持的导入格式: {}", other)),
+    };
+    let mut context = Context {
+        utc_offset_minutes: utc_offset_minutes.clamp(-DAY_MINUTES, DAY_MINUTES),
+        warnings: Vec::new(),
+        skipped: 0,
+    };
+    let parsed = match format {
+        "todoist" => todoist::parse(content, &mut context)?,
+        "microsoft-todo" => microsoft_todo::parse(content, &mut context)?,
+        "ticktick" => ticktick::parse(content, &mut context)?,
+        "taskwarrior" => taskwarrior::parse(content, &mut context)?,
+        _ => todotxt::parse(content, &mut context)?,
+    };
+    if parsed.is_empty() {
+        return Err("文件中没有找到可导入的任务".to_string());
+    }
+    Ok(build_preview(format, parsed, context))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    macro_rules! fixture {
+        ($name:literal) => {
+            include_str!(concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/tests/fixtures/importers/",
+                $name
+            ))
+        };
+    }
+
+    /// UTC+8, so zoned timestamps visibly move.
+    const OFFSET: i64 = 8 * 60;
+
+    fn context(utc_offset_minutes: i64) -> Context {
+        Context {
+            utc_offset_minutes,
+            warnings: Vec::new(),
+            skipped: 0,
+        }
+    }
+
+    fn stamp(value: &str) -> Option<String> {
+        context(OFFSET)
+            .parse_stamp(value)
+            .map(|stamp| stamp.format("--:--"))
+    }
+
+    fn days(recurrence: Recurrence) -> Option<Vec<u8>> {
+        match recurrence {
+            Recurrence::Days(days) => Some(days),
+            Recurrence::Unsupported(_) => None,
+        }
+    }
+
+    fn preview(content: &str) -> ImportPreview {
+        preview_task_import(content.to_string(), None, OFFSET).unwrap()
+    }
+
+    fn find<'a>(tasks: &'a [Value], title: &str) -> &'a Value {
+        tasks
+            .iter()
+            .find(|task| task["title"] == title)
+            .unwrap_or_else(|| panic!("{} missing from {:?}", title, tasks))
+    }
+
+    fn subtasks(task: &Value) -> Vec<(&str, &str)> {
+        task["subtasks"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|step| {
+                (
+                    step["title"].as_str().unwrap(),
+                    step["status"].as_str().unwrap(),
+                )
+            })
+            .collect()
+    }
+
+    fn has_warning(preview: &ImportPreview, needle: &str) -> bool {
+        preview
+            .report
+            .warnings
+            .iter()
+            .any(|warning| warning.contains(needle))
+    }
+
+    #[test]
+    fn stamps_convert_zones_and_compact_forms() {
+        assert_eq!(stamp("2024-05-12").as_deref(), Some("2024-05-12T--:--"));
+        assert_eq!(stamp("2024/05/12 14:05").as_deref(), Some("2024-05-12T14:05"));
+        assert_eq!(stamp("20240512").as_deref(), Some("2024-05-12T--:--"));
+        assert_eq!(stamp("20240512T013000Z").as_deref(), Some("2024-05-12T09:30"));
+        assert_eq!(
+            stamp("2024-05-12T23:30:00-02:00").as_deref(),
+            Some("2024-05-13T09:30")
+        );
+        assert_eq!(
+            stamp("2024-05-12T10:00:00+0530").as_deref(),
+            Some("2024-05-12T12:30")
+        );
+        assert_eq!(
+            stamp("2024-05-12T00:00:00.0000000").as_deref(),
+            Some("2024-05-12T00:00")
+        );
+        assert_eq!(
+            context(-5 * 60)
+                .parse_stamp("2024-01-01T02:00:00Z")
+                .map(|stamp| stamp.format("")),
+            Some("2023-12-31T21:00".to_string())
+        );
+
+        for invalid in ["2024-13-01", "2024-05-12T25:00", "tomorrow", "+1234567", ""] {
+            assert_eq!(stamp(invalid), None, "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn non_ascii_dates_are_rejected_without_panicking() {
+        for value in ["2024-5-12日", "2024-05-1日", "2024年5月12日", "2024-05-12日", "日日日日日"] {
+            assert_eq!(stamp(value), None, "{}", value);
+        }
+    }
+
+    #[test]
+    fn rrules_map_onto_repeat_days() {
+        let sunday = context(0).parse_stamp("2024-05-12");
+        assert_eq!(
+            days(recurrence_from_rrule("RRULE:FREQ=WEEKLY;BYDAY=MO,WE", None)),
+            Some(vec![1, 3])
+        );
+        assert_eq!(
+            days(recurrence_from_rrule("FREQ=DAILY;INTERVAL=1", None)),
+            Some(ALL_DAYS.to_vec())
+        );
+        assert_eq!(days(recurrence_from_rrule("FREQ=WEEKLY", sunday)), Some(vec![0]));
+        for rule in [
+            "FREQ=WEEKLY",
+            "FREQ=WEEKLY;INTERVAL=2;BYDAY=MO",
+            "FREQ=DAILY;COUNT=5",
+            "FREQ=DAILY;UNTIL=20240601T000000Z",
+            "FREQ=MONTHLY;BYMONTHDAY=1",
+            "FREQ=WEEKLY;BYDAY=MO;BYHOUR=9",
+        ] {
+            assert!(days(recurrence_from_rrule(rule, None)).is_none(), "{}", rule);
+        }
+    }
+
+    #[test]
+    fn recurrence_text_maps_onto_repeat_days() {
+        let sunday = context(0).parse_stamp("2024-05-12");
+        let cases: [(&str, Option<Vec<u8>>); 9] = [
+            ("every day", Some(ALL_DAYS.to_vec())),
+            ("every weekday", Some(WORKDAYS.to_vec())),
+            ("every weekend", Some(vec![0, 6])),
+            ("every mon, fri", Some(vec![1, 5])),
+            ("每周一、三", Some(vec![1, 3])),
+            ("every week", Some(vec![0])),
+            ("every 2 weeks", None),
+            ("every other day", None),
+            ("every month", None),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(days(recurrence_from_text(text, sunday)), expected, "{}", text);
+        }
+        assert!(days(recurrence_from_text("every week", None)).is_none());
+    }
+
+    #[test]
+    fn fold_subtasks_survives_cycles_and_missing_parents() {
+        let task = |id: &str, parent: Option<&str>| {
+            let mut task = ImportedTask::new(id);
+            task.source_id = Some(id.to_string());
+            task.parent_id = parent.map(str::to_string);
+            task
+        };
+        let mut context = context(0);
+        let folded = fold_subtasks(
+            vec![
+                task("a", Some("b")),
+                task("b", Some("a")),
+                task("self", Some("self")),
+                task("orphan", Some("missing")),
+                task("child", Some("orphan")),
+                task("grandchild", Some("child")),
+            ],
+            &mut context,
+        );
+
+        let titles: Vec<&str> = folded.iter().map(|task| task.title.as_str()).collect();
+        assert_eq!(titles, ["a", "b", "self", "orphan"]);
+        let orphan = &folded[3];
+        assert_eq!(
+            orphan.subtasks,
+            [("child".to_string(), false), ("grandchild".to_string(), false)]
+        );
+        assert!(context.warnings.iter().any(|w| w.contains("missing")));
+    }
+
+    #[test]
+    fn todoist_csv_nests_by_indent() {
+        let preview = preview(fixture!("todoist.csv"));
+        assert_eq!(preview.report.format, "todoist");
+
+        let report = find(&preview.tasks, "Write report");
+        assert_eq!(report["priority"], "critical");
+        assert_eq!(report["date"], "2024-05-12T09:00");
+        assert_eq!(report["deadline"], "2024-05-15T23:59");
+        assert_eq!(report["note"], "Draft the Q2 summary\n\nRemember the appendix");
+        assert_eq!(
+            subtasks(report),
+            [("Collect numbers", "todo"), ("Check charts", "todo")]
+        );
+
+        let passport = find(&preview.tasks, "Renew passport");
+        assert_eq!(passport["priority"], "urgent");
+        assert_eq!(passport["date"], "");
+        assert_eq!(passport["note"], "#Home");
+        assert!(has_warning(&preview, "2024-5-12日"));
+
+        let plants = find(&preview.scheduled_tasks, "Water plants");
+        assert_eq!(plants["repeatDays"], json!([1, 5]));
+        assert_eq!(preview.tasks.len(), 2);
+    }
+
+    #[test]
+    fn todoist_backup_resolves_projects_and_parents() {
+        let preview = preview(fixture!("todoist.json"));
+        assert_eq!(preview.report.format, "todoist");
+        assert_eq!(preview.report.skipped, 1);
+
+        let sprint = find(&preview.tasks, "Plan sprint");
+        assert_eq!(sprint["priority"], "critical");
+        assert_eq!(sprint["date"], "2024-05-12T09:30");
+        assert_eq!(sprint["note"], "#Work\n\n#focus");
+        assert_eq!(subtasks(sprint), [("Draft goals", "done")]);
+
+        let standup = find(&preview.scheduled_tasks, "Standup");
+        assert_eq!(standup["repeatDays"], json!(WORKDAYS));
+
+        let review = find(&preview.tasks, "Monthly review");
+        assert_eq!(review["note"], "重复: every month");
+        assert!(has_warning(&preview, "every month"));
+    }
+
+    #[test]
+    fn ticktick_backup_reads_checklists_and_all_day_dates() {
+        let preview = preview(fixture!("ticktick.csv"));
+        assert_eq!(preview.report.format, "ticktick");
+        assert_eq!(preview.report.skipped, 1);
+
+        let release = find(&preview.tasks, "Ship release");
+        assert_eq!(release["priority"], "critical");
+        assert_eq!(release["date"], "2024-05-12T09:00");
+        assert_eq!(release["deadline"], "2024-05-14T18:00");
+        assert_eq!(release["note"], "Final checks\n\n#Work\n\n#Inbox\n\n#release");
+
+        // All-day dates are stored as local midnight in UTC.
+        let packing = find(&preview.tasks, "Packing");
+        assert_eq!(packing["priority"], "urgent");
+        assert_eq!(packing["date"], "2024-05-21T09:00");
+        assert_eq!(packing["note"], "Bring:\n\n#Travel");
+        assert_eq!(
+            subtasks(packing),
+            [("Passport", "todo"), ("Tickets", "done"), ("Book hotel", "done")]
+        );
+
+        let gym = find(&preview.scheduled_tasks, "Gym");
+        assert_eq!(gym["repeatDays"], json!([1, 4]));
+    }
+
+    #[test]
+    fn todotxt_reads_priorities_dates_and_recurrence() {
+        let preview = preview(fixture!("todo.txt"));
+        assert_eq!(preview.report.format, "todotxt");
+        assert_eq!(preview.report.skipped, 1);
+
+        let bank = find(&preview.tasks, "Call bank");
+        assert_eq!(bank["priority"], "critical");
+        assert_eq!(bank["date"], "2024-05-03T09:00");
+        assert_eq!(bank["deadline"], "2024-05-03T23:59");
+        assert_eq!(bank["note"], "#Finance\n\n#phone");
+
+        assert_eq!(find(&preview.tasks, "Pay rent")["status"], "done");
+        assert_eq!(find(&preview.tasks, "Renew passport")["date"], "");
+        assert!(has_warning(&preview, "2024-5-12日"));
+
+        let plants = find(&preview.scheduled_tasks, "Water plants");
+        assert_eq!(plants["priority"], "urgent");
+        assert_eq!(plants["repeatDays"], json!(ALL_DAYS));
+    }
+
+    #[test]
+    fn taskwarrior_export_skips_instances_of_recurring_templates() {
+        let preview = preview(fixture!("taskwarrior.json"));
+        assert_eq!(preview.report.format, "taskwarrior");
+        assert_eq!(preview.report.skipped, 1);
+
+        let tests = find(&preview.tasks, "Write tests");
+        assert_eq!(tests["priority"], "critical");
+        assert_eq!(tests["date"], "2024-05-12T17:30");
+        assert_eq!(tests["note"], "cover importers\n\n#dev\n\n#rust");
+
+        let review = find(&preview.tasks, "Review PR");
+        assert_eq!(review["status"], "doing");
+        assert_eq!(review["date"], "2024-05-11T08:00");
+
+        assert_eq!(preview.scheduled_tasks.len(), 1);
+        let sync = find(&preview.scheduled_tasks, "Weekly sync");
+        assert_eq!(sync["repeatDays"], json!([1]));
+        assert!(preview.tasks.iter().all(|task| task["title"] != "Weekly sync"));
+    }
+
+    #[test]
+    fn microsoft_todo_reads_lists_steps_and_patterns() {
+        let preview = preview(fixture!("microsoft_todo.json"));
+        assert_eq!(preview.report.format, "microsoft-todo");
+
+        let milk = find(&preview.tasks, "Buy milk");
+        assert_eq!(milk["priority"], "urgent");
+        assert_eq!(milk["date"], "2024-05-12T09:00");
+        assert_eq!(milk["note"], "2 & skim\n\n#Groceries\n\n#Errand");
+        assert_eq!(subtasks(milk), [("Check fridge", "done")]);
+
+        let trash = find(&preview.scheduled_tasks, "Take out trash");
+        assert_eq!(trash["repeatDays"], json!([1, 4]));
+
+        let bills = find(&preview.tasks, "Pay bills");
+        assert_eq!(bills["status"], "done");
+        assert!(bills["note"]
+            .as_str()
+            .unwrap()
+            .ends_with("重复: absoluteMonthly / 1"));
+    }
+}
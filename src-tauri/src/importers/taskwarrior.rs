@@ -0,0 +1,89 @@
+use serde_json::Value;
+use std::collections::HashSet;
+
+use super::{recurrence_from_period, Context, ImportedTask, Status};
+
+/// `task export` output: a JSON array, or one JSON object per line.
+pub fn parse(content: &str, context: &mut Context) -> Result<Vec<ImportedTask>, String> {
+    let items: Vec<Value> = if content.trim_start().starts_with('[') {
+        serde_json::from_str(content).map_err(|e| format!("Taskwarrior JSON 无效: {}", e))?
+    } else {
+        content
+            .lines()
+            .map(|line| line.trim().trim_end_matches(','))
+            .filter(|line| !line.is_empty())
+            .map(serde_json::from_str)
+            .collect::<Result<_, _>>()
+            .map_err(|e| format!("Taskwarrior JSON 无效: {}", e))?
+    };
+    let text = |item: &Value, key: &str| {
+        item.get(key)
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_string()
+    };
+    // Instances of a recurring task are regenerated from its template.
+    let templates: HashSet<String> = items
+        .iter()
+        .filter(|item| text(item, "status") == "recurring")
+        .map(|item| text(item, "uuid"))
+        .collect();
+
+    let mut deleted = 0;
+    let mut tasks = Vec::new();
+    for item in &items {
+        let status = text(item, "status");
+        if status == "deleted" {
+            deleted += 1;
+            continue;
+        }
+        if templates.contains(&text(item, "parent")) {
+            continue;
+        }
+        let mut task = ImportedTask::new(&text(item, "description"));
+        task.priority = match text(item, "priority").as_str() {
+            "H" => "critical",
+            "M" => "urgent",
+            _ => "normal",
+        };
+        task.status = if status == "completed" {
+            Status::Done
+        } else if item.get("start").is_some() {
+            Status::Doing
+        } else {
+            Status::Todo
+        };
+        let due = context.stamp_or_warn(&text(item, "due"), &task.title);
+        let scheduled = context.stamp_or_warn(&text(item, "scheduled"), &task.title);
+        task.date = scheduled.or(due);
+        task.deadline = due;
+        if status == "recurring" {
+            task.recurrence = Some(recurrence_from_period(&text(item, "recur"), due));
+        }
+        task.note = item
+            .get("annotations")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(|annotation| annotation.get("description")?.as_str())
+            .collect::<Vec<_>>()
+            .join("\n");
+        let project = text(item, "project");
+        if !project.is_empty() {
+            task.labels.push(project);
+        }
+        task.labels.extend(
+            item.get("tags")
+                .and_then(Value::as_array)
+                .into_iter()
+                .flatten()
+                .filter_map(|tag| tag.as_str().map(str::to_string)),
+        );
+        tasks.push(task);
+    }
+    if deleted > 0 {
+        context.skipped += deleted;
+        context.warn(format!("跳过了 {} 个已删除的任务", deleted));
+    }
+    Ok(tasks)
+}
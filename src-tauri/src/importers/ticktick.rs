@@ -0,0 +1,93 @@
+use super::{recurrence_from_rrule, Context, CsvTable, ImportedTask, Status};
+
+/// A TickTick backup CSV. The export starts with a few lines of metadata
+/// before the header row.
+pub fn parse(content: &str, context: &mut Context) -> Result<Vec<ImportedTask>, String> {
+    let start = content
+        .lines()
+        .position(|line| line.contains("Title") && line.contains("List Name"))
+        .ok_or_else(|| "不是有效的滴答清单 CSV 文件（缺少表头）".to_string())?;
+    let body = content.lines().skip(start).collect::<Vec<_>>().join("\n");
+    let table = CsvTable::parse(&body)?;
+
+    let mut notes = 0;
+    let mut tasks = Vec::new();
+    for row in &table.rows {
+        let title = table.get(row, "Title");
+        if title.is_empty() {
+            continue;
+        }
+        if table.get(row, "Kind").eq_ignore_ascii_case("NOTE") {
+            notes += 1;
+            continue;
+        }
+        let mut task = ImportedTask::new(title);
+        let content = table.get(row, "Content");
+        if table.get(row, "Is Check list").eq_ignore_ascii_case("Y") {
+            let mut note = Vec::new();
+            for line in content
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty())
+            {
+                if let Some(item) = line.strip_prefix('▫') {
+                    task.subtasks.push((item.trim().to_string(), false));
+                } else if let Some(item) = line.strip_prefix('▪') {
+                    task.subtasks.push((item.trim().to_string(), true));
+                } else {
+                    note.push(line);
+                }
+            }
+            task.note = note.join("\n");
+        } else {
+            task.note = content.to_string();
+        }
+        task.priority = match table.get(row, "Priority") {
+            "5" => "critical",
+            "3" => "urgent",
+            _ => "normal",
+        };
+        // 0 is open; completed and archived tasks both count as done.
+        if !matches!(table.get(row, "Status"), "" | "0") {
+            task.status = Status::Done;
+        }
+
+        let all_day = table.get(row, "Is All Day").eq_ignore_ascii_case("true");
+        let mut stamp = |column: &str| {
+            context
+                .stamp_or_warn(table.get(row, column), title)
+                .map(|stamp| if all_day { stamp.date_only() } else { stamp })
+        };
+        let start_date = stamp("Start Date");
+        let due = stamp("Due Date");
+        task.date = start_date.or(due);
+        task.deadline = due.filter(|due| Some(*due) != task.date);
+        let repeat = table.get(row, "Repeat");
+        if !repeat.is_empty() {
+            task.recurrence = Some(recurrence_from_rrule(repeat, task.date));
+        }
+
+        for column in ["Folder Name", "List Name"] {
+            let name = table.get(row, column);
+            if !name.is_empty() {
+                task.labels.push(name.to_string());
+            }
+        }
+        task.labels.extend(
+            table
+                .get(row, "Tags")
+                .split(',')
+                .map(str::trim)
+                .filter(|tag| !tag.is_empty())
+                .map(str::to_string),
+        );
+        task.source_id = Some(table.get(row, "taskId").to_string()).filter(|id| !id.is_empty());
+        task.parent_id = Some(table.get(row, "parentId").to_string()).filter(|id| !id.is_empty());
+        tasks.push(task);
+    }
+    if notes > 0 {
+        context.skipped += notes;
+        context.warn(format!("跳过了 {} 条笔记类型的条目", notes));
+    }
+    Ok(tasks)
+}
@@ -0,0 +1,187 @@
+use serde_json::Value;
+use std::collections::HashMap;
+
+use super::{recurrence_from_text, Context, CsvTable, ImportedTask, Status};
+
+/// Todoist counts priorities from p4 (lowest) in its API and backups but lists
+/// them from p1 (highest) in CSV templates.
+fn priority(level: i64, from_highest: bool) -> &'static str {
+    match (level, from_highest) {
+        (1, true) | (4, false) => "critical",
+        (2, true) | (3, false) => "urgent",
+        _ => "normal",
+    }
+}
+
+pub fn parse(content: &str, context: &mut Context) -> Result<Vec<ImportedTask>, String> {
+    let trimmed = content.trim_start();
+    if trimmed.starts_with('[') || trimmed.starts_with('{') {
+        parse_json(trimmed, context)
+    } else {
+        parse_csv(content, context)
+    }
+}
+
+/// A CSV template: `TYPE` rows of `task`, `section` or `note`, with `INDENT`
+/// nesting subtasks under the task above.
+fn parse_csv(content: &str, context: &mut Context) -> Result<Vec<ImportedTask>, String> {
+    let table = CsvTable::parse(content)?;
+    if !table.has("CONTENT") {
+        return Err("不是有效的 Todoist CSV 文件（缺少 CONTENT 列）".to_string());
+    }
+    let mut tasks: Vec<ImportedTask> = Vec::new();
+    let mut section = String::new();
+    // (indent, source id) of the open ancestors.
+    let mut ancestors: Vec<(usize, String)> = Vec::new();
+    for (row_index, row) in table.rows.iter().enumerate() {
+        let text = table.get(row, "CONTENT");
+        match table.get(row, "TYPE").to_lowercase().as_str() {
+            "section" => {
+                section = text.to_string();
+                ancestors.clear();
+                continue;
+            }
+            "note" => {
+                if let Some(task) = tasks.last_mut() {
+                    if !task.note.is_empty() {
+                        task.note.push_str("\n\n");
+                    }
+                    task.note.push_str(text);
+                }
+                continue;
+            }
+            "task" | "" if !text.is_empty() => {}
+            _ => continue,
+        }
+
+        let mut task = ImportedTask::new(text);
+        task.note = table.get(row, "DESCRIPTION").to_string();
+        task.priority = priority(table.get(row, "PRIORITY").parse().unwrap_or(4), true);
+        let date = table.get(row, "DATE");
+        let lower = date.to_lowercase();
+        if lower.starts_with("every") || date.starts_with('每') {
+            task.recurrence = Some(recurrence_from_text(date, None));
+        } else {
+            task.date = context.stamp_or_warn(date, &task.title);
+        }
+        task.deadline = context.stamp_or_warn(table.get(row, "DEADLINE"), &task.title);
+        if !section.is_empty() {
+            task.labels.push(section.clone());
+        }
+
+        let indent: usize = table.get(row, "INDENT").parse().unwrap_or(1);
+        while ancestors.last().is_some_and(|(level, _)| *level >= indent) {
+            ancestors.pop();
+        }
+        task.parent_id = ancestors.last().map(|(_, id)| id.clone());
+        let id = format!("row-{}", row_index);
+        task.source_id = Some(id.clone());
+        ancestors.push((indent, id));
+        tasks.push(task);
+    }
+    Ok(tasks)
+}
+
+fn id_string(value: Option<&Value>) -> Option<String> {
+    match value? {
+        Value::String(id) if !id.is_empty() => Some(id.clone()),
+        Value::Number(id) => Some(id.to_string()),
+        _ => None,
+    }
+}
+
+/// A REST task list, or a backup or sync payload with `items` (or `tasks`)
+/// next to `projects` and `sections`.
+fn parse_json(content: &str, context: &mut Context) -> Result<Vec<ImportedTask>, String> {
+    let value: Value =
+        serde_json::from_str(content).map_err(|e| format!("Todoist JSON 无效: {}", e))?;
+    let names = |key: &str| -> HashMap<String, String> {
+        value
+            .get(key)
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(|item| {
+                Some((
+                    id_string(item.get("id"))?,
+                    item.get("name")?.as_str()?.to_string(),
+                ))
+            })
+            .collect()
+    };
+    let projects = names("projects");
+    let sections = names("sections");
+    let items = match &value {
+        Value::Array(items) => items,
+        object => object
+            .get("items")
+            .or_else(|| object.get("tasks"))
+            .and_then(Value::as_array)
+            .ok_or_else(|| "Todoist JSON 中没有任务列表".to_string())?,
+    };
+
+    let mut tasks = Vec::new();
+    for item in items {
+        let text = |key: &str| item.get(key).and_then(Value::as_str).unwrap_or_default();
+        let flag = |key: &str| item.get(key).and_then(Value::as_bool).unwrap_or(false);
+        let title = text("content");
+        if title.trim().is_empty() {
+            continue;
+        }
+        if flag("is_deleted") {
+            context.skipped += 1;
+            continue;
+        }
+        let mut task = ImportedTask::new(title);
+        task.note = text("description").to_string();
+        task.priority = priority(
+            item.get("priority").and_then(Value::as_i64).unwrap_or(1),
+            false,
+        );
+        if flag("checked") || flag("is_completed") || flag("completed") {
+            task.status = Status::Done;
+        }
+        if let Some(due) = item.get("due").filter(|due| due.is_object()) {
+            let when = due
+                .get("datetime")
+                .or_else(|| due.get("date"))
+                .and_then(Value::as_str)
+                .unwrap_or_default();
+            task.date = context.stamp_or_warn(when, &task.title);
+            if due
+                .get("is_recurring")
+                .and_then(Value::as_bool)
+                .unwrap_or(false)
+            {
+                let rule = due
+                    .get("string")
+                    .and_then(Value::as_str)
+                    .unwrap_or_default();
+                task.recurrence = Some(recurrence_from_text(rule, task.date));
+            }
+        }
+        if let Some(deadline) = item
+            .get("deadline")
+            .and_then(|deadline| deadline.get("date"))
+            .and_then(Value::as_str)
+        {
+            task.deadline = context.stamp_or_warn(deadline, &task.title);
+        }
+        for (key, names) in [("project_id", &projects), ("section_id", &sections)] {
+            if let Some(name) = id_string(item.get(key)).and_then(|id| names.get(&id)) {
+                task.labels.push(name.clone());
+            }
+        }
+        task.labels.extend(
+            item.get("labels")
+                .and_then(Value::as_array)
+                .into_iter()
+                .flatten()
+                .filter_map(|label| label.as_str().map(str::to_string)),
+        );
+        task.source_id = id_string(item.get("id"));
+        task.parent_id = id_string(item.get("parent_id"));
+        tasks.push(task);
+    }
+    Ok(tasks)
+}
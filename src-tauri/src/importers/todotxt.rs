@@ -0,0 +1,76 @@
+use super::{recurrence_from_period, Context, ImportedTask, Status};
+
+fn is_date(word: &str) -> bool {
+    word.len() == 10 && word.as_bytes()[4] == b'-' && word.as_bytes()[7] == b'-'
+}
+
+/// One task per line in the todo.txt format:
+/// `x (A) 2024-05-01 Title +project @context due:2024-05-03`.
+pub fn parse(content: &str, context: &mut Context) -> Result<Vec<ImportedTask>, String> {
+    let mut tasks = Vec::new();
+    for line in content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+    {
+        let mut words = line.split_whitespace().peekable();
+        let mut done = false;
+        if words.peek() == Some(&"x") {
+            done = true;
+            words.next();
+        }
+        let mut priority = "normal";
+        if let Some(word) = words.peek() {
+            let bytes = word.as_bytes();
+            if bytes.len() == 3
+                && bytes[0] == b'('
+                && bytes[2] == b')'
+                && bytes[1].is_ascii_uppercase()
+            {
+                priority = match bytes[1] {
+                    b'A' => "critical",
+                    b'B' => "urgent",
+                    _ => "normal",
+                };
+                words.next();
+            }
+        }
+        // Completion and creation dates precede the description.
+        while words.peek().is_some_and(|word| is_date(word)) {
+            words.next();
+        }
+
+        let mut title = Vec::new();
+        let mut labels = Vec::new();
+        let (mut due, mut threshold, mut rec) = (None, None, None);
+        for word in words {
+            match word.split_once(':') {
+                Some(("due", value)) => due = Some(value),
+                Some(("t", value)) => threshold = Some(value),
+                Some(("rec", value)) => rec = Some(value),
+                Some(("h", _)) => {}
+                _ if word.len() > 1 && (word.starts_with('+') || word.starts_with('@')) => {
+                    labels.push(word[1..].to_string())
+                }
+                _ => title.push(word),
+            }
+        }
+        let mut task = ImportedTask::new(&title.join(" "));
+        if task.title.is_empty() {
+            context.skip(format!("跳过了没有标题的行: {}", line));
+            continue;
+        }
+        task.priority = priority;
+        if done {
+            task.status = Status::Done;
+        }
+        let due = due.and_then(|value| context.stamp_or_warn(value, &task.title));
+        let threshold = threshold.and_then(|value| context.stamp_or_warn(value, &task.title));
+        task.date = threshold.or(due);
+        task.deadline = due;
+        task.recurrence = rec.map(|period| recurrence_from_period(period, task.date));
+        task.labels = labels;
+        tasks.push(task);
+    }
+    Ok(tasks)
+}
@@ -6,17 +6,34 @@ use tauri::{
 };
 #[cfg(any(target_os = "android", target_os = "ios"))]
 use tauri::Manager;
-use scraper::{Html, Selector};
 use serde::Serialize;
 use std::fs;
 use std::path::PathBuf;
 use std::sync::atomic::{AtomicBool, Ordering};
 use tauri_plugin_opener::OpenerExt;
-use url::Url;
 use walkdir::WalkDir;
 
 #[cfg(not(any(target_os = "android", target_os = "ios")))]
 mod autostart;
+mod backup;
+mod crawler;
+mod data_archive;
+mod downloads;
+mod http_cache;
+mod importers;
+mod network;
+mod notes_mirror;
+mod search_index;
+mod semantic_index;
+mod sync;
+mod task_report;
+#[cfg(test)]
+mod test_support;
+mod time_tracking;
+mod watcher;
+mod web_archive;
+mod web_content;
+mod web_search;
 
 static CLOSE_TO_QUIT: AtomicBool = AtomicBool::new(false);
 
@@ -46,19 +63,58 @@ struct LocalFileMutationResult {
     size: usize,
 }
 
-#[derive(Serialize)]
-#[serde(rename_all = "camelCase")]
-struct WebSearchEntry {
-    title: String,
-    url: String,
-    snippet: String,
-    source: String,
-}
-
 fn current_workspace_root() -> Result<PathBuf, String> {
     std::env::current_dir().map_err(|e| e.to_string())
 }
 
+fn app_data_file(app: &tauri::AppHandle, name: &str) -> Result<PathBuf, String> {
+    let data_dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+    fs::create_dir_all(&data_dir).map_err(|e| format!("创建数据目录失败: {}", e))?;
+    Ok(data_dir.join(name))
+}
+
+/// Splits a Unix timestamp in milliseconds into UTC (year, month, day, hour, minute,
+/// second) using the civil-from-days algorithm; a date crate is not worth it here.
+fn utc_date_time(millis: u64) -> (i64, i64, i64, u64, u64, u64) {
+    let secs = millis / 1000;
+    let days = (secs / 86_400) as i64;
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (
+        year,
+        month,
+        day,
+        (secs % 86_400) / 3600,
+        (secs % 3600) / 60,
+        secs % 60,
+    )
+}
+
+/// Days since 1970-01-01 for the `YYYY-MM-DD` prefix of `date`; the inverse of
+/// the date part of `utc_date_time`.
+fn date_days(date: &str) -> Option<i64> {
+    let mut parts = date.get(..10)?.split('-');
+    let year: i64 = parts.next()?.parse().ok()?;
+    let month: i64 = parts.next()?.parse().ok()?;
+    let day: i64 = parts.next()?.parse().ok()?;
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let doy = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    Some(era * 146_097 + doe - 719_468)
+}
+
 fn normalize_pathbuf(path: PathBuf) -> Result<PathBuf, String> {
     let absolute = if path.is_absolute() {
         path
@@ -116,36 +172,6 @@ fn ensure_mutation_allowed(path: &str, trusted_dirs: &[String]) -> Result<PathBu
     ))
 }
 
-fn extract_duckduckgo_url(raw_url: &str) -> String {
-    let prefixed = if raw_url.starts_with("http://") || raw_url.starts_with("https://") {
-        raw_url.to_string()
-    } else if raw_url.starts_with('/') {
-        format!("https://html.duckduckgo.com{}", raw_url)
-    } else {
-        raw_url.to_string()
-    };
-
-    if let Ok(parsed) = Url::parse(&prefixed) {
-        if let Some(decoded) = parsed
-            .query_pairs()
-            .find_map(|(key, value)| (key == "uddg").then(|| value.to_string()))
-        {
-            if let Ok(decoded_url) = Url::parse(&decoded) {
-                if matches!(decoded_url.scheme(), "http" | "https") {
-                    return decoded;
-                }
-            }
-            return String::new();
-        }
-
-        if matches!(parsed.scheme(), "http" | "https") {
-            return prefixed;
-        }
-    }
-
-    String::new()
-}
-
 fn is_newer_version(current: &str, latest: &str) -> bool {
     let parse_version = |v: &str| -> Vec<u32> {
         v.trim_start_matches('v')
@@ -169,11 +195,13 @@ fn is_newer_version(current: &str, latest: &str) -> bool {
 }
 
 #[tauri::command]
-async fn check_update() -> Result<serde_json::Value, String> {
-    let client = reqwest::Client::new();
+async fn check_update(
+    app: tauri::AppHandle,
+    network: tauri::State<'_, network::NetworkState>,
+) -> Result<serde_json::Value, String> {
+    let client = network.client(&app)?;
     let response = client
         .get("https://api.github.com/repos/MakotoArai-CN/WorkPlan-with-AI/releases/latest")
-        .header("User-Agent", "WorkPlan")
         .send()
         .await
         .map_err(|e| e.to_string())?;
@@ -358,201 +386,74 @@ fn delete_local_file(
     })
 }
 
-#[tauri::command]
-async fn search_web(
-    query: String,
-    max_results: Option<usize>,
-) -> Result<Vec<WebSearchEntry>, String> {
-    let needle = query.trim();
-    if needle.is_empty() {
-        return Ok(Vec::new());
-    }
-
-    let limit = max_results.unwrap_or(6).clamp(1, 10);
-    let client = reqwest::Client::builder()
-        .user_agent("Mozilla/5.0 (compatible; WorkPlan/0.3.3; +https://github.com/MakotoArai-CN/WorkPlan-with-AI)")
-        .build()
-        .map_err(|e| e.to_string())?;
-
-    let response = client
-        .get("https://html.duckduckgo.com/html/")
-        .query(&[("q", needle)])
-        .send()
-        .await
-        .map_err(|e| format!("网页搜索请求失败: {}", e))?;
-
-    if !response.status().is_success() {
-        return Err(format!("网页搜索失败: HTTP {}", response.status()));
+/// Applies the fetch policy to `url` before it is requested with the fetch client.
+async fn check_fetch_target(config: &network::NetworkConfig, url: &str) -> Result<(), String> {
+    let policy = config.fetch_policy.clone();
+    let parsed = url::Url::parse(url).map_err(|e| format!("URL 无效: {}", e))?;
+    policy.check_url(&parsed)?;
+    if config.may_use_proxy() {
+        // Behind a proxy the target name is resolved remotely, so resolve it here first.
+        tauri::async_runtime::spawn_blocking(move || policy.check_resolved(&parsed))
+            .await
+            .map_err(|e| e.to_string())??;
     }
+    Ok(())
+}
 
-    let html = response
-        .text()
+/// Fetches an arbitrary URL under the network policy, going through the HTTP cache.
+async fn fetch_web_bytes(
+    app: &tauri::AppHandle,
+    cache: &http_cache::HttpCacheState,
+    network: &network::NetworkState,
+    url: &str,
+) -> Result<http_cache::CachedResponse, String> {
+    let (client, config) = network.fetch_client(app)?;
+    check_fetch_target(&config, url).await?;
+
+    cache
+        .fetch(
+            app,
+            &http_cache::normalize_url(url),
+            http_cache::PAGE_TTL,
+            config.fetch_policy.max_response_bytes,
+            client.get(url).timeout(std::time::Duration::from_secs(20)),
+        )
         .await
-        .map_err(|e| format!("读取搜索结果失败: {}", e))?;
-
-    let document = Html::parse_document(&html);
-    let result_selector = Selector::parse(".result").map_err(|e| e.to_string())?;
-    let title_selector = Selector::parse("a.result__a").map_err(|e| e.to_string())?;
-    let snippet_selector = Selector::parse(".result__snippet").map_err(|e| e.to_string())?;
-
-    let mut entries = Vec::new();
-    for result in document.select(&result_selector) {
-        if entries.len() >= limit {
-            break;
-        }
-
-        let Some(title_link) = result.select(&title_selector).next() else {
-            continue;
-        };
-
-        let title = title_link
-            .text()
-            .collect::<Vec<_>>()
-            .join("")
-            .trim()
-            .to_string();
-        let raw_url = title_link.value().attr("href").unwrap_or("").trim();
-        let url = extract_duckduckgo_url(raw_url);
-        let snippet = result
-            .select(&snippet_selector)
-            .next()
-            .map(|item| item.text().collect::<Vec<_>>().join("").trim().to_string())
-            .unwrap_or_default();
-
-        if title.is_empty() || url.is_empty() {
-            continue;
-        }
-
-        entries.push(WebSearchEntry {
-            title,
-            url,
-            snippet,
-            source: "DuckDuckGo".to_string(),
-        });
-    }
-
-    Ok(entries)
 }
 
 #[tauri::command]
 async fn fetch_web_content(
+    app: tauri::AppHandle,
+    cache: tauri::State<'_, http_cache::HttpCacheState>,
+    network: tauri::State<'_, network::NetworkState>,
     url: String,
     max_chars: Option<usize>,
-) -> Result<String, String> {
+) -> Result<web_content::WebContentResult, String> {
     let url = url.trim().to_string();
     if url.is_empty() {
         return Err("URL 不能为空".to_string());
     }
 
     let limit = max_chars.unwrap_or(4000).clamp(200, 12000);
-    let client = reqwest::Client::builder()
-        .user_agent("Mozilla/5.0 (compatible; WorkPlan/0.3.3; +https://github.com/MakotoArai-CN/WorkPlan-with-AI)")
-        .timeout(std::time::Duration::from_secs(10))
-        .redirect(reqwest::redirect::Policy::limited(5))
-        .build()
-        .map_err(|e| e.to_string())?;
-
-    let response = client
-        .get(&url)
-        .send()
-        .await
-        .map_err(|e| format!("请求失败: {}", e))?;
-
-    if !response.status().is_success() {
-        return Err(format!("HTTP {}", response.status()));
-    }
-
-    let content_type = response
-        .headers()
-        .get("content-type")
-        .and_then(|v| v.to_str().ok())
-        .unwrap_or("")
-        .to_lowercase();
-
-    if !content_type.contains("text/html") && !content_type.contains("text/plain") {
-        return Err(format!("不支持的内容类型: {}", content_type));
-    }
-
-    let html = response
-        .text()
-        .await
-        .map_err(|e| format!("读取内容失败: {}", e))?;
-
-    // Parse HTML and extract text content
-    let document = Html::parse_document(&html);
-
-    // Remove script, style, nav, footer, header tags
-    let skip_tags: std::collections::HashSet<&str> =
-        ["script", "style", "nav", "footer", "header", "aside", "noscript", "svg", "form"]
-            .iter()
-            .copied()
-            .collect();
-
-    let mut text_parts: Vec<String> = Vec::new();
-
-    fn extract_text(
-        node: ego_tree::NodeRef<scraper::Node>,
-        skip_tags: &std::collections::HashSet<&str>,
-        parts: &mut Vec<String>,
-    ) {
-        match node.value() {
-            scraper::Node::Text(t) => {
-                let trimmed = t.text.trim();
-                if !trimmed.is_empty() {
-                    parts.push(trimmed.to_string());
-                }
-            }
-            scraper::Node::Element(el) => {
-                if skip_tags.contains(el.name()) {
-                    return;
-                }
-                for child in node.children() {
-                    extract_text(child, skip_tags, parts);
-                }
-                // Add line break after block elements
-                let block_tags = ["p", "div", "br", "h1", "h2", "h3", "h4", "h5", "h6", "li", "tr", "blockquote", "pre", "article", "section"];
-                if block_tags.contains(&el.name()) {
-                    parts.push("\n".to_string());
-                }
-            }
-            _ => {
-                for child in node.children() {
-                    extract_text(child, skip_tags, parts);
-                }
-            }
-        }
-    }
-
-    let root = document.tree.root();
-    for child in root.children() {
-        extract_text(child, &skip_tags, &mut text_parts);
-    }
+    let response = fetch_web_bytes(&app, &cache, &network, &url).await?;
 
-    let mut result = text_parts.join(" ");
-    // Clean up whitespace
-    result = result
-        .lines()
-        .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
-        .filter(|line| !line.is_empty())
-        .collect::<Vec<_>>()
-        .join("\n");
-
-    // Truncate
-    if result.len() > limit {
-        let truncated: String = result.chars().take(limit).collect();
-        result = format!("{}...[已截断]", truncated);
+    if !response.is_success() {
+        return Err(format!("HTTP {}", response.status));
     }
 
-    Ok(result)
+    web_content::build_result(
+        url,
+        response.final_url,
+        &response.content_type,
+        &response.body,
+        limit,
+    )
 }
 
-#[tauri::command]
-async fn save_file_to_downloads(
-    app: tauri::AppHandle,
-    filename: String,
-    content: String,
-) -> Result<String, String> {
-    let filename = filename.trim().to_string();
+/// A path for `filename` in the downloads directory, numbered so that an existing
+/// file is never overwritten.
+fn download_target(app: &tauri::AppHandle, filename: &str) -> Result<PathBuf, String> {
+    let filename = filename.trim();
     if filename.is_empty() {
         return Err("文件名不能为空".to_string());
     }
@@ -581,7 +482,7 @@ async fn save_file_to_downloads(
     fs::create_dir_all(&download_dir)
         .map_err(|e| format!("创建下载目录失败: {}", e))?;
 
-    let mut target = download_dir.join(&filename);
+    let mut target = download_dir.join(filename);
 
     // If file already exists, add a number suffix to avoid overwriting
     if target.exists() {
@@ -600,7 +501,28 @@ async fn save_file_to_downloads(
         }
     }
 
-    fs::write(&target, content.as_bytes())
+    Ok(target)
+}
+
+#[tauri::command]
+async fn save_file_to_downloads(
+    app: tauri::AppHandle,
+    filename: String,
+    content: String,
+) -> Result<String, String> {
+    save_to_downloads(&app, &filename, content.as_bytes())
+}
+
+/// Writes `bytes` under a free name in the downloads directory and returns
+/// the final path.
+fn save_to_downloads(
+    app: &tauri::AppHandle,
+    filename: &str,
+    bytes: &[u8],
+) -> Result<String, String> {
+    let target = download_target(app, filename)?;
+
+    fs::write(&target, bytes)
         .map_err(|e| format!("写入文件失败: {}", e))?;
 
     Ok(target.to_string_lossy().to_string())
@@ -649,6 +571,16 @@ pub fn run() {
         .plugin(tauri_plugin_http::init())
         .plugin(tauri_plugin_store::Builder::new().build())
         .plugin(tauri_plugin_dialog::init())
+        .manage(watcher::FileWatcherState::default())
+        .manage(notes_mirror::NotesMirrorState::default())
+        .manage(time_tracking::TimeTrackingState::default())
+        .manage(http_cache::HttpCacheState::default())
+        .manage(network::NetworkState::default())
+        .manage(search_index::SearchIndexState::default())
+        .manage(semantic_index::SemanticIndexState::default())
+        .manage(web_archive::WebArchiveState::default())
+        .manage(sync::SyncState::default())
+        .manage(backup::BackupState::default())
         .invoke_handler(tauri::generate_handler![
             check_update,
             set_autostart,
@@ -659,14 +591,76 @@ pub fn run() {
             read_local_file,
             write_local_file,
             delete_local_file,
-            search_web,
+            web_search::search_web,
+            http_cache::inspect_http_cache,
+            http_cache::clear_http_cache,
+            network::get_network_config,
+            network::set_network_config,
             fetch_web_content,
+            crawler::crawl_site,
+            web_archive::archive_web_page,
+            web_archive::list_web_archives,
+            web_archive::read_web_archive,
+            web_archive::delete_web_archive,
+            sync::sync_now,
+            sync::clear_sync_data,
+            sync::queue::get_sync_queue,
+            sync::queue::retry_pending_sync,
+            sync::conflicts::list_sync_conflicts,
+            sync::conflicts::dismiss_sync_conflicts,
+            sync::lan::start_lan_sync,
+            sync::lan::stop_lan_sync,
+            sync::lan::get_lan_status,
+            sync::lan::begin_lan_pairing,
+            sync::lan::pair_lan_peer,
+            sync::lan::forget_lan_peer,
+            sync::lan::sync_lan_peer,
+            sync::lan::lan_sync_respond,
+            backup::configure_backups,
+            backup::get_backup_status,
+            backup::create_backup,
+            backup::list_backups,
+            backup::restore_backup,
+            data_archive::export_workplan_archive,
+            data_archive::inspect_workplan_archive,
+            data_archive::import_workplan_archive,
+            importers::preview_task_import,
+            task_report::export_task_report,
+            time_tracking::get_time_tracking,
+            time_tracking::start_timer,
+            time_tracking::pause_timer,
+            time_tracking::resume_timer,
+            time_tracking::stop_timer,
+            time_tracking::mark_timer_idle,
+            time_tracking::set_idle_minutes,
+            time_tracking::add_time_entry,
+            time_tracking::update_time_entry,
+            time_tracking::delete_time_entry,
+            time_tracking::list_time_entries,
+            time_tracking::summarize_time,
+            time_tracking::export_timesheet_csv,
             save_file_to_downloads,
+            downloads::save_bytes_to_downloads,
             open_github,
             open_releases,
             set_close_to_quit,
             get_close_to_quit,
-            exit_app
+            exit_app,
+            watcher::start_file_watcher,
+            watcher::stop_file_watcher,
+            watcher::get_file_watcher_status,
+            watcher::attach_task_to_file,
+            watcher::detach_task_from_file,
+            watcher::list_file_task_links,
+            notes_mirror::sync_notes_mirror,
+            notes_mirror::import_markdown_notes,
+            notes_mirror::start_notes_mirror,
+            notes_mirror::stop_notes_mirror,
+            notes_mirror::get_notes_mirror_status,
+            search_index::sync_search_index,
+            search_index::search_everything,
+            semantic_index::sync_semantic_index,
+            semantic_index::semantic_search
         ]);
 
     #[cfg(not(any(target_os = "android", target_os = "ios")))]
@@ -0,0 +1,851 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, ToSocketAddrs};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::{AppHandle, State};
+use url::Url;
+
+const CONFIG_FILE: &str = "network_config.json";
+const MAX_REDIRECTS: usize = 5;
+const DEFAULT_CONNECT_TIMEOUT_SECS: u64 = 10;
+const DEFAULT_TIMEOUT_SECS: u64 = 30;
+const PROXY_ENV_VARS: [&str; 6] = [
+    "HTTPS_PROXY",
+    "https_proxy",
+    "HTTP_PROXY",
+    "http_proxy",
+    "ALL_PROXY",
+    "all_proxy",
+];
+pub const DEFAULT_MAX_RESPONSE_BYTES: u64 = 5 * 1024 * 1024;
+pub const USER_AGENT: &str =
+    "Mozilla/5.0 (compatible; WorkPlan/0.3.3; +https://github.com/MakotoArai-CN/WorkPlan-with-AI)";
+
+#[derive(Default)]
+pub struct NetworkState {
+    config: Mutex<Option<NetworkConfig>>,
+    client: Mutex<Option<reqwest::Client>>,
+    fetch_client: Mutex<Option<reqwest::Client>>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct NetworkConfig {
+    pub fetch_policy: FetchPolicy,
+    pub proxy: ProxyConfig,
+    /// PEM files trusted in addition to the bundled web roots, e.g. a corporate CA.
+    pub ca_certificates: Vec<String>,
+    pub connect_timeout_secs: u64,
+    pub timeout_secs: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ProxyMode {
+    /// `HTTP(S)_PROXY` / `ALL_PROXY` / `NO_PROXY` from the environment, then OS settings.
+    #[default]
+    System,
+    None,
+    Manual,
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ProxyConfig {
+    pub mode: ProxyMode,
+    /// `http://`, `https://`, `socks5://` or `socks5h://`; credentials may be embedded.
+    pub url: String,
+    pub no_proxy: String,
+}
+
+/// Limits on what `fetch_web_content` may reach. Private, loopback and link-local
+/// addresses are refused unless `allow_private_networks` is set or the host/CIDR is
+/// listed in `allow_list`.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase", default)]
+pub struct FetchPolicy {
+    pub allow_private_networks: bool,
+    pub allow_list: Vec<String>,
+    pub max_response_bytes: u64,
+}
+
+impl Default for FetchPolicy {
+    fn default() -> Self {
+        Self {
+            allow_private_networks: false,
+            allow_list: Vec::new(),
+            max_response_bytes: DEFAULT_MAX_RESPONSE_BYTES,
+        }
+    }
+}
+
+fn is_blocked_v4(ip: Ipv4Addr) -> bool {
+    let [a, b, ..] = ip.octets();
+    ip.is_loopback()
+        || ip.is_private()
+        || ip.is_link_local()
+        || ip.is_unspecified()
+        || ip.is_broadcast()
+        || ip.is_multicast()
+        || a == 0
+        || (a == 100 && (64..128).contains(&b))
+}
+
+fn is_blocked_v6(ip: Ipv6Addr) -> bool {
+    if let Some(mapped) = ip.to_ipv4_mapped() {
+        return is_blocked_v4(mapped);
+    }
+    let first = ip.segments()[0];
+    ip.is_loopback()
+        || ip.is_unspecified()
+        || ip.is_multicast()
+        || (first & 0xfe00) == 0xfc00
+        || (first & 0xffc0) == 0xfe80
+}
+
+pub fn is_internal_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_blocked_v4(ip),
+        IpAddr::V6(ip) => is_blocked_v6(ip),
+    }
+}
+
+fn cidr_contains(cidr: &str, ip: IpAddr) -> bool {
+    let (base, prefix) = cidr.split_once('/').unwrap_or((cidr, ""));
+    let Ok(base) = base.trim().parse::<IpAddr>() else {
+        return false;
+    };
+    let (base, ip, width) = match (base, ip) {
+        (IpAddr::V4(base), IpAddr::V4(ip)) => (u32::from(base) as u128, u32::from(ip) as u128, 32),
+        (IpAddr::V6(base), IpAddr::V6(ip)) => (u128::from(base), u128::from(ip), 128),
+        _ => return false,
+    };
+    let prefix = prefix.trim().parse::<u32>().unwrap_or(width).min(width);
+    if prefix == 0 {
+        return true;
+    }
+    let shift = width - prefix;
+    (base >> shift) == (ip >> shift)
+}
+
+impl FetchPolicy {
+    fn host_allowed(&self, host: &str) -> bool {
+        let host = host.trim_end_matches('.').to_ascii_lowercase();
+        self.allow_list.iter().any(|entry| {
+            let entry = entry.trim().to_ascii_lowercase();
+            match entry.strip_prefix("*.") {
+                Some(suffix) => host.ends_with(&format!(".{}", suffix)),
+                None => !entry.is_empty() && host == entry,
+            }
+        })
+    }
+
+    fn ip_allowed(&self, ip: IpAddr) -> bool {
+        self.allow_private_networks
+            || !is_internal_ip(ip)
+            || self.allow_list.iter().any(|entry| cidr_contains(entry, ip))
+    }
+
+    /// Checks scheme and literal-IP hosts; named hosts are checked again once resolved.
+    pub fn check_url(&self, url: &Url) -> Result<(), String> {
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(format!("不支持的协议: {}", url.scheme()));
+        }
+        let blocked = match url.host() {
+            Some(url::Host::Ipv4(ip)) => !self.ip_allowed(IpAddr::V4(ip)),
+            Some(url::Host::Ipv6(ip)) => !self.ip_allowed(IpAddr::V6(ip)),
+            Some(url::Host::Domain(domain)) => {
+                let domain = domain.to_ascii_lowercase();
+                !self.allow_private_networks
+                    && !self.host_allowed(&domain)
+                    && (domain == "localhost" || domain.ends_with(".localhost"))
+            }
+            None => true,
+        };
+        if blocked {
+            return Err(format!("网络策略禁止访问该地址: {}", url));
+        }
+        Ok(())
+    }
+
+    /// Resolves a named host up front. Needed whenever a proxy may be in use, since
+    /// the proxy resolves the target name and it never reaches `PolicyResolver`.
+    pub fn check_resolved(&self, url: &Url) -> Result<(), String> {
+        let Some(url::Host::Domain(domain)) = url.host() else {
+            return Ok(());
+        };
+        if self.allow_private_networks || self.host_allowed(domain) {
+            return Ok(());
+        }
+        let port = url.port_or_known_default().unwrap_or(80);
+        let addrs = (domain, port)
+            .to_socket_addrs()
+            .map_err(|e| format!("解析域名失败: {}", e))?;
+        if addrs.into_iter().any(|addr| !self.ip_allowed(addr.ip())) {
+            return Err(format!("网络策略禁止访问内网地址: {}", domain));
+        }
+        Ok(())
+    }
+
+    /// Redirect hops are re-checked so a public URL cannot bounce into the intranet.
+    pub fn redirect_policy(&self, resolve_hosts: bool) -> reqwest::redirect::Policy {
+        let policy = self.clone();
+        reqwest::redirect::Policy::custom(move |attempt| {
+            if attempt.previous().len() >= MAX_REDIRECTS {
+                return attempt.error("重定向次数过多");
+            }
+            let checked = policy.check_url(attempt.url()).and_then(|_| {
+                if resolve_hosts {
+                    policy.check_resolved(attempt.url())
+                } else {
+                    Ok(())
+                }
+            });
+            match checked {
+                Ok(()) => attempt.follow(),
+                Err(e) => attempt.error(e),
+            }
+        })
+    }
+
+    /// Keeps the resolved addresses of `host` that may be connected to.
+    fn filter_resolved(
+        &self,
+        host: &str,
+        resolved: Vec<SocketAddr>,
+    ) -> Result<Vec<SocketAddr>, String> {
+        if self.host_allowed(host) {
+            return Ok(resolved);
+        }
+        let permitted: Vec<SocketAddr> = resolved
+            .into_iter()
+            .filter(|addr| self.ip_allowed(addr.ip()))
+            .collect();
+        if permitted.is_empty() {
+            return Err(format!("网络策略禁止访问内网地址: {}", host));
+        }
+        Ok(permitted)
+    }
+
+    /// `proxy_hosts` are resolved without the policy, so a proxy on the intranet
+    /// stays reachable.
+    pub fn resolver(&self, proxy_hosts: Vec<String>) -> Arc<PolicyResolver> {
+        Arc::new(PolicyResolver {
+            policy: self.clone(),
+            proxy_hosts,
+        })
+    }
+}
+
+/// DNS resolver that drops addresses the policy forbids, so the check applies to the
+/// address actually connected to rather than a separate lookup.
+pub struct PolicyResolver {
+    policy: FetchPolicy,
+    proxy_hosts: Vec<String>,
+}
+
+impl reqwest::dns::Resolve for PolicyResolver {
+    fn resolve(&self, name: reqwest::dns::Name) -> reqwest::dns::Resolving {
+        let policy = self.policy.clone();
+        let host = name.as_str().to_string();
+        let is_proxy = self
+            .proxy_hosts
+            .iter()
+            .any(|proxy| proxy.eq_ignore_ascii_case(host.trim_end_matches('.')));
+        Box::pin(async move {
+            let resolved: Vec<SocketAddr> =
+                tokio::net::lookup_host((host.as_str(), 0)).await?.collect();
+            if is_proxy {
+                return Ok(Box::new(resolved.into_iter()) as reqwest::dns::Addrs);
+            }
+            let permitted = policy.filter_resolved(&host, resolved)?;
+            Ok(Box::new(permitted.into_iter()) as reqwest::dns::Addrs)
+        })
+    }
+}
+
+/// Reads the body, failing as soon as it grows past `max_bytes`.
+pub async fn read_limited(
+    mut response: reqwest::Response,
+    max_bytes: u64,
+) -> Result<Vec<u8>, String> {
+    if response.content_length().is_some_and(|len| len > max_bytes) {
+        return Err(format!("响应内容超过大小限制 ({} 字节)", max_bytes));
+    }
+    let mut body = Vec::new();
+    while let Some(chunk) = response
+        .chunk()
+        .await
+        .map_err(|e| format!("读取内容失败: {}", e))?
+    {
+        if (body.len() + chunk.len()) as u64 > max_bytes {
+            return Err(format!("响应内容超过大小限制 ({} 字节)", max_bytes));
+        }
+        body.extend_from_slice(&chunk);
+    }
+    Ok(body)
+}
+
+fn config_path(app: &AppHandle) -> Result<std::path::PathBuf, String> {
+    crate::app_data_file(app, CONFIG_FILE)
+}
+
+impl NetworkConfig {
+    /// Whether requests may go through a proxy, so targets must be resolved and
+    /// checked before sending. Always true in system mode: reqwest also reads the
+    /// OS proxy settings (Windows registry, macOS network settings), which are not
+    /// visible from here.
+    pub fn may_use_proxy(&self) -> bool {
+        match self.proxy.mode {
+            ProxyMode::Manual => !self.proxy.url.trim().is_empty(),
+            ProxyMode::None => false,
+            ProxyMode::System => true,
+        }
+    }
+
+    /// Names of the configured proxies. A proxy set only in the OS settings is
+    /// not known, and has to be allow-listed if it resolves to a private address.
+    fn proxy_hosts(&self) -> Vec<String> {
+        let urls: Vec<String> = match self.proxy.mode {
+            ProxyMode::Manual => vec![self.proxy.url.clone()],
+            ProxyMode::None => Vec::new(),
+            ProxyMode::System => PROXY_ENV_VARS
+                .iter()
+                .filter_map(|key| std::env::var(key).ok())
+                .collect(),
+        };
+        urls.iter()
+            .filter_map(|url| {
+                let url = url.trim();
+                // Proxy variables often omit the scheme.
+                Url::parse(url)
+                    .ok()
+                    .filter(|parsed| parsed.has_host())
+                    .or_else(|| Url::parse(&format!("http://{}", url)).ok())
+            })
+            .filter_map(|url| match url.host()? {
+                url::Host::Domain(domain) => Some(domain.to_ascii_lowercase()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Client for fetching arbitrary pages, with the fetch policy applied to DNS
+    /// results and redirect hops.
+    pub fn fetch_client(&self) -> Result<reqwest::Client, String> {
+        let policy = &self.fetch_policy;
+        self.client_builder()?
+            .redirect(policy.redirect_policy(self.may_use_proxy()))
+            .dns_resolver(policy.resolver(self.proxy_hosts()))
+            .build()
+            .map_err(|e| e.to_string())
+    }
+
+    /// Base builder shared by every outbound request: user agent, proxy, extra CA
+    /// certificates and timeouts.
+    pub fn client_builder(&self) -> Result<reqwest::ClientBuilder, String> {
+        let connect_timeout = match self.connect_timeout_secs {
+            0 => DEFAULT_CONNECT_TIMEOUT_SECS,
+            secs => secs,
+        };
+        let timeout = match self.timeout_secs {
+            0 => DEFAULT_TIMEOUT_SECS,
+            secs => secs,
+        };
+        let mut builder = reqwest::Client::builder()
+            .user_agent(USER_AGENT)
+            .connect_timeout(Duration::from_secs(connect_timeout))
+            .timeout(Duration::from_secs(timeout));
+
+        match self.proxy.mode {
+            ProxyMode::System => {}
+            ProxyMode::None => builder = builder.no_proxy(),
+            ProxyMode::Manual => {
+                let proxy_url = self.proxy.url.trim();
+                if proxy_url.is_empty() {
+                    return Err("代理地址不能为空".to_string());
+                }
+                let proxy = reqwest::Proxy::all(proxy_url)
+                    .map_err(|e| format!("代理地址无效: {}", e))?
+                    .no_proxy(reqwest::NoProxy::from_string(&self.proxy.no_proxy));
+                builder = builder.proxy(proxy);
+            }
+        }
+
+        for path in &self.ca_certificates {
+            let pem = fs::read(path.trim()).map_err(|e| format!("读取证书失败 {}: {}", path, e))?;
+            let certificates = reqwest::Certificate::from_pem_bundle(&pem)
+                .map_err(|e| format!("解析证书失败 {}: {}", path, e))?;
+            if certificates.is_empty() {
+                return Err(format!("证书文件中没有 PEM 证书: {}", path));
+            }
+            for certificate in certificates {
+                builder = builder.add_root_certificate(certificate);
+            }
+        }
+
+        Ok(builder)
+    }
+}
+
+impl NetworkState {
+    /// Shared client for API calls (updates, search, embeddings).
+    pub fn client(&self, app: &AppHandle) -> Result<reqwest::Client, String> {
+        let config = self.config(app)?;
+        let mut guard = self.client.lock().map_err(|e| e.to_string())?;
+        if let Some(client) = guard.as_ref() {
+            return Ok(client.clone());
+        }
+        let client = config
+            .client_builder()?
+            .build()
+            .map_err(|e| e.to_string())?;
+        *guard = Some(client.clone());
+        Ok(client)
+    }
+
+    /// The cached `NetworkConfig::fetch_client`, with the config it was built from.
+    pub fn fetch_client(
+        &self,
+        app: &AppHandle,
+    ) -> Result<(reqwest::Client, NetworkConfig), String> {
+        let config = self.config(app)?;
+        let mut guard = self.fetch_client.lock().map_err(|e| e.to_string())?;
+        if let Some(client) = guard.as_ref() {
+            return Ok((client.clone(), config));
+        }
+        let client = config.fetch_client()?;
+        *guard = Some(client.clone());
+        Ok((client, config))
+    }
+
+    pub fn config(&self, app: &AppHandle) -> Result<NetworkConfig, String> {
+        let mut guard = self.config.lock().map_err(|e| e.to_string())?;
+        if guard.is_none() {
+            let loaded = fs::read_to_string(config_path(app)?)
+                .ok()
+                .and_then(|raw| serde_json::from_str(&raw).ok())
+                .unwrap_or_default();
+            *guard = Some(loaded);
+        }
+        Ok(guard.clone().unwrap_or_default())
+    }
+}
+
+#[tauri::command]
+pub fn get_network_config(
+    app: AppHandle,
+    state: State<'_, NetworkState>,
+) -> Result<NetworkConfig, String> {
+    state.config(&app)
+}
+
+#[tauri::command]
+pub fn set_network_config(
+    app: AppHandle,
+    state: State<'_, NetworkState>,
+    config: NetworkConfig,
+) -> Result<NetworkConfig, String> {
+    let mut config = config;
+    if config.fetch_policy.max_response_bytes == 0 {
+        config.fetch_policy.max_response_bytes = DEFAULT_MAX_RESPONSE_BYTES;
+    }
+    // Reject proxy or certificate settings no client could be built from.
+    config
+        .client_builder()?
+        .build()
+        .map_err(|e| e.to_string())?;
+    let data = serde_json::to_string_pretty(&config).map_err(|e| e.to_string())?;
+    fs::write(config_path(&app)?, data).map_err(|e| format!("保存网络配置失败: {}", e))?;
+    *state.config.lock().map_err(|e| e.to_string())? = Some(config.clone());
+    *state.client.lock().map_err(|e| e.to_string())? = None;
+    *state.fetch_client.lock().map_err(|e| e.to_string())? = None;
+    Ok(config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{serve, Response};
+    use reqwest::dns::Resolve;
+    use std::str::FromStr;
+
+    fn direct_config() -> NetworkConfig {
+        NetworkConfig {
+            proxy: ProxyConfig {
+                mode: ProxyMode::None,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    fn fetch_client(policy: &FetchPolicy) -> reqwest::Client {
+        direct_config()
+            .client_builder()
+            .unwrap()
+            .redirect(policy.redirect_policy(false))
+            .dns_resolver(policy.resolver(Vec::new()))
+            .build()
+            .unwrap()
+    }
+
+    /// The error with all of its sources, since reqwest wraps ours.
+    fn error_chain(error: &(dyn std::error::Error + 'static)) -> String {
+        let mut text = error.to_string();
+        let mut source = error.source();
+        while let Some(inner) = source {
+            text.push_str(&format!(": {}", inner));
+            source = inner.source();
+        }
+        text
+    }
+
+    fn port_of(base: &str) -> u16 {
+        Url::parse(base).unwrap().port().unwrap()
+    }
+
+    #[test]
+    fn resolved_internal_addresses_are_dropped() {
+        let policy = FetchPolicy::default();
+        for ip in [
+            "127.0.0.1",
+            "::1",
+            "10.1.2.3",
+            "172.16.0.9",
+            "192.168.1.20",
+            "100.64.0.1",
+            "169.254.169.254",
+            "fe80::1",
+            "fd00::1",
+            "::ffff:192.168.0.1",
+        ] {
+            let addr = SocketAddr::new(ip.parse().unwrap(), 0);
+            assert!(
+                policy
+                    .filter_resolved("intranet.example", vec![addr])
+                    .is_err(),
+                "{} should be refused",
+                ip
+            );
+        }
+
+        let public: SocketAddr = "93.184.216.34:0".parse().unwrap();
+        let private: SocketAddr = "10.0.0.1:0".parse().unwrap();
+        let permitted = policy
+            .filter_resolved("mixed.example", vec![private, public])
+            .unwrap();
+        assert_eq!(permitted, vec![public]);
+
+        let allowed = FetchPolicy {
+            allow_list: vec!["10.0.0.0/8".to_string(), "*.corp.example".to_string()],
+            ..Default::default()
+        };
+        assert!(allowed.filter_resolved("a.example", vec![private]).is_ok());
+        let link_local: SocketAddr = "169.254.1.1:0".parse().unwrap();
+        assert!(allowed
+            .filter_resolved("wiki.corp.example", vec![link_local])
+            .is_ok());
+        assert!(allowed
+            .filter_resolved("b.example", vec![link_local])
+            .is_err());
+    }
+
+    #[test]
+    fn literal_internal_hosts_are_refused() {
+        let policy = FetchPolicy::default();
+        for url in [
+            "http://127.0.0.1/",
+            "http://localhost:8080/",
+            "http://app.localhost/",
+            "http://10.0.0.1/",
+            "http://192.168.0.1/",
+            "http://169.254.169.254/latest/meta-data",
+            "http://[::1]/",
+            "http://[fe80::1]/",
+            "file:///etc/passwd",
+        ] {
+            assert!(
+                policy.check_url(&Url::parse(url).unwrap()).is_err(),
+                "{}",
+                url
+            );
+        }
+        assert!(policy
+            .check_url(&Url::parse("https://93.184.216.34/").unwrap())
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn resolver_refuses_loopback_names() {
+        let policy = FetchPolicy::default();
+        let name = reqwest::dns::Name::from_str("localhost").unwrap();
+        let error = match policy.resolver(Vec::new()).resolve(name).await {
+            Ok(_) => panic!("localhost resolved"),
+            Err(error) => error,
+        };
+        assert!(error.to_string().contains("内网地址"));
+    }
+
+    #[tokio::test]
+    async fn requests_to_loopback_are_blocked_after_resolution() {
+        let base = serve(|_| Response::new(200, "secret"));
+        let url = format!("http://localhost:{}/", port_of(&base));
+
+        let error = fetch_client(&FetchPolicy::default())
+            .get(&url)
+            .send()
+            .await
+            .unwrap_err();
+        assert!(error_chain(&error).contains("网络策略禁止访问内网地址"));
+
+        // Allow-listing the loopback range lets the same request through.
+        let allowed = FetchPolicy {
+            allow_list: vec!["127.0.0.0/8".to_string()],
+            ..Default::default()
+        };
+        let body = fetch_client(&allowed)
+            .get(&url)
+            .send()
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+        assert_eq!(body, "secret");
+    }
+
+    #[tokio::test]
+    async fn redirects_into_blocked_addresses_are_refused() {
+        let base = serve(|request| match request.path() {
+            "/metadata" => {
+                Response::new(302, "").header("Location", "http://169.254.169.254/latest/meta-data")
+            }
+            "/loopback" => Response::new(302, "").header("Location", "http://127.0.0.1:9/"),
+            "/hop" => Response::new(301, "").header("Location", "/done"),
+            _ => Response::new(200, "done"),
+        });
+        let port = port_of(&base);
+        let policy = FetchPolicy {
+            allow_list: vec!["localhost".to_string()],
+            ..Default::default()
+        };
+        let client = fetch_client(&policy);
+
+        for path in ["metadata", "loopback"] {
+            let error = client
+                .get(format!("http://localhost:{}/{}", port, path))
+                .send()
+                .await
+                .unwrap_err();
+            assert!(error.is_redirect(), "{}", path);
+            assert!(
+                error_chain(&error).contains("网络策略禁止访问该地址"),
+                "{}",
+                path
+            );
+        }
+
+        let response = client
+            .get(format!("http://localhost:{}/hop", port))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.url().path(), "/done");
+        assert_eq!(response.text().await.unwrap(), "done");
+    }
+
+    const TEST_CA: &str = "\
+-----BEGIN CERTIFICATE-----
+MIIBjTCCATOgAwIBAgIUWqVQ5dRMcw0AI2iW4pAoGlfhS84wCgYIKoZIzj0EAwIw
+GzEZMBcGA1UEAwwQV29ya1BsYW4gVGVzdCBDQTAgFw0yNjEwMTkwMzUyNDVaGA8y
+MTI2MDkyNTAzNTI0NVowGzEZMBcGA1UEAwwQV29ya1BsYW4gVGVzdCBDQTBZMBMG
+ByqGSM49AgEGCCqGSM49AwEHA0IABHdrkE5zdnBrXzfU9jufqWFj90C1VH1XOsGY
+LgCvXf4oYZT3E3PmAKvoDHFFWPHDIauluAXx2bEHp/wPRGAyiKyjUzBRMB0GA1Ud
+DgQWBBSNjkD07tGlurOTSAFLaqEFQ2SYTjAfBgNVHSMEGDAWgBSNjkD07tGlurOT
+SAFLaqEFQ2SYTjAPBgNVHRMBAf8EBTADAQH/MAoGCCqGSM49BAMCA0gAMEUCIQDt
+D0Bcjq3AqWwMixttEriwNGNTt0Ew+00HBSZEz7U1iwIgQrda6AxEL4IMEiI85WWX
+HrwA0APhUKAkzHwd5eoTHF0=
+-----END CERTIFICATE-----
+";
+
+    fn manual_proxy(url: &str, no_proxy: &str) -> NetworkConfig {
+        NetworkConfig {
+            proxy: ProxyConfig {
+                mode: ProxyMode::Manual,
+                url: url.to_string(),
+                no_proxy: no_proxy.to_string(),
+            },
+            ..Default::default()
+        }
+    }
+
+    /// What `set_network_config` checks before saving.
+    fn build(config: &NetworkConfig) -> Result<reqwest::Client, String> {
+        config.client_builder()?.build().map_err(|e| e.to_string())
+    }
+
+    fn write_temp(name: &str, content: &str) -> String {
+        let path = std::env::temp_dir().join(format!(
+            "workplan-network-{}-{}.pem",
+            std::process::id(),
+            name
+        ));
+        fs::write(&path, content).unwrap();
+        path.to_string_lossy().to_string()
+    }
+
+    #[tokio::test]
+    async fn proxy_mode_none_connects_directly() {
+        let direct = serve(|_| Response::new(200, "direct"));
+        let config = NetworkConfig {
+            proxy: ProxyConfig {
+                mode: ProxyMode::None,
+                url: "http://127.0.0.1:9".to_string(),
+                no_proxy: String::new(),
+            },
+            ..Default::default()
+        };
+        assert!(!config.may_use_proxy());
+        let body = build(&config)
+            .unwrap()
+            .get(&direct)
+            .send()
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+        assert_eq!(body, "direct");
+    }
+
+    #[test]
+    fn proxy_mode_system_always_checks_targets_up_front() {
+        // OS proxy settings are invisible here, so an empty environment proves nothing.
+        let config = NetworkConfig::default();
+        assert!(config.proxy.mode == ProxyMode::System);
+        assert!(config.may_use_proxy());
+        assert!(build(&config).is_ok());
+        assert!(config.fetch_client().is_ok());
+    }
+
+    #[tokio::test]
+    async fn proxy_mode_manual_routes_through_the_proxy() {
+        let proxy = serve(|request| Response::new(200, format!("proxied {}", request.target)));
+        let direct = serve(|_| Response::new(200, "direct"));
+
+        let config = manual_proxy(&proxy, "");
+        assert!(config.may_use_proxy());
+        let client = build(&config).unwrap();
+        let body = client
+            .get("http://example.invalid/page?q=1")
+            .send()
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+        assert_eq!(body, "proxied http://example.invalid/page?q=1");
+
+        let bypass = build(&manual_proxy(&proxy, "127.0.0.1")).unwrap();
+        let body = bypass
+            .get(&direct)
+            .send()
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+        assert_eq!(body, "direct");
+    }
+
+    #[tokio::test]
+    async fn a_named_proxy_on_the_intranet_is_reachable_from_the_fetch_client() {
+        let proxy = serve(|request| Response::new(200, format!("proxied {}", request.target)));
+        // `localhost` resolves to loopback, which the policy refuses for targets.
+        let named = format!("http://LocalHost:{}", port_of(&proxy));
+        let config = manual_proxy(&named, "");
+        assert_eq!(config.proxy_hosts(), ["localhost"]);
+
+        let body = config
+            .fetch_client()
+            .unwrap()
+            .get("http://93.184.216.34/page")
+            .send()
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+        assert_eq!(body, "proxied http://93.184.216.34/page");
+
+        // Targets named like the proxy are still checked before they are sent.
+        let target = Url::parse(&format!("{}/admin", named)).unwrap();
+        assert!(config.fetch_policy.check_resolved(&target).is_err());
+    }
+
+    #[test]
+    fn proxy_hosts_accept_urls_without_a_scheme() {
+        let mut config = manual_proxy("proxy.corp:8080", "");
+        assert_eq!(config.proxy_hosts(), ["proxy.corp"]);
+        config.proxy.url = "socks5h://user:pw@Socks.Corp:1080".to_string();
+        assert_eq!(config.proxy_hosts(), ["socks.corp"]);
+        config.proxy.url = "http://10.0.0.8:3128".to_string();
+        assert!(config.proxy_hosts().is_empty());
+        assert!(direct_config().proxy_hosts().is_empty());
+    }
+
+    #[test]
+    fn invalid_manual_proxies_are_rejected() {
+        let empty = manual_proxy("  ", "");
+        assert!(!empty.may_use_proxy());
+        assert!(build(&empty).unwrap_err().contains("代理地址不能为空"));
+        assert!(build(&manual_proxy("http://[::1", ""))
+            .unwrap_err()
+            .contains("代理地址无效"));
+    }
+
+    #[test]
+    fn custom_ca_certificates_are_validated() {
+        let with_ca = |path: String| NetworkConfig {
+            ca_certificates: vec![path],
+            ..direct_config()
+        };
+
+        assert!(build(&with_ca(write_temp("valid", TEST_CA))).is_ok());
+
+        let missing = std::env::temp_dir().join("workplan-network-missing.pem");
+        let error = build(&with_ca(missing.to_string_lossy().to_string())).unwrap_err();
+        assert!(error.contains("读取证书失败"), "{}", error);
+
+        let error = build(&with_ca(write_temp("text", "not a certificate"))).unwrap_err();
+        assert!(error.contains("没有 PEM 证书"), "{}", error);
+
+        let broken_base64 = "-----BEGIN CERTIFICATE-----\n%%%%\n-----END CERTIFICATE-----\n";
+        assert!(build(&with_ca(write_temp("base64", broken_base64))).is_err());
+
+        let not_der = "-----BEGIN CERTIFICATE-----\naGVsbG8gd29ybGQ=\n-----END CERTIFICATE-----\n";
+        assert!(build(&with_ca(write_temp("der", not_der))).is_err());
+    }
+
+    #[tokio::test]
+    async fn read_limited_enforces_the_size_cap() {
+        let base = serve(|request| match request.path() {
+            "/declared" => Response::new(200, vec![b'a'; 2000]),
+            "/streamed" => Response::new(200, vec![b'b'; 2000]).chunked(),
+            _ => Response::new(200, vec![b'c'; 1000]).chunked(),
+        });
+        let client = direct_config().client_builder().unwrap().build().unwrap();
+        let get = |path: &str| client.get(format!("{}/{}", base, path)).send();
+
+        let declared = read_limited(get("declared").await.unwrap(), 1000).await;
+        assert!(declared.unwrap_err().contains("超过大小限制"));
+        let streamed = read_limited(get("streamed").await.unwrap(), 1000).await;
+        assert!(streamed.unwrap_err().contains("超过大小限制"));
+        let exact = read_limited(get("exact").await.unwrap(), 1000)
+            .await
+            .unwrap();
+        assert_eq!(exact.len(), 1000);
+    }
+}
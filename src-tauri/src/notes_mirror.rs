@@ -0,0 +1,1207 @@
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use serde_yaml::Mapping;
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Emitter, State};
+
+const INDEX_FILE: &str = "notes_mirror.json";
+/// Obsidian's own trash folder, so notes deleted in the app can still be recovered there.
+const TRASH_DIR: &str = ".trash";
+/// App folders of Obsidian and Logseq, and sync tool metadata.
+const IGNORED_DIRS: [&str; 6] = [
+    ".obsidian",
+    ".trash",
+    ".git",
+    ".stfolder",
+    "logseq",
+    ".logseq",
+];
+const ALL_CATEGORY: &str = "全部";
+const UNTITLED: &str = "无标题笔记";
+/// Front matter keys the mirror owns; any others are kept as they are.
+const MANAGED_KEYS: [&str; 6] = ["id", "title", "category", "tags", "created", "updated"];
+const MAX_NAME_CHARS: usize = 80;
+/// Losing more than half of the tracked files, and at least this many, in one
+/// sync is taken for a folder that was moved or swapped rather than for edits.
+const MASS_DELETION_MIN: usize = 10;
+
+#[derive(Default)]
+pub struct NotesMirrorState {
+    active: Mutex<Option<ActiveMirror>>,
+    index: Arc<Mutex<Option<MirrorIndex>>>,
+    /// Serializes syncs, so one started by the watcher cannot interleave with
+    /// one started by an edit in the app.
+    syncing: Mutex<()>,
+}
+
+struct ActiveMirror {
+    _watcher: RecommendedWatcher,
+    folder: PathBuf,
+}
+
+/// What the last sync saw on both sides, so the next one can tell which side changed.
+#[derive(Serialize, Deserialize, Default, Clone)]
+#[serde(rename_all = "camelCase")]
+struct MirrorIndex {
+    folder: String,
+    last_sync_at: u64,
+    /// Keyed by note id.
+    entries: HashMap<String, MirrorEntry>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+struct MirrorEntry {
+    /// Relative to the folder, with `/` separators.
+    path: String,
+    file_hash: String,
+    note_hash: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NotesMirrorStatus {
+    running: bool,
+    folder: String,
+    tracked: usize,
+    last_sync_at: u64,
+}
+
+#[derive(Serialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct MirrorOutcome {
+    /// Notes to add or replace in the app, whole.
+    upserts: Vec<Value>,
+    /// Ids of notes to remove from the app.
+    deleted: Vec<String>,
+    written: usize,
+    imported: usize,
+    updated: usize,
+    removed: usize,
+    conflicts: usize,
+    warnings: Vec<String>,
+    synced_at: u64,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct MirrorChangeEvent {
+    folder: String,
+    paths: Vec<String>,
+}
+
+/// A Markdown file in the folder.
+struct MarkdownFile {
+    text: String,
+    hash: String,
+    modified: u64,
+}
+
+/// The note fields a Markdown file carries.
+struct ParsedNote {
+    id: Option<String>,
+    title: String,
+    content: String,
+    category: String,
+    tags: Vec<String>,
+    created: Option<String>,
+    updated: Option<String>,
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+fn hex_digest(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// Formats like `Date.toISOString()`, so timestamps compare as strings.
+fn iso_time(millis: u64) -> String {
+    let (year, month, day, hour, minute, second) = crate::utc_date_time(millis);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        hour,
+        minute,
+        second,
+        millis % 1000
+    )
+}
+
+fn text<'a>(note: &'a Value, key: &str) -> &'a str {
+    note.get(key).and_then(Value::as_str).unwrap_or_default()
+}
+
+fn note_tags(note: &Value) -> Vec<String> {
+    note.get("tags")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|tag| tag.as_str().map(str::to_string))
+        .collect()
+}
+
+/// Hash of the fields the mirror writes, so edits to anything else (attachments,
+/// the AI lock) do not count as changes. Files always end in a newline, so
+/// trailing whitespace is not compared.
+fn note_hash(note: &Value) -> String {
+    let fields = json!([
+        text(note, "title"),
+        text(note, "content").trim_end(),
+        text(note, "category"),
+        note_tags(note)
+    ]);
+    hex_digest(fields.to_string().as_bytes())
+}
+
+fn is_ignored(relative: &Path) -> bool {
+    relative.components().any(|component| {
+        let segment = component.as_os_str().to_string_lossy();
+        IGNORED_DIRS.contains(&segment.as_ref())
+    })
+}
+
+fn is_markdown(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("md"))
+}
+
+fn relative_key(folder: &Path, path: &Path) -> Option<String> {
+    let relative = path.strip_prefix(folder).ok()?;
+    if is_ignored(relative) {
+        return None;
+    }
+    Some(
+        relative
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/"),
+    )
+}
+
+fn scan(folder: &Path, warnings: &mut Vec<String>) -> HashMap<String, MarkdownFile> {
+    let mut files = HashMap::new();
+    for entry in walkdir::WalkDir::new(folder)
+        .into_iter()
+        .filter_entry(|entry| {
+            entry
+                .path()
+                .strip_prefix(folder)
+                .is_ok_and(|relative| !is_ignored(relative))
+        })
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_file() && is_markdown(entry.path()))
+    {
+        let Some(key) = relative_key(folder, entry.path()) else {
+            continue;
+        };
+        let bytes = match fs::read(entry.path()) {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                warnings.push(format!("读取 {} 失败: {}", key, e));
+                continue;
+            }
+        };
+        let modified = entry
+            .metadata()
+            .ok()
+            .and_then(|meta| meta.modified().ok())
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or(0);
+        files.insert(
+            key,
+            MarkdownFile {
+                hash: hex_digest(&bytes),
+                text: String::from_utf8_lossy(&bytes).into_owned(),
+                modified,
+            },
+        );
+    }
+    files
+}
+
+/// Splits YAML front matter, or Logseq's leading `key:: value` properties, from
+/// the body.
+fn split_front_matter(text: &str) -> Result<(Mapping, String), String> {
+    let text = text.trim_start_matches('\u{feff}').replace("\r\n", "\n");
+    if let Some(rest) = text.strip_prefix("---\n") {
+        let end = if rest.starts_with("---\n") {
+            Some((0, 4))
+        } else {
+            rest.find("\n---\n").map(|i| (i, i + 5)).or_else(|| {
+                rest.strip_suffix("\n---")
+                    .map(|yaml| (yaml.len(), rest.len()))
+            })
+        };
+        if let Some((yaml_end, body_start)) = end {
+            let yaml = &rest[..yaml_end];
+            let mapping = if yaml.trim().is_empty() {
+                Mapping::new()
+            } else {
+                serde_yaml::from_str(yaml).map_err(|e| format!("front matter 无效: {}", e))?
+            };
+            let body = rest[body_start..].trim_start_matches('\n').to_string();
+            return Ok((mapping, body));
+        }
+    }
+
+    let mut mapping = Mapping::new();
+    let mut lines = text.lines().peekable();
+    while let Some((key, value)) = lines.peek().and_then(|line| line.split_once(":: ")) {
+        let key = key.trim();
+        if key.is_empty()
+            || !key
+                .chars()
+                .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+        {
+            break;
+        }
+        mapping.insert(key.into(), value.trim().into());
+        lines.next();
+    }
+    if mapping.is_empty() {
+        return Ok((mapping, text));
+    }
+    let body = lines.collect::<Vec<_>>().join("\n");
+    Ok((mapping, body.trim_start_matches('\n').to_string()))
+}
+
+fn yaml_string(value: &serde_yaml::Value) -> Option<String> {
+    match value {
+        serde_yaml::Value::String(s) => Some(s.trim().to_string()),
+        serde_yaml::Value::Number(n) => Some(n.to_string()),
+        serde_yaml::Value::Bool(b) => Some(b.to_string()),
+        _ => None,
+    }
+    .filter(|s| !s.is_empty())
+}
+
+/// Tags as a YAML list or as a comma or space separated string, with or
+/// without the leading `#`.
+fn yaml_tags(value: Option<&serde_yaml::Value>) -> Vec<String> {
+    let raw: Vec<String> = match value {
+        Some(serde_yaml::Value::Sequence(items)) => items.iter().filter_map(yaml_string).collect(),
+        Some(other) => yaml_string(other)
+            .map(|s| {
+                s.split(|c: char| c == ',' || c.is_whitespace())
+                    .map(str::to_string)
+                    .collect()
+            })
+            .unwrap_or_default(),
+        None => Vec::new(),
+    };
+    let mut tags = Vec::new();
+    for tag in raw {
+        // Logseq writes page references as `[[tag]]`.
+        let tag = tag
+            .trim()
+            .trim_start_matches('#')
+            .trim_matches(|c| c == '[' || c == ']')
+            .to_string();
+        if !tag.is_empty() && !tags.contains(&tag) {
+            tags.push(tag);
+        }
+    }
+    tags
+}
+
+fn file_stem(key: &str) -> String {
+    let name = key.rsplit('/').next().unwrap_or(key);
+    name[..name.len() - 3].to_string()
+}
+
+fn parse_note(key: &str, file: &MarkdownFile) -> Result<ParsedNote, String> {
+    let (front, body) = split_front_matter(&file.text)?;
+    let get = |name: &str| front.get(name).and_then(yaml_string);
+    // A note in a subfolder belongs to the category named after the folder,
+    // unless its front matter says otherwise.
+    let folder_category = key.split_once('/').map(|(dir, _)| dir.to_string());
+    Ok(ParsedNote {
+        id: get("id"),
+        title: get("title").unwrap_or_else(|| file_stem(key)),
+        content: body.trim_end().to_string(),
+        category: get("category")
+            .or(folder_category)
+            .unwrap_or_else(|| ALL_CATEGORY.to_string()),
+        tags: yaml_tags(front.get("tags")),
+        created: get("created"),
+        updated: get("updated"),
+    })
+}
+
+/// Applies a file's contents to a note, keeping fields the file does not carry.
+fn merge_parsed(note: Option<&Value>, id: &str, parsed: ParsedNote, file: &MarkdownFile) -> Value {
+    let modified = iso_time(file.modified);
+    // An edit outside the app leaves `updated` stale; the file time is newer then.
+    let updated = parsed
+        .updated
+        .filter(|updated| *updated > modified)
+        .unwrap_or(modified);
+    let mut note = note.cloned().unwrap_or_else(|| {
+        json!({
+            "id": id,
+            "createdAt": parsed.created.clone().unwrap_or_else(|| updated.clone()),
+            "attachments": [],
+            "aiLocked": false
+        })
+    });
+    if let Value::Object(object) = &mut note {
+        object.insert("title".into(), parsed.title.into());
+        object.insert("content".into(), parsed.content.into());
+        object.insert("category".into(), parsed.category.into());
+        object.insert("tags".into(), json!(parsed.tags));
+        object.insert("updatedAt".into(), updated.into());
+    }
+    note
+}
+
+/// Renders a note as Markdown, keeping unmanaged front matter from `previous`.
+fn render(note: &Value, stem: &str, previous: Option<&MarkdownFile>) -> Result<String, String> {
+    let mut front = Mapping::new();
+    front.insert("id".into(), text(note, "id").into());
+    let title = text(note, "title");
+    // The file name is the title in Obsidian; it is only spelled out when the
+    // name had to be changed to be valid.
+    if title != stem {
+        front.insert("title".into(), title.into());
+    }
+    let category = text(note, "category");
+    if !category.is_empty() && category != ALL_CATEGORY {
+        front.insert("category".into(), category.into());
+    }
+    let tags = note_tags(note);
+    if !tags.is_empty() {
+        front.insert(
+            "tags".into(),
+            serde_yaml::to_value(tags).map_err(|e| e.to_string())?,
+        );
+    }
+    for (key, field) in [("created", "createdAt"), ("updated", "updatedAt")] {
+        let value = text(note, field);
+        if !value.is_empty() {
+            front.insert(key.into(), value.into());
+        }
+    }
+    if let Some((extra, _)) = previous.and_then(|file| split_front_matter(&file.text).ok()) {
+        for (key, value) in extra {
+            if !key.as_str().is_some_and(|key| MANAGED_KEYS.contains(&key)) {
+                front.insert(key, value);
+            }
+        }
+    }
+    let yaml =
+        serde_yaml::to_string(&front).map_err(|e| format!("生成 front matter 失败: {}", e))?;
+    Ok(format!(
+        "---\n{}---\n\n{}\n",
+        yaml,
+        text(note, "content").trim_end()
+    ))
+}
+
+/// A file name for the title, without characters that are invalid on common
+/// file systems or that Obsidian reserves for links.
+fn sanitize_stem(title: &str) -> String {
+    let cleaned: String = title
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' | '#' | '^' | '[' | ']' => ' ',
+            c if c.is_control() => ' ',
+            c => c,
+        })
+        .collect();
+    let stem = cleaned
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .trim_matches('.')
+        .chars()
+        .take(MAX_NAME_CHARS)
+        .collect::<String>()
+        .trim()
+        .to_string();
+    if stem.is_empty() {
+        UNTITLED.to_string()
+    } else {
+        stem
+    }
+}
+
+/// A free path for `stem` in `dir`, numbering it when taken. `own` counts as
+/// free, so a note keeps its numbered name.
+fn free_key(
+    dir: &str,
+    stem: &str,
+    own: Option<&str>,
+    taken: &HashSet<String>,
+    folder: &Path,
+) -> String {
+    let key_for = |name: String| {
+        if dir.is_empty() {
+            name
+        } else {
+            format!("{}/{}", dir, name)
+        }
+    };
+    let free = |key: &String| {
+        own == Some(key.as_str()) || (!taken.contains(key) && !folder.join(key).exists())
+    };
+    let key = key_for(format!("{}.md", stem));
+    if free(&key) {
+        return key;
+    }
+    (2..)
+        .map(|n| key_for(format!("{} {}.md", stem, n)))
+        .find(free)
+        .unwrap_or(key)
+}
+
+struct Reconciler<'a> {
+    folder: &'a Path,
+    files: HashMap<String, MarkdownFile>,
+    /// Paths already accounted for by a note.
+    claimed: HashSet<String>,
+    used_ids: HashSet<String>,
+    entries: HashMap<String, MirrorEntry>,
+    outcome: MirrorOutcome,
+}
+
+impl Reconciler<'_> {
+    fn new_id(&mut self) -> String {
+        let mut id = now_millis();
+        while self.used_ids.contains(&id.to_string()) {
+            id += 1;
+        }
+        self.used_ids.insert(id.to_string());
+        id.to_string()
+    }
+
+    /// Writes a note to its file, moving it when the title no longer matches
+    /// the file name. New notes go to a folder named after their category.
+    fn write(&mut self, note: &Value, current: Option<&str>) -> Result<(), String> {
+        let id = text(note, "id").to_string();
+        let stem = sanitize_stem(text(note, "title"));
+        let dir = match current {
+            Some(key) => key
+                .rsplit_once('/')
+                .map(|(dir, _)| dir.to_string())
+                .unwrap_or_default(),
+            None => match text(note, "category") {
+                "" | ALL_CATEGORY => String::new(),
+                category => sanitize_stem(category),
+            },
+        };
+        let key = free_key(&dir, &stem, current, &self.claimed, self.folder);
+        let previous = current.and_then(|key| self.files.get(key));
+        let rendered = render(note, &file_stem(&key), previous)?;
+        let path = self.folder.join(&key);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| format!("创建目录失败: {}", e))?;
+        }
+        let temp = path.with_extension("md.tmp");
+        fs::write(&temp, &rendered).map_err(|e| format!("写入 {} 失败: {}", key, e))?;
+        fs::rename(&temp, &path).map_err(|e| format!("写入 {} 失败: {}", key, e))?;
+        if let Some(old) = current.filter(|old| *old != key) {
+            let _ = fs::remove_file(self.folder.join(old));
+            // Keeps the old name claimed, so it is not imported as a new note.
+            self.files.remove(old);
+        }
+        self.claimed.insert(key.clone());
+        self.entries.insert(
+            id,
+            MirrorEntry {
+                path: key,
+                file_hash: hex_digest(rendered.as_bytes()),
+                note_hash: note_hash(note),
+            },
+        );
+        self.outcome.written += 1;
+        Ok(())
+    }
+
+    /// Adopts a file's contents for a note, new or existing.
+    fn adopt(&mut self, key: &str, note: Option<&Value>, id: String) -> Option<Value> {
+        let file = self.files.get(key)?;
+        let parsed = match parse_note(key, file) {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                self.outcome.warnings.push(format!("{}: {}", key, e));
+                return None;
+            }
+        };
+        let merged = merge_parsed(note, &id, parsed, file);
+        self.claimed.insert(key.to_string());
+        self.entries.insert(
+            id,
+            MirrorEntry {
+                path: key.to_string(),
+                file_hash: file.hash.clone(),
+                note_hash: note_hash(&merged),
+            },
+        );
+        self.outcome.upserts.push(merged.clone());
+        Some(merged)
+    }
+
+    fn trash(&mut self, key: &str) {
+        let source = self.folder.join(key);
+        let trash = self.folder.join(TRASH_DIR);
+        let name = key.rsplit('/').next().unwrap_or(key);
+        let stem = &name[..name.len() - 3];
+        let target = free_key(TRASH_DIR, stem, None, &HashSet::new(), self.folder);
+        let moved =
+            fs::create_dir_all(&trash).and_then(|_| fs::rename(&source, self.folder.join(&target)));
+        if let Err(e) = moved {
+            self.outcome
+                .warnings
+                .push(format!("移动 {} 到回收站失败: {}", key, e));
+        }
+        self.outcome.removed += 1;
+    }
+}
+
+/// Reconciles the notes with the folder. Changes are judged against the
+/// previous sync: a side that changed wins, and when both did the newer one
+/// is kept and the other is kept as a separate conflict note.
+fn reconcile(
+    folder: &Path,
+    notes: Vec<Value>,
+    index: &mut MirrorIndex,
+) -> Result<MirrorOutcome, String> {
+    let mut warnings = Vec::new();
+    let files = scan(folder, &mut warnings);
+    let notes: Vec<Value> = notes
+        .into_iter()
+        .filter(|note| !text(note, "id").is_empty())
+        .collect();
+    let by_id: HashMap<String, &Value> = notes
+        .iter()
+        .map(|note| (text(note, "id").to_string(), note))
+        .collect();
+    let mut sync = Reconciler {
+        folder,
+        files,
+        claimed: HashSet::new(),
+        used_ids: by_id.keys().cloned().collect(),
+        entries: HashMap::new(),
+        outcome: MirrorOutcome {
+            warnings,
+            ..Default::default()
+        },
+    };
+    check_not_mass_deleted(&index.entries, &sync.files)?;
+    let mut entries = std::mem::take(&mut index.entries);
+
+    // A tracked file that disappeared while an untracked one with the same
+    // bytes appeared was renamed or moved outside the app.
+    let tracked: HashSet<String> = entries.values().map(|entry| entry.path.clone()).collect();
+    let mut untracked: HashMap<String, String> = sync
+        .files
+        .iter()
+        .filter(|(key, _)| !tracked.contains(*key))
+        .map(|(key, file)| (file.hash.clone(), key.clone()))
+        .collect();
+    for entry in entries.values_mut() {
+        if !sync.files.contains_key(&entry.path) {
+            if let Some(key) = untracked.remove(&entry.file_hash) {
+                entry.path = key;
+            }
+        }
+    }
+    // Untracked files that belong to a note the index does not know, as after
+    // the index was lost: by the id in their front matter, or by identical
+    // contents for files the app never wrote. Matched by id, both sides count
+    // as changed and get compared.
+    let mut unindexed: HashMap<String, String> = notes
+        .iter()
+        .filter(|note| !entries.contains_key(text(note, "id")))
+        .map(|note| (note_hash(note), text(note, "id").to_string()))
+        .collect();
+    let mut keys: Vec<&String> = sync
+        .files
+        .keys()
+        .filter(|key| !tracked.contains(*key))
+        .collect();
+    keys.sort();
+    for key in keys {
+        let file = &sync.files[key];
+        let Ok(parsed) = parse_note(key, file) else {
+            continue;
+        };
+        if let Some(id) = parsed.id.clone() {
+            if by_id.contains_key(&id) && !entries.contains_key(&id) {
+                unindexed.retain(|_, other| *other != id);
+                entries.insert(
+                    id,
+                    MirrorEntry {
+                        path: key.clone(),
+                        ..Default::default()
+                    },
+                );
+            }
+            continue;
+        }
+        let hash = note_hash(&merge_parsed(None, "", parsed, file));
+        if let Some(id) = unindexed.remove(&hash) {
+            entries.insert(
+                id,
+                MirrorEntry {
+                    path: key.clone(),
+                    file_hash: file.hash.clone(),
+                    note_hash: hash,
+                },
+            );
+        }
+    }
+
+    let mut ids: Vec<String> = entries.keys().cloned().collect();
+    ids.sort();
+    for id in ids {
+        let entry = entries.remove(&id).unwrap_or_default();
+        let note = by_id.get(&id).copied();
+        let file_hash = sync.files.get(&entry.path).map(|file| file.hash.clone());
+        match (note, file_hash) {
+            (None, None) => {}
+            (None, Some(hash)) => {
+                // Deleted in the app; a file edited since is imported again instead.
+                if hash == entry.file_hash {
+                    sync.claimed.insert(entry.path.clone());
+                    sync.trash(&entry.path);
+                }
+            }
+            (Some(note), None) => {
+                if note_hash(note) == entry.note_hash {
+                    sync.outcome.deleted.push(id);
+                } else {
+                    sync.write(note, None)?;
+                }
+            }
+            (Some(note), Some(hash)) => {
+                sync.claimed.insert(entry.path.clone());
+                let app_changed = note_hash(note) != entry.note_hash;
+                let file_changed = hash != entry.file_hash;
+                match (app_changed, file_changed) {
+                    (false, false) => {
+                        sync.entries.insert(id, entry);
+                    }
+                    (true, false) => sync.write(note, Some(&entry.path))?,
+                    (false, true) => {
+                        if sync.adopt(&entry.path, Some(note), id).is_some() {
+                            sync.outcome.updated += 1;
+                        }
+                    }
+                    (true, true) => resolve_conflict(&mut sync, note, id, &entry.path)?,
+                }
+            }
+        }
+    }
+
+    for note in &notes {
+        if !sync.entries.contains_key(text(note, "id"))
+            && !sync.outcome.deleted.iter().any(|id| id == text(note, "id"))
+        {
+            sync.write(note, None)?;
+        }
+    }
+
+    let mut new_files: Vec<String> = sync
+        .files
+        .keys()
+        .filter(|key| !sync.claimed.contains(*key))
+        .cloned()
+        .collect();
+    new_files.sort();
+    for key in new_files {
+        let id = sync.new_id();
+        if sync.adopt(&key, None, id).is_some() {
+            sync.outcome.imported += 1;
+        }
+    }
+
+    index.entries = sync.entries;
+    index.folder = folder.to_string_lossy().to_string();
+    index.last_sync_at = now_millis();
+    sync.outcome.synced_at = index.last_sync_at;
+    Ok(sync.outcome)
+}
+
+/// Refuses to go on when the tracked files are gone wholesale. A moved vault or
+/// a volume that is not mounted looks like every note was deleted outside the
+/// app, and acting on that would delete the notes themselves.
+fn check_not_mass_deleted(
+    entries: &HashMap<String, MirrorEntry>,
+    files: &HashMap<String, MarkdownFile>,
+) -> Result<(), String> {
+    let gone = entries
+        .values()
+        .filter(|entry| !files.contains_key(&entry.path))
+        .count();
+    if gone > 0
+        && (gone == entries.len() || (gone >= MASS_DELETION_MIN && gone * 2 > entries.len()))
+    {
+        return Err(format!(
+            "笔记同步目录中 {}/{} 个已同步的文件不见了，为避免误删笔记已停止同步，请确认目录没有被移动或替换",
+            gone,
+            entries.len()
+        ));
+    }
+    Ok(())
+}
+
+fn resolve_conflict(
+    sync: &mut Reconciler,
+    note: &Value,
+    id: String,
+    key: &str,
+) -> Result<(), String> {
+    let Some(file) = sync.files.get(key) else {
+        return Ok(());
+    };
+    let parsed = parse_note(key, file)?;
+    let from_file = merge_parsed(Some(note), &id, parsed, file);
+    // Both sides made the same edit.
+    if note_hash(&from_file) == note_hash(note) {
+        sync.entries.insert(
+            id,
+            MirrorEntry {
+                path: key.to_string(),
+                file_hash: file.hash.clone(),
+                note_hash: note_hash(note),
+            },
+        );
+        return Ok(());
+    }
+
+    let file_is_newer = text(&from_file, "updatedAt") > text(note, "updatedAt");
+    let (winner, loser) = if file_is_newer {
+        (from_file, note.clone())
+    } else {
+        (note.clone(), from_file)
+    };
+    let copy_id = sync.new_id();
+    let mut copy = loser;
+    if let Value::Object(object) = &mut copy {
+        let title = format!("{} (冲突副本)", text(note, "title"));
+        object.insert("id".into(), copy_id.into());
+        object.insert("title".into(), title.into());
+    }
+    sync.outcome.warnings.push(format!(
+        "「{}」在应用和文件中都被修改，已保留较新的版本，另一版本另存为冲突副本",
+        text(note, "title")
+    ));
+    sync.outcome.conflicts += 1;
+
+    if file_is_newer {
+        sync.adopt(key, Some(note), id);
+    } else {
+        sync.write(&winner, Some(key))?;
+    }
+    sync.write(&copy, None)?;
+    sync.outcome.upserts.push(copy);
+    Ok(())
+}
+
+fn index_path(app: &AppHandle) -> Result<PathBuf, String> {
+    crate::app_data_file(app, INDEX_FILE)
+}
+
+fn with_index<T>(
+    app: &AppHandle,
+    state: &NotesMirrorState,
+    f: impl FnOnce(&mut MirrorIndex) -> T,
+) -> Result<T, String> {
+    let mut guard = state.index.lock().map_err(|e| e.to_string())?;
+    if guard.is_none() {
+        let loaded = fs::read_to_string(index_path(app)?)
+            .ok()
+            .and_then(|raw| serde_json::from_str(&raw).ok())
+            .unwrap_or_default();
+        *guard = Some(loaded);
+    }
+    Ok(f(guard.get_or_insert_with(MirrorIndex::default)))
+}
+
+fn persist_index(app: &AppHandle, index: &MirrorIndex) -> Result<(), String> {
+    let data = serde_json::to_string(index).map_err(|e| e.to_string())?;
+    fs::write(index_path(app)?, data).map_err(|e| format!("保存笔记同步记录失败: {}", e))
+}
+
+/// Resolves the mirror folder, creating it only for a new mirror. A folder the
+/// index was synced with that is now missing was moved or sits on an absent
+/// volume, and recreating it empty would read as every note deleted.
+fn mirror_folder(folder: &str, indexed: &str) -> Result<PathBuf, String> {
+    let folder = folder.trim();
+    if folder.is_empty() || !Path::new(folder).is_absolute() {
+        return Err("笔记同步目录必须是绝对路径".to_string());
+    }
+    if !Path::new(folder).is_dir() {
+        if !indexed.is_empty() && Path::new(folder) == Path::new(indexed) {
+            return Err(format!(
+                "笔记同步目录不存在: {}，请确认它没有被移动且所在磁盘已连接",
+                folder
+            ));
+        }
+        fs::create_dir_all(folder).map_err(|e| format!("创建笔记同步目录失败: {}", e))?;
+    }
+    fs::canonicalize(folder).map_err(|e| e.to_string())
+}
+
+/// Forwards changed Markdown files whose contents the index does not already
+/// know, so the app's own writes do not bounce back as changes.
+fn run_watch_loop(
+    app: AppHandle,
+    rx: Receiver<notify::Result<notify::Event>>,
+    folder: PathBuf,
+    index: Arc<Mutex<Option<MirrorIndex>>>,
+    debounce: Duration,
+) {
+    let mut pending: HashSet<String> = HashSet::new();
+    let mut last_event = Instant::now();
+    loop {
+        let received = if pending.is_empty() {
+            rx.recv().map_err(|_| RecvTimeoutError::Disconnected)
+        } else {
+            rx.recv_timeout(debounce.saturating_sub(last_event.elapsed()))
+        };
+        match received {
+            Ok(Ok(event)) => {
+                pending.extend(
+                    event
+                        .paths
+                        .iter()
+                        .filter(|path| is_markdown(path))
+                        .filter_map(|path| relative_key(&folder, path)),
+                );
+                last_event = Instant::now();
+            }
+            Ok(Err(_)) => {}
+            Err(RecvTimeoutError::Timeout) => {
+                let known: HashMap<String, String> = index
+                    .lock()
+                    .ok()
+                    .and_then(|guard| guard.clone())
+                    .map(|index| {
+                        index
+                            .entries
+                            .into_values()
+                            .map(|entry| (entry.path, entry.file_hash))
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                let mut paths: Vec<String> = std::mem::take(&mut pending)
+                    .into_iter()
+                    .filter(|key| {
+                        let current = fs::read(folder.join(key)).ok().map(|b| hex_digest(&b));
+                        current.as_ref() != known.get(key)
+                    })
+                    .collect();
+                if paths.is_empty() {
+                    continue;
+                }
+                paths.sort();
+                let _ = app.emit(
+                    "notes-mirror-changed",
+                    MirrorChangeEvent {
+                        folder: folder.to_string_lossy().to_string(),
+                        paths,
+                    },
+                );
+            }
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+    }
+}
+
+fn build_status(app: &AppHandle, state: &NotesMirrorState) -> Result<NotesMirrorStatus, String> {
+    let folder = state
+        .active
+        .lock()
+        .map_err(|e| e.to_string())?
+        .as_ref()
+        .map(|active| active.folder.to_string_lossy().to_string());
+    let (indexed_folder, tracked, last_sync_at) = with_index(app, state, |index| {
+        (
+            index.folder.clone(),
+            index.entries.len(),
+            index.last_sync_at,
+        )
+    })?;
+    Ok(NotesMirrorStatus {
+        running: folder.is_some(),
+        folder: folder.unwrap_or(indexed_folder),
+        tracked,
+        last_sync_at,
+    })
+}
+
+/// Mirrors `notes` to `folder` and returns the changes the app should apply.
+/// Switching to another folder starts over, importing what is already there.
+#[tauri::command]
+pub fn sync_notes_mirror(
+    app: AppHandle,
+    state: State<'_, NotesMirrorState>,
+    folder: String,
+    notes: Vec<Value>,
+) -> Result<MirrorOutcome, String> {
+    let _syncing = state.syncing.lock().map_err(|e| e.to_string())?;
+    let mut index = with_index(&app, &state, |index| index.clone())?;
+    let folder = mirror_folder(&folder, &index.folder)?;
+    if Path::new(&index.folder) != folder {
+        index = MirrorIndex::default();
+    }
+    let outcome = reconcile(&folder, notes, &mut index)?;
+    persist_index(&app, &index)?;
+    with_index(&app, &state, |current| *current = index)?;
+    Ok(outcome)
+}
+
+/// Reads a folder of Markdown notes, such as an Obsidian vault or a Logseq
+/// graph, into new notes without mirroring it.
+#[tauri::command]
+pub fn import_markdown_notes(
+    folder: String,
+    existing_ids: Vec<String>,
+) -> Result<MirrorOutcome, String> {
+    let folder = PathBuf::from(folder.trim());
+    if !folder.is_dir() {
+        return Err(format!("目录不存在: {}", folder.to_string_lossy()));
+    }
+    let mut outcome = MirrorOutcome::default();
+    let files = scan(&folder, &mut outcome.warnings);
+    let mut keys: Vec<&String> = files.keys().collect();
+    keys.sort();
+    let mut used: HashSet<String> = existing_ids.into_iter().collect();
+    let mut next_id = now_millis();
+    for key in keys {
+        match parse_note(key, &files[key]) {
+            Ok(parsed) => {
+                while used.contains(&next_id.to_string()) {
+                    next_id += 1;
+                }
+                let id = next_id.to_string();
+                used.insert(id.clone());
+                outcome
+                    .upserts
+                    .push(merge_parsed(None, &id, parsed, &files[key]));
+                outcome.imported += 1;
+            }
+            Err(e) => outcome.warnings.push(format!("{}: {}", key, e)),
+        }
+    }
+    outcome.synced_at = now_millis();
+    Ok(outcome)
+}
+
+#[tauri::command]
+pub fn start_notes_mirror(
+    app: AppHandle,
+    state: State<'_, NotesMirrorState>,
+    folder: String,
+    debounce_ms: Option<u64>,
+) -> Result<NotesMirrorStatus, String> {
+    let indexed = with_index(&app, &state, |index| index.folder.clone())?;
+    let folder = mirror_folder(&folder, &indexed)?;
+    let debounce_ms = debounce_ms.unwrap_or(1000).clamp(200, 10_000);
+    let (tx, rx) = mpsc::channel();
+    let mut watcher =
+        notify::recommended_watcher(tx).map_err(|e| format!("创建文件监听失败: {}", e))?;
+    watcher
+        .watch(&folder, RecursiveMode::Recursive)
+        .map_err(|e| format!("监听目录失败 {}: {}", folder.to_string_lossy(), e))?;
+
+    let worker_app = app.clone();
+    let worker_folder = folder.clone();
+    let worker_index = state.index.clone();
+    std::thread::spawn(move || {
+        run_watch_loop(
+            worker_app,
+            rx,
+            worker_folder,
+            worker_index,
+            Duration::from_millis(debounce_ms),
+        )
+    });
+
+    // Replacing the previous watcher drops its sender, which ends the old worker thread.
+    *state.active.lock().map_err(|e| e.to_string())? = Some(ActiveMirror {
+        _watcher: watcher,
+        folder,
+    });
+    build_status(&app, &state)
+}
+
+#[tauri::command]
+pub fn stop_notes_mirror(
+    app: AppHandle,
+    state: State<'_, NotesMirrorState>,
+) -> Result<NotesMirrorStatus, String> {
+    state.active.lock().map_err(|e| e.to_string())?.take();
+    build_status(&app, &state)
+}
+
+#[tauri::command]
+pub fn get_notes_mirror_status(
+    app: AppHandle,
+    state: State<'_, NotesMirrorState>,
+) -> Result<NotesMirrorStatus, String> {
+    build_status(&app, &state)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+
+    fn note(id: &str, title: &str, content: &str) -> Value {
+        json!({
+            "id": id,
+            "title": title,
+            "content": content,
+            "category": ALL_CATEGORY,
+            "tags": [],
+            "createdAt": "2000-01-01T00:00:00.000Z",
+            "updatedAt": "2000-01-01T00:00:00.000Z",
+        })
+    }
+
+    fn notes() -> Vec<Value> {
+        vec![
+            note("1", "Alpha", "alpha body"),
+            note("2", "Beta", "beta body"),
+            note("3", "Gamma", "gamma body"),
+        ]
+    }
+
+    /// A folder mirrored once from `notes()`.
+    fn mirrored() -> (TempDir, MirrorIndex) {
+        let dir = TempDir::new("notes-mirror");
+        let mut index = MirrorIndex::default();
+        let outcome = reconcile(dir.path(), notes(), &mut index).unwrap();
+        assert_eq!(outcome.written, 3);
+        assert!(dir.path().join("Alpha.md").is_file());
+        (dir, index)
+    }
+
+    fn unchanged(outcome: &MirrorOutcome) -> bool {
+        outcome.upserts.is_empty()
+            && outcome.deleted.is_empty()
+            && outcome.written == 0
+            && outcome.removed == 0
+    }
+
+    #[test]
+    fn a_second_sync_without_edits_changes_nothing() {
+        let (dir, mut index) = mirrored();
+        let outcome = reconcile(dir.path(), notes(), &mut index).unwrap();
+        assert!(unchanged(&outcome));
+        assert_eq!(index.entries.len(), 3);
+    }
+
+    #[test]
+    fn files_moved_outside_the_app_are_followed() {
+        let (dir, mut index) = mirrored();
+        fs::create_dir_all(dir.path().join("Archive")).unwrap();
+        fs::rename(
+            dir.path().join("Alpha.md"),
+            dir.path().join("Archive/Alpha.md"),
+        )
+        .unwrap();
+
+        let outcome = reconcile(dir.path(), notes(), &mut index).unwrap();
+        assert!(unchanged(&outcome));
+        assert_eq!(outcome.imported, 0);
+        assert_eq!(index.entries["1"].path, "Archive/Alpha.md");
+    }
+
+    #[test]
+    fn notes_deleted_in_the_app_go_to_the_trash() {
+        let (dir, mut index) = mirrored();
+        let outcome = reconcile(dir.path(), notes()[1..].to_vec(), &mut index).unwrap();
+        assert_eq!(outcome.removed, 1);
+        assert!(outcome.deleted.is_empty());
+        assert!(!dir.path().join("Alpha.md").exists());
+        assert!(dir.path().join(TRASH_DIR).join("Alpha.md").is_file());
+        assert!(!index.entries.contains_key("1"));
+    }
+
+    #[test]
+    fn files_deleted_in_the_folder_delete_the_note() {
+        let (dir, mut index) = mirrored();
+        fs::remove_file(dir.path().join("Alpha.md")).unwrap();
+        let outcome = reconcile(dir.path(), notes(), &mut index).unwrap();
+        assert_eq!(outcome.deleted, ["1"]);
+        assert_eq!(outcome.written, 0);
+        assert!(!index.entries.contains_key("1"));
+    }
+
+    #[test]
+    fn edits_on_both_sides_keep_a_conflict_copy() {
+        let (dir, mut index) = mirrored();
+        let path = dir.path().join("Alpha.md");
+        let written = fs::read_to_string(&path).unwrap();
+        fs::write(&path, written.replace("alpha body", "edited in the vault")).unwrap();
+        let mut edited = notes();
+        edited[0]["content"] = "edited in the app".into();
+
+        let outcome = reconcile(dir.path(), edited, &mut index).unwrap();
+        assert_eq!(outcome.conflicts, 1);
+        // The file is newer than the note's `updatedAt`, so it wins.
+        let contents: Vec<&str> = outcome
+            .upserts
+            .iter()
+            .map(|note| text(note, "content"))
+            .collect();
+        assert!(contents.contains(&"edited in the vault"));
+        let copy = outcome
+            .upserts
+            .iter()
+            .find(|note| text(note, "title") == "Alpha (冲突副本)")
+            .unwrap();
+        assert_eq!(text(copy, "content"), "edited in the app");
+        assert_ne!(text(copy, "id"), "1");
+        assert!(dir.path().join("Alpha (冲突副本).md").is_file());
+    }
+
+    #[test]
+    fn a_missing_folder_is_not_recreated_or_read_as_deletions() {
+        let (dir, mut index) = mirrored();
+        let folder = dir.path().to_string_lossy().to_string();
+        let indexed = fs::canonicalize(dir.path())
+            .unwrap()
+            .to_string_lossy()
+            .to_string();
+        fs::remove_dir_all(dir.path()).unwrap();
+
+        assert!(mirror_folder(&folder, &indexed).is_err());
+        assert!(!dir.path().exists());
+
+        // An empty folder in its place, e.g. an unmounted volume's mount point.
+        fs::create_dir_all(dir.path()).unwrap();
+        let before = index.entries.len();
+        assert!(reconcile(dir.path(), notes(), &mut index).is_err());
+        assert_eq!(index.entries.len(), before);
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 0);
+
+        // A different folder is a new mirror and is created.
+        let other = dir.path().join("elsewhere");
+        assert!(mirror_folder(&other.to_string_lossy(), &indexed).is_ok());
+        assert!(other.is_dir());
+    }
+}
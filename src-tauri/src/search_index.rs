@@ -0,0 +1,687 @@
+use serde::Serialize;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::sync::Mutex;
+use tantivy::collector::{DocSetCollector, TopDocs};
+use tantivy::directory::MmapDirectory;
+use tantivy::query::{
+    AllQuery, BooleanQuery, BoostQuery, Occur, Query, QueryParser, RegexQuery, TermQuery,
+};
+use tantivy::schema::{
+    Field, IndexRecordOption, Schema, TextFieldIndexing, TextOptions, Value as _, STORED, STRING,
+};
+use tantivy::snippet::SnippetGenerator;
+use tantivy::tokenizer::{LowerCaser, RemoveLongFilter, TextAnalyzer, Token, TokenStream, Tokenizer};
+use tantivy::{doc, Index, IndexReader, ReloadPolicy, TantivyDocument, Term};
+use tauri::{AppHandle, State};
+
+const INDEX_DIR: &str = "search_index";
+const TOKENIZER_NAME: &str = "workplan_cjk";
+
+#[derive(Default)]
+pub struct SearchIndexState {
+    index: Mutex<Option<SearchIndex>>,
+}
+
+struct SearchIndex {
+    index: Index,
+    reader: IndexReader,
+    fields: SearchFields,
+    /// Content digest of every indexed document by `kind:id`, so a sync only
+    /// rewrites the documents that changed.
+    digests: HashMap<String, String>,
+}
+
+#[derive(Clone, Copy)]
+struct SearchFields {
+    key: Field,
+    digest: Field,
+    kind: Field,
+    id: Field,
+    title: Field,
+    body: Field,
+    updated_at: Field,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchHit {
+    kind: String,
+    id: String,
+    title: String,
+    snippet: String,
+    title_html: String,
+    updated_at: String,
+    score: f32,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchIndexSummary {
+    tasks: usize,
+    notes: usize,
+    chats: usize,
+}
+
+/// Splits Latin text on word boundaries and CJK runs into overlapping bigrams,
+/// so Chinese and Japanese match without a dictionary-based segmenter.
+#[derive(Clone)]
+struct CjkTokenizer;
+
+struct CjkTokenStream {
+    tokens: Vec<Token>,
+    cursor: usize,
+}
+
+fn is_cjk(c: char) -> bool {
+    matches!(c as u32,
+        0x3040..=0x30FF   // Hiragana, Katakana
+        | 0x3400..=0x4DBF // CJK Extension A
+        | 0x4E00..=0x9FFF // CJK Unified Ideographs
+        | 0xAC00..=0xD7AF // Hangul Syllables
+        | 0xF900..=0xFAFF // CJK Compatibility Ideographs
+        | 0xFF66..=0xFF9F // Halfwidth Katakana
+        | 0x20000..=0x2A6DF)
+}
+
+fn push_token(tokens: &mut Vec<Token>, text: &str, from: usize, to: usize) {
+    tokens.push(Token {
+        offset_from: from,
+        offset_to: to,
+        position: tokens.len(),
+        text: text[from..to].to_string(),
+        position_length: 1,
+    });
+}
+
+fn flush_cjk_run(tokens: &mut Vec<Token>, text: &str, run: &[(usize, char)]) {
+    if run.len() == 1 {
+        let (start, c) = run[0];
+        push_token(tokens, text, start, start + c.len_utf8());
+        return;
+    }
+    for pair in run.windows(2) {
+        let (start, _) = pair[0];
+        let (second, c) = pair[1];
+        push_token(tokens, text, start, second + c.len_utf8());
+    }
+}
+
+pub(crate) fn tokenize(text: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut word_start: Option<usize> = None;
+    let mut cjk_run: Vec<(usize, char)> = Vec::new();
+
+    for (offset, c) in text.char_indices() {
+        if is_cjk(c) {
+            if let Some(start) = word_start.take() {
+                push_token(&mut tokens, text, start, offset);
+            }
+            cjk_run.push((offset, c));
+        } else if c.is_alphanumeric() {
+            if !cjk_run.is_empty() {
+                flush_cjk_run(&mut tokens, text, &cjk_run);
+                cjk_run.clear();
+            }
+            word_start.get_or_insert(offset);
+        } else {
+            if let Some(start) = word_start.take() {
+                push_token(&mut tokens, text, start, offset);
+            }
+            if !cjk_run.is_empty() {
+                flush_cjk_run(&mut tokens, text, &cjk_run);
+                cjk_run.clear();
+            }
+        }
+    }
+    if let Some(start) = word_start {
+        push_token(&mut tokens, text, start, text.len());
+    }
+    if !cjk_run.is_empty() {
+        flush_cjk_run(&mut tokens, text, &cjk_run);
+    }
+    tokens
+}
+
+impl Tokenizer for CjkTokenizer {
+    type TokenStream<'a> = CjkTokenStream;
+
+    fn token_stream<'a>(&'a mut self, text: &'a str) -> CjkTokenStream {
+        CjkTokenStream {
+            tokens: tokenize(text),
+            cursor: 0,
+        }
+    }
+}
+
+impl TokenStream for CjkTokenStream {
+    fn advance(&mut self) -> bool {
+        if self.cursor < self.tokens.len() {
+            self.cursor += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn token(&self) -> &Token {
+        &self.tokens[self.cursor - 1]
+    }
+
+    fn token_mut(&mut self) -> &mut Token {
+        &mut self.tokens[self.cursor - 1]
+    }
+}
+
+fn build_schema() -> (Schema, SearchFields) {
+    let mut builder = Schema::builder();
+    let text_options = TextOptions::default()
+        .set_indexing_options(
+            TextFieldIndexing::default()
+                .set_tokenizer(TOKENIZER_NAME)
+                .set_index_option(IndexRecordOption::WithFreqsAndPositions),
+        )
+        .set_stored();
+    let fields = SearchFields {
+        key: builder.add_text_field("key", STRING | STORED),
+        digest: builder.add_text_field("digest", STORED),
+        kind: builder.add_text_field("kind", STRING | STORED),
+        id: builder.add_text_field("id", STRING | STORED),
+        title: builder.add_text_field("title", text_options.clone()),
+        body: builder.add_text_field("body", text_options),
+        updated_at: builder.add_text_field("updated_at", STORED),
+    };
+    (builder.build(), fields)
+}
+
+fn open_index(app: &AppHandle) -> Result<SearchIndex, String> {
+    let dir = crate::app_data_file(app, INDEX_DIR)?;
+    fs::create_dir_all(&dir).map_err(|e| format!("创建索引目录失败: {}", e))?;
+
+    let (schema, fields) = build_schema();
+    let open = |schema: Schema| -> tantivy::Result<Index> {
+        Index::open_or_create(MmapDirectory::open(&dir)?, schema)
+    };
+    let index = match open(schema.clone()) {
+        Ok(index) => index,
+        Err(_) => {
+            // The index is only a cache of frontend data, so an incompatible one is rebuilt.
+            let _ = fs::remove_dir_all(&dir);
+            fs::create_dir_all(&dir).map_err(|e| format!("创建索引目录失败: {}", e))?;
+            open(schema).map_err(|e| format!("打开搜索索引失败: {}", e))?
+        }
+    };
+
+    load_index(index, fields)
+}
+
+fn load_index(index: Index, fields: SearchFields) -> Result<SearchIndex, String> {
+    index.tokenizers().register(
+        TOKENIZER_NAME,
+        TextAnalyzer::builder(CjkTokenizer)
+            .filter(RemoveLongFilter::limit(64))
+            .filter(LowerCaser)
+            .build(),
+    );
+
+    let reader = index
+        .reader_builder()
+        .reload_policy(ReloadPolicy::Manual)
+        .try_into()
+        .map_err(|e| format!("打开搜索索引失败: {}", e))?;
+
+    let searcher = reader.searcher();
+    let addresses = searcher
+        .search(&AllQuery, &DocSetCollector)
+        .map_err(|e| format!("读取搜索索引失败: {}", e))?;
+    let mut digests = HashMap::new();
+    for address in addresses {
+        let document: TantivyDocument = searcher
+            .doc(address)
+            .map_err(|e| format!("读取搜索索引失败: {}", e))?;
+        digests.insert(
+            stored_text(&document, fields.key),
+            stored_text(&document, fields.digest),
+        );
+    }
+
+    Ok(SearchIndex {
+        index,
+        reader,
+        fields,
+        digests,
+    })
+}
+
+fn with_index<T>(
+    app: &AppHandle,
+    state: &SearchIndexState,
+    f: impl FnOnce(&mut SearchIndex) -> Result<T, String>,
+) -> Result<T, String> {
+    let mut guard = state.index.lock().map_err(|e| e.to_string())?;
+    if guard.is_none() {
+        *guard = Some(open_index(app)?);
+    }
+    f(guard.as_mut().expect("search index initialized above"))
+}
+
+pub(crate) fn text_of(value: &Value, key: &str) -> String {
+    match value.get(key) {
+        Some(Value::String(text)) => text.clone(),
+        Some(Value::Number(number)) => number.to_string(),
+        _ => String::new(),
+    }
+}
+
+/// The searchable text of one task, note or chat session.
+struct Entry {
+    kind: &'static str,
+    id: String,
+    title: String,
+    body: String,
+    updated_at: String,
+}
+
+impl Entry {
+    fn key(&self) -> String {
+        format!("{}:{}", self.kind, self.id)
+    }
+
+    fn digest(&self) -> String {
+        let mut hasher = Sha256::new();
+        for part in [&self.title, &self.body, &self.updated_at] {
+            hasher.update(part.as_bytes());
+            hasher.update([0]);
+        }
+        hasher
+            .finalize()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect()
+    }
+
+    fn document(&self, fields: &SearchFields, digest: &str) -> TantivyDocument {
+        doc!(
+            fields.key => self.key(),
+            fields.digest => digest,
+            fields.kind => self.kind,
+            fields.id => self.id.as_str(),
+            fields.title => self.title.as_str(),
+            fields.body => self.body.as_str(),
+            fields.updated_at => self.updated_at.as_str(),
+        )
+    }
+}
+
+fn task_entry(task: &Value) -> Option<Entry> {
+    let id = text_of(task, "id");
+    if id.is_empty() {
+        return None;
+    }
+    let subtasks = task
+        .get("subtasks")
+        .and_then(Value::as_array)
+        .map(|items| {
+            items
+                .iter()
+                .map(|item| text_of(item, "title"))
+                .collect::<Vec<_>>()
+                .join("\n")
+        })
+        .unwrap_or_default();
+    let body = [text_of(task, "note"), subtasks]
+        .into_iter()
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n");
+    Some(Entry {
+        kind: "task",
+        id,
+        title: text_of(task, "title"),
+        body,
+        updated_at: text_of(task, "date"),
+    })
+}
+
+fn note_entry(note: &Value) -> Option<Entry> {
+    let id = text_of(note, "id");
+    if id.is_empty() {
+        return None;
+    }
+    let tags = note
+        .get("tags")
+        .and_then(Value::as_array)
+        .map(|items| {
+            items
+                .iter()
+                .filter_map(Value::as_str)
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .unwrap_or_default();
+    let body = [text_of(note, "content"), tags]
+        .into_iter()
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n");
+    Some(Entry {
+        kind: "note",
+        id,
+        title: text_of(note, "title"),
+        body,
+        updated_at: text_of(note, "updatedAt"),
+    })
+}
+
+fn chat_entry(session: &Value) -> Option<Entry> {
+    let id = text_of(session, "id");
+    if id.is_empty() {
+        return None;
+    }
+    let body = session
+        .get("history")
+        .and_then(Value::as_array)
+        .map(|messages| {
+            messages
+                .iter()
+                .map(|message| text_of(message, "content"))
+                .filter(|content| !content.is_empty())
+                .collect::<Vec<_>>()
+                .join("\n")
+        })
+        .unwrap_or_default();
+    Some(Entry {
+        kind: "chat",
+        id,
+        title: text_of(session, "title"),
+        body,
+        updated_at: text_of(session, "updatedAt"),
+    })
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn stored_text(doc: &TantivyDocument, field: Field) -> String {
+    doc.get_first(field)
+        .and_then(|value| value.as_str())
+        .unwrap_or("")
+        .to_string()
+}
+
+/// Rewrites only the documents whose content changed and drops the ones that
+/// are gone, instead of rebuilding the whole index on every store change.
+fn apply_entries(search: &mut SearchIndex, entries: Vec<Entry>) -> Result<(), String> {
+    let fields = search.fields;
+    let mut removed: HashSet<String> = search.digests.keys().cloned().collect();
+    let mut changed = Vec::new();
+    for entry in entries {
+        let key = entry.key();
+        removed.remove(&key);
+        let digest = entry.digest();
+        if search.digests.get(&key) != Some(&digest) {
+            changed.push((key, digest, entry));
+        }
+    }
+    if changed.is_empty() && removed.is_empty() {
+        return Ok(());
+    }
+
+    let mut writer = search
+        .index
+        .writer_with_num_threads::<TantivyDocument>(1, 20_000_000)
+        .map_err(|e| format!("打开索引写入器失败: {}", e))?;
+    for key in &removed {
+        writer.delete_term(Term::from_field_text(fields.key, key));
+    }
+    for (key, digest, entry) in &changed {
+        writer.delete_term(Term::from_field_text(fields.key, key));
+        writer
+            .add_document(entry.document(&fields, digest))
+            .map_err(|e| format!("写入搜索索引失败: {}", e))?;
+    }
+    writer
+        .commit()
+        .map_err(|e| format!("提交搜索索引失败: {}", e))?;
+    search
+        .reader
+        .reload()
+        .map_err(|e| format!("刷新搜索索引失败: {}", e))?;
+
+    for key in removed {
+        search.digests.remove(&key);
+    }
+    for (key, digest, _) in changed {
+        search.digests.insert(key, digest);
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn sync_search_index(
+    app: AppHandle,
+    state: State<'_, SearchIndexState>,
+    tasks: Vec<Value>,
+    notes: Vec<Value>,
+    chat_sessions: Vec<Value>,
+) -> Result<SearchIndexSummary, String> {
+    let tasks: Vec<Entry> = tasks.iter().filter_map(task_entry).collect();
+    let notes: Vec<Entry> = notes.iter().filter_map(note_entry).collect();
+    let chats: Vec<Entry> = chat_sessions.iter().filter_map(chat_entry).collect();
+    let summary = SearchIndexSummary {
+        tasks: tasks.len(),
+        notes: notes.len(),
+        chats: chats.len(),
+    };
+    with_index(&app, &state, |search| {
+        apply_entries(search, tasks.into_iter().chain(notes).chain(chats).collect())
+    })?;
+    Ok(summary)
+}
+
+#[tauri::command]
+pub fn search_everything(
+    app: AppHandle,
+    state: State<'_, SearchIndexState>,
+    query: String,
+    kinds: Option<Vec<String>>,
+    limit: Option<usize>,
+) -> Result<Vec<SearchHit>, String> {
+    let needle = query.trim();
+    if needle.is_empty() {
+        return Ok(Vec::new());
+    }
+    let limit = limit.unwrap_or(20).clamp(1, 100);
+    with_index(&app, &state, |search| {
+        search_in(search, needle, kinds.unwrap_or_default(), limit)
+    })
+}
+
+/// A lone CJK character, which the index mostly holds inside bigrams.
+fn is_single_cjk_char(word: &str) -> bool {
+    let mut chars = word.chars();
+    matches!((chars.next(), chars.next()), (Some(c), None) if is_cjk(c))
+}
+
+/// Parses the query, matching single CJK characters against every bigram (or
+/// lone-character token) that contains them.
+fn text_query(search: &SearchIndex, needle: &str) -> Result<Box<dyn Query>, String> {
+    let fields = search.fields;
+    let (chars, words): (Vec<&str>, Vec<&str>) = needle
+        .split_whitespace()
+        .partition(|word| is_single_cjk_char(word));
+
+    let mut parser = QueryParser::for_index(&search.index, vec![fields.title, fields.body]);
+    parser.set_field_boost(fields.title, 2.0);
+    let mut clauses: Vec<(Occur, Box<dyn Query>)> = Vec::new();
+    if !words.is_empty() {
+        let (query, _) = parser.parse_query_lenient(&words.join(" "));
+        clauses.push((Occur::Should, query));
+    }
+    for word in chars {
+        let pattern = format!("{0}|{0}.|.{0}", word);
+        for (field, boost) in [(fields.title, 2.0), (fields.body, 1.0)] {
+            let query = RegexQuery::from_pattern(&pattern, field)
+                .map_err(|e| format!("搜索失败: {}", e))?;
+            clauses.push((
+                Occur::Should,
+                Box::new(BoostQuery::new(Box::new(query), boost)),
+            ));
+        }
+    }
+    Ok(if clauses.len() == 1 {
+        clauses.remove(0).1
+    } else {
+        Box::new(BooleanQuery::new(clauses))
+    })
+}
+
+fn search_in(
+    search: &SearchIndex,
+    needle: &str,
+    kinds: Vec<String>,
+    limit: usize,
+) -> Result<Vec<SearchHit>, String> {
+    let fields = search.fields;
+    let searcher = search.reader.searcher();
+    let text_query = text_query(search, needle)?;
+
+    let kind_filter: Vec<(Occur, Box<dyn Query>)> = kinds
+        .iter()
+        .map(|kind| kind.trim())
+        .filter(|kind| !kind.is_empty())
+        .map(|kind| {
+            let term = Term::from_field_text(fields.kind, kind);
+            (
+                Occur::Should,
+                Box::new(TermQuery::new(term, IndexRecordOption::Basic)) as Box<dyn Query>,
+            )
+        })
+        .collect();
+    let query: Box<dyn Query> = if kind_filter.is_empty() {
+        text_query
+    } else {
+        Box::new(BooleanQuery::new(vec![
+            (Occur::Must, text_query),
+            (Occur::Must, Box::new(BooleanQuery::new(kind_filter))),
+        ]))
+    };
+
+    let top_docs = searcher
+        .search(&query, &TopDocs::with_limit(limit))
+        .map_err(|e| format!("搜索失败: {}", e))?;
+
+    let mut body_snippets = SnippetGenerator::create(&searcher, &*query, fields.body)
+        .map_err(|e| format!("生成摘要失败: {}", e))?;
+    body_snippets.set_max_num_chars(160);
+    let title_snippets = SnippetGenerator::create(&searcher, &*query, fields.title)
+        .map_err(|e| format!("生成摘要失败: {}", e))?;
+
+    let mut hits = Vec::new();
+    for (score, address) in top_docs {
+        let document: TantivyDocument = searcher
+            .doc(address)
+            .map_err(|e| format!("读取搜索结果失败: {}", e))?;
+        let title = stored_text(&document, fields.title);
+        let body = stored_text(&document, fields.body);
+        let mut snippet = body_snippets.snippet(&body).to_html();
+        if snippet.is_empty() {
+            snippet = escape_html(&body.chars().take(160).collect::<String>());
+        }
+        let mut title_html = title_snippets.snippet(&title).to_html();
+        if title_html.is_empty() {
+            title_html = escape_html(&title);
+        }
+        hits.push(SearchHit {
+            kind: stored_text(&document, fields.kind),
+            id: stored_text(&document, fields.id),
+            title,
+            snippet,
+            title_html,
+            updated_at: stored_text(&document, fields.updated_at),
+            score,
+        });
+    }
+    Ok(hits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn memory_index() -> SearchIndex {
+        let (schema, fields) = build_schema();
+        load_index(Index::create_in_ram(schema), fields).unwrap()
+    }
+
+    fn sync(search: &mut SearchIndex, tasks: &[Value], notes: &[Value]) {
+        let entries = tasks
+            .iter()
+            .filter_map(task_entry)
+            .chain(notes.iter().filter_map(note_entry))
+            .collect();
+        apply_entries(search, entries).unwrap();
+    }
+
+    fn ids(search: &SearchIndex, query: &str) -> Vec<String> {
+        let mut ids: Vec<String> = search_in(search, query, Vec::new(), 20)
+            .unwrap()
+            .into_iter()
+            .map(|hit| format!("{}:{}", hit.kind, hit.id))
+            .collect();
+        ids.sort();
+        ids
+    }
+
+    #[test]
+    fn single_cjk_characters_match_inside_bigrams() {
+        let mut search = memory_index();
+        sync(
+            &mut search,
+            &[
+                json!({ "id": "1", "title": "周一开会讨论预算" }),
+                json!({ "id": "2", "title": "会" }),
+                json!({ "id": "3", "title": "写周报" }),
+            ],
+            &[json!({ "id": "n1", "title": "Notes", "content": "会议纪要" })],
+        );
+
+        assert_eq!(ids(&search, "会"), ["note:n1", "task:1", "task:2"]);
+        assert_eq!(ids(&search, "开会"), ["task:1"]);
+        assert_eq!(ids(&search, "周 budget"), ["task:1", "task:3"]);
+        assert!(ids(&search, "猫").is_empty());
+    }
+
+    #[test]
+    fn sync_rewrites_only_changed_documents() {
+        let mut search = memory_index();
+        let first = json!({ "id": "1", "title": "draft report" });
+        let second = json!({ "id": "2", "title": "weekly review" });
+        sync(&mut search, &[first.clone(), second.clone()], &[]);
+        assert_eq!(search.digests.len(), 2);
+        let opstamp = search.index.load_metas().unwrap().opstamp;
+
+        // An unchanged store leaves the index untouched.
+        sync(&mut search, &[first.clone(), second.clone()], &[]);
+        assert_eq!(search.index.load_metas().unwrap().opstamp, opstamp);
+
+        let edited = json!({ "id": "1", "title": "final report" });
+        sync(&mut search, &[edited], &[]);
+        assert_eq!(ids(&search, "report"), ["task:1"]);
+        assert!(ids(&search, "draft").is_empty());
+        assert!(ids(&search, "weekly").is_empty());
+        assert_eq!(search.reader.searcher().num_docs(), 1);
+        assert_eq!(search.digests.len(), 1);
+
+        // Digests are restored from the stored documents when the index reopens.
+        let reopened = load_index(search.index.clone(), search.fields).unwrap();
+        assert_eq!(reopened.digests, search.digests);
+    }
+}
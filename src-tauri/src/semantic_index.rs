@@ -0,0 +1,721 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, State};
+use walkdir::WalkDir;
+
+use crate::network::NetworkState;
+
+const INDEX_FILE: &str = "semantic_index.json";
+const LOCAL_DIMENSIONS: usize = 384;
+const EMBED_BATCH_SIZE: usize = 32;
+const EMBED_TIMEOUT: Duration = Duration::from_secs(60);
+const MAX_DOCUMENT_CHARS: usize = 4000;
+const FILE_CHUNK_CHARS: usize = 1200;
+const MAX_FILE_BYTES: u64 = 512 * 1024;
+const MAX_INDEXED_FILES: usize = 500;
+const TEXT_EXTENSIONS: [&str; 12] = [
+    "md", "markdown", "txt", "rst", "org", "csv", "log", "json", "yaml", "yml", "toml", "html",
+];
+/// Dependency, build and VCS folders inside trusted directories; their files
+/// would crowd out the user's own under `MAX_INDEXED_FILES`.
+const IGNORED_DIRS: [&str; 4] = [".git", "node_modules", "target", ".svelte-kit"];
+
+#[derive(Default)]
+pub struct SemanticIndexState {
+    records: Mutex<Option<Vec<EmbeddingRecord>>>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct EmbeddingRecord {
+    key: String,
+    kind: String,
+    id: String,
+    title: String,
+    excerpt: String,
+    content_hash: String,
+    model: String,
+    vector: Vec<f32>,
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct EmbeddingConfig {
+    /// `openai` (any OpenAI-compatible `/embeddings` API), `ollama`, or `local`.
+    provider: String,
+    #[serde(default)]
+    api_url: String,
+    #[serde(default)]
+    api_key: String,
+    #[serde(default)]
+    model: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SemanticIndexSummary {
+    embedded: usize,
+    reused: usize,
+    removed: usize,
+    total: usize,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SemanticHit {
+    kind: String,
+    id: String,
+    title: String,
+    excerpt: String,
+    score: f32,
+}
+
+struct PendingDocument {
+    key: String,
+    kind: String,
+    id: String,
+    title: String,
+    text: String,
+}
+
+impl EmbeddingConfig {
+    fn signature(&self) -> String {
+        match self.provider.as_str() {
+            "local" => format!("local:hash-{}", LOCAL_DIMENSIONS),
+            provider => format!("{}:{}", provider, self.model),
+        }
+    }
+}
+
+fn content_hash(text: &str) -> String {
+    let digest = Sha256::digest(text.as_bytes());
+    digest.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn truncate_chars(text: &str, limit: usize) -> String {
+    text.chars().take(limit).collect()
+}
+
+fn normalize(mut vector: Vec<f32>) -> Vec<f32> {
+    let norm = vector.iter().map(|value| value * value).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        vector.iter_mut().for_each(|value| *value /= norm);
+    }
+    vector
+}
+
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325u64, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// Lexical fallback for offline use: feature-hashes the same word/CJK-bigram
+/// tokens the full-text index uses. Texts only score as similar when they share
+/// words, so this is keyword matching rather than semantic search; the settings
+/// label it as such.
+fn local_embedding(text: &str) -> Vec<f32> {
+    let mut vector = vec![0.0f32; LOCAL_DIMENSIONS];
+    for token in crate::search_index::tokenize(text) {
+        let hash = fnv1a(token.text.to_lowercase().as_bytes());
+        let slot = (hash % LOCAL_DIMENSIONS as u64) as usize;
+        let sign = if (hash >> 63) == 0 { 1.0 } else { -1.0 };
+        vector[slot] += sign;
+    }
+    normalize(vector)
+}
+
+fn embeddings_url(config: &EmbeddingConfig) -> Result<String, String> {
+    let base = config.api_url.trim().trim_end_matches('/');
+    match config.provider.as_str() {
+        "openai" => {
+            if base.is_empty() {
+                return Err("请填写 Embeddings 接口地址".to_string());
+            }
+            Ok(if base.ends_with("/embeddings") {
+                base.to_string()
+            } else {
+                format!("{}/embeddings", base)
+            })
+        }
+        "ollama" => {
+            let base = if base.is_empty() {
+                "http://127.0.0.1:11434"
+            } else {
+                base
+            };
+            Ok(if base.ends_with("/api/embed") {
+                base.to_string()
+            } else {
+                format!("{}/api/embed", base)
+            })
+        }
+        other => Err(format!("不支持的 Embeddings 服务: {}", other)),
+    }
+}
+
+fn parse_float_array(value: &Value) -> Option<Vec<f32>> {
+    value
+        .as_array()?
+        .iter()
+        .map(|item| item.as_f64().map(|number| number as f32))
+        .collect()
+}
+
+async fn embed_remote(
+    client: &reqwest::Client,
+    config: &EmbeddingConfig,
+    texts: &[String],
+) -> Result<Vec<Vec<f32>>, String> {
+    let url = embeddings_url(config)?;
+    let mut request = client.post(&url).timeout(EMBED_TIMEOUT).json(&serde_json::json!({
+        "model": config.model,
+        "input": texts,
+    }));
+    if !config.api_key.trim().is_empty() {
+        request = request.bearer_auth(config.api_key.trim());
+    }
+
+    let response = request
+        .send()
+        .await
+        .map_err(|e| format!("Embeddings 请求失败: {}", e))?;
+    if !response.status().is_success() {
+        let status = response.status();
+        let body = response.text().await.unwrap_or_default();
+        return Err(format!(
+            "Embeddings 请求失败: HTTP {} {}",
+            status,
+            truncate_chars(&body, 200)
+        ));
+    }
+    let payload: Value = response
+        .json()
+        .await
+        .map_err(|e| format!("解析 Embeddings 响应失败: {}", e))?;
+
+    let vectors: Option<Vec<Vec<f32>>> = if config.provider == "ollama" {
+        payload
+            .get("embeddings")
+            .and_then(Value::as_array)
+            .map(|items| items.iter().filter_map(parse_float_array).collect())
+    } else {
+        payload.get("data").and_then(Value::as_array).map(|items| {
+            let mut indexed: Vec<(u64, Vec<f32>)> = items
+                .iter()
+                .filter_map(|item| {
+                    let index = item.get("index").and_then(Value::as_u64).unwrap_or(0);
+                    parse_float_array(item.get("embedding")?).map(|vector| (index, vector))
+                })
+                .collect();
+            indexed.sort_by_key(|(index, _)| *index);
+            indexed.into_iter().map(|(_, vector)| vector).collect()
+        })
+    };
+
+    match vectors {
+        Some(vectors) if vectors.len() == texts.len() => {
+            Ok(vectors.into_iter().map(normalize).collect())
+        }
+        _ => Err("Embeddings 响应格式不正确".to_string()),
+    }
+}
+
+async fn embed_texts(
+    app: &AppHandle,
+    network: &NetworkState,
+    config: &EmbeddingConfig,
+    texts: &[String],
+) -> Result<Vec<Vec<f32>>, String> {
+    if config.provider == "local" {
+        return Ok(texts.iter().map(|text| local_embedding(text)).collect());
+    }
+
+    let client = network.client(app)?;
+    let mut vectors = Vec::with_capacity(texts.len());
+    for batch in texts.chunks(EMBED_BATCH_SIZE) {
+        vectors.extend(embed_remote(&client, config, batch).await?);
+    }
+    Ok(vectors)
+}
+
+fn join_parts(parts: &[String]) -> String {
+    parts
+        .iter()
+        .filter(|part| !part.trim().is_empty())
+        .cloned()
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn task_documents(tasks: &[Value]) -> Vec<PendingDocument> {
+    tasks
+        .iter()
+        .filter_map(|task| {
+            let id = crate::search_index::text_of(task, "id");
+            if id.is_empty() {
+                return None;
+            }
+            let title = crate::search_index::text_of(task, "title");
+            let subtasks = task
+                .get("subtasks")
+                .and_then(Value::as_array)
+                .map(|items| {
+                    items
+                        .iter()
+                        .map(|item| format!("- {}", crate::search_index::text_of(item, "title")))
+                        .collect::<Vec<_>>()
+                        .join("\n")
+                })
+                .unwrap_or_default();
+            Some(PendingDocument {
+                key: format!("task:{}", id),
+                kind: "task".to_string(),
+                id,
+                text: join_parts(&[title.clone(), crate::search_index::text_of(task, "note"), subtasks]),
+                title,
+            })
+        })
+        .collect()
+}
+
+fn note_documents(notes: &[Value]) -> Vec<PendingDocument> {
+    notes
+        .iter()
+        .filter_map(|note| {
+            let id = crate::search_index::text_of(note, "id");
+            if id.is_empty() {
+                return None;
+            }
+            let title = crate::search_index::text_of(note, "title");
+            Some(PendingDocument {
+                key: format!("note:{}", id),
+                kind: "note".to_string(),
+                id,
+                text: join_parts(&[title.clone(), crate::search_index::text_of(note, "content")]),
+                title,
+            })
+        })
+        .collect()
+}
+
+fn chunk_text(text: &str) -> Vec<String> {
+    let mut chunks = Vec::new();
+    let mut current = String::new();
+    for paragraph in text.split("\n\n") {
+        let paragraph = paragraph.trim();
+        if paragraph.is_empty() {
+            continue;
+        }
+        if !current.is_empty()
+            && current.chars().count() + paragraph.chars().count() > FILE_CHUNK_CHARS
+        {
+            chunks.push(std::mem::take(&mut current));
+        }
+        if paragraph.chars().count() > FILE_CHUNK_CHARS {
+            let chars: Vec<char> = paragraph.chars().collect();
+            for piece in chars.chunks(FILE_CHUNK_CHARS) {
+                chunks.push(piece.iter().collect());
+            }
+            continue;
+        }
+        if !current.is_empty() {
+            current.push_str("\n\n");
+        }
+        current.push_str(paragraph);
+    }
+    if !current.is_empty() {
+        chunks.push(current);
+    }
+    chunks
+}
+
+fn is_text_file(path: &Path) -> bool {
+    path.extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .is_some_and(|ext| TEXT_EXTENSIONS.contains(&ext.as_str()))
+}
+
+fn file_documents(trusted_dirs: &[String]) -> Result<Vec<PendingDocument>, String> {
+    let mut documents = Vec::new();
+    let mut file_count = 0;
+
+    for dir in trusted_dirs {
+        if dir.trim().is_empty() {
+            continue;
+        }
+        let root = crate::normalize_path(dir)?;
+        if !root.is_dir() {
+            continue;
+        }
+        for entry in WalkDir::new(&root)
+            .follow_links(false)
+            .into_iter()
+            .filter_entry(|entry| {
+                entry.depth() == 0
+                    || !(entry.file_type().is_dir()
+                        && IGNORED_DIRS.contains(&entry.file_name().to_string_lossy().as_ref()))
+            })
+            .filter_map(|item| item.ok())
+        {
+            if file_count >= MAX_INDEXED_FILES {
+                return Ok(documents);
+            }
+            let path = entry.path();
+            if !entry.file_type().is_file() || !is_text_file(path) {
+                continue;
+            }
+            if entry.metadata().map(|meta| meta.len()).unwrap_or(u64::MAX) > MAX_FILE_BYTES {
+                continue;
+            }
+            let Ok(content) = fs::read_to_string(path) else {
+                continue;
+            };
+            file_count += 1;
+
+            let path_text = path.to_string_lossy().to_string();
+            let name = entry.file_name().to_string_lossy().to_string();
+            for (index, chunk) in chunk_text(&content).into_iter().enumerate() {
+                documents.push(PendingDocument {
+                    key: format!("file:{}#{}", path_text, index),
+                    kind: "file".to_string(),
+                    id: path_text.clone(),
+                    title: name.clone(),
+                    text: chunk,
+                });
+            }
+        }
+    }
+
+    Ok(documents)
+}
+
+fn index_path(app: &AppHandle) -> Result<std::path::PathBuf, String> {
+    crate::app_data_file(app, INDEX_FILE)
+}
+
+fn load_records(app: &AppHandle, state: &SemanticIndexState) -> Result<Vec<EmbeddingRecord>, String> {
+    let mut guard = state.records.lock().map_err(|e| e.to_string())?;
+    if guard.is_none() {
+        let loaded = fs::read_to_string(index_path(app)?)
+            .ok()
+            .and_then(|raw| serde_json::from_str(&raw).ok())
+            .unwrap_or_default();
+        *guard = Some(loaded);
+    }
+    Ok(guard.clone().unwrap_or_default())
+}
+
+fn store_records(
+    app: &AppHandle,
+    state: &SemanticIndexState,
+    records: Vec<EmbeddingRecord>,
+) -> Result<(), String> {
+    let data = serde_json::to_string(&records).map_err(|e| e.to_string())?;
+    fs::write(index_path(app)?, data).map_err(|e| format!("保存向量索引失败: {}", e))?;
+    *state.records.lock().map_err(|e| e.to_string())? = Some(records);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn sync_semantic_index(
+    app: AppHandle,
+    state: State<'_, SemanticIndexState>,
+    network: State<'_, NetworkState>,
+    config: EmbeddingConfig,
+    tasks: Vec<Value>,
+    notes: Vec<Value>,
+    trusted_dirs: Vec<String>,
+) -> Result<SemanticIndexSummary, String> {
+    let model = config.signature();
+    let existing: HashMap<String, EmbeddingRecord> = load_records(&app, &state)?
+        .into_iter()
+        .map(|record| (record.key.clone(), record))
+        .collect();
+
+    let mut documents = task_documents(&tasks);
+    documents.extend(note_documents(&notes));
+    // Walking and reading trusted directories is blocking file I/O.
+    let files = tauri::async_runtime::spawn_blocking(move || file_documents(&trusted_dirs))
+        .await
+        .map_err(|e| e.to_string())??;
+    documents.extend(files);
+
+    let mut records = Vec::with_capacity(documents.len());
+    let mut pending = Vec::new();
+    for document in documents {
+        let text = truncate_chars(&document.text, MAX_DOCUMENT_CHARS);
+        let hash = content_hash(&text);
+        match existing.get(&document.key) {
+            Some(record) if record.content_hash == hash && record.model == model => {
+                records.push(record.clone());
+            }
+            _ => pending.push((document, text, hash)),
+        }
+    }
+
+    let reused = records.len();
+    let texts: Vec<String> = pending.iter().map(|(_, text, _)| text.clone()).collect();
+    let vectors = embed_texts(&app, &network, &config, &texts).await?;
+    for ((document, text, hash), vector) in pending.into_iter().zip(vectors) {
+        records.push(EmbeddingRecord {
+            key: document.key,
+            kind: document.kind,
+            id: document.id,
+            title: document.title,
+            excerpt: truncate_chars(&text, 240),
+            content_hash: hash,
+            model: model.clone(),
+            vector,
+        });
+    }
+
+    let current_keys: HashSet<&str> = records.iter().map(|record| record.key.as_str()).collect();
+    let summary = SemanticIndexSummary {
+        embedded: records.len() - reused,
+        reused,
+        removed: existing
+            .keys()
+            .filter(|key| !current_keys.contains(key.as_str()))
+            .count(),
+        total: records.len(),
+    };
+    store_records(&app, &state, records)?;
+    Ok(summary)
+}
+
+#[tauri::command]
+pub async fn semantic_search(
+    app: AppHandle,
+    state: State<'_, SemanticIndexState>,
+    network: State<'_, NetworkState>,
+    config: EmbeddingConfig,
+    query: String,
+    kinds: Option<Vec<String>>,
+    limit: Option<usize>,
+) -> Result<Vec<SemanticHit>, String> {
+    let needle = query.trim();
+    if needle.is_empty() {
+        return Ok(Vec::new());
+    }
+    let limit = limit.unwrap_or(8).clamp(1, 50);
+    let kinds = kinds.unwrap_or_default();
+    let model = config.signature();
+
+    let records = load_records(&app, &state)?;
+    let query_vector = embed_texts(&app, &network, &config, &[needle.to_string()])
+        .await?
+        .into_iter()
+        .next()
+        .unwrap_or_default();
+
+    let mut hits: Vec<SemanticHit> = records
+        .into_iter()
+        .filter(|record| record.model == model)
+        .filter(|record| kinds.is_empty() || kinds.contains(&record.kind))
+        .filter(|record| record.vector.len() == query_vector.len())
+        .map(|record| SemanticHit {
+            score: record
+                .vector
+                .iter()
+                .zip(&query_vector)
+                .map(|(a, b)| a * b)
+                .sum(),
+            kind: record.kind,
+            id: record.id,
+            title: record.title,
+            excerpt: record.excerpt,
+        })
+        .collect();
+    hits.sort_by(|a, b| b.score.total_cmp(&a.score));
+    hits.truncate(limit);
+    Ok(hits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{serve, Response, TempDir};
+    use serde_json::json;
+
+    fn config(provider: &str, api_url: &str) -> EmbeddingConfig {
+        EmbeddingConfig {
+            provider: provider.to_string(),
+            api_url: api_url.to_string(),
+            api_key: String::new(),
+            model: "test-model".to_string(),
+        }
+    }
+
+    fn client() -> reqwest::Client {
+        reqwest::Client::builder().no_proxy().build().unwrap()
+    }
+
+    fn texts(items: &[&str]) -> Vec<String> {
+        items.iter().map(|item| item.to_string()).collect()
+    }
+
+    fn cosine(a: &[f32], b: &[f32]) -> f32 {
+        a.iter().zip(b).map(|(a, b)| a * b).sum()
+    }
+
+    #[test]
+    fn chunk_text_packs_paragraphs_up_to_the_chunk_size() {
+        let short = "a".repeat(500);
+        let text = format!("{short}\n\n\n\n{short}\n\n  \n\n{short}");
+        let chunks = chunk_text(&text);
+        assert_eq!(chunks, vec![format!("{short}\n\n{short}"), short.clone()]);
+        assert!(chunk_text(" \n\n ").is_empty());
+    }
+
+    #[test]
+    fn chunk_text_splits_long_paragraphs_on_characters() {
+        let long = "任务".repeat(FILE_CHUNK_CHARS);
+        let chunks = chunk_text(&format!("intro\n\n{long}\n\noutro"));
+        assert_eq!(chunks.len(), 4);
+        assert_eq!(chunks[0], "intro");
+        assert_eq!(chunks[1].chars().count(), FILE_CHUNK_CHARS);
+        assert_eq!(chunks[2].chars().count(), FILE_CHUNK_CHARS);
+        assert_eq!(chunks[3], "outro");
+    }
+
+    #[test]
+    fn local_embedding_reflects_shared_words_only() {
+        let report = local_embedding("Write the quarterly report");
+        assert_eq!(report.len(), LOCAL_DIMENSIONS);
+        assert!((cosine(&report, &report) - 1.0).abs() < 1e-5);
+        assert_eq!(report, local_embedding("WRITE THE QUARTERLY REPORT"));
+
+        let related = local_embedding("quarterly report draft");
+        let unrelated = local_embedding("buy groceries");
+        assert!(cosine(&report, &related) > 0.5);
+        assert!(cosine(&report, &related) > cosine(&report, &unrelated));
+        // A synonym shares no tokens, which is what makes this lexical.
+        assert!(cosine(&local_embedding("car"), &local_embedding("automobile")) < 0.5);
+        assert!(local_embedding("").iter().all(|value| *value == 0.0));
+    }
+
+    #[test]
+    fn embeddings_url_completes_the_endpoint() {
+        let url = |provider: &str, api_url: &str| embeddings_url(&config(provider, api_url));
+        assert_eq!(
+            url("openai", "https://api.openai.com/v1/").unwrap(),
+            "https://api.openai.com/v1/embeddings"
+        );
+        assert_eq!(
+            url("openai", "https://proxy.example/v1/embeddings").unwrap(),
+            "https://proxy.example/v1/embeddings"
+        );
+        assert_eq!(
+            url("openai", " ").unwrap_err(),
+            "请填写 Embeddings 接口地址"
+        );
+        assert_eq!(
+            url("ollama", "").unwrap(),
+            "http://127.0.0.1:11434/api/embed"
+        );
+        assert_eq!(
+            url("ollama", "http://gpu-box:11434/api/embed").unwrap(),
+            "http://gpu-box:11434/api/embed"
+        );
+        assert!(url("local", "").is_err());
+    }
+
+    #[tokio::test]
+    async fn openai_responses_are_ordered_by_index_and_normalized() {
+        let base = serve(|request| {
+            let body: Value = serde_json::from_slice(&request.body).unwrap_or_default();
+            if request.path() != "/v1/embeddings"
+                || request.header("authorization") != Some("Bearer sk-test")
+                || body["model"] != "test-model"
+                || body["input"] != json!(["first", "second"])
+            {
+                return Response::new(400, "unexpected request");
+            }
+            Response::new(
+                200,
+                json!({
+                    "data": [
+                        { "index": 1, "embedding": [0.0, 2.0] },
+                        { "index": 0, "embedding": [3.0, 4.0] },
+                    ]
+                })
+                .to_string(),
+            )
+        });
+        let mut config = config("openai", &format!("{}/v1", base));
+        config.api_key = " sk-test ".to_string();
+        let vectors = embed_remote(&client(), &config, &texts(&["first", "second"]))
+            .await
+            .unwrap();
+        assert_eq!(vectors, vec![vec![0.6, 0.8], vec![0.0, 1.0]]);
+    }
+
+    #[tokio::test]
+    async fn ollama_responses_are_read_from_embeddings() {
+        let base = serve(|request| match request.path() {
+            "/api/embed" => Response::new(200, r#"{"embeddings": [[1.0, 0.0], [0.0, -5.0]]}"#),
+            _ => Response::new(404, ""),
+        });
+        let vectors = embed_remote(&client(), &config("ollama", &base), &texts(&["a", "b"]))
+            .await
+            .unwrap();
+        assert_eq!(vectors, vec![vec![1.0, 0.0], vec![0.0, -1.0]]);
+    }
+
+    #[tokio::test]
+    async fn malformed_and_failed_responses_are_errors() {
+        let base = serve(|request| match request.path() {
+            "/short/embeddings" => Response::new(200, r#"{"data": [{"embedding": [1.0]}]}"#),
+            "/text/embeddings" => Response::new(200, r#"{"data": [{"embedding": ["x"]}]}"#),
+            _ => Response::new(500, "model not loaded"),
+        });
+        let two = texts(&["a", "b"]);
+        for path in ["short", "text"] {
+            let error = embed_remote(
+                &client(),
+                &config("openai", &format!("{}/{}", base, path)),
+                &two,
+            )
+            .await
+            .unwrap_err();
+            assert_eq!(error, "Embeddings 响应格式不正确");
+        }
+        let error = embed_remote(&client(), &config("openai", &base), &two)
+            .await
+            .unwrap_err();
+        assert!(
+            error.contains("500") && error.contains("model not loaded"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn file_documents_skip_dependency_and_build_folders() {
+        let dir = TempDir::new("semantic-files");
+        let root = dir.path().join("target");
+        for folder in ["docs", "node_modules/pkg", ".git", "target/debug"] {
+            fs::create_dir_all(root.join(folder)).unwrap();
+        }
+        fs::write(root.join("docs/plan.md"), "Plan\n\nShip it").unwrap();
+        fs::write(root.join("notes.txt"), "Notes").unwrap();
+        fs::write(root.join("image.png"), "not text").unwrap();
+        fs::write(root.join("node_modules/pkg/README.md"), "dependency").unwrap();
+        fs::write(root.join(".git/description"), "repo").unwrap();
+        fs::write(root.join("target/debug/build.log"), "build output").unwrap();
+
+        // The trusted directory itself may carry an ignored name.
+        let documents = file_documents(&[root.to_string_lossy().to_string()]).unwrap();
+        let mut titles: Vec<&str> = documents.iter().map(|doc| doc.title.as_str()).collect();
+        titles.sort();
+        assert_eq!(titles, vec!["notes.txt", "plan.md"]);
+        assert!(documents.iter().all(|doc| doc.kind == "file"));
+    }
+}
@@ -0,0 +1,102 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use tauri::{AppHandle, Emitter};
+
+use super::merge::Conflict;
+use super::{hex_digest, now_millis, sync_dir};
+
+const CONFLICTS_FILE: &str = "conflicts.json";
+/// Oldest entries are dropped beyond this; they have long been merged anyway.
+const MAX_CONFLICTS: usize = 200;
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncConflict {
+    id: String,
+    scope: String,
+    detected_at: u64,
+    #[serde(flatten)]
+    conflict: Conflict,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct ConflictsChanged {
+    added: usize,
+    total: usize,
+}
+
+fn load(app: &AppHandle) -> Vec<SyncConflict> {
+    sync_dir(app)
+        .ok()
+        .and_then(|dir| fs::read_to_string(dir.join(CONFLICTS_FILE)).ok())
+        .and_then(|raw| serde_json::from_str(&raw).ok())
+        .unwrap_or_default()
+}
+
+fn store(app: &AppHandle, conflicts: &[SyncConflict], added: usize) -> Result<(), String> {
+    let data = serde_json::to_string(conflicts).map_err(|e| e.to_string())?;
+    fs::write(sync_dir(app)?.join(CONFLICTS_FILE), data)
+        .map_err(|e| format!("保存同步冲突失败: {}", e))?;
+    let _ = app.emit(
+        "sync-conflicts",
+        ConflictsChanged {
+            added,
+            total: conflicts.len(),
+        },
+    );
+    Ok(())
+}
+
+/// Appends conflicts found by a merge and notifies the UI. Returns how many were added.
+pub fn record(app: &AppHandle, scope: &str, found: Vec<Conflict>) -> Result<usize, String> {
+    if found.is_empty() {
+        return Ok(0);
+    }
+    let detected_at = now_millis();
+    let added = found.len();
+    let mut conflicts = load(app);
+    for (index, conflict) in found.into_iter().enumerate() {
+        let id = hex_digest(&format!(
+            "{}|{}|{}|{:?}|{}|{}",
+            scope, conflict.collection, conflict.entity_id, conflict.field, detected_at, index
+        ))[..16]
+            .to_string();
+        conflicts.push(SyncConflict {
+            id,
+            scope: scope.to_string(),
+            detected_at,
+            conflict,
+        });
+    }
+    if conflicts.len() > MAX_CONFLICTS {
+        conflicts.drain(..conflicts.len() - MAX_CONFLICTS);
+    }
+    store(app, &conflicts, added)?;
+    Ok(added)
+}
+
+/// Conflicts resolved automatically by the last syncs, newest first, so the user
+/// can restore a discarded value.
+#[tauri::command]
+pub fn list_sync_conflicts(app: AppHandle) -> Vec<SyncConflict> {
+    let mut conflicts = load(&app);
+    conflicts.reverse();
+    conflicts
+}
+
+/// Removes the given conflicts, or all of them when `ids` is omitted.
+#[tauri::command]
+pub fn dismiss_sync_conflicts(app: AppHandle, ids: Option<Vec<String>>) -> Result<usize, String> {
+    let mut conflicts = load(&app);
+    let before = conflicts.len();
+    match ids {
+        Some(ids) => conflicts.retain(|conflict| !ids.contains(&conflict.id)),
+        None => conflicts.clear(),
+    }
+    let removed = before - conflicts.len();
+    if removed > 0 {
+        store(&app, &conflicts, 0)?;
+    }
+    Ok(removed)
+}
@@ -0,0 +1,386 @@
+use argon2::{Algorithm, Argon2, Params, Version};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+pub const ENVELOPE_KEY: &str = "_encrypted";
+const ALGORITHM: &str = "xchacha20poly1305";
+const KDF: &str = "argon2id";
+const AAD: &[u8] = b"workplan-sync-v1";
+const SALT_LEN: usize = 16;
+const M_COST_KIB: u32 = 19 * 1024;
+const T_COST: u32 = 2;
+/// Bounds on parameters read from a remote envelope.
+const MAX_M_COST_KIB: u32 = 256 * 1024;
+const MAX_T_COST: u32 = 10;
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct EncryptionConfig {
+    enabled: bool,
+    /// Sync passphrase, required when enabled. It cannot be the access key,
+    /// which hosted backends receive as the row filter.
+    passphrase: String,
+    /// Passphrases replaced by rotation, still accepted for decryption. An empty
+    /// entry stands for the access key, which earlier versions encrypted with.
+    previous_passphrases: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Envelope {
+    version: u32,
+    alg: String,
+    kdf: String,
+    m_cost: u32,
+    t_cost: u32,
+    salt: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+/// Derived keys by secret and salt, so Argon2 runs once per passphrase rather than
+/// on every sync.
+#[derive(Default)]
+pub struct KeyCache {
+    keys: Mutex<HashMap<String, [u8; 32]>>,
+}
+
+impl KeyCache {
+    fn derive(
+        &self,
+        secret: &str,
+        salt: &[u8],
+        m_cost: u32,
+        t_cost: u32,
+    ) -> Result<[u8; 32], String> {
+        let mut id = Sha256::new();
+        id.update(secret.as_bytes());
+        id.update(salt);
+        id.update(format!("{}:{}", m_cost, t_cost).as_bytes());
+        let id: String = id.finalize().iter().map(|b| format!("{:02x}", b)).collect();
+        if let Some(key) = self.keys.lock().map_err(|e| e.to_string())?.get(&id) {
+            return Ok(*key);
+        }
+
+        let params =
+            Params::new(m_cost, t_cost, 1, Some(32)).map_err(|e| format!("密钥参数无效: {}", e))?;
+        let mut key = [0u8; 32];
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+            .hash_password_into(secret.as_bytes(), salt, &mut key)
+            .map_err(|e| format!("派生密钥失败: {}", e))?;
+        self.keys.lock().map_err(|e| e.to_string())?.insert(id, key);
+        Ok(key)
+    }
+}
+
+pub struct Opened<T = Value> {
+    pub value: T,
+    /// Whether the current secret opened it; otherwise it should be re-encrypted.
+    pub current: bool,
+    pub salt: Vec<u8>,
+}
+
+/// Encrypts synced documents so the remote only ever stores ciphertext.
+pub struct SyncCipher<'a> {
+    /// Current secret first, then rotated-out ones.
+    secrets: Vec<String>,
+    cache: &'a KeyCache,
+}
+
+pub fn is_envelope(payload: &Value) -> bool {
+    payload.get(ENVELOPE_KEY).is_some()
+}
+
+fn decode(field: &str, value: &str) -> Result<Vec<u8>, String> {
+    BASE64
+        .decode(value)
+        .map_err(|_| format!("加密数据损坏: {}", field))
+}
+
+impl<'a> SyncCipher<'a> {
+    pub fn from_config(
+        config: &EncryptionConfig,
+        access_key: &str,
+        cache: &'a KeyCache,
+    ) -> Result<Option<Self>, String> {
+        if !config.enabled {
+            return Ok(None);
+        }
+        if config.passphrase.is_empty() || config.passphrase == access_key {
+            return Err("端到端加密需要设置与访问密钥不同的同步口令".to_string());
+        }
+        let resolve = |passphrase: &String| {
+            if passphrase.is_empty() {
+                access_key.to_string()
+            } else {
+                passphrase.clone()
+            }
+        };
+        let mut secrets = vec![resolve(&config.passphrase)];
+        for previous in &config.previous_passphrases {
+            let secret = resolve(previous);
+            if !secrets.contains(&secret) {
+                secrets.push(secret);
+            }
+        }
+        Ok(Some(SyncCipher { secrets, cache }))
+    }
+
+    /// A cipher with a single secret, for data outside the sync documents.
+    pub fn from_passphrase(passphrase: &str, cache: &'a KeyCache) -> Self {
+        SyncCipher {
+            secrets: vec![passphrase.to_string()],
+            cache,
+        }
+    }
+
+    /// Encrypts with the current secret, reusing `salt` when given so the derived
+    /// key stays cached.
+    pub fn seal(&self, value: &Value, salt: Option<&[u8]>) -> Result<Value, String> {
+        let plaintext = serde_json::to_vec(value).map_err(|e| e.to_string())?;
+        self.seal_bytes(&plaintext, salt)
+    }
+
+    pub fn seal_bytes(&self, plaintext: &[u8], salt: Option<&[u8]>) -> Result<Value, String> {
+        let salt = match salt {
+            Some(salt) => salt.to_vec(),
+            None => {
+                let mut salt = vec![0u8; SALT_LEN];
+                OsRng.fill_bytes(&mut salt);
+                salt
+            }
+        };
+        let key = self
+            .cache
+            .derive(&self.secrets[0], &salt, M_COST_KIB, T_COST)?;
+        let cipher = XChaCha20Poly1305::new(&key.into());
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = cipher
+            .encrypt(
+                &nonce,
+                Payload {
+                    msg: plaintext,
+                    aad: AAD,
+                },
+            )
+            .map_err(|_| "加密失败".to_string())?;
+
+        let envelope = Envelope {
+            version: 1,
+            alg: ALGORITHM.to_string(),
+            kdf: KDF.to_string(),
+            m_cost: M_COST_KIB,
+            t_cost: T_COST,
+            salt: BASE64.encode(&salt),
+            nonce: BASE64.encode(nonce),
+            ciphertext: BASE64.encode(ciphertext),
+        };
+        let mut wrapper = serde_json::Map::new();
+        wrapper.insert(
+            ENVELOPE_KEY.to_string(),
+            serde_json::to_value(envelope).map_err(|e| e.to_string())?,
+        );
+        Ok(Value::Object(wrapper))
+    }
+
+    pub fn open(&self, payload: &Value) -> Result<Opened, String> {
+        let opened = self.open_bytes(payload)?;
+        let value = serde_json::from_slice(&opened.value)
+            .map_err(|e| format!("解密后的数据无效: {}", e))?;
+        Ok(Opened {
+            value,
+            current: opened.current,
+            salt: opened.salt,
+        })
+    }
+
+    pub fn open_bytes(&self, payload: &Value) -> Result<Opened<Vec<u8>>, String> {
+        let envelope: Envelope = payload
+            .get(ENVELOPE_KEY)
+            .cloned()
+            .and_then(|value| serde_json::from_value(value).ok())
+            .ok_or_else(|| "加密数据格式无效".to_string())?;
+        if envelope.alg != ALGORITHM || envelope.kdf != KDF {
+            return Err(format!(
+                "不支持的加密算法: {}/{}",
+                envelope.alg, envelope.kdf
+            ));
+        }
+        if envelope.m_cost > MAX_M_COST_KIB || envelope.t_cost > MAX_T_COST {
+            return Err("加密参数超出范围".to_string());
+        }
+        let salt = decode("salt", &envelope.salt)?;
+        let nonce = decode("nonce", &envelope.nonce)?;
+        let ciphertext = decode("ciphertext", &envelope.ciphertext)?;
+        if nonce.len() != 24 {
+            return Err("加密数据损坏: nonce".to_string());
+        }
+        let nonce = XNonce::from_slice(&nonce);
+
+        for (index, secret) in self.secrets.iter().enumerate() {
+            let key = self
+                .cache
+                .derive(secret, &salt, envelope.m_cost, envelope.t_cost)?;
+            let cipher = XChaCha20Poly1305::new(&key.into());
+            if let Ok(plaintext) = cipher.decrypt(
+                nonce,
+                Payload {
+                    msg: &ciphertext,
+                    aad: AAD,
+                },
+            ) {
+                return Ok(Opened {
+                    value: plaintext,
+                    current: index == 0,
+                    salt,
+                });
+            }
+        }
+        Err("无法解密同步数据，请检查同步口令".to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const ACCESS_KEY: &str = "access-key";
+
+    fn config(passphrase: &str, previous: &[&str]) -> EncryptionConfig {
+        EncryptionConfig {
+            enabled: true,
+            passphrase: passphrase.to_string(),
+            previous_passphrases: previous.iter().map(|p| p.to_string()).collect(),
+        }
+    }
+
+    fn cipher<'a>(config: &EncryptionConfig, cache: &'a KeyCache) -> SyncCipher<'a> {
+        SyncCipher::from_config(config, ACCESS_KEY, cache)
+            .unwrap()
+            .unwrap()
+    }
+
+    fn data() -> Value {
+        json!({ "tasks": [{ "id": "1", "title": "秘密" }] })
+    }
+
+    fn envelope_field<'a>(payload: &'a mut Value, field: &str) -> &'a mut Value {
+        payload
+            .get_mut(ENVELOPE_KEY)
+            .unwrap()
+            .get_mut(field)
+            .unwrap()
+    }
+
+    #[test]
+    fn sealed_data_opens_with_the_same_passphrase() {
+        let cache = KeyCache::default();
+        let cipher = cipher(&config("correct horse", &[]), &cache);
+        let sealed = cipher.seal(&data(), None).unwrap();
+        assert!(is_envelope(&sealed));
+        assert!(!sealed.to_string().contains("秘密"));
+
+        let opened = cipher.open(&sealed).unwrap();
+        assert_eq!(opened.value, data());
+        assert!(opened.current);
+        assert_eq!(opened.salt.len(), SALT_LEN);
+    }
+
+    #[test]
+    fn a_wrong_passphrase_fails() {
+        let cache = KeyCache::default();
+        let sealed = cipher(&config("correct horse", &[]), &cache)
+            .seal(&data(), None)
+            .unwrap();
+        let error = cipher(&config("battery staple", &[]), &cache)
+            .open(&sealed)
+            .err()
+            .unwrap();
+        assert!(error.contains("同步口令"), "{}", error);
+    }
+
+    #[test]
+    fn rotated_passphrases_open_and_reseal_under_the_current_one() {
+        let cache = KeyCache::default();
+        let old = cipher(&config("old phrase", &[]), &cache);
+        let sealed = old.seal(&data(), None).unwrap();
+
+        let rotated = cipher(&config("new phrase", &["old phrase"]), &cache);
+        let opened = rotated.open(&sealed).unwrap();
+        assert_eq!(opened.value, data());
+        assert!(!opened.current);
+
+        let resealed = rotated.seal(&opened.value, Some(&opened.salt)).unwrap();
+        assert!(rotated.open(&resealed).unwrap().current);
+        assert!(old.open(&resealed).is_err());
+    }
+
+    #[test]
+    fn data_sealed_with_the_access_key_opens_through_an_empty_previous_entry() {
+        let cache = KeyCache::default();
+        let legacy = SyncCipher::from_passphrase(ACCESS_KEY, &cache)
+            .seal(&data(), None)
+            .unwrap();
+        let opened = cipher(&config("new phrase", &[""]), &cache)
+            .open(&legacy)
+            .unwrap();
+        assert_eq!(opened.value, data());
+        assert!(!opened.current);
+    }
+
+    #[test]
+    fn the_access_key_is_not_accepted_as_the_passphrase() {
+        let cache = KeyCache::default();
+        assert!(SyncCipher::from_config(&config("", &[]), ACCESS_KEY, &cache).is_err());
+        assert!(SyncCipher::from_config(&config(ACCESS_KEY, &[]), ACCESS_KEY, &cache).is_err());
+        let disabled = EncryptionConfig::default();
+        assert!(SyncCipher::from_config(&disabled, ACCESS_KEY, &cache)
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn out_of_bounds_remote_parameters_are_rejected() {
+        let cache = KeyCache::default();
+        let cipher = cipher(&config("correct horse", &[]), &cache);
+        let sealed = cipher.seal(&data(), None).unwrap();
+        for (field, value) in [
+            ("mCost", MAX_M_COST_KIB + 1),
+            ("tCost", MAX_T_COST + 1),
+            ("tCost", 0),
+        ] {
+            let mut payload = sealed.clone();
+            *envelope_field(&mut payload, field) = value.into();
+            assert!(cipher.open(&payload).is_err(), "{} = {}", field, value);
+        }
+
+        let mut payload = sealed.clone();
+        *envelope_field(&mut payload, "alg") = "aes-128-ecb".into();
+        assert!(cipher.open(&payload).is_err());
+    }
+
+    #[test]
+    fn tampered_ciphertext_fails() {
+        let cache = KeyCache::default();
+        let cipher = cipher(&config("correct horse", &[]), &cache);
+        let mut sealed = cipher.seal(&data(), None).unwrap();
+        let field = envelope_field(&mut sealed, "ciphertext");
+        let mut bytes = BASE64.decode(field.as_str().unwrap()).unwrap();
+        bytes[0] ^= 1;
+        *field = BASE64.encode(&bytes).into();
+        assert!(cipher.open(&sealed).is_err());
+
+        let mut truncated = cipher.seal(&data(), None).unwrap();
+        *envelope_field(&mut truncated, "nonce") = BASE64.encode([0u8; 12]).into();
+        assert!(cipher.open(&truncated).is_err());
+    }
+}
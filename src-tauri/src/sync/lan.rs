@@ -0,0 +1,1086 @@
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use mdns_sd::{ServiceDaemon, ServiceEvent, ServiceInfo};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::fs;
+use std::future::Future;
+use std::net::{IpAddr, SocketAddr};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::async_runtime::JoinHandle;
+use tauri::{AppHandle, Emitter, Manager, State};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{oneshot, Semaphore};
+use x25519_dalek::StaticSecret;
+
+use super::lan_channel::{self, Channel, Ephemeral, PairingShare, SessionKeys, NONCE_LEN};
+use super::merge::{Clock, Document};
+use super::{
+    conflicts, hex_digest, load_replica, now_millis, replica_path, save_replica, sync_dir, to_hex,
+    Replica, SyncOutcome, SyncState,
+};
+
+const SERVICE_TYPE: &str = "_workplan._tcp.local.";
+const LAN_FILE: &str = "lan.json";
+const DEFAULT_DEVICE_NAME: &str = "WorkPlan";
+const PAIRING_TTL_MS: u64 = 2 * 60 * 1000;
+const MAX_PAIRING_FAILURES: u32 = 5;
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+/// Connections handled at once; further ones are dropped until a slot frees.
+const MAX_INCOMING: usize = 8;
+/// How long an incoming sync waits for the frontend to hand over its data.
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// A device paired with this one; `key` is its long-term X25519 public key.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct PairedPeer {
+    id: String,
+    name: String,
+    key: String,
+    /// Where the device was last reached, for networks without multicast.
+    address: String,
+    paired_at: u64,
+    last_sync_at: Option<u64>,
+}
+
+/// This device's LAN identity and its paired devices.
+#[derive(Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase", default)]
+struct LanStore {
+    id: String,
+    name: String,
+    secret: String,
+    peers: Vec<PairedPeer>,
+}
+
+struct Identity {
+    id: String,
+    secret: StaticSecret,
+}
+
+impl LanStore {
+    fn identity(&self) -> Result<Identity, String> {
+        let secret: [u8; 32] = BASE64
+            .decode(&self.secret)
+            .ok()
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(|| "局域网身份密钥损坏".to_string())?;
+        Ok(Identity {
+            id: self.id.clone(),
+            secret: StaticSecret::from(secret),
+        })
+    }
+
+    fn peer(&self, id: &str) -> Option<&PairedPeer> {
+        self.peers.iter().find(|peer| peer.id == id)
+    }
+
+    fn upsert(&mut self, peer: PairedPeer) {
+        self.peers.retain(|existing| existing.id != peer.id);
+        self.peers.push(peer);
+    }
+}
+
+fn store_path(app: &AppHandle) -> Result<PathBuf, String> {
+    Ok(sync_dir(app)?.join(LAN_FILE))
+}
+
+/// Loads the store, creating the device identity on first use.
+fn load_store(app: &AppHandle) -> Result<LanStore, String> {
+    let path = store_path(app)?;
+    let mut store: LanStore = fs::read_to_string(&path)
+        .ok()
+        .and_then(|raw| serde_json::from_str(&raw).ok())
+        .unwrap_or_default();
+    if store.id.is_empty() || store.secret.is_empty() {
+        store.id = to_hex(&lan_channel::random_bytes::<8>());
+        store.secret = BASE64.encode(lan_channel::random_bytes::<32>());
+        save_store(app, &store)?;
+    }
+    Ok(store)
+}
+
+fn save_store(app: &AppHandle, store: &LanStore) -> Result<(), String> {
+    let data = serde_json::to_string_pretty(store).map_err(|e| e.to_string())?;
+    fs::write(store_path(app)?, data).map_err(|e| format!("保存局域网配对信息失败: {}", e))
+}
+
+/// Unencrypted handshake messages.
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+enum Plain {
+    #[serde(rename_all = "camelCase")]
+    Hello {
+        pairing: bool,
+        id: String,
+        nonce: String,
+        key: String,
+    },
+    Refused {
+        message: String,
+    },
+}
+
+/// Messages sent over the encrypted channel.
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+enum Message {
+    Identity {
+        id: String,
+        name: String,
+        key: String,
+        port: u16,
+    },
+    #[serde(rename_all = "camelCase")]
+    SyncRequest {
+        key_hash: String,
+        document: Value,
+    },
+    SyncReply {
+        document: Value,
+    },
+    Refused {
+        message: String,
+    },
+}
+
+fn decode_key<const N: usize>(value: &str) -> Result<[u8; N], String> {
+    BASE64
+        .decode(value)
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| "局域网握手数据无效".to_string())
+}
+
+/// Tells the devices apart without sending the access key itself.
+fn key_hash(access_key: &str) -> String {
+    hex_digest(&format!("workplan-lan|{}", access_key))
+}
+
+struct Running {
+    name: String,
+    port: u16,
+    /// `None` when multicast is unavailable; pairing by address still works.
+    daemon: Option<(ServiceDaemon, String)>,
+    tasks: Vec<JoinHandle<()>>,
+}
+
+struct Discovered {
+    fullname: String,
+    name: String,
+    address: String,
+}
+
+struct Pairing {
+    code: String,
+    expires_at: u64,
+    failures: u32,
+}
+
+/// The frontend's answer to an incoming sync request.
+struct Handover {
+    access_key: String,
+    data: Option<Value>,
+    reply: oneshot::Sender<Result<SyncOutcome, String>>,
+}
+
+#[derive(Default)]
+pub struct LanState {
+    running: Mutex<Option<Running>>,
+    discovered: Mutex<HashMap<String, Discovered>>,
+    pairing: Mutex<Option<Pairing>>,
+    requests: Mutex<HashMap<String, oneshot::Sender<Handover>>>,
+    /// Serializes access to the per-peer replicas.
+    replicas: Mutex<()>,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct LanPeer {
+    id: String,
+    name: String,
+    address: Option<String>,
+    paired: bool,
+    online: bool,
+    last_sync_at: Option<u64>,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct LanStatus {
+    running: bool,
+    id: String,
+    name: String,
+    /// `ip:port` pairs another device can pair with by hand.
+    addresses: Vec<String>,
+    discovery: bool,
+    pairing_code: Option<String>,
+    pairing_expires_at: Option<u64>,
+    peers: Vec<LanPeer>,
+}
+
+fn local_addresses(port: u16) -> Vec<String> {
+    if_addrs::get_if_addrs()
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|interface| !interface.is_loopback())
+        .filter_map(|interface| match interface.ip() {
+            IpAddr::V4(ip) => Some(format!("{}:{}", ip, port)),
+            IpAddr::V6(_) => None,
+        })
+        .collect()
+}
+
+fn status(app: &AppHandle, lan: &LanState) -> Result<LanStatus, String> {
+    let store = load_store(app)?;
+    let running = lan.running.lock().map_err(|e| e.to_string())?;
+    let discovered = lan.discovered.lock().map_err(|e| e.to_string())?;
+    let mut pairing = lan.pairing.lock().map_err(|e| e.to_string())?;
+    if pairing
+        .as_ref()
+        .is_some_and(|pairing| pairing.expires_at <= now_millis())
+    {
+        *pairing = None;
+    }
+
+    let mut peers: Vec<LanPeer> = store
+        .peers
+        .iter()
+        .map(|peer| {
+            let found = discovered.get(&peer.id);
+            LanPeer {
+                id: peer.id.clone(),
+                name: found.map_or_else(|| peer.name.clone(), |found| found.name.clone()),
+                address: found
+                    .map(|found| found.address.clone())
+                    .or_else(|| Some(peer.address.clone()).filter(|a| !a.is_empty())),
+                paired: true,
+                online: found.is_some(),
+                last_sync_at: peer.last_sync_at,
+            }
+        })
+        .collect();
+    for (id, found) in discovered.iter() {
+        if store.peer(id).is_none() {
+            peers.push(LanPeer {
+                id: id.clone(),
+                name: found.name.clone(),
+                address: Some(found.address.clone()),
+                paired: false,
+                online: true,
+                last_sync_at: None,
+            });
+        }
+    }
+    peers.sort_by(|a, b| b.paired.cmp(&a.paired).then_with(|| a.name.cmp(&b.name)));
+
+    Ok(LanStatus {
+        running: running.is_some(),
+        id: store.id.clone(),
+        name: running
+            .as_ref()
+            .map_or_else(|| device_name(&store.name), |running| running.name.clone()),
+        addresses: running
+            .as_ref()
+            .map(|running| local_addresses(running.port))
+            .unwrap_or_default(),
+        discovery: running
+            .as_ref()
+            .is_some_and(|running| running.daemon.is_some()),
+        pairing_code: pairing.as_ref().map(|pairing| pairing.code.clone()),
+        pairing_expires_at: pairing.as_ref().map(|pairing| pairing.expires_at),
+        peers,
+    })
+}
+
+fn emit_status(app: &AppHandle) {
+    let state = app.state::<SyncState>();
+    if let Ok(status) = status(app, &state.lan) {
+        let _ = app.emit("lan-status", status);
+    }
+}
+
+fn device_name(name: &str) -> String {
+    let name = name.trim();
+    if name.is_empty() {
+        DEFAULT_DEVICE_NAME.to_string()
+    } else {
+        name.chars().take(64).collect()
+    }
+}
+
+/// The name and listening port this device announces.
+fn local_endpoint(app: &AppHandle, store: &LanStore) -> (String, u16) {
+    let state = app.state::<SyncState>();
+    let running = state.lan.running.lock().ok();
+    match running.as_ref().and_then(|running| running.as_ref()) {
+        Some(running) => (running.name.clone(), running.port),
+        None => (device_name(&store.name), 0),
+    }
+}
+
+async fn with_timeout<T>(
+    duration: Duration,
+    future: impl Future<Output = Result<T, String>>,
+) -> Result<T, String> {
+    tokio::time::timeout(duration, future)
+        .await
+        .map_err(|_| "局域网连接超时".to_string())?
+}
+
+async fn connect(address: &str) -> Result<TcpStream, String> {
+    with_timeout(CONNECT_TIMEOUT, async {
+        TcpStream::connect(address)
+            .await
+            .map_err(|e| format!("无法连接到 {}: {}", address, e))
+    })
+    .await
+}
+
+fn peer_address(ip: IpAddr, port: u16) -> String {
+    if port == 0 {
+        String::new()
+    } else {
+        SocketAddr::new(ip, port).to_string()
+    }
+}
+
+/// Announces this device over mDNS and collects other instances as they come
+/// and go.
+fn start_discovery(
+    app: &AppHandle,
+    id: &str,
+    name: &str,
+    port: u16,
+) -> Result<(ServiceDaemon, String, JoinHandle<()>), String> {
+    let daemon = ServiceDaemon::new().map_err(|e| format!("启动局域网发现失败: {}", e))?;
+    let properties = [("id", id), ("name", name)];
+    let service = ServiceInfo::new(
+        SERVICE_TYPE,
+        id,
+        &format!("workplan-{}.local.", id),
+        "",
+        port,
+        &properties[..],
+    )
+    .map_err(|e| format!("启动局域网发现失败: {}", e))?
+    .enable_addr_auto();
+    let fullname = service.get_fullname().to_string();
+    daemon
+        .register(service)
+        .map_err(|e| format!("启动局域网发现失败: {}", e))?;
+    let events = daemon
+        .browse(SERVICE_TYPE)
+        .map_err(|e| format!("启动局域网发现失败: {}", e))?;
+
+    let app = app.clone();
+    let own_id = id.to_string();
+    let task = tauri::async_runtime::spawn(async move {
+        while let Ok(event) = events.recv_async().await {
+            let state = app.state::<SyncState>();
+            let changed = match event {
+                ServiceEvent::ServiceResolved(info) => {
+                    let Some(id) = info.get_property_val_str("id").map(str::to_string) else {
+                        continue;
+                    };
+                    let Some(ip) = info.get_addresses_v4().into_iter().next().copied() else {
+                        continue;
+                    };
+                    if id == own_id {
+                        continue;
+                    }
+                    let found = Discovered {
+                        fullname: info.get_fullname().to_string(),
+                        name: device_name(info.get_property_val_str("name").unwrap_or_default()),
+                        address: peer_address(IpAddr::V4(ip), info.get_port()),
+                    };
+                    state
+                        .lan
+                        .discovered
+                        .lock()
+                        .map(|mut discovered| discovered.insert(id, found))
+                        .is_ok()
+                }
+                ServiceEvent::ServiceRemoved(_, fullname) => state
+                    .lan
+                    .discovered
+                    .lock()
+                    .map(|mut discovered| discovered.retain(|_, found| found.fullname != fullname))
+                    .is_ok(),
+                _ => false,
+            };
+            if changed {
+                emit_status(&app);
+            }
+        }
+    });
+    Ok((daemon, fullname, task))
+}
+
+/// Starts listening for paired devices and announcing this one on the network.
+#[tauri::command]
+pub async fn start_lan_sync(
+    app: AppHandle,
+    state: State<'_, SyncState>,
+    device_name: Option<String>,
+) -> Result<LanStatus, String> {
+    let mut store = load_store(&app)?;
+    let name = self::device_name(device_name.as_deref().unwrap_or(&store.name));
+    if store.name != name {
+        store.name = name.clone();
+        save_store(&app, &store)?;
+    }
+    stop(&state.lan)?;
+
+    let listener = TcpListener::bind("0.0.0.0:0")
+        .await
+        .map_err(|e| format!("启动局域网同步失败: {}", e))?;
+    let port = listener
+        .local_addr()
+        .map_err(|e| format!("启动局域网同步失败: {}", e))?
+        .port();
+    let accept_app = app.clone();
+    let slots = Arc::new(Semaphore::new(MAX_INCOMING));
+    let mut tasks = vec![tauri::async_runtime::spawn(async move {
+        while let Ok((stream, remote)) = listener.accept().await {
+            let Ok(slot) = slots.clone().try_acquire_owned() else {
+                continue;
+            };
+            let app = accept_app.clone();
+            tauri::async_runtime::spawn(async move {
+                let _ = handle_incoming(&app, stream, remote.ip()).await;
+                drop(slot);
+            });
+        }
+    })];
+
+    // Multicast is often filtered (guest Wi-Fi, Android without a multicast
+    // lock); devices can then still pair and sync by address.
+    let daemon = match start_discovery(&app, &store.id, &name, port) {
+        Ok((daemon, fullname, task)) => {
+            tasks.push(task);
+            Some((daemon, fullname))
+        }
+        Err(_) => None,
+    };
+
+    *state.lan.running.lock().map_err(|e| e.to_string())? = Some(Running {
+        name,
+        port,
+        daemon,
+        tasks,
+    });
+    let status = status(&app, &state.lan)?;
+    let _ = app.emit("lan-status", &status);
+    Ok(status)
+}
+
+fn stop(lan: &LanState) -> Result<(), String> {
+    let running = lan.running.lock().map_err(|e| e.to_string())?.take();
+    if let Some(running) = running {
+        for task in running.tasks {
+            task.abort();
+        }
+        if let Some((daemon, fullname)) = running.daemon {
+            let _ = daemon.unregister(&fullname);
+            let _ = daemon.shutdown();
+        }
+    }
+    lan.discovered.lock().map_err(|e| e.to_string())?.clear();
+    *lan.pairing.lock().map_err(|e| e.to_string())? = None;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn stop_lan_sync(app: AppHandle, state: State<'_, SyncState>) -> Result<(), String> {
+    stop(&state.lan)?;
+    emit_status(&app);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_lan_status(app: AppHandle, state: State<'_, SyncState>) -> Result<LanStatus, String> {
+    status(&app, &state.lan)
+}
+
+/// Shows a one-time code for another device to pair with this one.
+#[tauri::command]
+pub fn begin_lan_pairing(app: AppHandle, state: State<'_, SyncState>) -> Result<LanStatus, String> {
+    if state
+        .lan
+        .running
+        .lock()
+        .map_err(|e| e.to_string())?
+        .is_none()
+    {
+        return Err("请先开启局域网同步".to_string());
+    }
+    let value = u32::from_be_bytes(lan_channel::random_bytes::<4>()) % 1_000_000;
+    *state.lan.pairing.lock().map_err(|e| e.to_string())? = Some(Pairing {
+        code: format!("{:06}", value),
+        expires_at: now_millis() + PAIRING_TTL_MS,
+        failures: 0,
+    });
+    let status = status(&app, &state.lan)?;
+    let _ = app.emit("lan-status", &status);
+    Ok(status)
+}
+
+/// Pairs with the device at `address` showing `code`.
+#[tauri::command]
+pub async fn pair_lan_peer(
+    app: AppHandle,
+    address: String,
+    code: String,
+) -> Result<LanPeer, String> {
+    let code = code.trim().to_string();
+    if code.len() != 6 || !code.chars().all(|c| c.is_ascii_digit()) {
+        return Err("配对码应为 6 位数字".to_string());
+    }
+    let mut store = load_store(&app)?;
+    let identity = store.identity()?;
+    let (name, port) = local_endpoint(&app, &store);
+    let address = address.trim().to_string();
+    let mut stream = connect(&address).await?;
+    let remote_ip = stream
+        .peer_addr()
+        .map_err(|e| format!("无法连接到 {}: {}", address, e))?
+        .ip();
+
+    let nonce = lan_channel::random_bytes::<NONCE_LEN>();
+    let share = PairingShare::new(&code, &nonce);
+    let peer = with_timeout(HANDSHAKE_TIMEOUT, async {
+        lan_channel::send_plain(
+            &mut stream,
+            &Plain::Hello {
+                pairing: true,
+                id: identity.id.clone(),
+                nonce: BASE64.encode(nonce),
+                key: BASE64.encode(share.public),
+            },
+        )
+        .await?;
+        let (peer_nonce, peer_share) = match lan_channel::recv_plain(&mut stream).await? {
+            Plain::Hello { nonce, key, .. } => {
+                (decode_key::<NONCE_LEN>(&nonce)?, decode_key::<32>(&key)?)
+            }
+            Plain::Refused { message } => return Err(message),
+        };
+        let keys = lan_channel::pairing_keys(&share, &peer_share, true, &nonce, &peer_nonce)?;
+        // The responder drops the connection when our frame does not decrypt.
+        let mut channel = Channel::open(stream, &keys, true)
+            .await
+            .map_err(|_| "配对码错误或已过期".to_string())?;
+        channel
+            .send(&Message::Identity {
+                id: identity.id.clone(),
+                name,
+                key: BASE64.encode(lan_channel::public_key(&identity.secret)),
+                port,
+            })
+            .await?;
+        match channel.recv().await {
+            Ok(Message::Identity {
+                id,
+                name,
+                key,
+                port,
+            }) => {
+                decode_key::<32>(&key)?;
+                Ok(PairedPeer {
+                    id,
+                    name: device_name(&name),
+                    key,
+                    address: peer_address(remote_ip, port),
+                    paired_at: now_millis(),
+                    last_sync_at: None,
+                })
+            }
+            Ok(_) => Err("局域网消息无效".to_string()),
+            Err(error) => Err(error),
+        }
+    })
+    .await?;
+
+    if peer.id == identity.id {
+        return Err("不能与本机配对".to_string());
+    }
+    store.upsert(peer.clone());
+    save_store(&app, &store)?;
+    let _ = app.emit("lan-paired", json!({ "id": peer.id, "name": peer.name }));
+    emit_status(&app);
+    Ok(LanPeer {
+        id: peer.id,
+        name: peer.name,
+        address: Some(peer.address).filter(|address| !address.is_empty()),
+        paired: true,
+        online: true,
+        last_sync_at: None,
+    })
+}
+
+#[tauri::command]
+pub fn forget_lan_peer(app: AppHandle, id: String) -> Result<(), String> {
+    let mut store = load_store(&app)?;
+    store.peers.retain(|peer| peer.id != id);
+    save_store(&app, &store)?;
+    emit_status(&app);
+    Ok(())
+}
+
+async fn handle_incoming(
+    app: &AppHandle,
+    mut stream: TcpStream,
+    remote_ip: IpAddr,
+) -> Result<(), String> {
+    let hello = with_timeout(HANDSHAKE_TIMEOUT, lan_channel::recv_plain(&mut stream)).await?;
+    let Plain::Hello {
+        pairing,
+        id,
+        nonce,
+        key,
+    } = hello
+    else {
+        return Ok(());
+    };
+    let nonce = decode_key::<NONCE_LEN>(&nonce)?;
+    let key = decode_key::<32>(&key)?;
+    if pairing {
+        respond_pairing(app, stream, remote_ip, &nonce, &key).await
+    } else {
+        respond_sync(app, stream, &id, &nonce, &key).await
+    }
+}
+
+async fn refuse(stream: &mut TcpStream, message: &str) -> Result<(), String> {
+    lan_channel::send_plain(
+        stream,
+        &Plain::Refused {
+            message: message.to_string(),
+        },
+    )
+    .await
+}
+
+/// Counts a wrong-code attempt against the current pairing code.
+fn pairing_failed(app: &AppHandle) {
+    let state = app.state::<SyncState>();
+    if let Ok(mut pairing) = state.lan.pairing.lock() {
+        if let Some(current) = pairing.as_mut() {
+            current.failures += 1;
+            // Too many guesses: the code is burned and a new one must be shown.
+            if current.failures >= MAX_PAIRING_FAILURES {
+                *pairing = None;
+            }
+        }
+    }
+    emit_status(app);
+}
+
+async fn respond_pairing(
+    app: &AppHandle,
+    mut stream: TcpStream,
+    remote_ip: IpAddr,
+    peer_nonce: &[u8; NONCE_LEN],
+    peer_share: &[u8; 32],
+) -> Result<(), String> {
+    let state = app.state::<SyncState>();
+    let code = state
+        .lan
+        .pairing
+        .lock()
+        .map_err(|e| e.to_string())?
+        .as_ref()
+        .filter(|pairing| pairing.expires_at > now_millis())
+        .map(|pairing| pairing.code.clone());
+    let Some(code) = code else {
+        return refuse(
+            &mut stream,
+            "对方设备未开启配对，请先在对方设备上生成配对码",
+        )
+        .await;
+    };
+    let mut store = load_store(app)?;
+    let identity = store.identity()?;
+    let (name, port) = local_endpoint(app, &store);
+
+    let nonce = lan_channel::random_bytes::<NONCE_LEN>();
+    let share = PairingShare::new(&code, peer_nonce);
+    lan_channel::send_plain(
+        &mut stream,
+        &Plain::Hello {
+            pairing: true,
+            id: identity.id.clone(),
+            nonce: BASE64.encode(nonce),
+            key: BASE64.encode(share.public),
+        },
+    )
+    .await?;
+    let opened = match lan_channel::pairing_keys(&share, peer_share, false, peer_nonce, &nonce) {
+        Ok(keys) => with_timeout(HANDSHAKE_TIMEOUT, Channel::open(stream, &keys, false)).await,
+        Err(error) => Err(error),
+    };
+    let mut channel = match opened {
+        Ok(channel) => channel,
+        // Also counted when the peer hangs up: it has seen our confirmation
+        // frame by then and could check its guess against it.
+        Err(error) => {
+            pairing_failed(app);
+            return Err(error);
+        }
+    };
+    let peer = match with_timeout(HANDSHAKE_TIMEOUT, channel.recv()).await {
+        Ok(Message::Identity {
+            id,
+            name,
+            key,
+            port,
+        }) if id != identity.id => {
+            decode_key::<32>(&key)?;
+            PairedPeer {
+                id,
+                name: device_name(&name),
+                key,
+                address: peer_address(remote_ip, port),
+                paired_at: now_millis(),
+                last_sync_at: None,
+            }
+        }
+        Ok(_) => return Err("局域网消息无效".to_string()),
+        Err(error) => return Err(error),
+    };
+    channel
+        .send(&Message::Identity {
+            id: identity.id.clone(),
+            name,
+            key: BASE64.encode(lan_channel::public_key(&identity.secret)),
+            port,
+        })
+        .await?;
+
+    *state.lan.pairing.lock().map_err(|e| e.to_string())? = None;
+    store.upsert(peer.clone());
+    save_store(app, &store)?;
+    let _ = app.emit("lan-paired", json!({ "id": peer.id, "name": peer.name }));
+    emit_status(app);
+    Ok(())
+}
+
+/// The per-peer replica with this sync's local edits recorded.
+struct LanReplica {
+    scope: String,
+    path: PathBuf,
+    replica: Replica,
+    clock: Clock,
+    local: Document,
+    base: Document,
+}
+
+impl LanReplica {
+    fn open(
+        app: &AppHandle,
+        peer_id: &str,
+        access_key: &str,
+        data: Option<&Value>,
+    ) -> Result<Self, String> {
+        let state = app.state::<SyncState>();
+        let _replicas = state.lan.replicas.lock().map_err(|e| e.to_string())?;
+        let scope = hex_digest(&format!("lan|{}|{}", peer_id, access_key))[..16].to_string();
+        let path = replica_path(app, &scope)?;
+        let mut replica = load_replica(&path);
+        let mut clock = Clock::new(&replica.node, &replica.clock);
+        let mut local = Document::from_value(&replica.document).unwrap_or_default();
+        let base = Document::from_value(&replica.base).unwrap_or_default();
+        if let Some(data) = data.and_then(Value::as_object) {
+            let stamp = clock.tick(now_millis());
+            if local.apply_changes(data, &stamp) {
+                replica.clock = clock.last();
+                replica.document = local.to_value();
+                save_replica(&path, &replica)?;
+            }
+        }
+        Ok(LanReplica {
+            scope,
+            path,
+            replica,
+            clock,
+            local,
+            base,
+        })
+    }
+
+    /// Merges the peer's document; both devices end up with the same result.
+    fn settle(
+        mut self,
+        app: &AppHandle,
+        remote: &Document,
+    ) -> Result<(Document, SyncOutcome), String> {
+        let state = app.state::<SyncState>();
+        let merged = self.local.merge(remote);
+        let found = self.local.conflicts(remote, &self.base);
+        if let Some(stamp) = merged.max_stamp() {
+            self.clock.observe(stamp);
+        }
+        let revision = now_millis().to_string();
+        {
+            let _replicas = state.lan.replicas.lock().map_err(|e| e.to_string())?;
+            self.replica.clock = self.clock.last();
+            self.replica.document = merged.to_value();
+            self.replica.base = merged.to_value();
+            self.replica.revision = Some(revision.clone());
+            save_replica(&self.path, &self.replica)?;
+        }
+        let outcome = SyncOutcome {
+            data: Value::Object(merged.data()),
+            revision: Some(revision),
+            pushed: true,
+            queued: false,
+            conflicts: conflicts::record(app, &self.scope, found)?,
+        };
+        Ok((merged, outcome))
+    }
+}
+
+fn touch_peer(app: &AppHandle, id: &str, address: Option<String>) -> Result<(), String> {
+    let mut store = load_store(app)?;
+    let Some(peer) = store.peers.iter_mut().find(|peer| peer.id == id) else {
+        return Ok(());
+    };
+    peer.last_sync_at = Some(now_millis());
+    if let Some(address) = address {
+        peer.address = address;
+    }
+    save_store(app, &store)?;
+    emit_status(app);
+    Ok(())
+}
+
+async fn respond_sync(
+    app: &AppHandle,
+    mut stream: TcpStream,
+    peer_id: &str,
+    peer_nonce: &[u8; NONCE_LEN],
+    peer_ephemeral: &[u8; 32],
+) -> Result<(), String> {
+    let store = load_store(app)?;
+    let Some(peer) = store.peer(peer_id).cloned() else {
+        return refuse(&mut stream, "设备未配对，请重新配对").await;
+    };
+    let identity = store.identity()?;
+    let peer_identity = decode_key::<32>(&peer.key)?;
+    let ephemeral = Ephemeral::new();
+    lan_channel::send_plain(
+        &mut stream,
+        &Plain::Hello {
+            pairing: false,
+            id: identity.id.clone(),
+            nonce: BASE64.encode(ephemeral.nonce),
+            key: BASE64.encode(ephemeral.public),
+        },
+    )
+    .await?;
+    let keys = lan_channel::session_keys(
+        &SessionKeys {
+            identity: &identity.secret,
+            ephemeral: &ephemeral,
+            peer_identity: &peer_identity,
+            peer_ephemeral,
+        },
+        false,
+        peer_nonce,
+        &ephemeral.nonce,
+        &format!("{}|{}", peer.id, identity.id),
+    )?;
+    let mut channel = with_timeout(HANDSHAKE_TIMEOUT, Channel::open(stream, &keys, false)).await?;
+    let (hash, document) = match with_timeout(HANDSHAKE_TIMEOUT, channel.recv()).await? {
+        Message::SyncRequest { key_hash, document } => (key_hash, document),
+        _ => return Err("局域网消息无效".to_string()),
+    };
+    let remote = Document::from_value(&document).ok_or_else(|| "对方同步数据无效".to_string())?;
+
+    let state = app.state::<SyncState>();
+    let request_id = to_hex(&lan_channel::random_bytes::<8>());
+    let (sender, receiver) = oneshot::channel();
+    state
+        .lan
+        .requests
+        .lock()
+        .map_err(|e| e.to_string())?
+        .insert(request_id.clone(), sender);
+    let _ = app.emit(
+        "lan-sync-request",
+        json!({ "requestId": request_id, "peerId": peer.id, "peerName": peer.name }),
+    );
+    let handover = tokio::time::timeout(RESPONSE_TIMEOUT, receiver).await;
+    if let Ok(mut requests) = state.lan.requests.lock() {
+        requests.remove(&request_id);
+    }
+    let Ok(Ok(handover)) = handover else {
+        return channel
+            .send(&Message::Refused {
+                message: "对方设备未响应，请确认应用处于前台".to_string(),
+            })
+            .await;
+    };
+
+    let refusal = if handover.access_key.is_empty() {
+        Some("对方设备未登录")
+    } else if key_hash(&handover.access_key) != hash {
+        Some("两台设备的访问密钥不一致")
+    } else {
+        None
+    };
+    if let Some(message) = refusal {
+        let _ = handover.reply.send(Err(message.to_string()));
+        return channel
+            .send(&Message::Refused {
+                message: message.to_string(),
+            })
+            .await;
+    }
+
+    let settled = LanReplica::open(app, &peer.id, &handover.access_key, handover.data.as_ref())
+        .and_then(|replica| replica.settle(app, &remote));
+    let (merged, outcome) = match settled {
+        Ok(settled) => settled,
+        Err(error) => {
+            let _ = handover.reply.send(Err(error.clone()));
+            return channel.send(&Message::Refused { message: error }).await;
+        }
+    };
+    let sent = channel
+        .send(&Message::SyncReply {
+            document: merged.to_value(),
+        })
+        .await;
+    touch_peer(app, &peer.id, None)?;
+    let _ = handover.reply.send(sent.map(|_| outcome));
+    Ok(())
+}
+
+/// Hands the frontend's current data to an incoming sync announced by a
+/// `lan-sync-request` event. An empty `access_key` declines it.
+#[tauri::command]
+pub async fn lan_sync_respond(
+    state: State<'_, SyncState>,
+    request_id: String,
+    access_key: String,
+    data: Option<Value>,
+) -> Result<SyncOutcome, String> {
+    let sender = state
+        .lan
+        .requests
+        .lock()
+        .map_err(|e| e.to_string())?
+        .remove(&request_id)
+        .ok_or_else(|| "同步请求已过期".to_string())?;
+    let (reply, outcome) = oneshot::channel();
+    sender
+        .send(Handover {
+            access_key: access_key.trim().to_string(),
+            data,
+            reply,
+        })
+        .map_err(|_| "同步请求已过期".to_string())?;
+    outcome.await.map_err(|_| "局域网同步已中断".to_string())?
+}
+
+/// Syncs `data` directly with a paired device, which merges it with its own and
+/// sends back the result. Returns the merged collections for the frontend.
+#[tauri::command]
+pub async fn sync_lan_peer(
+    app: AppHandle,
+    state: State<'_, SyncState>,
+    peer_id: String,
+    access_key: String,
+    data: Option<Value>,
+) -> Result<SyncOutcome, String> {
+    let access_key = access_key.trim().to_string();
+    if access_key.is_empty() {
+        return Err("未登录，无法同步".to_string());
+    }
+    let store = load_store(&app)?;
+    let peer = store
+        .peer(&peer_id)
+        .cloned()
+        .ok_or_else(|| "设备未配对".to_string())?;
+    let identity = store.identity()?;
+    let peer_identity = decode_key::<32>(&peer.key)?;
+    let address = state
+        .lan
+        .discovered
+        .lock()
+        .map_err(|e| e.to_string())?
+        .get(&peer.id)
+        .map(|found| found.address.clone())
+        .unwrap_or_else(|| peer.address.clone());
+    if address.is_empty() {
+        return Err("找不到该设备，请确认两台设备在同一网络且已开启局域网同步".to_string());
+    }
+
+    let mut stream = connect(&address).await?;
+    let ephemeral = Ephemeral::new();
+    let mut channel = with_timeout(HANDSHAKE_TIMEOUT, async {
+        lan_channel::send_plain(
+            &mut stream,
+            &Plain::Hello {
+                pairing: false,
+                id: identity.id.clone(),
+                nonce: BASE64.encode(ephemeral.nonce),
+                key: BASE64.encode(ephemeral.public),
+            },
+        )
+        .await?;
+        let (id, nonce, key) = match lan_channel::recv_plain(&mut stream).await? {
+            Plain::Hello { id, nonce, key, .. } => (id, nonce, key),
+            Plain::Refused { message } => return Err(message),
+        };
+        if id != peer.id {
+            return Err("设备身份不匹配，请重新配对".to_string());
+        }
+        let keys = lan_channel::session_keys(
+            &SessionKeys {
+                identity: &identity.secret,
+                ephemeral: &ephemeral,
+                peer_identity: &peer_identity,
+                peer_ephemeral: &decode_key::<32>(&key)?,
+            },
+            true,
+            &ephemeral.nonce,
+            &decode_key::<NONCE_LEN>(&nonce)?,
+            &format!("{}|{}", identity.id, peer.id),
+        )?;
+        Channel::open(stream, &keys, true)
+            .await
+            // A peer holding a different key for us cannot produce a valid frame.
+            .map_err(|error| match error.as_str() {
+                "局域网数据校验失败" => "对方设备已不再信任本机，请重新配对".to_string(),
+                _ => error,
+            })
+    })
+    .await?;
+
+    let replica = LanReplica::open(&app, &peer.id, &access_key, data.as_ref())?;
+    channel
+        .send(&Message::SyncRequest {
+            key_hash: key_hash(&access_key),
+            document: replica.local.to_value(),
+        })
+        .await?;
+    let reply = with_timeout(RESPONSE_TIMEOUT + HANDSHAKE_TIMEOUT, channel.recv()).await?;
+    let remote = match reply {
+        Message::SyncReply { document } => {
+            Document::from_value(&document).ok_or_else(|| "对方同步数据无效".to_string())?
+        }
+        Message::Refused { message } => return Err(message),
+        _ => return Err("局域网消息无效".to_string()),
+    };
+    let (_, outcome) = replica.settle(&app, &remote)?;
+    touch_peer(&app, &peer.id, Some(address))?;
+    Ok(outcome)
+}
@@ -0,0 +1,455 @@
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Nonce};
+use curve25519_dalek::ristretto::{CompressedRistretto, RistrettoPoint};
+use curve25519_dalek::scalar::Scalar;
+use curve25519_dalek::traits::IsIdentity;
+use hkdf::Hkdf;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use sha2::{Digest, Sha256, Sha512};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use x25519_dalek::{PublicKey, StaticSecret};
+
+pub const NONCE_LEN: usize = 16;
+/// Upper bound for one frame; a full document is well below this.
+const MAX_FRAME: usize = 32 * 1024 * 1024;
+/// Upper bound for frames read before the peer has proven it holds the channel
+/// keys, so that an unauthenticated connection cannot make us allocate much.
+const MAX_HANDSHAKE_FRAME: usize = 4 * 1024;
+
+pub fn random_bytes<const N: usize>() -> [u8; N] {
+    let mut bytes = [0u8; N];
+    OsRng.fill_bytes(&mut bytes);
+    bytes
+}
+
+pub fn public_key(secret: &StaticSecret) -> [u8; 32] {
+    PublicKey::from(secret).to_bytes()
+}
+
+/// One side's fresh key pair and nonce for a handshake.
+pub struct Ephemeral {
+    secret: StaticSecret,
+    pub public: [u8; 32],
+    pub nonce: [u8; NONCE_LEN],
+}
+
+impl Ephemeral {
+    pub fn new() -> Self {
+        let secret = StaticSecret::from(random_bytes::<32>());
+        Ephemeral {
+            public: public_key(&secret),
+            secret,
+            nonce: random_bytes(),
+        }
+    }
+
+    fn agree(&self, peer: &[u8; 32]) -> Result<[u8; 32], String> {
+        agree(&self.secret, peer)
+    }
+}
+
+fn agree(secret: &StaticSecret, peer: &[u8; 32]) -> Result<[u8; 32], String> {
+    let shared = secret.diffie_hellman(&PublicKey::from(*peer));
+    // A low-order peer key yields a fixed secret that the code alone would protect.
+    if !shared.was_contributory() {
+        return Err("对方密钥无效".to_string());
+    }
+    Ok(shared.to_bytes())
+}
+
+fn expand<const N: usize>(salt: &[u8], ikm: &[u8], info: &[u8]) -> [u8; N] {
+    let mut out = [0u8; N];
+    Hkdf::<Sha256>::new(Some(salt), ikm)
+        .expand(info, &mut out)
+        .expect("HKDF output length is valid");
+    out
+}
+
+/// One side of a CPace pairing exchange (draft-irtf-cfrg-cpace, ristretto255).
+/// The generator is derived from the code, and every share is a valid group
+/// element whatever code produced it, so a listener learns nothing it could
+/// test candidate codes against offline and an active attacker gets one guess
+/// per connection.
+pub struct PairingShare {
+    secret: Scalar,
+    pub public: [u8; 32],
+}
+
+fn pairing_generator(code: &str, initiator_nonce: &[u8]) -> RistrettoPoint {
+    let mut hash = Sha512::new();
+    for part in [
+        b"workplan-lan-cpace".as_slice(),
+        code.as_bytes(),
+        initiator_nonce,
+    ] {
+        hash.update((part.len() as u64).to_be_bytes());
+        hash.update(part);
+    }
+    let mut wide = [0u8; 64];
+    wide.copy_from_slice(&hash.finalize());
+    RistrettoPoint::from_uniform_bytes(&wide)
+}
+
+impl PairingShare {
+    pub fn new(code: &str, initiator_nonce: &[u8]) -> Self {
+        let secret = Scalar::from_bytes_mod_order_wide(&random_bytes());
+        let public = (pairing_generator(code, initiator_nonce) * secret).compress();
+        PairingShare {
+            secret,
+            public: public.to_bytes(),
+        }
+    }
+}
+
+/// Channel keys for a pairing run. A peer that used another code ends up with
+/// different keys, which shows up as the key confirmation failing to decrypt.
+pub fn pairing_keys(
+    own: &PairingShare,
+    peer: &[u8; 32],
+    initiator: bool,
+    initiator_nonce: &[u8],
+    responder_nonce: &[u8],
+) -> Result<[u8; 64], String> {
+    let point = CompressedRistretto(*peer)
+        .decompress()
+        .filter(|point| !point.is_identity())
+        .ok_or_else(|| "对方密钥无效".to_string())?;
+    let shared = (point * own.secret).compress();
+    let (initiator_share, responder_share) = if initiator {
+        (&own.public, peer)
+    } else {
+        (peer, &own.public)
+    };
+    let ikm = [shared.to_bytes(), *initiator_share, *responder_share].concat();
+    let salt = [initiator_nonce, responder_nonce].concat();
+    Ok(expand(&salt, &ikm, b"workplan-lan-pair"))
+}
+
+/// The long-term and ephemeral keys of both ends of a sync connection.
+pub struct SessionKeys<'a> {
+    pub identity: &'a StaticSecret,
+    pub ephemeral: &'a Ephemeral,
+    pub peer_identity: &'a [u8; 32],
+    pub peer_ephemeral: &'a [u8; 32],
+}
+
+/// Channel keys between two paired devices, mixing all four Diffie-Hellman
+/// results so that each side proves possession of its paired key and the
+/// session stays confidential if a long-term key leaks later.
+pub fn session_keys(
+    keys: &SessionKeys,
+    initiator: bool,
+    initiator_nonce: &[u8],
+    responder_nonce: &[u8],
+    context: &str,
+) -> Result<[u8; 64], String> {
+    let ee = keys.ephemeral.agree(keys.peer_ephemeral)?;
+    let ss = agree(keys.identity, keys.peer_identity)?;
+    // Ordered as (initiator static, responder ephemeral), then the reverse.
+    let (static_ephemeral, ephemeral_static) = if initiator {
+        (
+            agree(keys.identity, keys.peer_ephemeral)?,
+            keys.ephemeral.agree(keys.peer_identity)?,
+        )
+    } else {
+        (
+            keys.ephemeral.agree(keys.peer_identity)?,
+            agree(keys.identity, keys.peer_ephemeral)?,
+        )
+    };
+    let ikm = [ee, static_ephemeral, ephemeral_static, ss].concat();
+    let salt = [initiator_nonce, responder_nonce].concat();
+    let info = format!("workplan-lan-sync|{}", context);
+    Ok(expand(&salt, &ikm, info.as_bytes()))
+}
+
+fn io_error(error: std::io::Error) -> String {
+    format!("局域网连接中断: {}", error)
+}
+
+async fn write_frame(stream: &mut TcpStream, bytes: &[u8]) -> Result<(), String> {
+    if bytes.len() > MAX_FRAME {
+        return Err("同步数据过大".to_string());
+    }
+    stream
+        .write_all(&(bytes.len() as u32).to_be_bytes())
+        .await
+        .map_err(io_error)?;
+    stream.write_all(bytes).await.map_err(io_error)?;
+    stream.flush().await.map_err(io_error)
+}
+
+async fn read_frame(stream: &mut TcpStream, limit: usize) -> Result<Vec<u8>, String> {
+    let mut len = [0u8; 4];
+    stream.read_exact(&mut len).await.map_err(io_error)?;
+    let len = u32::from_be_bytes(len) as usize;
+    if len > limit {
+        return Err("同步数据过大".to_string());
+    }
+    let mut bytes = vec![0u8; len];
+    stream.read_exact(&mut bytes).await.map_err(io_error)?;
+    Ok(bytes)
+}
+
+/// Sends an unencrypted handshake message.
+pub async fn send_plain<T: Serialize>(stream: &mut TcpStream, message: &T) -> Result<(), String> {
+    let bytes = serde_json::to_vec(message).map_err(|e| e.to_string())?;
+    write_frame(stream, &bytes).await
+}
+
+pub async fn recv_plain<T: DeserializeOwned>(stream: &mut TcpStream) -> Result<T, String> {
+    let bytes = read_frame(stream, MAX_HANDSHAKE_FRAME).await?;
+    serde_json::from_slice(&bytes).map_err(|e| format!("局域网消息无效: {}", e))
+}
+
+/// Length-prefixed ChaCha20-Poly1305 frames with a per-direction key and a
+/// counter nonce.
+pub struct Channel {
+    stream: TcpStream,
+    send_cipher: ChaCha20Poly1305,
+    recv_cipher: ChaCha20Poly1305,
+    sent: u64,
+    received: u64,
+}
+
+fn counter_nonce(counter: u64) -> Nonce {
+    let mut nonce = [0u8; 12];
+    nonce[4..].copy_from_slice(&counter.to_be_bytes());
+    Nonce::from(nonce)
+}
+
+impl Channel {
+    /// The first half of `keys` encrypts initiator-to-responder traffic.
+    fn new(stream: TcpStream, keys: &[u8; 64], initiator: bool) -> Self {
+        let (outgoing, incoming) = if initiator {
+            (&keys[..32], &keys[32..])
+        } else {
+            (&keys[32..], &keys[..32])
+        };
+        Channel {
+            stream,
+            send_cipher: ChaCha20Poly1305::new(outgoing.into()),
+            recv_cipher: ChaCha20Poly1305::new(incoming.into()),
+            sent: 0,
+            received: 0,
+        }
+    }
+
+    /// Opens the channel by exchanging an empty sealed frame, so that wrong
+    /// keys surface at once and full-size frames are only read from a peer
+    /// that has proven it holds the keys.
+    pub async fn open(stream: TcpStream, keys: &[u8; 64], initiator: bool) -> Result<Self, String> {
+        let mut channel = Channel::new(stream, keys, initiator);
+        channel.send_sealed(&[]).await?;
+        channel.recv_sealed(MAX_HANDSHAKE_FRAME).await?;
+        Ok(channel)
+    }
+
+    async fn send_sealed(&mut self, bytes: &[u8]) -> Result<(), String> {
+        let sealed = self
+            .send_cipher
+            .encrypt(&counter_nonce(self.sent), bytes)
+            .map_err(|_| "加密失败".to_string())?;
+        self.sent += 1;
+        write_frame(&mut self.stream, &sealed).await
+    }
+
+    async fn recv_sealed(&mut self, limit: usize) -> Result<Vec<u8>, String> {
+        let sealed = read_frame(&mut self.stream, limit).await?;
+        let bytes = self
+            .recv_cipher
+            .decrypt(&counter_nonce(self.received), sealed.as_slice())
+            .map_err(|_| "局域网数据校验失败".to_string())?;
+        self.received += 1;
+        Ok(bytes)
+    }
+
+    pub async fn send<T: Serialize>(&mut self, message: &T) -> Result<(), String> {
+        let bytes = serde_json::to_vec(message).map_err(|e| e.to_string())?;
+        self.send_sealed(&bytes).await
+    }
+
+    pub async fn recv<T: DeserializeOwned>(&mut self) -> Result<T, String> {
+        let bytes = self.recv_sealed(MAX_FRAME).await?;
+        serde_json::from_slice(&bytes).map_err(|e| format!("局域网消息无效: {}", e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    async fn connected() -> (TcpStream, TcpStream) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let (client, server) = tokio::join!(TcpStream::connect(address), listener.accept());
+        (client.unwrap(), server.unwrap().0)
+    }
+
+    fn pairing(initiator_code: &str, responder_code: &str) -> ([u8; 64], [u8; 64]) {
+        let (initiator_nonce, responder_nonce) =
+            (random_bytes::<NONCE_LEN>(), random_bytes::<NONCE_LEN>());
+        let initiator = PairingShare::new(initiator_code, &initiator_nonce);
+        let responder = PairingShare::new(responder_code, &initiator_nonce);
+        (
+            pairing_keys(
+                &initiator,
+                &responder.public,
+                true,
+                &initiator_nonce,
+                &responder_nonce,
+            )
+            .unwrap(),
+            pairing_keys(
+                &responder,
+                &initiator.public,
+                false,
+                &initiator_nonce,
+                &responder_nonce,
+            )
+            .unwrap(),
+        )
+    }
+
+    async fn open(
+        initiator_keys: &[u8; 64],
+        responder_keys: &[u8; 64],
+    ) -> (Result<Channel, String>, Result<Channel, String>) {
+        let (client, server) = connected().await;
+        tokio::join!(
+            Channel::open(client, initiator_keys, true),
+            Channel::open(server, responder_keys, false)
+        )
+    }
+
+    fn seal(keys: &[u8; 64], counter: u64, message: &str) -> Vec<u8> {
+        let bytes = serde_json::to_vec(message).unwrap();
+        ChaCha20Poly1305::new(keys[..32].into())
+            .encrypt(&counter_nonce(counter), bytes.as_slice())
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn pairing_with_the_same_code_opens_a_channel() {
+        let (initiator_keys, responder_keys) = pairing("123456", "123456");
+        assert_eq!(initiator_keys, responder_keys);
+
+        let (initiator, responder) = open(&initiator_keys, &responder_keys).await;
+        let (mut initiator, mut responder) = (initiator.unwrap(), responder.unwrap());
+        // Once authenticated, frames well above the handshake cap go through.
+        let document = "x".repeat(64 * 1024);
+        initiator.send(&document).await.unwrap();
+        assert_eq!(responder.recv::<String>().await.unwrap(), document);
+        responder.send(&"reply").await.unwrap();
+        assert_eq!(initiator.recv::<String>().await.unwrap(), "reply");
+    }
+
+    #[tokio::test]
+    async fn pairing_with_a_wrong_code_fails_key_confirmation() {
+        let (initiator_keys, responder_keys) = pairing("123456", "654321");
+        assert_ne!(initiator_keys, responder_keys);
+
+        let (initiator, responder) = open(&initiator_keys, &responder_keys).await;
+        assert_eq!(initiator.err().unwrap(), "局域网数据校验失败");
+        assert_eq!(responder.err().unwrap(), "局域网数据校验失败");
+    }
+
+    #[test]
+    fn pairing_rejects_shares_outside_the_group() {
+        let nonce = random_bytes::<NONCE_LEN>();
+        let share = PairingShare::new("123456", &nonce);
+        // The identity element and a non-canonical encoding.
+        for peer in [[0u8; 32], [0xff; 32]] {
+            assert!(pairing_keys(&share, &peer, true, &nonce, &nonce).is_err());
+        }
+    }
+
+    #[test]
+    fn session_keys_agree_only_between_paired_devices() {
+        let (initiator_identity, responder_identity) = (
+            StaticSecret::from(random_bytes::<32>()),
+            StaticSecret::from(random_bytes::<32>()),
+        );
+        let (initiator_ephemeral, responder_ephemeral) = (Ephemeral::new(), Ephemeral::new());
+        let derive = |identity: &StaticSecret, peer_identity: &StaticSecret, initiator: bool| {
+            let (ephemeral, peer_ephemeral) = if initiator {
+                (&initiator_ephemeral, &responder_ephemeral)
+            } else {
+                (&responder_ephemeral, &initiator_ephemeral)
+            };
+            session_keys(
+                &SessionKeys {
+                    identity,
+                    ephemeral,
+                    peer_identity: &public_key(peer_identity),
+                    peer_ephemeral: &peer_ephemeral.public,
+                },
+                initiator,
+                &initiator_ephemeral.nonce,
+                &responder_ephemeral.nonce,
+                "a|b",
+            )
+            .unwrap()
+        };
+
+        let initiator = derive(&initiator_identity, &responder_identity, true);
+        assert_eq!(
+            initiator,
+            derive(&responder_identity, &initiator_identity, false)
+        );
+        // A device that was never paired cannot stand in for the initiator.
+        let stranger = StaticSecret::from(random_bytes::<32>());
+        assert_ne!(initiator, derive(&responder_identity, &stranger, false));
+    }
+
+    #[tokio::test]
+    async fn channel_rejects_tampered_and_replayed_frames() {
+        let keys = random_bytes::<64>();
+
+        let (mut client, server) = connected().await;
+        let mut channel = Channel::new(server, &keys, false);
+        let frame = seal(&keys, 0, "first");
+        write_frame(&mut client, &frame).await.unwrap();
+        assert_eq!(channel.recv::<String>().await.unwrap(), "first");
+        write_frame(&mut client, &frame).await.unwrap();
+        assert_eq!(
+            channel.recv::<String>().await.unwrap_err(),
+            "局域网数据校验失败"
+        );
+
+        let (mut client, server) = connected().await;
+        let mut channel = Channel::new(server, &keys, false);
+        let mut frame = seal(&keys, 0, "first");
+        frame[0] ^= 1;
+        write_frame(&mut client, &frame).await.unwrap();
+        assert_eq!(
+            channel.recv::<String>().await.unwrap_err(),
+            "局域网数据校验失败"
+        );
+    }
+
+    #[tokio::test]
+    async fn oversized_frames_are_refused_before_authentication() {
+        let (mut client, mut server) = connected().await;
+        client.write_all(&u32::MAX.to_be_bytes()).await.unwrap();
+        assert_eq!(
+            recv_plain::<String>(&mut server).await.unwrap_err(),
+            "同步数据过大"
+        );
+
+        let keys = random_bytes::<64>();
+        let (mut client, server) = connected().await;
+        client
+            .write_all(&((MAX_HANDSHAKE_FRAME + 1) as u32).to_be_bytes())
+            .await
+            .unwrap();
+        assert_eq!(
+            Channel::open(server, &keys, false).await.err().unwrap(),
+            "同步数据过大"
+        );
+    }
+}
@@ -0,0 +1,476 @@
+use reqwest::{Client, StatusCode};
+use serde_json::{json, Value};
+
+use super::{
+    is_retryable_status, next_revision, request_error, BoxFuture, RemoteSnapshot, StoreOutcome,
+    SyncBackend, SyncConfig, SyncError,
+};
+
+const DEFAULT_TABLE: &str = "planpro_data";
+
+/// A libSQL database (Turso or a self-hosted `sqld`) spoken to over the Hrana
+/// HTTP pipeline. Mirrors the PostgREST layout: one row per access key with
+/// `updated_at` as the revision, checked in the `UPDATE`'s `WHERE` clause.
+pub struct LibSqlBackend {
+    client: Client,
+    pipeline_url: String,
+    token: String,
+    table: String,
+    user_key: String,
+}
+
+struct ExecResult {
+    rows: Vec<Vec<Value>>,
+    affected: u64,
+}
+
+fn text(value: &str) -> Value {
+    json!({ "type": "text", "value": value })
+}
+
+fn integer(value: u64) -> Value {
+    // Hrana encodes 64-bit integers as strings.
+    json!({ "type": "integer", "value": value.to_string() })
+}
+
+/// Converts a Hrana value to its plain form: text as-is, integers as strings.
+fn cell_text(cell: &Value) -> Option<String> {
+    match cell.get("type").and_then(Value::as_str) {
+        Some("text") | Some("integer") | Some("float") => {
+            cell.get("value").map(|value| match value {
+                Value::String(s) => s.clone(),
+                other => other.to_string(),
+            })
+        }
+        _ => None,
+    }
+}
+
+fn pipeline_url(url: &str) -> String {
+    let url = url.trim().trim_end_matches('/');
+    // Turso hands out `libsql://` URLs; the HTTP API lives on the same host.
+    let url = match url.strip_prefix("libsql://") {
+        Some(rest) => format!("https://{}", rest),
+        None => url.to_string(),
+    };
+    if url.ends_with("/v2/pipeline") {
+        url
+    } else {
+        format!("{}/v2/pipeline", url)
+    }
+}
+
+impl LibSqlBackend {
+    pub fn new(client: Client, config: &SyncConfig, user_key: &str) -> Result<Self, String> {
+        if config.url.trim().is_empty() {
+            return Err("libSQL 数据库地址未配置".to_string());
+        }
+        let table = config.table_name.trim();
+        let table = if table.is_empty() {
+            DEFAULT_TABLE
+        } else {
+            table
+        };
+        // Interpolated into SQL, so only plain identifiers are accepted.
+        if !table.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+            || table.starts_with(|c: char| c.is_ascii_digit())
+        {
+            return Err(format!("数据表名无效: {}", table));
+        }
+        Ok(LibSqlBackend {
+            client,
+            pipeline_url: pipeline_url(&config.url),
+            token: config.api_key.trim().to_string(),
+            table: table.to_string(),
+            user_key: user_key.to_string(),
+        })
+    }
+
+    /// Runs the statements in one pipeline and returns each result, failing on
+    /// the first statement error.
+    async fn execute(
+        &self,
+        statements: Vec<(String, Vec<Value>)>,
+    ) -> Result<Vec<ExecResult>, SyncError> {
+        let mut requests: Vec<Value> = statements
+            .into_iter()
+            .map(|(sql, args)| json!({ "type": "execute", "stmt": { "sql": sql, "args": args } }))
+            .collect();
+        requests.push(json!({ "type": "close" }));
+
+        let mut request = self
+            .client
+            .post(&self.pipeline_url)
+            .timeout(super::SYNC_TIMEOUT)
+            .json(&json!({ "requests": requests }));
+        if !self.token.is_empty() {
+            request = request.bearer_auth(&self.token);
+        }
+        let response = request.send().await.map_err(request_error)?;
+        let status = response.status();
+        if status == StatusCode::UNAUTHORIZED || status == StatusCode::FORBIDDEN {
+            return Err("libSQL 认证失败，请检查数据库令牌".to_string().into());
+        }
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(SyncError {
+                message: format!("libSQL 请求失败: HTTP {} {}", status, body.trim()),
+                retryable: is_retryable_status(status),
+            });
+        }
+        let body: Value = response
+            .json()
+            .await
+            .map_err(|e| format!("libSQL 响应无效: {}", e))?;
+
+        let mut results = Vec::new();
+        for result in body
+            .get("results")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+        {
+            match result.get("type").and_then(Value::as_str) {
+                Some("ok") => {}
+                Some("error") => {
+                    let message = result
+                        .pointer("/error/message")
+                        .and_then(Value::as_str)
+                        .unwrap_or("未知错误");
+                    return Err(format!("libSQL 执行失败: {}", message).into());
+                }
+                _ => return Err("libSQL 响应无效".to_string().into()),
+            }
+            // The trailing `close` has no execute result.
+            let Some(execute) = result.pointer("/response/result") else {
+                continue;
+            };
+            results.push(ExecResult {
+                rows: execute
+                    .get("rows")
+                    .and_then(Value::as_array)
+                    .map(|rows| {
+                        rows.iter()
+                            .map(|row| row.as_array().cloned().unwrap_or_default())
+                            .collect()
+                    })
+                    .unwrap_or_default(),
+                affected: execute
+                    .get("affected_row_count")
+                    .and_then(Value::as_u64)
+                    .unwrap_or(0),
+            });
+        }
+        Ok(results)
+    }
+
+    async fn load_row(&self) -> Result<RemoteSnapshot, SyncError> {
+        let sql = format!(
+            "SELECT content, updated_at FROM {} WHERE user_key = ? LIMIT 1",
+            self.table
+        );
+        let results = match self.execute(vec![(sql, vec![text(&self.user_key)])]).await {
+            Ok(results) => results,
+            // The table is created by the first write.
+            Err(error) if error.message.contains("no such table") => {
+                return Ok(RemoteSnapshot::default())
+            }
+            Err(error) => return Err(error),
+        };
+        let Some(row) = results
+            .into_iter()
+            .next()
+            .and_then(|result| result.rows.into_iter().next())
+        else {
+            return Ok(RemoteSnapshot::default());
+        };
+        let payload = match row.first().and_then(cell_text) {
+            Some(content) => Some(
+                serde_json::from_str(&content)
+                    .map_err(|e| format!("libSQL 同步数据无效: {}", e))?,
+            ),
+            None => None,
+        };
+        Ok(RemoteSnapshot {
+            payload,
+            revision: Some(row.get(1).and_then(cell_text).unwrap_or_default()),
+        })
+    }
+
+    async fn store_row(
+        &self,
+        payload: &Value,
+        expected: Option<&str>,
+    ) -> Result<StoreOutcome, SyncError> {
+        let revision = next_revision(expected);
+        let content = serde_json::to_string(payload).map_err(|e| e.to_string())?;
+        let statements = match expected {
+            None => vec![
+                (
+                    format!(
+                        "CREATE TABLE IF NOT EXISTS {} (user_key TEXT PRIMARY KEY, content TEXT, updated_at INTEGER)",
+                        self.table
+                    ),
+                    Vec::new(),
+                ),
+                (
+                    format!(
+                        "INSERT INTO {} (user_key, content, updated_at) VALUES (?, ?, ?) ON CONFLICT(user_key) DO NOTHING",
+                        self.table
+                    ),
+                    vec![text(&self.user_key), text(&content), integer(revision)],
+                ),
+            ],
+            Some(expected) => {
+                let (condition, mut args) = if expected.is_empty() {
+                    ("updated_at IS NULL", Vec::new())
+                } else {
+                    let expected = expected
+                        .parse::<u64>()
+                        .map_err(|_| format!("libSQL 版本号无效: {}", expected))?;
+                    ("updated_at = ?", vec![integer(expected)])
+                };
+                let mut bound = vec![text(&content), integer(revision), text(&self.user_key)];
+                bound.append(&mut args);
+                vec![(
+                    format!(
+                        "UPDATE {} SET content = ?, updated_at = ? WHERE user_key = ? AND {}",
+                        self.table, condition
+                    ),
+                    bound,
+                )]
+            }
+        };
+        let results = self.execute(statements).await?;
+        // Zero affected rows: another writer inserted or updated the row first.
+        if results.last().map(|result| result.affected).unwrap_or(0) == 0 {
+            return Ok(StoreOutcome::Conflict);
+        }
+        Ok(StoreOutcome::Stored(revision.to_string()))
+    }
+
+    async fn delete_row(&self) -> Result<(), SyncError> {
+        let sql = format!("DELETE FROM {} WHERE user_key = ?", self.table);
+        match self.execute(vec![(sql, vec![text(&self.user_key)])]).await {
+            Err(error) if !error.message.contains("no such table") => Err(error),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl SyncBackend for LibSqlBackend {
+    fn load(&self) -> BoxFuture<'_, Result<RemoteSnapshot, SyncError>> {
+        Box::pin(self.load_row())
+    }
+
+    fn store<'a>(
+        &'a self,
+        payload: &'a Value,
+        expected: Option<&'a str>,
+    ) -> BoxFuture<'a, Result<StoreOutcome, SyncError>> {
+        Box::pin(self.store_row(payload, expected))
+    }
+
+    fn clear(&self) -> BoxFuture<'_, Result<(), SyncError>> {
+        Box::pin(self.delete_row())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{serve, Request, Response};
+    use std::sync::{Arc, Mutex};
+
+    /// A `sqld` stand-in that understands just the statements the backend sends.
+    #[derive(Default)]
+    struct Database {
+        table: bool,
+        /// `(content, updated_at)` of the single row.
+        row: Option<(String, u64)>,
+        statements: Vec<String>,
+    }
+
+    impl Database {
+        fn handle(&mut self, request: &Request) -> Response {
+            assert_eq!(request.path(), "/v2/pipeline");
+            if request.header("Authorization") != Some("Bearer db-token") {
+                return Response::new(401, "");
+            }
+            let body: Value = serde_json::from_slice(&request.body).unwrap();
+            let mut results = Vec::new();
+            for step in body["requests"].as_array().unwrap() {
+                if step["type"] == "close" {
+                    results.push(json!({ "type": "ok", "response": { "type": "close" } }));
+                    continue;
+                }
+                let sql = step["stmt"]["sql"].as_str().unwrap().to_string();
+                let args: Vec<String> = step["stmt"]["args"]
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .map(|arg| arg["value"].as_str().unwrap().to_string())
+                    .collect();
+                self.statements.push(sql.clone());
+                match self.execute(&sql, &args) {
+                    Ok((rows, affected)) => results.push(json!({
+                        "type": "ok",
+                        "response": {
+                            "type": "execute",
+                            "result": { "cols": [], "rows": rows, "affected_row_count": affected },
+                        },
+                    })),
+                    Err(message) => {
+                        results.push(json!({ "type": "error", "error": { "message": message } }));
+                        break;
+                    }
+                }
+            }
+            Response::new(200, json!({ "results": results }).to_string())
+        }
+
+        fn execute(&mut self, sql: &str, args: &[String]) -> Result<(Vec<Value>, u64), String> {
+            if sql.starts_with("CREATE TABLE") {
+                self.table = true;
+                return Ok((Vec::new(), 0));
+            }
+            if !self.table {
+                return Err("SQLite error: no such table: planpro_data".to_string());
+            }
+            let changed = if sql.starts_with("SELECT") {
+                let rows = self.row.iter().map(|(content, updated_at)| {
+                    json!([
+                        { "type": "text", "value": content },
+                        { "type": "integer", "value": updated_at.to_string() },
+                    ])
+                });
+                return Ok((rows.collect(), 0));
+            } else if sql.starts_with("INSERT") {
+                self.row.is_none()
+                    && self
+                        .row
+                        .replace((args[1].clone(), args[2].parse().unwrap()))
+                        .is_none()
+            } else if sql.starts_with("UPDATE") {
+                let current = self.row.as_ref().map(|row| row.1.to_string());
+                current.as_deref() == Some(args[3].as_str())
+                    && self
+                        .row
+                        .replace((args[0].clone(), args[1].parse().unwrap()))
+                        .is_some()
+            } else if sql.starts_with("DELETE") {
+                self.row.take().is_some()
+            } else {
+                return Err(format!("unexpected statement: {}", sql));
+            };
+            Ok((Vec::new(), changed as u64))
+        }
+    }
+
+    fn libsql(database: Database) -> (LibSqlBackend, Arc<Mutex<Database>>) {
+        let database = Arc::new(Mutex::new(database));
+        let shared = database.clone();
+        let base = serve(move |request: &Request| shared.lock().unwrap().handle(request));
+        let config = SyncConfig {
+            url: base,
+            api_key: "db-token".to_string(),
+            ..Default::default()
+        };
+        let client = Client::builder().no_proxy().build().unwrap();
+        let backend = LibSqlBackend::new(client, &config, "key-1").unwrap();
+        (backend, database)
+    }
+
+    fn stored(outcome: Result<StoreOutcome, SyncError>) -> String {
+        match outcome {
+            Ok(StoreOutcome::Stored(revision)) => revision,
+            Ok(StoreOutcome::Conflict) => panic!("unexpected conflict"),
+            Err(error) => panic!("{}", error.message),
+        }
+    }
+
+    #[test]
+    fn pipeline_url_targets_the_hrana_endpoint() {
+        assert_eq!(
+            pipeline_url(" libsql://notes-acme.turso.io "),
+            "https://notes-acme.turso.io/v2/pipeline"
+        );
+        assert_eq!(
+            pipeline_url("http://127.0.0.1:8080/"),
+            "http://127.0.0.1:8080/v2/pipeline"
+        );
+        assert_eq!(
+            pipeline_url("https://db.example.com/v2/pipeline"),
+            "https://db.example.com/v2/pipeline"
+        );
+    }
+
+    #[test]
+    fn hrana_values_decode_to_text() {
+        assert_eq!(cell_text(&text("hello")).as_deref(), Some("hello"));
+        assert_eq!(cell_text(&integer(u64::MAX)), Some(u64::MAX.to_string()));
+        assert_eq!(
+            cell_text(&json!({ "type": "float", "value": 1.5 })).as_deref(),
+            Some("1.5")
+        );
+        assert_eq!(cell_text(&json!({ "type": "null" })), None);
+        assert_eq!(
+            cell_text(&json!({ "type": "blob", "base64": "AA==" })),
+            None
+        );
+    }
+
+    #[test]
+    fn table_names_must_be_identifiers() {
+        let client = Client::new();
+        for table in ["data; DROP TABLE x", "1data", "my-data"] {
+            let config = SyncConfig {
+                url: "libsql://db.turso.io".to_string(),
+                table_name: table.to_string(),
+                ..Default::default()
+            };
+            assert!(LibSqlBackend::new(client.clone(), &config, "key-1").is_err());
+        }
+    }
+
+    #[tokio::test]
+    async fn first_write_creates_the_table_and_later_writes_check_the_revision() {
+        let (backend, database) = libsql(Database::default());
+        // Nothing synced yet: the missing table reads as empty.
+        let snapshot = backend
+            .load()
+            .await
+            .unwrap_or_else(|e| panic!("{}", e.message));
+        assert!(snapshot.payload.is_none() && snapshot.revision.is_none());
+
+        let first = stored(backend.store(&json!({ "v": 1 }), None).await);
+        let snapshot = backend
+            .load()
+            .await
+            .unwrap_or_else(|e| panic!("{}", e.message));
+        assert_eq!(snapshot.payload, Some(json!({ "v": 1 })));
+        assert_eq!(snapshot.revision.as_deref(), Some(first.as_str()));
+
+        // Zero affected rows means another writer got there first.
+        assert!(matches!(
+            backend.store(&json!({ "v": 2 }), None).await,
+            Ok(StoreOutcome::Conflict)
+        ));
+        let second = stored(backend.store(&json!({ "v": 2 }), Some(&first)).await);
+        assert!(matches!(
+            backend.store(&json!({ "v": 3 }), Some(&first)).await,
+            Ok(StoreOutcome::Conflict)
+        ));
+        assert_eq!(
+            database.lock().unwrap().row,
+            Some((r#"{"v":2}"#.to_string(), second.parse().unwrap()))
+        );
+    }
+
+    #[tokio::test]
+    async fn clearing_a_missing_table_succeeds() {
+        let (backend, database) = libsql(Database::default());
+        assert!(backend.clear().await.is_ok());
+        assert_eq!(database.lock().unwrap().statements.len(), 1);
+    }
+}
@@ -0,0 +1,702 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+pub const META_KEY: &str = "_sync";
+const FORMAT_VERSION: u32 = 1;
+/// UI-only fields that never leave the device.
+const LOCAL_FIELDS: [&str; 2] = ["expanded", "isFromSchedule"];
+
+/// Hybrid logical clock. Stamps are `millis-counter-node`, zero padded so that
+/// string order is causal order.
+pub struct Clock {
+    node: String,
+    millis: u64,
+    counter: u32,
+}
+
+fn parse_stamp(stamp: &str) -> Option<(u64, u32)> {
+    let mut parts = stamp.splitn(3, '-');
+    let millis = parts.next()?.parse().ok()?;
+    let counter = parts.next()?.parse().ok()?;
+    Some((millis, counter))
+}
+
+fn format_stamp(millis: u64, counter: u32, node: &str) -> String {
+    format!("{:013}-{:04}-{}", millis, counter, node)
+}
+
+/// Stamp attributed to a write made by a client that does not track clocks.
+pub fn legacy_stamp(millis: u64) -> String {
+    format_stamp(millis, 0, "legacy")
+}
+
+impl Clock {
+    pub fn new(node: &str, last: &str) -> Self {
+        let (millis, counter) = parse_stamp(last).unwrap_or((0, 0));
+        Clock {
+            node: node.to_string(),
+            millis,
+            counter,
+        }
+    }
+
+    pub fn tick(&mut self, now_millis: u64) -> String {
+        if now_millis > self.millis {
+            self.millis = now_millis;
+            self.counter = 0;
+        } else {
+            self.counter += 1;
+        }
+        self.last()
+    }
+
+    /// Moves the clock past a stamp seen from another replica.
+    pub fn observe(&mut self, stamp: &str) {
+        if let Some((millis, counter)) = parse_stamp(stamp) {
+            if (millis, counter) > (self.millis, self.counter) {
+                self.millis = millis;
+                self.counter = counter;
+            }
+        }
+    }
+
+    pub fn last(&self) -> String {
+        format_stamp(self.millis, self.counter, &self.node)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct EntityMeta {
+    /// Stamp of the last write to each field, including fields since removed.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    fields: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    deleted: Option<String>,
+}
+
+impl EntityMeta {
+    fn latest(&self) -> Option<&String> {
+        self.fields.values().chain(self.deleted.iter()).max()
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+struct SyncMeta {
+    version: u32,
+    #[serde(default)]
+    entities: BTreeMap<String, BTreeMap<String, EntityMeta>>,
+}
+
+impl Default for SyncMeta {
+    fn default() -> Self {
+        SyncMeta {
+            version: FORMAT_VERSION,
+            entities: BTreeMap::new(),
+        }
+    }
+}
+
+type Entity = (String, Map<String, Value>);
+
+/// A concurrent write that lost during a merge. `field` is `None` when an edit
+/// raced a deletion of the whole entity.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Conflict {
+    pub collection: String,
+    pub entity_id: String,
+    pub title: String,
+    pub field: Option<String>,
+    pub kept: Option<Value>,
+    pub discarded: Option<Value>,
+    pub discarded_side: String,
+}
+
+/// A replica of the synced collections plus the per-field clocks needed to merge
+/// it with another replica. Deleted entities keep their metadata as tombstones.
+#[derive(Clone, Default, PartialEq)]
+pub struct Document {
+    collections: BTreeMap<String, Vec<Entity>>,
+    meta: SyncMeta,
+}
+
+fn entity_id(object: &Map<String, Value>) -> String {
+    match object.get("id") {
+        Some(Value::String(id)) => id.clone(),
+        Some(Value::Number(id)) => id.to_string(),
+        _ => Value::Object(object.clone()).to_string(),
+    }
+}
+
+fn parse_collection(items: &[Value]) -> Vec<Entity> {
+    let mut seen = HashSet::new();
+    items
+        .iter()
+        .filter_map(Value::as_object)
+        .map(|object| {
+            let mut object = object.clone();
+            for field in LOCAL_FIELDS {
+                object.remove(field);
+            }
+            (entity_id(&object), object)
+        })
+        .filter(|(id, _)| seen.insert(id.clone()))
+        .collect()
+}
+
+impl Document {
+    pub fn is_empty(&self) -> bool {
+        self.collections.values().all(Vec::is_empty) && self.meta.entities.is_empty()
+    }
+
+    /// Reads a synced payload. Returns `None` when it carries no clocks, i.e. it was
+    /// written by a client that predates per-entity sync.
+    pub fn from_value(value: &Value) -> Option<Document> {
+        let object = value.as_object()?;
+        let meta: SyncMeta = serde_json::from_value(object.get(META_KEY)?.clone()).ok()?;
+        Some(Document {
+            collections: Self::collections_of(object),
+            meta,
+        })
+    }
+
+    fn collections_of(object: &Map<String, Value>) -> BTreeMap<String, Vec<Entity>> {
+        object
+            .iter()
+            .filter(|(key, _)| key.as_str() != META_KEY)
+            .filter_map(|(key, value)| Some((key.clone(), parse_collection(value.as_array()?))))
+            .collect()
+    }
+
+    pub fn to_value(&self) -> Value {
+        let mut object = self.data();
+        object.insert(
+            META_KEY.to_string(),
+            serde_json::to_value(&self.meta).unwrap_or(Value::Null),
+        );
+        Value::Object(object)
+    }
+
+    /// The collections alone, in the shape the frontend stores them.
+    pub fn data(&self) -> Map<String, Value> {
+        self.collections
+            .iter()
+            .map(|(name, entities)| {
+                let items = entities
+                    .iter()
+                    .map(|(_, object)| Value::Object(object.clone()))
+                    .collect();
+                (name.clone(), Value::Array(items))
+            })
+            .collect()
+    }
+
+    pub fn max_stamp(&self) -> Option<&String> {
+        self.meta
+            .entities
+            .values()
+            .flat_map(BTreeMap::values)
+            .filter_map(EntityMeta::latest)
+            .max()
+    }
+
+    /// Records the difference between this replica and `data` as writes made at
+    /// `stamp`. Collections missing from `data` are left untouched. Returns whether
+    /// anything changed.
+    pub fn apply_changes(&mut self, data: &Map<String, Value>, stamp: &str) -> bool {
+        let mut changed = false;
+        for (name, value) in data {
+            let Some(items) = value.as_array().filter(|_| name != META_KEY) else {
+                continue;
+            };
+            let next = parse_collection(items);
+            let previous: HashMap<&str, &Map<String, Value>> = self
+                .collections
+                .get(name)
+                .map(|entities| {
+                    entities
+                        .iter()
+                        .map(|(id, object)| (id.as_str(), object))
+                        .collect()
+                })
+                .unwrap_or_default();
+            let metas = self.meta.entities.entry(name.clone()).or_default();
+
+            for (id, object) in &next {
+                let before = previous.get(id.as_str());
+                let meta = metas.entry(id.clone()).or_default();
+                for (field, value) in object {
+                    if before.and_then(|before| before.get(field)) != Some(value) {
+                        meta.fields.insert(field.clone(), stamp.to_string());
+                        changed = true;
+                    }
+                }
+                for field in before.map(|before| before.keys()).into_iter().flatten() {
+                    if !object.contains_key(field) {
+                        meta.fields.insert(field.clone(), stamp.to_string());
+                        changed = true;
+                    }
+                }
+            }
+
+            let next_ids: HashSet<&str> = next.iter().map(|(id, _)| id.as_str()).collect();
+            for id in previous.keys().filter(|id| !next_ids.contains(*id)) {
+                metas.entry(id.to_string()).or_default().deleted = Some(stamp.to_string());
+                changed = true;
+            }
+
+            self.collections.insert(name.clone(), next);
+        }
+        changed
+    }
+
+    /// Field-level merge: for every field the write with the newer stamp wins, and an
+    /// entity stays deleted unless one of its fields was written after the deletion.
+    pub fn merge(&self, other: &Document) -> Document {
+        let mut merged = Document::default();
+        let names: Vec<&String> = self
+            .collections
+            .keys()
+            .chain(self.meta.entities.keys())
+            .chain(other.collections.keys())
+            .chain(other.meta.entities.keys())
+            .collect::<std::collections::BTreeSet<_>>()
+            .into_iter()
+            .collect();
+
+        for name in names {
+            let ours = self.collections.get(name).map(Vec::as_slice).unwrap_or(&[]);
+            let theirs = other
+                .collections
+                .get(name)
+                .map(Vec::as_slice)
+                .unwrap_or(&[]);
+            let our_meta = self.meta.entities.get(name);
+            let their_meta = other.meta.entities.get(name);
+
+            let mut order: Vec<&str> = Vec::new();
+            let mut seen = HashSet::new();
+            let ids = ours
+                .iter()
+                .chain(theirs)
+                .map(|(id, _)| id.as_str())
+                .chain(
+                    our_meta
+                        .into_iter()
+                        .flat_map(|m| m.keys().map(String::as_str)),
+                )
+                .chain(
+                    their_meta
+                        .into_iter()
+                        .flat_map(|m| m.keys().map(String::as_str)),
+                );
+            for id in ids {
+                if seen.insert(id) {
+                    order.push(id);
+                }
+            }
+
+            let find = |entities: &'_ [Entity], id: &str| {
+                entities
+                    .iter()
+                    .find(|(entity, _)| entity == id)
+                    .map(|(_, object)| object.clone())
+            };
+            let mut entities = Vec::new();
+            let mut metas = BTreeMap::new();
+            for id in order {
+                let empty = EntityMeta::default();
+                let a_meta = our_meta.and_then(|m| m.get(id)).unwrap_or(&empty);
+                let b_meta = their_meta.and_then(|m| m.get(id)).unwrap_or(&empty);
+                let a = find(ours, id);
+                let b = find(theirs, id);
+                let (object, meta) = merge_entity(a.as_ref(), a_meta, b.as_ref(), b_meta);
+                if let Some(object) = object {
+                    entities.push((id.to_string(), object));
+                }
+                if meta != EntityMeta::default() {
+                    metas.insert(id.to_string(), meta);
+                }
+            }
+            merged.collections.insert(name.clone(), entities);
+            if !metas.is_empty() {
+                merged.meta.entities.insert(name.clone(), metas);
+            }
+        }
+        merged
+    }
+    fn object(&self, collection: &str, id: &str) -> Option<&Map<String, Value>> {
+        self.collections
+            .get(collection)?
+            .iter()
+            .find(|(entity, _)| entity == id)
+            .map(|(_, object)| object)
+    }
+
+    /// Writes that `merge` resolved by discarding one side: fields both replicas
+    /// changed to different values since `base`, and edits racing a deletion.
+    pub fn conflicts(&self, remote: &Document, base: &Document) -> Vec<Conflict> {
+        let empty = EntityMeta::default();
+        let mut conflicts = Vec::new();
+        for (collection, local_metas) in &self.meta.entities {
+            let Some(remote_metas) = remote.meta.entities.get(collection) else {
+                continue;
+            };
+            for (id, local_meta) in local_metas {
+                let Some(remote_meta) = remote_metas.get(id) else {
+                    continue;
+                };
+                let base_meta = base
+                    .meta
+                    .entities
+                    .get(collection)
+                    .and_then(|metas| metas.get(id))
+                    .unwrap_or(&empty);
+                let local_object = self.object(collection, id);
+                let remote_object = remote.object(collection, id);
+                let conflict = |field: Option<&String>, local_wins: bool| {
+                    let value = |object: Option<&Map<String, Value>>| match field {
+                        Some(field) => object.and_then(|object| object.get(field)).cloned(),
+                        None => object.map(|object| Value::Object(object.clone())),
+                    };
+                    let (kept, discarded) = if local_wins {
+                        (value(local_object), value(remote_object))
+                    } else {
+                        (value(remote_object), value(local_object))
+                    };
+                    Conflict {
+                        collection: collection.clone(),
+                        entity_id: id.clone(),
+                        title: local_object
+                            .or(remote_object)
+                            .and_then(|object| object.get("title"))
+                            .and_then(Value::as_str)
+                            .unwrap_or("")
+                            .to_string(),
+                        field: field.cloned(),
+                        kept,
+                        discarded,
+                        discarded_side: if local_wins { "remote" } else { "local" }.to_string(),
+                    }
+                };
+
+                let changed_since_base = |meta: &EntityMeta, field: &String| {
+                    meta.fields.get(field) > base_meta.fields.get(field)
+                };
+                for field in local_meta.fields.keys() {
+                    let local_stamp = local_meta.fields.get(field);
+                    let remote_stamp = remote_meta.fields.get(field);
+                    if local_stamp == remote_stamp
+                        || !changed_since_base(local_meta, field)
+                        || !changed_since_base(remote_meta, field)
+                    {
+                        continue;
+                    }
+                    let local_value = local_object.and_then(|object| object.get(field));
+                    let remote_value = remote_object.and_then(|object| object.get(field));
+                    if local_object.is_some()
+                        && remote_object.is_some()
+                        && local_value != remote_value
+                    {
+                        conflicts.push(conflict(Some(field), local_stamp > remote_stamp));
+                    }
+                }
+
+                let deleted_since_base =
+                    |meta: &EntityMeta| meta.deleted.is_some() && meta.deleted > base_meta.deleted;
+                let edited_since_base = |meta: &EntityMeta| {
+                    meta.fields
+                        .keys()
+                        .any(|field| changed_since_base(meta, field))
+                };
+                let delete_vs_edit = (deleted_since_base(local_meta)
+                    && edited_since_base(remote_meta))
+                    || (deleted_since_base(remote_meta) && edited_since_base(local_meta));
+                if delete_vs_edit {
+                    // The side whose object survived the merge is the one that won.
+                    let local_wins = match self.merge_one(remote, collection, id) {
+                        Some(_) => local_object.is_some(),
+                        None => local_object.is_none(),
+                    };
+                    conflicts.push(conflict(None, local_wins));
+                }
+            }
+        }
+        conflicts
+    }
+
+    fn merge_one(
+        &self,
+        other: &Document,
+        collection: &str,
+        id: &str,
+    ) -> Option<Map<String, Value>> {
+        let empty = EntityMeta::default();
+        let meta = |document: &Document| {
+            document
+                .meta
+                .entities
+                .get(collection)
+                .and_then(|metas| metas.get(id))
+                .cloned()
+                .unwrap_or_else(|| empty.clone())
+        };
+        merge_entity(
+            self.object(collection, id),
+            &meta(self),
+            other.object(collection, id),
+            &meta(other),
+        )
+        .0
+    }
+}
+
+fn merge_entity(
+    a: Option<&Map<String, Value>>,
+    a_meta: &EntityMeta,
+    b: Option<&Map<String, Value>>,
+    b_meta: &EntityMeta,
+) -> (Option<Map<String, Value>>, EntityMeta) {
+    let mut meta = EntityMeta {
+        fields: BTreeMap::new(),
+        deleted: a_meta.deleted.clone().max(b_meta.deleted.clone()),
+    };
+    let fields: std::collections::BTreeSet<&String> = a_meta
+        .fields
+        .keys()
+        .chain(b_meta.fields.keys())
+        .chain(a.into_iter().flat_map(Map::keys))
+        .chain(b.into_iter().flat_map(Map::keys))
+        .collect();
+
+    let mut object = Map::new();
+    for field in fields {
+        let a_stamp = a_meta.fields.get(field);
+        let b_stamp = b_meta.fields.get(field);
+        let (winner, loser) = if b_stamp > a_stamp { (b, a) } else { (a, b) };
+        // A side that deleted the entity keeps only clocks; the other side still
+        // holds the value both agree on.
+        let value = match winner {
+            Some(winner) => winner.get(field),
+            None => loser.and_then(|loser| loser.get(field)),
+        };
+        if let Some(value) = value {
+            object.insert(field.clone(), value.clone());
+        }
+        if let Some(stamp) = a_stamp.max(b_stamp) {
+            meta.fields.insert(field.clone(), stamp.clone());
+        }
+    }
+
+    let latest_write = meta.fields.values().max();
+    let alive = (a.is_some() || b.is_some())
+        && meta
+            .deleted
+            .as_ref()
+            .is_none_or(|deleted| latest_write.is_some_and(|write| write > deleted));
+    (alive.then_some(object), meta)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn stamp(millis: u64, node: &str) -> String {
+        format_stamp(millis, 0, node)
+    }
+
+    fn edit(document: &Document, tasks: Value, stamp: &str) -> Document {
+        let mut document = document.clone();
+        document.apply_changes(json!({ "tasks": tasks }).as_object().unwrap(), stamp);
+        document
+    }
+
+    fn tasks(document: &Document) -> Value {
+        document.data()["tasks"].clone()
+    }
+
+    fn base() -> Document {
+        edit(
+            &Document::default(),
+            json!([{ "id": "1", "title": "Draft", "done": false }]),
+            &stamp(1, "a"),
+        )
+    }
+
+    #[test]
+    fn clock_orders_stamps_causally() {
+        let mut clock = Clock::new("a", "");
+        assert_eq!(clock.tick(5), "0000000000005-0000-a");
+        assert_eq!(clock.tick(5), "0000000000005-0001-a");
+        // A clock running behind keeps counting from the last stamp.
+        assert_eq!(clock.tick(4), "0000000000005-0002-a");
+        clock.observe("0000000000009-0003-b");
+        assert_eq!(clock.tick(6), "0000000000009-0004-a");
+        assert!(clock.tick(10).as_str() > "0000000000009-0004-b");
+
+        let resumed = Clock::new("a", &clock.last());
+        assert_eq!(resumed.last(), clock.last());
+    }
+
+    #[test]
+    fn apply_changes_stamps_only_changed_fields() {
+        let mut document = base();
+        let unchanged =
+            json!({ "tasks": [{ "id": "1", "title": "Draft", "done": false, "expanded": true }] });
+        assert!(!document.apply_changes(unchanged.as_object().unwrap(), &stamp(2, "a")));
+
+        let changed = json!({ "tasks": [{ "id": "1", "title": "Final" }] });
+        assert!(document.apply_changes(changed.as_object().unwrap(), &stamp(3, "a")));
+        let meta = &document.meta.entities["tasks"]["1"];
+        assert_eq!(meta.fields["id"], stamp(1, "a"));
+        assert_eq!(meta.fields["title"], stamp(3, "a"));
+        // Removing a field is a write too, so the removal can win a merge.
+        assert_eq!(meta.fields["done"], stamp(3, "a"));
+        assert_eq!(document.max_stamp(), Some(&stamp(3, "a")));
+    }
+
+    #[test]
+    fn concurrent_edits_to_different_fields_both_survive() {
+        let base = base();
+        let local = edit(
+            &base,
+            json!([{ "id": "1", "title": "Final", "done": false }]),
+            &stamp(2, "a"),
+        );
+        let remote = edit(
+            &base,
+            json!([{ "id": "1", "title": "Draft", "done": true }]),
+            &stamp(3, "b"),
+        );
+
+        let merged = local.merge(&remote);
+        assert_eq!(
+            tasks(&merged),
+            json!([{ "id": "1", "title": "Final", "done": true }])
+        );
+        assert_eq!(merged.to_value(), remote.merge(&local).to_value());
+        assert!(local.conflicts(&remote, &base).is_empty());
+    }
+
+    #[test]
+    fn newer_stamp_wins_a_field_and_is_reported() {
+        let base = base();
+        let local = edit(
+            &base,
+            json!([{ "id": "1", "title": "Local", "done": false }]),
+            &stamp(3, "a"),
+        );
+        let remote = edit(
+            &base,
+            json!([{ "id": "1", "title": "Remote", "done": false }]),
+            &stamp(2, "b"),
+        );
+
+        assert_eq!(tasks(&local.merge(&remote))[0]["title"], "Local");
+        assert_eq!(tasks(&remote.merge(&local))[0]["title"], "Local");
+
+        let conflicts = local.conflicts(&remote, &base);
+        assert_eq!(conflicts.len(), 1);
+        let conflict = &conflicts[0];
+        assert_eq!(conflict.entity_id, "1");
+        assert_eq!(conflict.field.as_deref(), Some("title"));
+        assert_eq!(conflict.kept, Some(json!("Local")));
+        assert_eq!(conflict.discarded, Some(json!("Remote")));
+        assert_eq!(conflict.discarded_side, "remote");
+
+        let reversed = remote.conflicts(&local, &base);
+        assert_eq!(reversed[0].discarded_side, "local");
+    }
+
+    #[test]
+    fn writes_already_in_the_base_are_not_conflicts() {
+        let synced = edit(
+            &base(),
+            json!([{ "id": "1", "title": "Synced", "done": false }]),
+            &stamp(2, "b"),
+        );
+        // Only the remote changed the title since the last sync.
+        let local = edit(
+            &synced,
+            json!([{ "id": "1", "title": "Synced", "done": true }]),
+            &stamp(3, "a"),
+        );
+        let remote = edit(
+            &synced,
+            json!([{ "id": "1", "title": "Renamed", "done": false }]),
+            &stamp(4, "b"),
+        );
+        assert!(local.conflicts(&remote, &synced).is_empty());
+        assert!(local.conflicts(&local.merge(&remote), &synced).is_empty());
+
+        // Measured against an older base both sides look like they changed it.
+        let conflicts = local.conflicts(&remote, &Document::default());
+        assert_eq!(conflicts.len(), 2);
+        assert!(conflicts.iter().all(|conflict| conflict.field.is_some()));
+    }
+
+    #[test]
+    fn deletion_newer_than_every_edit_leaves_a_tombstone() {
+        let base = base();
+        let local = edit(&base, json!([]), &stamp(3, "a"));
+        let remote = edit(
+            &base,
+            json!([{ "id": "1", "title": "Edited", "done": false }]),
+            &stamp(2, "b"),
+        );
+
+        let merged = local.merge(&remote);
+        assert_eq!(tasks(&merged), json!([]));
+        let tombstone = &merged.meta.entities["tasks"]["1"];
+        assert_eq!(tombstone.deleted, Some(stamp(3, "a")));
+        assert_eq!(tombstone.fields["title"], stamp(2, "b"));
+
+        // The tombstone survives a round trip and keeps the entity deleted.
+        let reloaded = Document::from_value(&merged.to_value()).unwrap();
+        assert_eq!(tasks(&reloaded.merge(&remote)), json!([]));
+        assert_eq!(tasks(&remote.merge(&reloaded)), json!([]));
+
+        let conflicts = local.conflicts(&remote, &base);
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].field, None);
+        assert_eq!(conflicts[0].kept, None);
+        assert_eq!(conflicts[0].discarded_side, "remote");
+        assert_eq!(conflicts[0].title, "Edited");
+    }
+
+    #[test]
+    fn edit_after_a_deletion_restores_the_entity() {
+        let base = base();
+        let local = edit(&base, json!([]), &stamp(3, "a"));
+        let remote = edit(
+            &base,
+            json!([{ "id": "1", "title": "Edited", "done": false }]),
+            &stamp(4, "b"),
+        );
+
+        let merged = local.merge(&remote);
+        assert_eq!(
+            tasks(&merged),
+            json!([{ "id": "1", "title": "Edited", "done": false }])
+        );
+        assert_eq!(
+            merged.meta.entities["tasks"]["1"].deleted,
+            Some(stamp(3, "a"))
+        );
+
+        let conflicts = local.conflicts(&remote, &base);
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].field, None);
+        assert_eq!(conflicts[0].discarded_side, "local");
+    }
+
+    #[test]
+    fn payloads_without_clocks_are_not_documents() {
+        assert!(Document::from_value(&json!({ "tasks": [] })).is_none());
+        let value = base().to_value();
+        assert!(value.get(META_KEY).is_some());
+        assert_eq!(Document::from_value(&value).unwrap().to_value(), value);
+    }
+}
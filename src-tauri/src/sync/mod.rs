@@ -0,0 +1,650 @@
+pub mod conflicts;
+pub(crate) mod crypto;
+pub mod lan;
+mod lan_channel;
+mod libsql;
+mod merge;
+mod pocketbase;
+mod postgrest;
+pub mod queue;
+mod s3;
+mod webdav;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::future::Future;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::time::Duration;
+use tauri::{AppHandle, State};
+
+use crate::network::NetworkState;
+use crypto::{EncryptionConfig, KeyCache, SyncCipher};
+use merge::{Clock, Document};
+
+const SYNC_DIR: &str = "sync";
+const SYNC_TIMEOUT: Duration = Duration::from_secs(20);
+/// Load-merge-store rounds before giving up on a remote that keeps changing.
+const MAX_SYNC_ROUNDS: usize = 4;
+
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+#[derive(Default)]
+pub struct RemoteSnapshot {
+    /// The stored document, `None` when nothing has been synced yet.
+    pub payload: Option<Value>,
+    /// Opaque revision for optimistic concurrency, `None` when no record exists.
+    pub revision: Option<String>,
+}
+
+/// A failed sync step. Retryable errors (offline, timeouts, 429/5xx) leave the
+/// change queued for replay; anything else is reported to the user.
+pub struct SyncError {
+    pub message: String,
+    pub retryable: bool,
+}
+
+impl SyncError {
+    pub fn transient(message: impl Into<String>) -> Self {
+        SyncError {
+            message: message.into(),
+            retryable: true,
+        }
+    }
+}
+
+impl From<String> for SyncError {
+    fn from(message: String) -> Self {
+        SyncError {
+            message,
+            retryable: false,
+        }
+    }
+}
+
+pub fn request_error(error: reqwest::Error) -> SyncError {
+    SyncError {
+        retryable: error.is_timeout() || error.is_connect() || error.is_request(),
+        message: format!("同步请求失败: {}", error),
+    }
+}
+
+pub fn is_retryable_status(status: reqwest::StatusCode) -> bool {
+    status == reqwest::StatusCode::REQUEST_TIMEOUT
+        || status == reqwest::StatusCode::TOO_MANY_REQUESTS
+        || status.is_server_error()
+}
+
+pub enum StoreOutcome {
+    Stored(String),
+    /// The remote changed since it was loaded; the caller should merge again.
+    Conflict,
+}
+
+/// A place the synced document lives. Backends only move opaque JSON documents
+/// and detect concurrent writes; merging happens in the engine.
+pub trait SyncBackend: Send + Sync {
+    fn load(&self) -> BoxFuture<'_, Result<RemoteSnapshot, SyncError>>;
+    /// Writes `payload` only if the remote is still at `expected`.
+    fn store<'a>(
+        &'a self,
+        payload: &'a Value,
+        expected: Option<&'a str>,
+    ) -> BoxFuture<'a, Result<StoreOutcome, SyncError>>;
+    fn clear(&self) -> BoxFuture<'_, Result<(), SyncError>>;
+}
+
+/// The database settings saved by the frontend.
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SyncConfig {
+    service: String,
+    url: String,
+    api_key: String,
+    table_name: String,
+    /// Login for backends with user accounts (WebDAV, PocketBase), or the S3 access key ID;
+    /// `api_key` holds the matching password or secret.
+    username: String,
+    bucket: String,
+    region: String,
+    encryption: EncryptionConfig,
+}
+
+#[derive(Default)]
+pub struct SyncState {
+    running: tokio::sync::Mutex<()>,
+    keys: KeyCache,
+    worker: queue::SyncWorker,
+    lan: lan::LanState,
+}
+
+/// Local replica of one account on one backend.
+#[derive(Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+struct Replica {
+    node: String,
+    clock: String,
+    revision: Option<String>,
+    /// Local document, including edits not yet pushed.
+    document: Value,
+    /// The remote document as of the last successful sync; the reference point
+    /// for attributing changes made by clients without clocks.
+    base: Value,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncOutcome {
+    data: Value,
+    revision: Option<String>,
+    pushed: bool,
+    /// The remote was unreachable; local changes are queued for replay.
+    queued: bool,
+    conflicts: usize,
+}
+
+fn now_millis() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// Millisecond revision for a write that replaces `expected`, kept strictly
+/// increasing even if the local clock is behind the previous writer's.
+fn next_revision(expected: Option<&str>) -> u64 {
+    let previous = expected
+        .and_then(|rev| rev.parse::<u64>().ok())
+        .unwrap_or(0);
+    now_millis().max(previous + 1)
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn hex_digest(input: &str) -> String {
+    to_hex(&Sha256::digest(input.as_bytes()))
+}
+
+fn build_backend(
+    client: reqwest::Client,
+    config: &SyncConfig,
+    access_key: &str,
+) -> Result<Box<dyn SyncBackend>, String> {
+    match config.service.as_str() {
+        "" | "supabase" | "self-hosted" | "neon" | "nhost" => Ok(Box::new(
+            postgrest::PostgRestBackend::new(client, config, access_key)?,
+        )),
+        "s3" => Ok(Box::new(s3::S3Backend::new(client, config, access_key)?)),
+        "pocketbase" => Ok(Box::new(pocketbase::PocketBaseBackend::new(
+            client, config, access_key,
+        )?)),
+        "turso" => Ok(Box::new(libsql::LibSqlBackend::new(
+            client, config, access_key,
+        )?)),
+        "webdav" => Ok(Box::new(webdav::WebDavBackend::new(
+            client, config, access_key,
+        )?)),
+        other => Err(format!("暂不支持同步到 {}", other)),
+    }
+}
+
+fn sync_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    let dir = crate::app_data_file(app, SYNC_DIR)?;
+    fs::create_dir_all(&dir).map_err(|e| format!("创建同步目录失败: {}", e))?;
+    Ok(dir)
+}
+
+/// Identifies one account on one backend.
+fn scope_id(config: &SyncConfig, access_key: &str) -> String {
+    let mut scope = format!(
+        "{}|{}|{}|{}",
+        config.service,
+        config.url.trim(),
+        config.table_name.trim(),
+        access_key
+    );
+    // Appended only when set so existing replicas keep their scope.
+    for extra in [&config.username, &config.bucket] {
+        if !extra.trim().is_empty() {
+            scope.push('|');
+            scope.push_str(extra.trim());
+        }
+    }
+    hex_digest(&scope)[..16].to_string()
+}
+
+fn replica_path(app: &AppHandle, scope: &str) -> Result<PathBuf, String> {
+    Ok(sync_dir(app)?.join(format!("{}.json", scope)))
+}
+
+fn load_replica(path: &PathBuf) -> Replica {
+    let mut replica: Replica = fs::read_to_string(path)
+        .ok()
+        .and_then(|raw| serde_json::from_str(&raw).ok())
+        .unwrap_or_default();
+    if replica.node.is_empty() {
+        let seed = format!("{}|{:?}", path.display(), std::time::SystemTime::now());
+        replica.node = hex_digest(&seed)[..8].to_string();
+    }
+    replica
+}
+
+fn save_replica(path: &PathBuf, replica: &Replica) -> Result<(), String> {
+    let data = serde_json::to_string(replica).map_err(|e| e.to_string())?;
+    fs::write(path, data).map_err(|e| format!("保存同步状态失败: {}", e))
+}
+
+/// A remote payload after decryption.
+struct Decoded {
+    value: Option<Value>,
+    /// The payload should be rewritten even if its content is unchanged: it is
+    /// plaintext while encryption is on, or sealed with a rotated-out secret.
+    reseal: bool,
+    salt: Option<Vec<u8>>,
+}
+
+fn decode_payload(payload: Option<Value>, cipher: Option<&SyncCipher>) -> Result<Decoded, String> {
+    let Some(payload) = payload else {
+        return Ok(Decoded {
+            value: None,
+            reseal: false,
+            salt: None,
+        });
+    };
+    match (crypto::is_envelope(&payload), cipher) {
+        (true, Some(cipher)) => {
+            let opened = cipher.open(&payload)?;
+            Ok(Decoded {
+                value: Some(opened.value),
+                reseal: !opened.current,
+                salt: Some(opened.salt),
+            })
+        }
+        (true, None) => Err("云端数据已加密，请在设置中启用同步加密并填写口令".to_string()),
+        (false, cipher) => Ok(Decoded {
+            value: Some(payload),
+            reseal: cipher.is_some(),
+            salt: None,
+        }),
+    }
+}
+
+/// Interprets a remote payload. Documents from clients that do not track clocks
+/// are diffed against the last known remote state and attributed to the time of
+/// that write.
+fn remote_document(payload: Option<&Value>, revision: Option<&str>, base: &Document) -> Document {
+    let Some(payload) = payload else {
+        return Document::default();
+    };
+    if let Some(document) = Document::from_value(payload) {
+        return document;
+    }
+    let written_at = revision
+        .and_then(|rev| rev.parse().ok())
+        .unwrap_or_else(now_millis);
+    let mut document = base.clone();
+    if let Some(data) = payload.as_object() {
+        document.apply_changes(data, &merge::legacy_stamp(written_at));
+    }
+    document
+}
+
+/// One load-merge-store cycle for an account. `data`, when given, holds the
+/// frontend's current collections and is recorded as local edits first.
+async fn run_sync(
+    app: &AppHandle,
+    state: &SyncState,
+    network: &NetworkState,
+    config: &SyncConfig,
+    access_key: &str,
+    data: Option<Value>,
+) -> Result<SyncOutcome, SyncError> {
+    let _running = state.running.lock().await;
+    let backend = build_backend(network.client(app)?, config, access_key)?;
+    let cipher = SyncCipher::from_config(&config.encryption, access_key, &state.keys)?;
+    let scope = scope_id(config, access_key);
+    let path = replica_path(app, &scope)?;
+    let (mut outcome, conflicts) =
+        sync_replica(backend.as_ref(), cipher.as_ref(), &path, data).await?;
+    outcome.conflicts = conflicts::record(app, &scope, conflicts)?;
+    Ok(outcome)
+}
+
+/// The body of `run_sync` for the replica stored at `path`. Returns the outcome
+/// with the conflicts found, which the caller records for the account.
+async fn sync_replica(
+    backend: &dyn SyncBackend,
+    cipher: Option<&SyncCipher<'_>>,
+    path: &PathBuf,
+    data: Option<Value>,
+) -> Result<(SyncOutcome, Vec<merge::Conflict>), SyncError> {
+    let mut replica = load_replica(path);
+    let mut clock = Clock::new(&replica.node, &replica.clock);
+    let mut local = Document::from_value(&replica.document).unwrap_or_default();
+    let mut base = Document::from_value(&replica.base).unwrap_or_default();
+
+    if let Some(data) = data.as_ref().and_then(Value::as_object) {
+        let stamp = clock.tick(now_millis());
+        if local.apply_changes(data, &stamp) {
+            replica.clock = clock.last();
+            replica.document = local.to_value();
+            // Persist before touching the network so offline edits keep their stamps.
+            save_replica(path, &replica)?;
+        }
+    }
+
+    let mut pushed = false;
+    let mut settled = None;
+    for _ in 0..MAX_SYNC_ROUNDS {
+        let remote = backend.load().await?;
+        let decoded = decode_payload(remote.payload, cipher)?;
+        let remote_doc = remote_document(decoded.value.as_ref(), remote.revision.as_deref(), &base);
+        let merged = local.merge(&remote_doc);
+        let conflicts = local.conflicts(&remote_doc, &base);
+        if let Some(stamp) = merged.max_stamp() {
+            clock.observe(stamp);
+        }
+
+        if decoded.value.is_some() && !decoded.reseal && merged.to_value() == remote_doc.to_value()
+        {
+            replica.revision = remote.revision;
+            local = merged;
+            base = remote_doc;
+            settled = Some(conflicts);
+            break;
+        }
+        if merged.is_empty() && decoded.value.is_none() {
+            settled = Some(conflicts);
+            break;
+        }
+
+        let payload = match cipher {
+            Some(cipher) => cipher.seal(&merged.to_value(), decoded.salt.as_deref())?,
+            None => merged.to_value(),
+        };
+        match backend.store(&payload, remote.revision.as_deref()).await? {
+            StoreOutcome::Stored(revision) => {
+                replica.revision = Some(revision);
+                local = merged;
+                base = local.clone();
+                pushed = true;
+                settled = Some(conflicts);
+                break;
+            }
+            StoreOutcome::Conflict => continue,
+        }
+    }
+
+    replica.clock = clock.last();
+    replica.document = local.to_value();
+    replica.base = base.to_value();
+    save_replica(path, &replica)?;
+    let Some(conflicts) = settled else {
+        return Err(SyncError::transient(
+            "远端数据频繁变化，同步未完成，稍后将自动重试",
+        ));
+    };
+
+    let outcome = SyncOutcome {
+        data: Value::Object(local.data()),
+        revision: replica.revision,
+        pushed,
+        queued: false,
+        conflicts: 0,
+    };
+    Ok((outcome, conflicts))
+}
+
+/// Records local edits in `data` (when given), then merges with the remote and
+/// pushes the result. Returns the merged collections for the frontend to adopt.
+/// When the remote is unreachable the local replica is returned and the sync is
+/// queued for replay.
+#[tauri::command]
+pub async fn sync_now(
+    app: AppHandle,
+    state: State<'_, SyncState>,
+    network: State<'_, NetworkState>,
+    config: SyncConfig,
+    access_key: String,
+    data: Option<Value>,
+) -> Result<SyncOutcome, String> {
+    let access_key = access_key.trim().to_string();
+    if access_key.is_empty() {
+        return Err("未登录，无法同步".to_string());
+    }
+    let scope = scope_id(&config, &access_key);
+    match run_sync(&app, &state, &network, &config, &access_key, data).await {
+        Ok(outcome) => {
+            queue::complete(&app, &scope)?;
+            Ok(outcome)
+        }
+        Err(error) if error.retryable => {
+            queue::enqueue(&app, &scope, &config, &access_key, &error.message)?;
+            let replica = load_replica(&replica_path(&app, &scope)?);
+            let local = Document::from_value(&replica.document).unwrap_or_default();
+            Ok(SyncOutcome {
+                data: Value::Object(local.data()),
+                revision: replica.revision,
+                pushed: false,
+                queued: true,
+                conflicts: 0,
+            })
+        }
+        Err(error) => Err(error.message),
+    }
+}
+
+/// Deletes the account's remote record and the local replica.
+#[tauri::command]
+pub async fn clear_sync_data(
+    app: AppHandle,
+    state: State<'_, SyncState>,
+    network: State<'_, NetworkState>,
+    config: SyncConfig,
+    access_key: String,
+) -> Result<(), String> {
+    let access_key = access_key.trim().to_string();
+    let _running = state.running.lock().await;
+    let backend = build_backend(network.client(&app)?, &config, &access_key)?;
+    backend.clear().await.map_err(|e| e.message)?;
+    let scope = scope_id(&config, &access_key);
+    queue::complete(&app, &scope)?;
+    let path = replica_path(&app, &scope)?;
+    if path.exists() {
+        fs::remove_file(&path).map_err(|e| format!("删除同步状态失败: {}", e))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{serve, Request, Response};
+    use serde_json::json;
+    use std::sync::{Arc, Mutex};
+
+    /// The single `planpro_data` row of a PostgREST stand-in. While `interfere` is
+    /// positive, another client writes just before each of our stores lands.
+    #[derive(Default)]
+    struct Table {
+        content: Option<Value>,
+        updated_at: Option<u64>,
+        interfere: usize,
+        stores: usize,
+    }
+
+    impl Table {
+        fn handle(&mut self, request: &Request) -> Response {
+            if request.method == "GET" {
+                let rows = match &self.content {
+                    Some(content) => json!([{ "content": content, "updated_at": self.updated_at }]),
+                    None => json!([]),
+                };
+                return Response::new(200, rows.to_string());
+            }
+
+            self.stores += 1;
+            if self.interfere > 0 {
+                self.interfere -= 1;
+                let other = self.updated_at.unwrap_or(0) + 1;
+                let mut tasks = self.remote_tasks();
+                tasks.push(json!({ "id": format!("other-{}", other), "title": "Other" }));
+                self.content = Some(json!({ "tasks": tasks }));
+                self.updated_at = Some(other);
+            }
+            let body: Value = serde_json::from_slice(&request.body).unwrap();
+            let expected = request
+                .query()
+                .split('&')
+                .find_map(|pair| pair.strip_prefix("updated_at="))
+                .map(str::to_string);
+            let matches = match (request.method.as_str(), expected.as_deref()) {
+                ("POST", _) => self.content.is_none(),
+                (_, Some(filter)) => filter == format!("eq.{}", self.updated_at.unwrap_or(0)),
+                _ => false,
+            };
+            if !matches {
+                return Response::new(200, "[]");
+            }
+            let row = if body.is_array() {
+                body[0].clone()
+            } else {
+                body
+            };
+            self.content = Some(row["content"].clone());
+            self.updated_at = row["updated_at"].as_u64();
+            Response::new(200, json!([row]).to_string())
+        }
+
+        fn remote_tasks(&self) -> Vec<Value> {
+            self.content
+                .as_ref()
+                .and_then(|content| content["tasks"].as_array().cloned())
+                .unwrap_or_default()
+        }
+    }
+
+    fn postgrest(table: Table) -> (Box<dyn SyncBackend>, Arc<Mutex<Table>>) {
+        let table = Arc::new(Mutex::new(table));
+        let shared = table.clone();
+        let base = serve(move |request: &Request| shared.lock().unwrap().handle(request));
+        let config = SyncConfig {
+            url: base,
+            api_key: "anon".to_string(),
+            ..Default::default()
+        };
+        let client = reqwest::Client::builder().no_proxy().build().unwrap();
+        (build_backend(client, &config, "key-1").unwrap(), table)
+    }
+
+    fn replica_file(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "planpro-sync-{}-{}-{}.json",
+            name,
+            std::process::id(),
+            now_millis()
+        ));
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    fn task_ids(data: &Value) -> Vec<String> {
+        let mut ids: Vec<String> = data["tasks"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|task| task["id"].as_str().unwrap().to_string())
+            .collect();
+        ids.sort();
+        ids
+    }
+
+    fn local_edit() -> Option<Value> {
+        Some(json!({ "tasks": [{ "id": "local-1", "title": "Local" }] }))
+    }
+
+    #[tokio::test]
+    async fn a_store_that_loses_the_race_is_merged_and_retried() {
+        let (backend, table) = postgrest(Table {
+            content: Some(json!({ "tasks": [{ "id": "remote-1", "title": "Remote" }] })),
+            updated_at: Some(1000),
+            interfere: 1,
+            ..Default::default()
+        });
+        let path = replica_file("retry");
+
+        let (outcome, conflicts) = sync_replica(backend.as_ref(), None, &path, local_edit())
+            .await
+            .unwrap_or_else(|e| panic!("{}", e.message));
+        assert!(outcome.pushed);
+        assert!(conflicts.is_empty());
+        assert_eq!(
+            task_ids(&outcome.data),
+            ["local-1", "other-1001", "remote-1"]
+        );
+
+        let table = table.lock().unwrap();
+        assert_eq!(table.stores, 2);
+        let stored = Document::from_value(table.content.as_ref().unwrap()).unwrap();
+        assert_eq!(
+            task_ids(&Value::Object(stored.data())),
+            task_ids(&outcome.data)
+        );
+        assert_eq!(
+            outcome.revision,
+            table.updated_at.map(|revision| revision.to_string())
+        );
+
+        let replica = load_replica(&path);
+        assert_eq!(replica.revision, outcome.revision);
+        assert_eq!(replica.base, replica.document);
+        let _ = fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn a_remote_that_keeps_changing_is_retried_later() {
+        let (backend, table) = postgrest(Table {
+            content: Some(json!({ "tasks": [] })),
+            updated_at: Some(1000),
+            interfere: usize::MAX,
+            ..Default::default()
+        });
+        let path = replica_file("busy");
+
+        let error = sync_replica(backend.as_ref(), None, &path, local_edit())
+            .await
+            .err()
+            .expect("sync should give up");
+        assert!(error.retryable);
+        assert_eq!(table.lock().unwrap().stores, MAX_SYNC_ROUNDS);
+
+        // The local edit is kept for the queued replay.
+        let replica = load_replica(&path);
+        let local = Document::from_value(&replica.document).unwrap();
+        assert_eq!(task_ids(&Value::Object(local.data())), ["local-1"]);
+        assert_eq!(replica.revision, None);
+        let _ = fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn a_synced_replica_is_not_pushed_again() {
+        let (backend, table) = postgrest(Table::default());
+        let path = replica_file("settled");
+
+        let (first, _) = sync_replica(backend.as_ref(), None, &path, local_edit())
+            .await
+            .unwrap_or_else(|e| panic!("{}", e.message));
+        assert!(first.pushed);
+        let (second, _) = sync_replica(backend.as_ref(), None, &path, local_edit())
+            .await
+            .unwrap_or_else(|e| panic!("{}", e.message));
+        assert!(!second.pushed);
+        assert_eq!(second.revision, first.revision);
+        assert_eq!(table.lock().unwrap().stores, 1);
+        let _ = fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,481 @@
+use reqwest::{Client, Method, RequestBuilder, Response, StatusCode};
+use serde_json::{json, Value};
+use tokio::sync::Mutex;
+
+use super::{
+    is_retryable_status, next_revision, request_error, BoxFuture, RemoteSnapshot, StoreOutcome,
+    SyncBackend, SyncConfig, SyncError,
+};
+
+const DEFAULT_COLLECTION: &str = "planpro_data";
+/// Auth collections tried in order for a username/password login: regular users,
+/// then superusers (v0.23+), then the pre-0.23 admins endpoint.
+const AUTH_ENDPOINTS: [&str; 3] = [
+    "/api/collections/users/auth-with-password",
+    "/api/collections/_superusers/auth-with-password",
+    "/api/admins/auth-with-password",
+];
+
+/// A PocketBase collection with `user_key` (text, unique), `content` (json) and
+/// `updated_at` (number) fields.
+///
+/// PocketBase has no conditional update, so `store` re-reads `updated_at` right
+/// before patching. A writer slipping into that window is overwritten, but its
+/// edits stay in its own replica and are merged back on its next sync.
+pub struct PocketBaseBackend {
+    client: Client,
+    base_url: String,
+    records_url: String,
+    identity: String,
+    secret: String,
+    user_key: String,
+    token: Mutex<Option<String>>,
+}
+
+struct Record {
+    id: String,
+    content: Option<Value>,
+    revision: String,
+}
+
+async fn status_error(response: Response) -> SyncError {
+    let status = response.status();
+    let body: Value = response.json().await.unwrap_or(Value::Null);
+    let message = match status {
+        StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => {
+            "PocketBase 认证失败或无权访问该集合，请检查账号和集合规则".to_string()
+        }
+        StatusCode::NOT_FOUND => "PocketBase 集合不存在，请先创建 planpro_data 集合".to_string(),
+        _ => body
+            .get("message")
+            .and_then(Value::as_str)
+            .map(|message| format!("PocketBase 请求失败: {}", message))
+            .unwrap_or_else(|| format!("PocketBase 请求失败: HTTP {}", status)),
+    };
+    SyncError {
+        message,
+        retryable: is_retryable_status(status),
+    }
+}
+
+fn parse_record(record: &Value) -> Option<Record> {
+    let id = record.get("id")?.as_str()?.to_string();
+    // Number fields default to 0, which stands for "never written by us".
+    let revision = match record.get("updated_at") {
+        Some(Value::Number(n)) if n.as_u64() != Some(0) => n.to_string(),
+        Some(Value::String(s)) => s.clone(),
+        _ => String::new(),
+    };
+    Some(Record {
+        id,
+        content: record
+            .get("content")
+            .filter(|content| !content.is_null() && *content != "")
+            .cloned(),
+        revision,
+    })
+}
+
+impl PocketBaseBackend {
+    pub fn new(client: Client, config: &SyncConfig, user_key: &str) -> Result<Self, String> {
+        let base_url = config.url.trim().trim_end_matches('/').to_string();
+        if base_url.is_empty() || config.api_key.trim().is_empty() {
+            return Err("PocketBase 地址或凭据未配置".to_string());
+        }
+        let collection = config.table_name.trim();
+        let collection = if collection.is_empty() {
+            DEFAULT_COLLECTION
+        } else {
+            collection
+        };
+        Ok(PocketBaseBackend {
+            client,
+            records_url: format!("{}/api/collections/{}/records", base_url, collection),
+            base_url,
+            identity: config.username.trim().to_string(),
+            secret: config.api_key.trim().to_string(),
+            user_key: user_key.to_string(),
+            token: Mutex::new(None),
+        })
+    }
+
+    /// Without a username the secret is used as an auth token directly
+    /// (e.g. a long-lived superuser token); otherwise a password login runs once.
+    async fn token(&self) -> Result<String, SyncError> {
+        if self.identity.is_empty() {
+            return Ok(self.secret.clone());
+        }
+        let mut token = self.token.lock().await;
+        if let Some(token) = token.as_ref() {
+            return Ok(token.clone());
+        }
+        let body = json!({ "identity": self.identity, "password": self.secret });
+        for endpoint in AUTH_ENDPOINTS {
+            let response = self
+                .client
+                .post(format!("{}{}", self.base_url, endpoint))
+                .timeout(super::SYNC_TIMEOUT)
+                .json(&body)
+                .send()
+                .await
+                .map_err(request_error)?;
+            let status = response.status();
+            if status.is_success() {
+                let auth: Value = response
+                    .json()
+                    .await
+                    .map_err(|e| format!("PocketBase 登录响应无效: {}", e))?;
+                let value = auth
+                    .get("token")
+                    .and_then(Value::as_str)
+                    .ok_or_else(|| "PocketBase 登录响应缺少 token".to_string())?;
+                *token = Some(value.to_string());
+                return Ok(value.to_string());
+            }
+            // Wrong collection for this account; try the next one.
+            if matches!(status, StatusCode::BAD_REQUEST | StatusCode::NOT_FOUND) {
+                continue;
+            }
+            return Err(status_error(response).await);
+        }
+        Err("PocketBase 登录失败，请检查用户名和密码".to_string().into())
+    }
+
+    async fn request(&self, method: Method, url: &str) -> Result<RequestBuilder, SyncError> {
+        Ok(self
+            .client
+            .request(method, url)
+            .header("Authorization", self.token().await?)
+            .timeout(super::SYNC_TIMEOUT))
+    }
+
+    async fn find_record(&self) -> Result<Option<Record>, SyncError> {
+        let key = self.user_key.replace('\\', "\\\\").replace('\'', "\\'");
+        let response = self
+            .request(Method::GET, &self.records_url)
+            .await?
+            .query(&[
+                ("filter", format!("user_key='{}'", key)),
+                ("perPage", "1".to_string()),
+                ("skipTotal", "1".to_string()),
+            ])
+            .send()
+            .await
+            .map_err(request_error)?;
+        if !response.status().is_success() {
+            return Err(status_error(response).await);
+        }
+        let body: Value = response
+            .json()
+            .await
+            .map_err(|e| format!("PocketBase 响应无效: {}", e))?;
+        Ok(body
+            .get("items")
+            .and_then(Value::as_array)
+            .and_then(|items| items.first())
+            .and_then(parse_record))
+    }
+
+    async fn load_record(&self) -> Result<RemoteSnapshot, SyncError> {
+        Ok(match self.find_record().await? {
+            Some(record) => RemoteSnapshot {
+                payload: record.content,
+                revision: Some(record.revision),
+            },
+            None => RemoteSnapshot::default(),
+        })
+    }
+
+    async fn store_record(
+        &self,
+        payload: &Value,
+        expected: Option<&str>,
+    ) -> Result<StoreOutcome, SyncError> {
+        let revision = next_revision(expected);
+        let current = self.find_record().await?;
+        let request = match (expected, current) {
+            (None, None) => self
+                .request(Method::POST, &self.records_url)
+                .await?
+                .json(&json!({
+                    "user_key": self.user_key,
+                    "content": payload,
+                    "updated_at": revision,
+                })),
+            (Some(expected), Some(record)) if record.revision == expected => self
+                .request(
+                    Method::PATCH,
+                    &format!("{}/{}", self.records_url, record.id),
+                )
+                .await?
+                .json(&json!({ "content": payload, "updated_at": revision })),
+            _ => return Ok(StoreOutcome::Conflict),
+        };
+        let response = request.send().await.map_err(request_error)?;
+        let status = response.status();
+        if status.is_success() {
+            return Ok(StoreOutcome::Stored(revision.to_string()));
+        }
+        // A create racing another device's create trips the unique index; a patch
+        // racing a delete finds no record.
+        if status == StatusCode::NOT_FOUND && expected.is_some() {
+            return Ok(StoreOutcome::Conflict);
+        }
+        if status == StatusCode::BAD_REQUEST && expected.is_none() {
+            let body: Value = response.json().await.unwrap_or(Value::Null);
+            if body.pointer("/data/user_key/code").and_then(Value::as_str)
+                == Some("validation_not_unique")
+            {
+                return Ok(StoreOutcome::Conflict);
+            }
+            let message = body
+                .get("message")
+                .and_then(Value::as_str)
+                .unwrap_or("请求无效");
+            return Err(format!("PocketBase 请求失败: {}", message).into());
+        }
+        Err(status_error(response).await)
+    }
+
+    async fn delete_record(&self) -> Result<(), SyncError> {
+        let Some(record) = self.find_record().await? else {
+            return Ok(());
+        };
+        let response = self
+            .request(
+                Method::DELETE,
+                &format!("{}/{}", self.records_url, record.id),
+            )
+            .await?
+            .send()
+            .await
+            .map_err(request_error)?;
+        if response.status().is_success() || response.status() == StatusCode::NOT_FOUND {
+            return Ok(());
+        }
+        Err(status_error(response).await)
+    }
+}
+
+impl SyncBackend for PocketBaseBackend {
+    fn load(&self) -> BoxFuture<'_, Result<RemoteSnapshot, SyncError>> {
+        Box::pin(self.load_record())
+    }
+
+    fn store<'a>(
+        &'a self,
+        payload: &'a Value,
+        expected: Option<&'a str>,
+    ) -> BoxFuture<'a, Result<StoreOutcome, SyncError>> {
+        Box::pin(self.store_record(payload, expected))
+    }
+
+    fn clear(&self) -> BoxFuture<'_, Result<(), SyncError>> {
+        Box::pin(self.delete_record())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{serve, Request, Response};
+    use percent_encoding::percent_decode_str;
+    use std::sync::{Arc, Mutex};
+
+    const RECORDS: &str = "/api/collections/planpro_data/records";
+
+    /// A PocketBase stand-in where only the superuser login succeeds, holding at
+    /// most one record.
+    #[derive(Default)]
+    struct Server {
+        record: Option<Value>,
+        /// Answer creates as if another device created the record first.
+        duplicate_create: bool,
+        /// Method and path of every request, in order.
+        calls: Vec<String>,
+        filters: Vec<String>,
+    }
+
+    impl Server {
+        fn handle(&mut self, request: &Request) -> Response {
+            self.calls
+                .push(format!("{} {}", request.method, request.path()));
+            let body: Value = serde_json::from_slice(&request.body).unwrap_or(Value::Null);
+            match request.path() {
+                "/api/collections/users/auth-with-password" => {
+                    return Response::new(400, r#"{"message":"Failed to authenticate."}"#);
+                }
+                "/api/collections/_superusers/auth-with-password" => {
+                    assert_eq!(body, json!({ "identity": "admin", "password": "secret" }));
+                    return Response::new(200, r#"{"token":"su-token"}"#);
+                }
+                _ => {}
+            }
+            if request.header("Authorization") != Some("su-token") {
+                return Response::new(401, "{}");
+            }
+            match request.method.as_str() {
+                "GET" => {
+                    let filter = request
+                        .query()
+                        .split('&')
+                        .find_map(|pair| pair.strip_prefix("filter="))
+                        .unwrap_or_default()
+                        .replace('+', " ");
+                    self.filters
+                        .push(percent_decode_str(&filter).decode_utf8_lossy().to_string());
+                    let items: Vec<Value> = self.record.iter().cloned().collect();
+                    Response::new(200, json!({ "items": items }).to_string())
+                }
+                "POST" if self.duplicate_create => Response::new(
+                    400,
+                    json!({
+                        "message": "Failed to create record.",
+                        "data": { "user_key": { "code": "validation_not_unique" } },
+                    })
+                    .to_string(),
+                ),
+                "POST" if body["content"].is_null() => {
+                    Response::new(400, r#"{"message":"Failed to create record."}"#)
+                }
+                "POST" => {
+                    let mut record = body;
+                    record["id"] = json!("rec1");
+                    self.record = Some(record.clone());
+                    Response::new(200, record.to_string())
+                }
+                "PATCH" => match self.record.as_mut() {
+                    Some(record) if request.path() == format!("{}/rec1", RECORDS) => {
+                        record["content"] = body["content"].clone();
+                        record["updated_at"] = body["updated_at"].clone();
+                        Response::new(200, record.to_string())
+                    }
+                    _ => Response::new(404, "{}"),
+                },
+                _ => Response::new(405, "{}"),
+            }
+        }
+    }
+
+    fn pocketbase(server: Server, user_key: &str) -> (PocketBaseBackend, Arc<Mutex<Server>>) {
+        let server = Arc::new(Mutex::new(server));
+        let shared = server.clone();
+        let base = serve(move |request: &Request| shared.lock().unwrap().handle(request));
+        let config = SyncConfig {
+            url: format!("{}/", base),
+            username: "admin".to_string(),
+            api_key: "secret".to_string(),
+            ..Default::default()
+        };
+        let client = Client::builder().no_proxy().build().unwrap();
+        let backend = PocketBaseBackend::new(client, &config, user_key).unwrap();
+        (backend, server)
+    }
+
+    fn stored(outcome: StoreOutcome) -> String {
+        match outcome {
+            StoreOutcome::Stored(revision) => revision,
+            StoreOutcome::Conflict => panic!("unexpected conflict"),
+        }
+    }
+
+    #[tokio::test]
+    async fn login_falls_back_to_superusers_and_the_key_is_escaped() {
+        let (backend, server) = pocketbase(Server::default(), r"o'brien\x");
+        for _ in 0..2 {
+            let snapshot = backend
+                .load()
+                .await
+                .unwrap_or_else(|e| panic!("{}", e.message));
+            assert!(snapshot.payload.is_none() && snapshot.revision.is_none());
+        }
+
+        let server = server.lock().unwrap();
+        // Logged in once; the token is reused for the second load.
+        assert_eq!(
+            server.calls,
+            [
+                "POST /api/collections/users/auth-with-password",
+                "POST /api/collections/_superusers/auth-with-password",
+                &format!("GET {}", RECORDS),
+                &format!("GET {}", RECORDS),
+            ]
+        );
+        assert_eq!(server.filters[0], r"user_key='o\'brien\\x'");
+    }
+
+    #[tokio::test]
+    async fn stores_create_then_patch_and_detect_conflicts() {
+        let (backend, server) = pocketbase(Server::default(), "key-1");
+        let first = stored(
+            backend
+                .store(&json!({ "v": 1 }), None)
+                .await
+                .unwrap_or_else(|e| panic!("{}", e.message)),
+        );
+        // The record exists now, so a second create is refused without a request.
+        assert!(matches!(
+            backend.store(&json!({ "v": 2 }), None).await,
+            Ok(StoreOutcome::Conflict)
+        ));
+        let second = stored(
+            backend
+                .store(&json!({ "v": 2 }), Some(&first))
+                .await
+                .unwrap_or_else(|e| panic!("{}", e.message)),
+        );
+        assert!(second.parse::<u64>().unwrap() > first.parse::<u64>().unwrap());
+        assert!(matches!(
+            backend.store(&json!({ "v": 3 }), Some(&first)).await,
+            Ok(StoreOutcome::Conflict)
+        ));
+
+        let snapshot = backend
+            .load()
+            .await
+            .unwrap_or_else(|e| panic!("{}", e.message));
+        assert_eq!(snapshot.payload, Some(json!({ "v": 2 })));
+        assert_eq!(snapshot.revision, Some(second));
+        let server = server.lock().unwrap();
+        let writes: Vec<_> = server
+            .calls
+            .iter()
+            .filter(|call| !call.starts_with("GET") && !call.contains("auth"))
+            .collect();
+        assert_eq!(
+            writes,
+            [
+                &format!("POST {}", RECORDS),
+                &format!("PATCH {}/rec1", RECORDS)
+            ]
+        );
+        assert_eq!(server.record.as_ref().unwrap()["user_key"], "key-1");
+    }
+
+    #[tokio::test]
+    async fn a_racing_create_is_a_conflict() {
+        let (backend, _) = pocketbase(
+            Server {
+                duplicate_create: true,
+                ..Default::default()
+            },
+            "key-1",
+        );
+        assert!(matches!(
+            backend.store(&json!({ "v": 1 }), None).await,
+            Ok(StoreOutcome::Conflict)
+        ));
+
+        // Other validation errors are reported rather than retried as conflicts.
+        let (backend, _) = pocketbase(Server::default(), "key-1");
+        let error = match backend.store(&Value::Null, None).await {
+            Err(error) => error,
+            Ok(_) => panic!("invalid create should fail"),
+        };
+        assert_eq!(
+            error.message,
+            "PocketBase 请求失败: Failed to create record."
+        );
+        assert!(!error.retryable);
+    }
+}
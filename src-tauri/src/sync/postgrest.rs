@@ -0,0 +1,342 @@
+use reqwest::{Client, RequestBuilder, Response, StatusCode};
+use serde_json::{json, Value};
+
+use super::{
+    is_retryable_status, next_revision, request_error, BoxFuture, RemoteSnapshot, StoreOutcome,
+    SyncBackend, SyncConfig, SyncError,
+};
+
+const DEFAULT_TABLE: &str = "planpro_data";
+
+/// The `planpro_data` table behind Supabase or any PostgREST server: one row per
+/// access key, with `updated_at` doubling as the row revision.
+pub struct PostgRestBackend {
+    client: Client,
+    table_url: String,
+    api_key: String,
+    user_key: String,
+}
+
+fn rest_base_url(url: &str) -> String {
+    let url = url.trim().trim_end_matches('/');
+    if url.to_ascii_lowercase().ends_with("/rest/v1") {
+        url.to_string()
+    } else {
+        format!("{}/rest/v1", url)
+    }
+}
+
+async fn status_error(response: Response) -> SyncError {
+    let status = response.status();
+    let body: Value = response.json().await.unwrap_or(Value::Null);
+    let code = body.get("code").and_then(Value::as_str).unwrap_or("");
+    if code == "PGRST205" || code == "42P01" {
+        return "同步表不存在，请先在数据库中创建 planpro_data 表"
+            .to_string()
+            .into();
+    }
+    let message = body
+        .get("message")
+        .and_then(Value::as_str)
+        .map(|message| format!("数据库请求失败: {}", message))
+        .unwrap_or_else(|| format!("数据库请求失败: HTTP {}", status));
+    SyncError {
+        message,
+        retryable: is_retryable_status(status),
+    }
+}
+
+impl PostgRestBackend {
+    pub fn new(client: Client, config: &SyncConfig, user_key: &str) -> Result<Self, String> {
+        if config.url.trim().is_empty() || config.api_key.trim().is_empty() {
+            return Err("数据库地址或密钥未配置".to_string());
+        }
+        let table = config.table_name.trim();
+        let table = if table.is_empty() {
+            DEFAULT_TABLE
+        } else {
+            table
+        };
+        Ok(PostgRestBackend {
+            client,
+            table_url: format!("{}/{}", rest_base_url(&config.url), table),
+            api_key: config.api_key.trim().to_string(),
+            user_key: user_key.to_string(),
+        })
+    }
+
+    fn request(&self, method: reqwest::Method) -> RequestBuilder {
+        self.client
+            .request(method, &self.table_url)
+            .header("apikey", &self.api_key)
+            .bearer_auth(&self.api_key)
+            .header("Accept", "application/json")
+            .timeout(super::SYNC_TIMEOUT)
+    }
+
+    fn user_filter(&self) -> (&'static str, String) {
+        ("user_key", format!("eq.{}", self.user_key))
+    }
+
+    async fn load_row(&self) -> Result<RemoteSnapshot, SyncError> {
+        let response = self
+            .request(reqwest::Method::GET)
+            .query(&[
+                ("select", "content,updated_at".to_string()),
+                self.user_filter(),
+            ])
+            .query(&[("limit", "1")])
+            .send()
+            .await
+            .map_err(request_error)?;
+        if !response.status().is_success() {
+            return Err(status_error(response).await);
+        }
+        let rows: Vec<Value> = response
+            .json()
+            .await
+            .map_err(|e| format!("数据库响应无效: {}", e))?;
+        let Some(row) = rows.into_iter().next() else {
+            return Ok(RemoteSnapshot::default());
+        };
+        // A row whose `updated_at` is NULL still exists; an empty revision tells
+        // `store` to update rather than insert.
+        let revision = match row.get("updated_at") {
+            Some(Value::Number(n)) => n.to_string(),
+            Some(Value::String(s)) => s.clone(),
+            _ => String::new(),
+        };
+        Ok(RemoteSnapshot {
+            payload: row
+                .get("content")
+                .filter(|content| !content.is_null())
+                .cloned(),
+            revision: Some(revision),
+        })
+    }
+
+    async fn store_row(
+        &self,
+        payload: &Value,
+        expected: Option<&str>,
+    ) -> Result<StoreOutcome, SyncError> {
+        let revision = next_revision(expected);
+
+        let request = match expected {
+            None => self.request(reqwest::Method::POST).json(&json!([{
+                "user_key": self.user_key,
+                "content": payload,
+                "updated_at": revision,
+            }])),
+            Some(expected) => {
+                let revision_filter = if expected.is_empty() {
+                    "is.null".to_string()
+                } else {
+                    format!("eq.{}", expected)
+                };
+                self.request(reqwest::Method::PATCH)
+                    .query(&[self.user_filter(), ("updated_at", revision_filter)])
+                    .json(&json!({ "content": payload, "updated_at": revision }))
+            }
+        };
+
+        let response = request
+            .header("Prefer", "return=representation")
+            .send()
+            .await
+            .map_err(request_error)?;
+        let status = response.status();
+        if status == StatusCode::CONFLICT {
+            return Ok(StoreOutcome::Conflict);
+        }
+        if !status.is_success() {
+            return Err(status_error(response).await);
+        }
+        // A conditional PATCH that matched no row means someone else wrote first.
+        let rows: Vec<Value> = response.json().await.unwrap_or_default();
+        if rows.is_empty() {
+            return Ok(StoreOutcome::Conflict);
+        }
+        Ok(StoreOutcome::Stored(revision.to_string()))
+    }
+
+    async fn delete_row(&self) -> Result<(), SyncError> {
+        let response = self
+            .request(reqwest::Method::DELETE)
+            .query(&[self.user_filter()])
+            .send()
+            .await
+            .map_err(request_error)?;
+        if !response.status().is_success() {
+            return Err(status_error(response).await);
+        }
+        Ok(())
+    }
+}
+
+impl SyncBackend for PostgRestBackend {
+    fn load(&self) -> BoxFuture<'_, Result<RemoteSnapshot, SyncError>> {
+        Box::pin(self.load_row())
+    }
+
+    fn store<'a>(
+        &'a self,
+        payload: &'a Value,
+        expected: Option<&'a str>,
+    ) -> BoxFuture<'a, Result<StoreOutcome, SyncError>> {
+        Box::pin(self.store_row(payload, expected))
+    }
+
+    fn clear(&self) -> BoxFuture<'_, Result<(), SyncError>> {
+        Box::pin(self.delete_row())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{serve, Request, Response};
+    use std::sync::{Arc, Mutex};
+
+    struct Seen {
+        method: String,
+        target: String,
+        prefer: String,
+        body: Value,
+    }
+
+    /// A PostgREST stand-in answering every request with `status` and `body`.
+    fn mock_backend(status: u16, body: &'static str) -> (PostgRestBackend, Arc<Mutex<Vec<Seen>>>) {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let log = seen.clone();
+        let base = serve(move |request: &Request| {
+            log.lock().unwrap().push(Seen {
+                method: request.method.clone(),
+                target: request.target.clone(),
+                prefer: request.header("prefer").unwrap_or_default().to_string(),
+                body: serde_json::from_slice(&request.body).unwrap_or(Value::Null),
+            });
+            Response::new(status, body).header("Content-Type", "application/json")
+        });
+        let config = SyncConfig {
+            url: base,
+            api_key: "anon".to_string(),
+            ..Default::default()
+        };
+        let client = Client::builder().no_proxy().build().unwrap();
+        (
+            PostgRestBackend::new(client, &config, "key-1").unwrap(),
+            seen,
+        )
+    }
+
+    fn payload() -> Value {
+        json!({ "tasks": [] })
+    }
+
+    #[tokio::test]
+    async fn store_patches_only_the_loaded_revision() {
+        let (backend, seen) = mock_backend(200, r#"[{"user_key":"key-1"}]"#);
+        let outcome = backend
+            .store_row(&payload(), Some("1700000000000"))
+            .await
+            .unwrap_or_else(|e| panic!("{}", e.message));
+        let StoreOutcome::Stored(revision) = outcome else {
+            panic!("expected the row to be stored");
+        };
+        assert!(revision.parse::<u64>().unwrap() > 1_700_000_000_000);
+
+        let seen = seen.lock().unwrap();
+        assert_eq!(seen[0].method, "PATCH");
+        assert_eq!(
+            seen[0].target,
+            "/rest/v1/planpro_data?user_key=eq.key-1&updated_at=eq.1700000000000"
+        );
+        assert_eq!(seen[0].prefer, "return=representation");
+        assert_eq!(seen[0].body["content"], payload());
+        assert_eq!(seen[0].body["updated_at"].to_string(), revision);
+    }
+
+    #[tokio::test]
+    async fn store_over_a_null_revision_matches_is_null() {
+        let (backend, seen) = mock_backend(200, r#"[{"user_key":"key-1"}]"#);
+        assert!(matches!(
+            backend.store_row(&payload(), Some("")).await,
+            Ok(StoreOutcome::Stored(_))
+        ));
+        assert_eq!(
+            seen.lock().unwrap()[0].target,
+            "/rest/v1/planpro_data?user_key=eq.key-1&updated_at=is.null"
+        );
+    }
+
+    #[tokio::test]
+    async fn patch_matching_no_row_is_a_conflict() {
+        let (backend, _) = mock_backend(200, "[]");
+        assert!(matches!(
+            backend.store_row(&payload(), Some("1700000000000")).await,
+            Ok(StoreOutcome::Conflict)
+        ));
+    }
+
+    #[tokio::test]
+    async fn insert_racing_another_insert_is_a_conflict() {
+        let (backend, seen) = mock_backend(409, r#"{"code":"23505","message":"duplicate key"}"#);
+        assert!(matches!(
+            backend.store_row(&payload(), None).await,
+            Ok(StoreOutcome::Conflict)
+        ));
+        let seen = seen.lock().unwrap();
+        assert_eq!(seen[0].method, "POST");
+        assert_eq!(seen[0].body[0]["user_key"], "key-1");
+    }
+
+    #[tokio::test]
+    async fn load_reads_content_and_revision() {
+        let (backend, seen) = mock_backend(
+            200,
+            r#"[{"content":{"tasks":[]},"updated_at":1700000000000}]"#,
+        );
+        let snapshot = backend
+            .load_row()
+            .await
+            .unwrap_or_else(|e| panic!("{}", e.message));
+        assert_eq!(snapshot.payload, Some(payload()));
+        assert_eq!(snapshot.revision.as_deref(), Some("1700000000000"));
+        assert_eq!(
+            seen.lock().unwrap()[0].target,
+            "/rest/v1/planpro_data?select=content%2Cupdated_at&user_key=eq.key-1&limit=1"
+        );
+
+        let (backend, _) = mock_backend(200, r#"[{"content":null,"updated_at":null}]"#);
+        let snapshot = backend
+            .load_row()
+            .await
+            .unwrap_or_else(|e| panic!("{}", e.message));
+        assert_eq!(snapshot.payload, None);
+        assert_eq!(snapshot.revision.as_deref(), Some(""));
+
+        let (backend, _) = mock_backend(200, "[]");
+        let snapshot = backend
+            .load_row()
+            .await
+            .unwrap_or_else(|e| panic!("{}", e.message));
+        assert_eq!(snapshot.revision, None);
+    }
+
+    #[tokio::test]
+    async fn missing_table_and_server_errors_are_classified() {
+        let (backend, _) = mock_backend(404, r#"{"code":"PGRST205","message":"not found"}"#);
+        let error = backend.load_row().await.err().unwrap();
+        assert!(error.message.contains("planpro_data"), "{}", error.message);
+        assert!(!error.retryable);
+
+        let (backend, _) = mock_backend(503, "{}");
+        let error = backend
+            .store_row(&payload(), Some("1"))
+            .await
+            .err()
+            .unwrap();
+        assert!(error.retryable);
+    }
+}
@@ -0,0 +1,354 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::sync::Notify;
+
+use super::{
+    hex_digest, now_millis, run_sync, scope_id, sync_dir, SyncConfig, SyncOutcome, SyncState,
+};
+use crate::network::NetworkState;
+
+const QUEUE_FILE: &str = "queue.json";
+const BASE_DELAY: Duration = Duration::from_secs(5);
+const MAX_DELAY: Duration = Duration::from_secs(10 * 60);
+
+/// A sync that failed for a transient reason. The local replica already holds the
+/// changes, so replaying means running a sync for the same account again.
+///
+/// Only retry bookkeeping is written to disk. The config and access key it needs
+/// are held in memory, so after a restart an entry waits until the frontend
+/// hands them over again.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct PendingSync {
+    scope: String,
+    #[serde(default)]
+    service: String,
+    #[serde(default)]
+    key_hash: String,
+    queued_at: u64,
+    attempts: u32,
+    next_attempt_at: u64,
+    last_error: String,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PendingSyncStatus {
+    scope: String,
+    service: String,
+    /// Tells apart accounts without exposing their access keys.
+    key_hash: String,
+    queued_at: u64,
+    attempts: u32,
+    next_attempt_at: u64,
+    last_error: String,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct SyncCompleted<'a> {
+    access_key: &'a str,
+    outcome: &'a SyncOutcome,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct SyncFailed<'a> {
+    access_key: &'a str,
+    message: &'a str,
+}
+
+/// What a replay needs to reach the backend; never persisted.
+#[derive(Clone)]
+struct Credentials {
+    config: SyncConfig,
+    access_key: String,
+}
+
+#[derive(Default)]
+pub struct SyncWorker {
+    running: AtomicBool,
+    wake: Notify,
+    credentials: Mutex<HashMap<String, Credentials>>,
+}
+
+impl SyncWorker {
+    fn credentials(&self, scope: &str) -> Option<Credentials> {
+        self.credentials.lock().ok()?.get(scope).cloned()
+    }
+
+    fn remember(&self, scope: &str, config: &SyncConfig, access_key: &str) {
+        if let Ok(mut credentials) = self.credentials.lock() {
+            credentials.insert(
+                scope.to_string(),
+                Credentials {
+                    config: config.clone(),
+                    access_key: access_key.to_string(),
+                },
+            );
+        }
+    }
+
+    fn forget(&self, scope: &str) {
+        if let Ok(mut credentials) = self.credentials.lock() {
+            credentials.remove(scope);
+        }
+    }
+}
+
+fn key_hash(access_key: &str) -> String {
+    hex_digest(access_key)[..16].to_string()
+}
+
+fn backoff(attempts: u32) -> Duration {
+    BASE_DELAY
+        .saturating_mul(2u32.saturating_pow(attempts.saturating_sub(1)))
+        .min(MAX_DELAY)
+}
+
+fn load(app: &AppHandle) -> Vec<PendingSync> {
+    let Some(path) = sync_dir(app).ok().map(|dir| dir.join(QUEUE_FILE)) else {
+        return Vec::new();
+    };
+    let Ok(raw) = fs::read_to_string(&path) else {
+        return Vec::new();
+    };
+    let entries: Vec<PendingSync> = serde_json::from_str(&raw).unwrap_or_default();
+    // Queues written by earlier versions held the config and access key.
+    if let Ok(scrubbed) = serde_json::to_string(&entries) {
+        if scrubbed != raw {
+            let _ = fs::write(&path, scrubbed);
+        }
+    }
+    entries
+}
+
+fn store(app: &AppHandle, entries: &[PendingSync]) -> Result<(), String> {
+    let data = serde_json::to_string(entries).map_err(|e| e.to_string())?;
+    fs::write(sync_dir(app)?.join(QUEUE_FILE), data)
+        .map_err(|e| format!("保存同步队列失败: {}", e))?;
+    let _ = app.emit("sync-queue", statuses(entries));
+    Ok(())
+}
+
+fn statuses(entries: &[PendingSync]) -> Vec<PendingSyncStatus> {
+    entries
+        .iter()
+        .map(|entry| PendingSyncStatus {
+            scope: entry.scope.clone(),
+            service: entry.service.clone(),
+            key_hash: entry.key_hash.clone(),
+            queued_at: entry.queued_at,
+            attempts: entry.attempts,
+            next_attempt_at: entry.next_attempt_at,
+            last_error: entry.last_error.clone(),
+        })
+        .collect()
+}
+
+/// Queues (or reschedules) a replay for the account with exponential backoff.
+/// `config` and `access_key` are kept in memory for the replay only.
+pub fn enqueue(
+    app: &AppHandle,
+    scope: &str,
+    config: &SyncConfig,
+    access_key: &str,
+    error: &str,
+) -> Result<(), String> {
+    let now = now_millis();
+    app.state::<SyncState>()
+        .worker
+        .remember(scope, config, access_key);
+    let mut entries = load(app);
+    let index = match entries.iter().position(|entry| entry.scope == scope) {
+        Some(index) => index,
+        None => {
+            entries.push(PendingSync {
+                scope: scope.to_string(),
+                service: String::new(),
+                key_hash: key_hash(access_key),
+                queued_at: now,
+                attempts: 0,
+                next_attempt_at: now,
+                last_error: String::new(),
+            });
+            entries.len() - 1
+        }
+    };
+    let entry = &mut entries[index];
+    entry.service = config.service.clone();
+    entry.attempts += 1;
+    entry.next_attempt_at = now + backoff(entry.attempts).as_millis() as u64;
+    entry.last_error = error.to_string();
+    store(app, &entries)?;
+    ensure_worker(app);
+    Ok(())
+}
+
+pub fn complete(app: &AppHandle, scope: &str) -> Result<(), String> {
+    app.state::<SyncState>().worker.forget(scope);
+    let mut entries = load(app);
+    let before = entries.len();
+    entries.retain(|entry| entry.scope != scope);
+    if entries.len() != before {
+        store(app, &entries)?;
+    }
+    Ok(())
+}
+
+fn ensure_worker(app: &AppHandle) {
+    let state = app.state::<SyncState>();
+    if state.worker.running.swap(true, Ordering::SeqCst) {
+        state.worker.wake.notify_one();
+        return;
+    }
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        loop {
+            run_worker(&app).await;
+            let state = app.state::<SyncState>();
+            state.worker.running.store(false, Ordering::SeqCst);
+            // An entry queued while the worker was winding down would otherwise wait
+            // for the next manual sync.
+            if replayable(&app).is_empty() || state.worker.running.swap(true, Ordering::SeqCst) {
+                break;
+            }
+        }
+    });
+}
+
+/// Queued entries whose credentials are in memory.
+fn replayable(app: &AppHandle) -> Vec<(PendingSync, Credentials)> {
+    let worker = &app.state::<SyncState>().worker;
+    load(app)
+        .into_iter()
+        .filter_map(|entry| {
+            let credentials = worker.credentials(&entry.scope)?;
+            Some((entry, credentials))
+        })
+        .collect()
+}
+
+/// Replays due entries until none is left that can be replayed. Entries whose
+/// credentials are not in memory stay queued for the frontend to retry.
+async fn run_worker(app: &AppHandle) {
+    loop {
+        let entries = replayable(app);
+        let Some(next) = entries.iter().map(|(entry, _)| entry.next_attempt_at).min() else {
+            return;
+        };
+        let now = now_millis();
+        if next > now {
+            let wake = &app.state::<SyncState>().worker.wake;
+            let _ = tokio::time::timeout(Duration::from_millis(next - now), wake.notified()).await;
+            continue;
+        }
+        for (entry, credentials) in entries
+            .into_iter()
+            .filter(|(entry, _)| entry.next_attempt_at <= now)
+        {
+            replay(app, entry, credentials).await;
+        }
+    }
+}
+
+async fn replay(app: &AppHandle, entry: PendingSync, credentials: Credentials) {
+    let state = app.state::<SyncState>();
+    let network = app.state::<NetworkState>();
+    let Credentials { config, access_key } = credentials;
+    let result = run_sync(app, &state, &network, &config, &access_key, None).await;
+    match result {
+        Ok(outcome) => {
+            let _ = complete(app, &entry.scope);
+            let _ = app.emit(
+                "sync-completed",
+                SyncCompleted {
+                    access_key: &access_key,
+                    outcome: &outcome,
+                },
+            );
+        }
+        Err(error) if error.retryable => {
+            let _ = enqueue(app, &entry.scope, &config, &access_key, &error.message);
+        }
+        Err(error) => {
+            let _ = complete(app, &entry.scope);
+            let _ = app.emit(
+                "sync-failed",
+                SyncFailed {
+                    access_key: &access_key,
+                    message: &error.message,
+                },
+            );
+        }
+    }
+}
+
+#[tauri::command]
+pub fn get_sync_queue(app: AppHandle) -> Vec<PendingSyncStatus> {
+    statuses(&load(&app))
+}
+
+/// Replays the account's queued sync now, e.g. when the device comes back online,
+/// with the frontend's current config. Returns how many entries were scheduled.
+#[tauri::command]
+pub fn retry_pending_sync(
+    app: AppHandle,
+    config: SyncConfig,
+    access_key: String,
+) -> Result<usize, String> {
+    let access_key = access_key.trim();
+    if access_key.is_empty() {
+        return Ok(0);
+    }
+    let scope = scope_id(&config, access_key);
+    let mut entries = load(&app);
+    let Some(entry) = entries.iter_mut().find(|entry| entry.scope == scope) else {
+        return Ok(0);
+    };
+    entry.next_attempt_at = now_millis();
+    app.state::<SyncState>()
+        .worker
+        .remember(&scope, &config, access_key);
+    store(&app, &entries)?;
+    ensure_worker(&app);
+    Ok(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn legacy_entries_are_rewritten_without_credentials() {
+        let legacy = r#"[{
+            "scope": "0123456789abcdef",
+            "config": { "service": "supabase", "apiKey": "service-role", "encryption": { "passphrase": "hunter2" } },
+            "accessKey": "secret-access-key",
+            "queuedAt": 1, "attempts": 2, "nextAttemptAt": 3, "lastError": "offline"
+        }]"#;
+        let entries: Vec<PendingSync> = serde_json::from_str(legacy).unwrap();
+        assert_eq!(entries[0].attempts, 2);
+        let scrubbed = serde_json::to_string(&entries).unwrap();
+        for secret in ["service-role", "hunter2", "secret-access-key", "apiKey"] {
+            assert!(!scrubbed.contains(secret), "{}", scrubbed);
+        }
+    }
+
+    #[test]
+    fn credentials_stay_in_memory_per_scope() {
+        let worker = SyncWorker::default();
+        worker.remember("a", &SyncConfig::default(), "key-a");
+        assert_eq!(worker.credentials("a").unwrap().access_key, "key-a");
+        assert!(worker.credentials("b").is_none());
+        worker.forget("a");
+        assert!(worker.credentials("a").is_none());
+        assert_ne!(key_hash("key-a"), "key-a");
+        assert_eq!(key_hash("key-a").len(), 16);
+    }
+}
@@ -0,0 +1,344 @@
+use reqwest::header::{ETAG, IF_MATCH, IF_NONE_MATCH};
+use reqwest::{Client, Method, RequestBuilder, Response, StatusCode};
+use serde_json::Value;
+
+use super::{
+    hex_digest, is_retryable_status, request_error, BoxFuture, RemoteSnapshot, StoreOutcome,
+    SyncBackend, SyncConfig, SyncError,
+};
+
+const DEFAULT_FILE_PREFIX: &str = "workplan";
+
+/// One JSON file per access key in a WebDAV folder (Nextcloud, Synology, Apache
+/// mod_dav...). The file's ETag is the revision, checked with `If-Match` so a
+/// concurrent writer is detected instead of overwritten.
+pub struct WebDavBackend {
+    client: Client,
+    folder_url: String,
+    file_url: String,
+    username: String,
+    password: String,
+}
+
+fn status_error(response: &Response) -> SyncError {
+    let status = response.status();
+    let message = match status {
+        StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => {
+            "WebDAV 认证失败，请检查用户名和应用密码".to_string()
+        }
+        StatusCode::INSUFFICIENT_STORAGE => "WebDAV 存储空间不足".to_string(),
+        _ => format!("WebDAV 请求失败: HTTP {}", status),
+    };
+    SyncError {
+        message,
+        // A lock held by another client (e.g. a desktop sync app) is released shortly.
+        retryable: is_retryable_status(status) || status == StatusCode::LOCKED,
+    }
+}
+
+fn etag(response: &Response) -> Option<String> {
+    response
+        .headers()
+        .get(ETAG)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string)
+}
+
+impl WebDavBackend {
+    pub fn new(client: Client, config: &SyncConfig, user_key: &str) -> Result<Self, String> {
+        let folder = config.url.trim().trim_end_matches('/');
+        if folder.is_empty() {
+            return Err("WebDAV 地址未配置".to_string());
+        }
+        url::Url::parse(folder).map_err(|e| format!("WebDAV 地址无效: {}", e))?;
+        let prefix = config.table_name.trim();
+        let prefix = if prefix.is_empty() || prefix == "planpro_data" {
+            DEFAULT_FILE_PREFIX
+        } else {
+            prefix
+        };
+        // The access key doubles as the account secret, so it never appears in the path.
+        let file_name = format!("{}-{}.json", prefix, &hex_digest(user_key)[..16]);
+        Ok(WebDavBackend {
+            client,
+            folder_url: format!("{}/", folder),
+            file_url: format!("{}/{}", folder, file_name),
+            username: config.username.trim().to_string(),
+            password: config.api_key.clone(),
+        })
+    }
+
+    fn request(&self, method: Method, url: &str) -> RequestBuilder {
+        let request = self
+            .client
+            .request(method, url)
+            .timeout(super::SYNC_TIMEOUT);
+        if !self.username.is_empty() {
+            request.basic_auth(&self.username, Some(&self.password))
+        } else if !self.password.is_empty() {
+            request.bearer_auth(&self.password)
+        } else {
+            request
+        }
+    }
+
+    async fn load_file(&self) -> Result<RemoteSnapshot, SyncError> {
+        let response = self
+            .request(Method::GET, &self.file_url)
+            .header("Accept", "application/json")
+            .send()
+            .await
+            .map_err(request_error)?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(RemoteSnapshot::default());
+        }
+        if !response.status().is_success() {
+            return Err(status_error(&response));
+        }
+        let Some(revision) = etag(&response) else {
+            return Err("WebDAV 服务器未返回 ETag，无法安全地并发同步"
+                .to_string()
+                .into());
+        };
+        let body = response.bytes().await.map_err(request_error)?;
+        // An empty file is what some clients leave behind after an aborted upload.
+        let payload = if body.iter().all(u8::is_ascii_whitespace) {
+            None
+        } else {
+            Some(
+                serde_json::from_slice::<Value>(&body)
+                    .map_err(|e| format!("WebDAV 同步文件无效: {}", e))?,
+            )
+        };
+        Ok(RemoteSnapshot {
+            payload,
+            revision: Some(revision),
+        })
+    }
+
+    async fn put_file(&self, body: &[u8], expected: Option<&str>) -> Result<Response, SyncError> {
+        let request = self
+            .request(Method::PUT, &self.file_url)
+            .header("Content-Type", "application/json")
+            .body(body.to_vec());
+        let request = match expected {
+            Some(etag) => request.header(IF_MATCH, etag),
+            None => request.header(IF_NONE_MATCH, "*"),
+        };
+        request.send().await.map_err(request_error)
+    }
+
+    async fn create_folder(&self) -> Result<(), SyncError> {
+        let method = Method::from_bytes(b"MKCOL").map_err(|e| e.to_string())?;
+        let response = self
+            .request(method, &self.folder_url)
+            .send()
+            .await
+            .map_err(request_error)?;
+        // 405 means the folder already exists.
+        if response.status().is_success() || response.status() == StatusCode::METHOD_NOT_ALLOWED {
+            return Ok(());
+        }
+        if response.status() == StatusCode::CONFLICT {
+            return Err("WebDAV 上级目录不存在，请先在服务器上创建同步目录"
+                .to_string()
+                .into());
+        }
+        Err(status_error(&response))
+    }
+
+    async fn store_file(
+        &self,
+        payload: &Value,
+        expected: Option<&str>,
+    ) -> Result<StoreOutcome, SyncError> {
+        let body = serde_json::to_vec(payload).map_err(|e| e.to_string())?;
+        let mut response = self.put_file(&body, expected).await?;
+        // In WebDAV a 409 on PUT means the parent collection is missing.
+        if response.status() == StatusCode::CONFLICT {
+            self.create_folder().await?;
+            response = self.put_file(&body, expected).await?;
+        }
+        match response.status() {
+            StatusCode::PRECONDITION_FAILED => return Ok(StoreOutcome::Conflict),
+            // Deleted by another device since we loaded it.
+            StatusCode::NOT_FOUND if expected.is_some() => return Ok(StoreOutcome::Conflict),
+            status if !status.is_success() => return Err(status_error(&response)),
+            _ => {}
+        }
+        // Servers may omit the ETag on PUT; the next load picks it up.
+        Ok(StoreOutcome::Stored(etag(&response).unwrap_or_default()))
+    }
+
+    async fn delete_file(&self) -> Result<(), SyncError> {
+        let response = self
+            .request(Method::DELETE, &self.file_url)
+            .send()
+            .await
+            .map_err(request_error)?;
+        if response.status().is_success() || response.status() == StatusCode::NOT_FOUND {
+            return Ok(());
+        }
+        Err(status_error(&response))
+    }
+}
+
+impl SyncBackend for WebDavBackend {
+    fn load(&self) -> BoxFuture<'_, Result<RemoteSnapshot, SyncError>> {
+        Box::pin(self.load_file())
+    }
+
+    fn store<'a>(
+        &'a self,
+        payload: &'a Value,
+        expected: Option<&'a str>,
+    ) -> BoxFuture<'a, Result<StoreOutcome, SyncError>> {
+        Box::pin(self.store_file(payload, expected))
+    }
+
+    fn clear(&self) -> BoxFuture<'_, Result<(), SyncError>> {
+        Box::pin(self.delete_file())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    //! The integration test runs against a real server and is ignored by default:
+    //!
+    //! ```sh
+    //! WORKPLAN_WEBDAV_URL=https://cloud.example/remote.php/dav/files/me/tests \
+    //! WORKPLAN_WEBDAV_USER=me WORKPLAN_WEBDAV_PASSWORD=app-password \
+    //! cargo test webdav -- --ignored
+    //! ```
+    //!
+    //! It works in a fresh sub-folder of the URL and deletes its file afterwards.
+
+    use super::*;
+    use crate::test_support::{serve, Request, Response};
+    use serde_json::json;
+    use std::sync::{Arc, Mutex};
+
+    fn backend(url: &str, username: &str, password: &str) -> WebDavBackend {
+        let config = SyncConfig {
+            url: url.to_string(),
+            username: username.to_string(),
+            api_key: password.to_string(),
+            ..Default::default()
+        };
+        let client = Client::builder().no_proxy().build().unwrap();
+        WebDavBackend::new(client, &config, "integration-test-key").unwrap()
+    }
+
+    fn stored(outcome: Result<StoreOutcome, SyncError>) -> String {
+        match outcome {
+            Ok(StoreOutcome::Stored(revision)) => revision,
+            Ok(StoreOutcome::Conflict) => panic!("unexpected conflict"),
+            Err(error) => panic!("{}", error.message),
+        }
+    }
+
+    fn conflicted(outcome: Result<StoreOutcome, SyncError>) -> bool {
+        matches!(outcome, Ok(StoreOutcome::Conflict))
+    }
+
+    async fn load(backend: &WebDavBackend) -> RemoteSnapshot {
+        backend
+            .load_file()
+            .await
+            .unwrap_or_else(|e| panic!("{}", e.message))
+    }
+
+    async fn propfind_etag(backend: &WebDavBackend) -> String {
+        let response = backend
+            .request(Method::from_bytes(b"PROPFIND").unwrap(), &backend.file_url)
+            .header("Depth", "0")
+            .header("Content-Type", "application/xml")
+            .body(r#"<?xml version="1.0"?><d:propfind xmlns:d="DAV:"><d:prop><d:getetag/></d:prop></d:propfind>"#)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status().as_u16(), 207);
+        let body = response.text().await.unwrap();
+        let start = body.find("getetag>").expect("PROPFIND reports an ETag") + "getetag>".len();
+        let end = start + body[start..].find('<').unwrap();
+        body[start..end].replace("&quot;", "\"")
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a WebDAV server, see the module docs"]
+    async fn round_trip_against_a_real_server() {
+        let Ok(url) = std::env::var("WORKPLAN_WEBDAV_URL") else {
+            eprintln!("WORKPLAN_WEBDAV_URL is not set, skipping");
+            return;
+        };
+        let username = std::env::var("WORKPLAN_WEBDAV_USER").unwrap_or_default();
+        let password = std::env::var("WORKPLAN_WEBDAV_PASSWORD").unwrap_or_default();
+        // A missing folder makes the first PUT answer 409 and exercises MKCOL.
+        let folder = format!(
+            "{}/workplan-test-{}-{}",
+            url.trim_end_matches('/'),
+            std::process::id(),
+            super::super::now_millis()
+        );
+        let backend = backend(&folder, &username, &password);
+
+        assert!(load(&backend).await.revision.is_none());
+        let first = json!({ "tasks": [{ "id": "1", "title": "First" }] });
+        stored(backend.store_file(&first, None).await);
+
+        let snapshot = load(&backend).await;
+        assert_eq!(snapshot.payload, Some(first.clone()));
+        let etag = snapshot.revision.unwrap();
+        assert_eq!(
+            propfind_etag(&backend).await.trim_start_matches("W/"),
+            etag.trim_start_matches("W/")
+        );
+
+        // Creating a file that already exists is a conflict (If-None-Match: *).
+        assert!(conflicted(backend.store_file(&first, None).await));
+
+        let second = json!({ "tasks": [{ "id": "1", "title": "Second" }] });
+        stored(backend.store_file(&second, Some(&etag)).await);
+        // A writer still holding the old ETag must not overwrite it (If-Match).
+        let stale = json!({ "tasks": [{ "id": "1", "title": "Stale" }] });
+        assert!(conflicted(backend.store_file(&stale, Some(&etag)).await));
+        assert_eq!(load(&backend).await.payload, Some(second));
+
+        backend
+            .delete_file()
+            .await
+            .unwrap_or_else(|e| panic!("{}", e.message));
+        assert!(load(&backend).await.revision.is_none());
+        let _ = backend
+            .request(Method::DELETE, &backend.folder_url)
+            .send()
+            .await;
+    }
+
+    #[tokio::test]
+    async fn missing_folder_is_created_and_stale_etags_conflict() {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let log = seen.clone();
+        let url = serve(move |request: &Request| {
+            let mut log = log.lock().unwrap();
+            log.push(request.method.clone());
+            let if_match = request.header("if-match").unwrap_or_default();
+            match request.method.as_str() {
+                "MKCOL" => Response::new(201, ""),
+                // The folder is missing until MKCOL ran.
+                "PUT" if !log.iter().any(|method| method == "MKCOL") => Response::new(409, ""),
+                "PUT" if if_match == "\"stale\"" => Response::new(412, ""),
+                "PUT" => Response::new(201, "").header("ETag", "\"v2\""),
+                _ => Response::new(405, ""),
+            }
+        });
+        let backend = backend(&format!("{}/dav/sync", url), "me", "secret");
+        let payload = json!({ "tasks": [] });
+
+        assert_eq!(stored(backend.store_file(&payload, None).await), "\"v2\"");
+        assert_eq!(*seen.lock().unwrap(), ["PUT", "MKCOL", "PUT"]);
+        assert!(conflicted(
+            backend.store_file(&payload, Some("\"stale\"")).await
+        ));
+    }
+}
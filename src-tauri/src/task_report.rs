@@ -0,0 +1,1317 @@
+//! Native PDF work reports. The frontend hands over the tasks of a range; the
+//! report (summary, vector charts and a paginated task table) is laid out
+//! directly with lopdf and a subset of a system CJK font is embedded, so the
+//! result does not depend on how the webview happens to render.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::BTreeMap;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+
+use lopdf::content::{Content, Operation};
+use lopdf::{dictionary, Document, Object, ObjectId, Stream, StringFormat};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::AppHandle;
+use ttf_parser::{name_id, Face, GlyphId};
+
+use crate::downloads::{self, SaveOptions};
+
+const PAGE_WIDTH: f32 = 595.0;
+const PAGE_HEIGHT: f32 = 842.0;
+const MARGIN: f32 = 48.0;
+const CONTENT_WIDTH: f32 = PAGE_WIDTH - MARGIN * 2.0;
+const FOOTER_SPACE: f32 = 28.0;
+/// Beyond this many days the daily chart groups days into weeks.
+const MAX_DAILY_BARS: i64 = 31;
+
+type Rgb = (f32, f32, f32);
+
+const INK: Rgb = (0.12, 0.16, 0.23);
+const MUTED: Rgb = (0.45, 0.5, 0.58);
+const RULE: Rgb = (0.89, 0.91, 0.94);
+const PANEL: Rgb = (0.97, 0.98, 0.99);
+const ACCENT: Rgb = (0.31, 0.27, 0.9);
+const DONE: Rgb = (0.09, 0.64, 0.29);
+const DOING: Rgb = (0.23, 0.51, 0.96);
+const TODO: Rgb = (0.58, 0.64, 0.72);
+const ALERT: Rgb = (0.86, 0.15, 0.15);
+const WARN: Rgb = (0.96, 0.62, 0.04);
+
+/// Fonts known to cover Chinese, tried in order before scanning font folders.
+const FONT_CANDIDATES: &[&str] = &[
+    "C:\\Windows\\Fonts\\msyh.ttc",
+    "C:\\Windows\\Fonts\\msyh.ttf",
+    "C:\\Windows\\Fonts\\Deng.ttf",
+    "C:\\Windows\\Fonts\\simhei.ttf",
+    "C:\\Windows\\Fonts\\simsun.ttc",
+    "/System/Library/Fonts/PingFang.ttc",
+    "/System/Library/Fonts/Hiragino Sans GB.ttc",
+    "/System/Library/Fonts/STHeiti Light.ttc",
+    "/System/Library/Fonts/Supplemental/Songti.ttc",
+    "/Library/Fonts/Arial Unicode.ttf",
+    "/usr/share/fonts/opentype/noto/NotoSansCJK-Regular.ttc",
+    "/usr/share/fonts/noto-cjk/NotoSansCJK-Regular.ttc",
+    "/usr/share/fonts/google-noto-cjk/NotoSansCJK-Regular.ttc",
+    "/usr/share/fonts/truetype/wqy/wqy-microhei.ttc",
+    "/usr/share/fonts/wenquanyi/wqy-microhei/wqy-microhei.ttc",
+    "/usr/share/fonts/truetype/droid/DroidSansFallbackFull.ttf",
+    "/system/fonts/NotoSansCJK-Regular.ttc",
+    "/system/fonts/NotoSansSC-Regular.otf",
+    "/system/fonts/DroidSansFallback.ttf",
+];
+
+const FONT_DIRS: &[&str] = &[
+    "C:\\Windows\\Fonts",
+    "/System/Library/Fonts",
+    "/Library/Fonts",
+    "/usr/share/fonts",
+    "/usr/local/share/fonts",
+    "/system/fonts",
+];
+
+/// File name fragments worth opening while scanning font folders.
+const CJK_NAME_HINTS: &[&str] = &[
+    "cjk", "sc", "cn", "gb", "hei", "song", "kai", "ming", "wqy", "han", "yahei", "pingfang",
+    "fallback",
+];
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReportRequest {
+    title: String,
+    start: String,
+    end: String,
+    /// Local `YYYY-MM-DDTHH:MM`, used for overdue checks and the footer.
+    #[serde(default)]
+    now: String,
+    tasks: Vec<Value>,
+    #[serde(default)]
+    labels: ReportLabels,
+    #[serde(default)]
+    font_path: Option<String>,
+    #[serde(default)]
+    filename: String,
+    #[serde(default)]
+    save: SaveOptions,
+}
+
+/// Text printed in the report, localized by the frontend.
+#[derive(Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct ReportLabels {
+    summary: String,
+    total: String,
+    done: String,
+    doing: String,
+    todo: String,
+    overdue: String,
+    late: String,
+    rate: String,
+    status_chart: String,
+    priority_chart: String,
+    daily_chart: String,
+    planned: String,
+    completed: String,
+    task_list: String,
+    col_title: String,
+    col_status: String,
+    col_priority: String,
+    col_date: String,
+    col_deadline: String,
+    col_completed: String,
+    normal: String,
+    urgent: String,
+    critical: String,
+    empty: String,
+    generated: String,
+    /// `{page}` and `{pages}` are replaced.
+    page: String,
+}
+
+impl Default for ReportLabels {
+    fn default() -> Self {
+        let text = |s: &str| s.to_string();
+        Self {
+            summary: text("概览"),
+            total: text("总任务"),
+            done: text("已完成"),
+            doing: text("进行中"),
+            todo: text("未开始"),
+            overdue: text("已超时"),
+            late: text("超时完成"),
+            rate: text("完成率"),
+            status_chart: text("任务状态分布"),
+            priority_chart: text("优先级分布"),
+            daily_chart: text("每日任务"),
+            planned: text("计划任务"),
+            completed: text("完成任务"),
+            task_list: text("任务明细"),
+            col_title: text("任务"),
+            col_status: text("状态"),
+            col_priority: text("优先级"),
+            col_date: text("日期"),
+            col_deadline: text("截止"),
+            col_completed: text("完成"),
+            normal: text("普通"),
+            urgent: text("紧急"),
+            critical: text("特急"),
+            empty: text("暂无数据"),
+            generated: text("生成于"),
+            page: text("第 {page} / {pages} 页"),
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReportSummary {
+    path: String,
+    pages: usize,
+    tasks: usize,
+    font: String,
+}
+
+/// A font file plus the face inside it (collections hold several).
+struct FontSource {
+    data: Vec<u8>,
+    index: u32,
+    path: PathBuf,
+}
+
+/// Ranks the faces of a font file: `None` when no face covers CJK, lower is
+/// better otherwise (Simplified Chinese first, bold faces last).
+fn pick_face(data: &[u8]) -> Option<u32> {
+    let count = ttf_parser::fonts_in_collection(data).unwrap_or(1);
+    (0..count)
+        .filter_map(|index| {
+            let face = Face::parse(data, index).ok()?;
+            face.glyph_index('中')?;
+            let family = face_name(&face, name_id::FAMILY).unwrap_or_default();
+            let simplified = ["SC", "GB", "CN", "YaHei", "Hei", "Song"]
+                .iter()
+                .any(|hint| family.contains(hint));
+            Some((u8::from(!simplified) + 2 * u8::from(face.is_bold()), index))
+        })
+        .min()
+        .map(|(_, index)| index)
+}
+
+fn try_font(path: &Path) -> Option<FontSource> {
+    let data = fs::read(path).ok()?;
+    let index = pick_face(&data)?;
+    Some(FontSource {
+        data,
+        index,
+        path: path.to_path_buf(),
+    })
+}
+
+/// Uses `custom` when given, otherwise the first system font covering Chinese.
+fn load_font(custom: Option<&str>) -> Result<FontSource, String> {
+    if let Some(custom) = custom.map(str::trim).filter(|path| !path.is_empty()) {
+        let path = PathBuf::from(custom);
+        let data = fs::read(&path).map_err(|e| format!("读取字体文件失败: {}", e))?;
+        let index = match pick_face(&data) {
+            Some(index) => index,
+            None => {
+                Face::parse(&data, 0).map_err(|e| format!("无法解析字体文件: {}", e))?;
+                0
+            }
+        };
+        return Ok(FontSource { data, index, path });
+    }
+
+    if let Some(font) = FONT_CANDIDATES
+        .iter()
+        .map(Path::new)
+        .filter(|path| path.is_file())
+        .find_map(try_font)
+    {
+        return Ok(font);
+    }
+
+    let mut dirs: Vec<PathBuf> = FONT_DIRS.iter().map(PathBuf::from).collect();
+    if let Some(home) = dirs::home_dir() {
+        dirs.push(home.join(".local/share/fonts"));
+        dirs.push(home.join(".fonts"));
+        dirs.push(home.join("Library/Fonts"));
+    }
+    for dir in dirs.iter().filter(|dir| dir.is_dir()) {
+        let found = walkdir::WalkDir::new(dir)
+            .max_depth(4)
+            .into_iter()
+            .filter_map(Result::ok)
+            .filter(|entry| entry.file_type().is_file())
+            .filter(|entry| {
+                let name = entry.file_name().to_string_lossy().to_lowercase();
+                [".ttf", ".ttc", ".otf", ".otc"]
+                    .iter()
+                    .any(|ext| name.ends_with(ext))
+                    && CJK_NAME_HINTS.iter().any(|hint| name.contains(hint))
+            })
+            .find_map(|entry| try_font(entry.path()));
+        if let Some(font) = found {
+            return Ok(font);
+        }
+    }
+
+    Err("未找到支持中文的字体，请指定一个 TTF/OTF 字体文件".to_string())
+}
+
+fn face_name(face: &Face, id: u16) -> Option<String> {
+    face.names()
+        .into_iter()
+        .filter(|name| name.name_id == id)
+        .find_map(|name| name.to_string())
+}
+
+/// The embedded font: maps text to glyph ids and remembers which glyphs were
+/// drawn so only those are subset into the file.
+struct PdfFont<'a> {
+    face: Face<'a>,
+    source: &'a FontSource,
+    used: BTreeMap<u16, char>,
+}
+
+impl<'a> PdfFont<'a> {
+    fn new(source: &'a FontSource) -> Result<Self, String> {
+        let face = Face::parse(&source.data, source.index)
+            .map_err(|e| format!("无法解析字体文件: {}", e))?;
+        Ok(Self {
+            face,
+            source,
+            used: BTreeMap::new(),
+        })
+    }
+
+    fn glyph(&self, c: char) -> u16 {
+        let c = if c.is_whitespace() { ' ' } else { c };
+        self.face.glyph_index(c).map(|gid| gid.0).unwrap_or(0)
+    }
+
+    /// Advance of `gid` in 1/1000 em.
+    fn advance(&self, gid: u16) -> f32 {
+        let units = self.face.glyph_hor_advance(GlyphId(gid)).unwrap_or(0) as f32;
+        units * 1000.0 / self.face.units_per_em() as f32
+    }
+
+    fn width(&self, text: &str, size: f32) -> f32 {
+        text.chars()
+            .filter(|c| !c.is_control())
+            .map(|c| self.advance(self.glyph(c)))
+            .sum::<f32>()
+            * size
+            / 1000.0
+    }
+
+    /// Encodes `text` as 2-byte glyph ids for the Identity-H encoding.
+    fn encode(&mut self, text: &str) -> Object {
+        let mut bytes = Vec::with_capacity(text.len() * 2);
+        for c in text.chars().filter(|c| !c.is_control()) {
+            let gid = self.glyph(c);
+            self.used.entry(gid).or_insert(c);
+            bytes.extend_from_slice(&gid.to_be_bytes());
+        }
+        Object::String(bytes, StringFormat::Hexadecimal)
+    }
+
+    fn wrap(&self, text: &str, size: f32, max_width: f32) -> Vec<String> {
+        wrap_text(text, max_width, |line| self.width(line, size))
+    }
+
+    /// Writes the subset font as a Type0 font with Identity-H encoding.
+    fn embed(&self, doc: &mut Document) -> Result<ObjectId, String> {
+        let mut gids: Vec<u16> = self.used.keys().copied().collect();
+        if !gids.contains(&0) {
+            gids.insert(0, 0);
+        }
+        let subset = subsetter::subset(
+            &self.source.data,
+            self.source.index,
+            subsetter::Profile::pdf(&gids),
+        )
+        .map_err(|e| format!("嵌入字体失败: {:?}", e))?;
+
+        let mut hasher = DefaultHasher::new();
+        gids.hash(&mut hasher);
+        let mut tag_seed = hasher.finish();
+        let tag: String = (0..6)
+            .map(|_| {
+                let letter = (b'A' + (tag_seed % 26) as u8) as char;
+                tag_seed /= 26;
+                letter
+            })
+            .collect();
+        let postscript: String = face_name(&self.face, name_id::POST_SCRIPT_NAME)
+            .unwrap_or_else(|| "WorkPlanCJK".to_string())
+            .chars()
+            .filter(|c| c.is_ascii_alphanumeric() || *c == '-')
+            .collect();
+        let base_font = format!("{}+{}", tag, postscript);
+
+        let cff = self.face.tables().cff.is_some();
+        let file_id = if cff {
+            doc.add_object(Stream::new(dictionary! { "Subtype" => "OpenType" }, subset))
+        } else {
+            let length = subset.len() as i64;
+            doc.add_object(Stream::new(dictionary! { "Length1" => length }, subset))
+        };
+
+        let scale = 1000.0 / self.face.units_per_em() as f32;
+        let scaled = |value: i16| (value as f32 * scale).round() as i64;
+        let bbox = self.face.global_bounding_box();
+        let ascent = scaled(self.face.ascender());
+        let mut descriptor = dictionary! {
+            "Type" => "FontDescriptor",
+            "FontName" => Object::Name(base_font.clone().into_bytes()),
+            "Flags" => 4,
+            "FontBBox" => vec![
+                scaled(bbox.x_min).into(),
+                scaled(bbox.y_min).into(),
+                scaled(bbox.x_max).into(),
+                scaled(bbox.y_max).into(),
+            ],
+            "ItalicAngle" => 0,
+            "Ascent" => ascent,
+            "Descent" => scaled(self.face.descender()),
+            "CapHeight" => self.face.capital_height().map(scaled).unwrap_or(ascent),
+            "StemV" => 80,
+        };
+        descriptor.set(if cff { "FontFile3" } else { "FontFile2" }, file_id);
+        let descriptor_id = doc.add_object(descriptor);
+
+        let mut widths = Vec::new();
+        for &gid in &gids {
+            widths.push(Object::Integer(gid as i64));
+            widths.push(Object::Array(vec![Object::Integer(
+                self.advance(gid).round() as i64,
+            )]));
+        }
+        let mut cid_font = dictionary! {
+            "Type" => "Font",
+            "Subtype" => if cff { "CIDFontType0" } else { "CIDFontType2" },
+            "BaseFont" => Object::Name(base_font.clone().into_bytes()),
+            "CIDSystemInfo" => dictionary! {
+                "Registry" => Object::string_literal("Adobe"),
+                "Ordering" => Object::string_literal("Identity"),
+                "Supplement" => 0,
+            },
+            "FontDescriptor" => descriptor_id,
+            "DW" => 1000,
+            "W" => widths,
+        };
+        if !cff {
+            cid_font.set("CIDToGIDMap", "Identity");
+        }
+        let cid_font_id = doc.add_object(cid_font);
+        let to_unicode_id = doc.add_object(Stream::new(dictionary! {}, to_unicode(&self.used)));
+
+        Ok(doc.add_object(dictionary! {
+            "Type" => "Font",
+            "Subtype" => "Type0",
+            "BaseFont" => Object::Name(base_font.into_bytes()),
+            "Encoding" => "Identity-H",
+            "DescendantFonts" => vec![cid_font_id.into()],
+            "ToUnicode" => to_unicode_id,
+        }))
+    }
+}
+
+/// Splits `text` into lines no wider than `max_width` as measured by `width`,
+/// breaking at spaces when a line holds one and between any two characters
+/// otherwise.
+fn wrap_text(text: &str, max_width: f32, width: impl Fn(&str) -> f32) -> Vec<String> {
+    let mut lines = Vec::new();
+    for paragraph in text.lines() {
+        let mut line = String::new();
+        for c in paragraph.chars().filter(|c| !c.is_control()) {
+            line.push(c);
+            if width(&line) <= max_width || line.chars().count() == 1 {
+                continue;
+            }
+            line.pop();
+            let split = line
+                .rfind(' ')
+                .filter(|&at| at > 0 && c != ' ' && c.is_ascii());
+            match split {
+                Some(at) => {
+                    let rest = line[at + 1..].to_string();
+                    line.truncate(at);
+                    lines.push(std::mem::replace(&mut line, rest));
+                }
+                None => lines.push(std::mem::take(&mut line)),
+            }
+            if c != ' ' || !line.is_empty() {
+                line.push(c);
+            }
+        }
+        lines.push(line);
+    }
+    if lines.is_empty() {
+        lines.push(String::new());
+    }
+    lines
+}
+
+/// CMap mapping the drawn glyphs back to text, so the PDF stays searchable
+/// and copyable.
+fn to_unicode(used: &BTreeMap<u16, char>) -> Vec<u8> {
+    let mut cmap = String::from(
+        "/CIDInit /ProcSet findresource begin\n12 dict begin\nbegincmap\n\
+         /CIDSystemInfo << /Registry (Adobe) /Ordering (UCS) /Supplement 0 >> def\n\
+         /CMapName /Adobe-Identity-UCS def\n/CMapType 2 def\n\
+         1 begincodespacerange\n<0000> <FFFF>\nendcodespacerange\n",
+    );
+    let entries: Vec<(&u16, &char)> = used.iter().filter(|(gid, _)| **gid != 0).collect();
+    for chunk in entries.chunks(100) {
+        cmap.push_str(&format!("{} beginbfchar\n", chunk.len()));
+        for (gid, c) in chunk {
+            let utf16: String = c
+                .encode_utf16(&mut [0; 2])
+                .iter()
+                .map(|unit| format!("{:04X}", unit))
+                .collect();
+            cmap.push_str(&format!("<{:04X}> <{}>\n", gid, utf16));
+        }
+        cmap.push_str("endbfchar\n");
+    }
+    cmap.push_str("endcmap\nCMapName currentdict /CMap defineresource pop\nend\nend\n");
+    cmap.into_bytes()
+}
+
+/// One task flattened to what the report prints.
+struct TaskRow {
+    title: String,
+    status: String,
+    priority: String,
+    date: String,
+    deadline: String,
+    completed: String,
+    subtasks: Option<(usize, usize)>,
+}
+
+impl TaskRow {
+    fn from_value(task: &Value) -> Self {
+        let text = |key: &str| {
+            task.get(key)
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .trim()
+                .to_string()
+        };
+        let subtasks = task
+            .get("subtasks")
+            .and_then(Value::as_array)
+            .filter(|list| !list.is_empty())
+            .map(|list| {
+                let done = list
+                    .iter()
+                    .filter(|sub| sub.get("completed").and_then(Value::as_bool) == Some(true))
+                    .count();
+                (done, list.len())
+            });
+        Self {
+            title: text("title"),
+            status: text("status"),
+            priority: text("priority"),
+            date: text("date"),
+            deadline: text("deadline"),
+            completed: text("completedDate"),
+            subtasks,
+        }
+    }
+
+    fn is_done(&self) -> bool {
+        self.status == "done"
+    }
+
+    fn finished_late(&self) -> bool {
+        self.is_done() && !self.deadline.is_empty() && self.completed > self.deadline
+    }
+
+    fn is_overdue(&self, now: &str) -> bool {
+        !self.is_done() && !self.deadline.is_empty() && self.deadline.as_str() < now
+    }
+}
+
+fn short_date(day: i64) -> String {
+    let (_, month, date, ..) = crate::utc_date_time(day.max(0) as u64 * 86_400_000);
+    format!("{:02}-{:02}", month, date)
+}
+
+/// Lays out pages top-down; `y` is the distance from the top edge.
+struct Canvas<'a> {
+    font: PdfFont<'a>,
+    pages: Vec<Vec<Operation>>,
+    y: f32,
+}
+
+fn real(value: f32) -> Object {
+    Object::Real((value * 100.0).round() / 100.0)
+}
+
+impl<'a> Canvas<'a> {
+    fn new(font: PdfFont<'a>) -> Self {
+        Self {
+            font,
+            pages: vec![Vec::new()],
+            y: MARGIN,
+        }
+    }
+
+    fn ops(&mut self) -> &mut Vec<Operation> {
+        self.pages.last_mut().expect("canvas always has a page")
+    }
+
+    fn op(&mut self, operator: &str, operands: Vec<Object>) {
+        self.ops().push(Operation::new(operator, operands));
+    }
+
+    fn new_page(&mut self) {
+        self.pages.push(Vec::new());
+        self.y = MARGIN;
+    }
+
+    /// Starts a new page unless `height` still fits above the footer.
+    fn ensure_space(&mut self, height: f32) -> bool {
+        if self.y + height > PAGE_HEIGHT - MARGIN - FOOTER_SPACE {
+            self.new_page();
+            return true;
+        }
+        false
+    }
+
+    fn fill_color(&mut self, (r, g, b): Rgb) {
+        self.op("rg", vec![real(r), real(g), real(b)]);
+    }
+
+    fn rect(&mut self, x: f32, top: f32, width: f32, height: f32, color: Rgb) {
+        self.fill_color(color);
+        self.op(
+            "re",
+            vec![
+                real(x),
+                real(PAGE_HEIGHT - top - height),
+                real(width),
+                real(height),
+            ],
+        );
+        self.op("f", vec![]);
+    }
+
+    fn line(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, color: Rgb, width: f32) {
+        let (r, g, b) = color;
+        self.op("RG", vec![real(r), real(g), real(b)]);
+        self.op("w", vec![real(width)]);
+        self.op("m", vec![real(x1), real(PAGE_HEIGHT - y1)]);
+        self.op("l", vec![real(x2), real(PAGE_HEIGHT - y2)]);
+        self.op("S", vec![]);
+    }
+
+    /// Draws `text` with its baseline at `baseline`; `bold` fakes a heavier
+    /// weight by stroking the outlines as well.
+    fn text(&mut self, x: f32, baseline: f32, size: f32, color: Rgb, bold: bool, text: &str) {
+        if text.is_empty() {
+            return;
+        }
+        let encoded = self.font.encode(text);
+        let (r, g, b) = color;
+        self.op("BT", vec![]);
+        self.op("Tf", vec![Object::Name(b"F1".to_vec()), real(size)]);
+        self.fill_color(color);
+        if bold {
+            self.op("RG", vec![real(r), real(g), real(b)]);
+            self.op("w", vec![real(size / 30.0)]);
+            self.op("Tr", vec![Object::Integer(2)]);
+        }
+        self.op("Td", vec![real(x), real(PAGE_HEIGHT - baseline)]);
+        self.op("Tj", vec![encoded]);
+        if bold {
+            self.op("Tr", vec![Object::Integer(0)]);
+        }
+        self.op("ET", vec![]);
+    }
+
+    fn text_right(&mut self, right: f32, baseline: f32, size: f32, color: Rgb, text: &str) {
+        let width = self.font.width(text, size);
+        self.text(right - width, baseline, size, color, false, text);
+    }
+
+    fn text_center(&mut self, center: f32, baseline: f32, size: f32, color: Rgb, text: &str) {
+        let width = self.font.width(text, size);
+        self.text(center - width / 2.0, baseline, size, color, false, text);
+    }
+
+    /// Fills a pie slice from `start` to `end` (fractions of a turn, clockwise
+    /// from twelve o'clock), approximating the arc with Bézier curves.
+    fn wedge(&mut self, cx: f32, cy: f32, radius: f32, start: f32, end: f32, color: Rgb) {
+        let cy = PAGE_HEIGHT - cy;
+        let angle = |turn: f32| std::f32::consts::FRAC_PI_2 - turn * std::f32::consts::TAU;
+        let point = |a: f32| (cx + radius * a.cos(), cy + radius * a.sin());
+        let segments = ((end - start) * 4.0).ceil().max(1.0) as usize;
+        self.fill_color(color);
+        self.op("m", vec![real(cx), real(cy)]);
+        let (x, y) = point(angle(start));
+        self.op("l", vec![real(x), real(y)]);
+        for step in 0..segments {
+            let a = angle(start + (end - start) * step as f32 / segments as f32);
+            let b = angle(start + (end - start) * (step + 1) as f32 / segments as f32);
+            let k = 4.0 / 3.0 * ((b - a) / 4.0).tan() * radius;
+            let (x0, y0) = point(a);
+            let (x3, y3) = point(b);
+            self.op(
+                "c",
+                vec![
+                    real(x0 - k * a.sin()),
+                    real(y0 + k * a.cos()),
+                    real(x3 + k * b.sin()),
+                    real(y3 - k * b.cos()),
+                    real(x3),
+                    real(y3),
+                ],
+            );
+        }
+        self.op("h", vec![]);
+        self.op("f", vec![]);
+    }
+
+    fn legend(&mut self, x: f32, baseline: f32, color: Rgb, label: &str) -> f32 {
+        self.rect(x, baseline - 7.0, 8.0, 8.0, color);
+        self.text(x + 12.0, baseline, 8.0, INK, false, label);
+        x + 12.0 + self.font.width(label, 8.0) + 12.0
+    }
+
+    fn heading(&mut self, text: &str) {
+        self.ensure_space(40.0);
+        self.y += 14.0;
+        self.rect(MARGIN, self.y - 11.0, 3.0, 13.0, ACCENT);
+        self.text(MARGIN + 9.0, self.y, 12.0, INK, true, text);
+        self.y += 12.0;
+    }
+}
+
+/// Counts per status used by the summary and the status chart.
+struct Totals {
+    total: usize,
+    done: usize,
+    doing: usize,
+    todo: usize,
+    overdue: usize,
+    late: usize,
+}
+
+impl Totals {
+    fn of(rows: &[TaskRow], now: &str) -> Self {
+        let count = |status: &str| rows.iter().filter(|row| row.status == status).count();
+        Self {
+            total: rows.len(),
+            done: count("done"),
+            doing: count("doing"),
+            todo: count("todo"),
+            overdue: rows.iter().filter(|row| row.is_overdue(now)).count(),
+            late: rows.iter().filter(|row| row.finished_late()).count(),
+        }
+    }
+
+    fn rate(&self) -> f32 {
+        if self.total == 0 {
+            0.0
+        } else {
+            self.done as f32 * 100.0 / self.total as f32
+        }
+    }
+}
+
+fn draw_header(canvas: &mut Canvas, request: &ReportRequest) {
+    canvas.rect(MARGIN, canvas.y, CONTENT_WIDTH, 4.0, ACCENT);
+    canvas.y += 30.0;
+    for line in canvas.font.wrap(&request.title, 20.0, CONTENT_WIDTH) {
+        canvas.text(MARGIN, canvas.y, 20.0, INK, true, &line);
+        canvas.y += 26.0;
+    }
+    let range = if request.start == request.end {
+        request.start.clone()
+    } else {
+        format!("{} ~ {}", request.start, request.end)
+    };
+    canvas.text(MARGIN, canvas.y - 6.0, 10.0, MUTED, false, &range);
+    canvas.y += 8.0;
+}
+
+fn draw_summary(canvas: &mut Canvas, labels: &ReportLabels, totals: &Totals) {
+    canvas.heading(&labels.summary);
+    canvas.y += 8.0;
+    let cards = [
+        (&labels.total, totals.total.to_string(), INK),
+        (
+            &labels.done,
+            format!("{} · {:.1}%", totals.done, totals.rate()),
+            DONE,
+        ),
+        (&labels.doing, totals.doing.to_string(), DOING),
+        (&labels.todo, totals.todo.to_string(), TODO),
+        (&labels.overdue, totals.overdue.to_string(), ALERT),
+    ];
+    let gap = 8.0;
+    let width = (CONTENT_WIDTH - gap * (cards.len() - 1) as f32) / cards.len() as f32;
+    let top = canvas.y;
+    for (index, (label, value, color)) in cards.into_iter().enumerate() {
+        let x = MARGIN + index as f32 * (width + gap);
+        canvas.rect(x, top, width, 50.0, PANEL);
+        canvas.rect(x, top, 3.0, 50.0, color);
+        canvas.text(x + 10.0, top + 17.0, 8.0, MUTED, false, label);
+        let size = if canvas.font.width(&value, 16.0) > width - 16.0 {
+            12.0
+        } else {
+            16.0
+        };
+        canvas.text(x + 10.0, top + 40.0, size, color, true, &value);
+    }
+    canvas.y = top + 50.0;
+    if totals.late > 0 {
+        canvas.y += 14.0;
+        let note = format!("{}: {}", labels.late, totals.late);
+        canvas.text(MARGIN, canvas.y, 8.0, MUTED, false, &note);
+    }
+    canvas.y += 10.0;
+}
+
+fn draw_status_and_priority(
+    canvas: &mut Canvas,
+    labels: &ReportLabels,
+    totals: &Totals,
+    rows: &[TaskRow],
+) {
+    let height = 150.0;
+    canvas.ensure_space(height + 40.0);
+    let half = (CONTENT_WIDTH - 16.0) / 2.0;
+    let top = canvas.y + 12.0;
+    canvas.text(MARGIN, top, 10.0, INK, true, &labels.status_chart);
+    canvas.text(
+        MARGIN + half + 16.0,
+        top,
+        10.0,
+        INK,
+        true,
+        &labels.priority_chart,
+    );
+
+    let slices = [
+        (totals.done, DONE, &labels.done),
+        (totals.doing, DOING, &labels.doing),
+        (totals.todo, TODO, &labels.todo),
+    ];
+    let (cx, cy, radius) = (MARGIN + 62.0, top + 72.0, 50.0);
+    if totals.total == 0 {
+        canvas.wedge(cx, cy, radius, 0.0, 1.0, RULE);
+    } else {
+        let mut start = 0.0;
+        for (count, color, _) in slices {
+            if count == 0 {
+                continue;
+            }
+            let end = start + count as f32 / totals.total as f32;
+            canvas.wedge(cx, cy, radius, start, end, color);
+            start = end;
+        }
+    }
+    canvas.wedge(cx, cy, radius * 0.58, 0.0, 1.0, (1.0, 1.0, 1.0));
+    canvas.text_center(cx, cy + 2.0, 13.0, INK, &format!("{:.0}%", totals.rate()));
+    canvas.text_center(cx, cy + 13.0, 7.0, MUTED, &labels.rate);
+    for (index, (count, color, label)) in slices.into_iter().enumerate() {
+        let baseline = top + 42.0 + index as f32 * 18.0;
+        let x = cx + radius + 18.0;
+        canvas.legend(x, baseline, color, label);
+        canvas.text_right(MARGIN + half, baseline, 8.0, INK, &count.to_string());
+    }
+
+    let priorities = [
+        ("critical", &labels.critical, ALERT),
+        ("urgent", &labels.urgent, WARN),
+        ("normal", &labels.normal, TODO),
+    ];
+    let counts: Vec<usize> = priorities
+        .iter()
+        .map(|(key, ..)| {
+            rows.iter()
+                .filter(|row| row.priority == *key || (*key == "normal" && row.priority.is_empty()))
+                .count()
+        })
+        .collect();
+    let max = counts.iter().copied().max().unwrap_or(0).max(1);
+    let left = MARGIN + half + 16.0;
+    let label_width = priorities
+        .iter()
+        .map(|(_, label, _)| canvas.font.width(label, 8.0))
+        .fold(0.0, f32::max)
+        + 8.0;
+    let bar_space = half - label_width - 28.0;
+    for (index, ((_, label, color), count)) in priorities.into_iter().zip(counts).enumerate() {
+        let row_top = top + 26.0 + index as f32 * 34.0;
+        canvas.text(left, row_top + 14.0, 8.0, INK, false, label);
+        let bar_x = left + label_width;
+        canvas.rect(bar_x, row_top + 4.0, bar_space, 14.0, PANEL);
+        let width = bar_space * count as f32 / max as f32;
+        if width > 0.0 {
+            canvas.rect(bar_x, row_top + 4.0, width, 14.0, color);
+        }
+        canvas.text(
+            bar_x + width + 4.0,
+            row_top + 14.0,
+            8.0,
+            MUTED,
+            false,
+            &count.to_string(),
+        );
+    }
+    canvas.y = top + height;
+}
+
+/// Tasks planned and completed per day (per week for long ranges).
+fn draw_daily(canvas: &mut Canvas, request: &ReportRequest, rows: &[TaskRow]) {
+    let labels = &request.labels;
+    let (Some(first), Some(last)) = (
+        crate::date_days(&request.start),
+        crate::date_days(&request.end),
+    ) else {
+        return;
+    };
+    if last < first {
+        return;
+    }
+    let days = last - first + 1;
+    let span = if days > MAX_DAILY_BARS { 7 } else { 1 };
+    let buckets = ((days + span - 1) / span) as usize;
+    let mut planned = vec![0usize; buckets];
+    let mut completed = vec![0usize; buckets];
+    let bucket = |date: &str| {
+        crate::date_days(date)
+            .filter(|day| (first..=last).contains(day))
+            .map(|day| ((day - first) / span) as usize)
+    };
+    for row in rows {
+        if let Some(index) = bucket(&row.date) {
+            planned[index] += 1;
+        }
+        if let Some(index) = bucket(&row.completed).filter(|_| row.is_done()) {
+            completed[index] += 1;
+        }
+    }
+
+    let height = 150.0;
+    canvas.ensure_space(height + 30.0);
+    let top = canvas.y + 12.0;
+    canvas.text(MARGIN, top, 10.0, INK, true, &labels.daily_chart);
+    let legend_x = MARGIN + CONTENT_WIDTH - 150.0;
+    let next = canvas.legend(legend_x, top, ACCENT, &labels.planned);
+    canvas.legend(next, top, DONE, &labels.completed);
+
+    let chart_top = top + 14.0;
+    let chart_height = height - 46.0;
+    let axis_left = MARGIN + 24.0;
+    let axis_width = CONTENT_WIDTH - 24.0;
+    let baseline = chart_top + chart_height;
+    let max = planned
+        .iter()
+        .chain(&completed)
+        .copied()
+        .max()
+        .unwrap_or(0)
+        .max(1);
+    let step = (max as f32 / 4.0).ceil().max(1.0) as usize;
+    let scale_max = (step * 4) as f32;
+    for tick in 0..=4 {
+        let value = step * tick;
+        let y = baseline - chart_height * value as f32 / scale_max;
+        canvas.line(axis_left, y, axis_left + axis_width, y, RULE, 0.5);
+        canvas.text_right(axis_left - 4.0, y + 3.0, 7.0, MUTED, &value.to_string());
+    }
+
+    let slot = axis_width / buckets as f32;
+    let bar = (slot * 0.32).min(14.0);
+    let label_every = ((30.0 / slot).ceil() as usize).max(1);
+    for index in 0..buckets {
+        let center = axis_left + slot * (index as f32 + 0.5);
+        for (value, color, offset) in [
+            (planned[index], ACCENT, -bar),
+            (completed[index], DONE, 0.0),
+        ] {
+            let h = chart_height * value as f32 / scale_max;
+            if h > 0.0 {
+                canvas.rect(center + offset, baseline - h, bar, h, color);
+            }
+        }
+        if index % label_every == 0 {
+            let day = first + index as i64 * span;
+            canvas.text_center(center, baseline + 11.0, 7.0, MUTED, &short_date(day));
+        }
+    }
+    canvas.y = top + height;
+}
+
+fn draw_table(canvas: &mut Canvas, request: &ReportRequest, rows: &[TaskRow]) {
+    let labels = &request.labels;
+    canvas.heading(&labels.task_list);
+    canvas.y += 6.0;
+    if rows.is_empty() {
+        canvas.y += 14.0;
+        canvas.text(MARGIN, canvas.y, 9.0, MUTED, false, &labels.empty);
+        return;
+    }
+
+    const SIZE: f32 = 8.0;
+    const LEADING: f32 = 11.0;
+    const PAD: f32 = 5.0;
+    let columns = [
+        (&labels.col_title, 187.0),
+        (&labels.col_status, 56.0),
+        (&labels.col_priority, 46.0),
+        (&labels.col_date, 70.0),
+        (&labels.col_deadline, 70.0),
+        (&labels.col_completed, 70.0),
+    ];
+    let header = |canvas: &mut Canvas| {
+        canvas.rect(MARGIN, canvas.y, CONTENT_WIDTH, 20.0, (0.93, 0.94, 0.99));
+        let mut x = MARGIN;
+        for (label, width) in columns {
+            canvas.text(x + PAD, canvas.y + 13.5, SIZE, ACCENT, true, label);
+            x += width;
+        }
+        canvas.y += 20.0;
+    };
+    header(canvas);
+
+    let display = |value: &str| value.replace('T', " ");
+    for (index, row) in rows.iter().enumerate() {
+        let title = match row.subtasks {
+            Some((done, total)) => format!("{} ({}/{})", row.title, done, total),
+            None => row.title.clone(),
+        };
+        let (status, status_color) = match row.status.as_str() {
+            "done" if row.finished_late() => (&labels.late, ALERT),
+            "done" => (&labels.done, DONE),
+            "doing" => (&labels.doing, DOING),
+            _ => (&labels.todo, TODO),
+        };
+        let (priority, priority_color) = match row.priority.as_str() {
+            "critical" => (&labels.critical, ALERT),
+            "urgent" => (&labels.urgent, WARN),
+            _ => (&labels.normal, MUTED),
+        };
+        let deadline_color = if row.is_overdue(&request.now) {
+            ALERT
+        } else {
+            INK
+        };
+        let cells = [
+            (title, INK),
+            (status.clone(), status_color),
+            (priority.clone(), priority_color),
+            (display(&row.date), INK),
+            (display(&row.deadline), deadline_color),
+            (display(&row.completed), INK),
+        ];
+        let wrapped: Vec<(Vec<String>, Rgb)> = cells
+            .into_iter()
+            .zip(columns)
+            .map(|((text, color), (_, width))| {
+                (canvas.font.wrap(&text, SIZE, width - PAD * 2.0), color)
+            })
+            .collect();
+        let lines = wrapped
+            .iter()
+            .map(|(lines, _)| lines.len())
+            .max()
+            .unwrap_or(1);
+        let height = lines as f32 * LEADING + PAD * 2.0 - 2.0;
+        if canvas.ensure_space(height) {
+            header(canvas);
+        }
+        if index % 2 == 1 {
+            canvas.rect(MARGIN, canvas.y, CONTENT_WIDTH, height, PANEL);
+        }
+        let mut x = MARGIN;
+        for ((lines, color), (_, width)) in wrapped.iter().zip(columns) {
+            for (line_index, line) in lines.iter().enumerate() {
+                let baseline = canvas.y + PAD + 7.0 + line_index as f32 * LEADING;
+                canvas.text(x + PAD, baseline, SIZE, *color, false, line);
+            }
+            x += width;
+        }
+        canvas.y += height;
+        canvas.line(
+            MARGIN,
+            canvas.y,
+            MARGIN + CONTENT_WIDTH,
+            canvas.y,
+            RULE,
+            0.5,
+        );
+    }
+}
+
+fn draw_footers(canvas: &mut Canvas, request: &ReportRequest) {
+    let pages = std::mem::take(&mut canvas.pages);
+    let count = pages.len();
+    let generated = format!(
+        "{} {}",
+        request.labels.generated,
+        request.now.replace('T', " ")
+    );
+    let baseline = PAGE_HEIGHT - MARGIN + 8.0;
+    for (page, ops) in pages.into_iter().enumerate() {
+        canvas.pages.push(ops);
+        canvas.line(
+            MARGIN,
+            baseline - 14.0,
+            MARGIN + CONTENT_WIDTH,
+            baseline - 14.0,
+            RULE,
+            0.5,
+        );
+        canvas.text(MARGIN, baseline, 7.5, MUTED, false, &generated);
+        let number = request
+            .labels
+            .page
+            .replace("{page}", &(page + 1).to_string())
+            .replace("{pages}", &count.to_string());
+        canvas.text_right(MARGIN + CONTENT_WIDTH, baseline, 7.5, MUTED, &number);
+    }
+}
+
+/// Renders the report and returns the PDF bytes, the page count and the
+/// embedded font's file.
+fn render_report(request: &ReportRequest) -> Result<(Vec<u8>, usize, String), String> {
+    let source = load_font(request.font_path.as_deref())?;
+    let mut canvas = Canvas::new(PdfFont::new(&source)?);
+
+    let rows: Vec<TaskRow> = request.tasks.iter().map(TaskRow::from_value).collect();
+    let totals = Totals::of(&rows, &request.now);
+    draw_header(&mut canvas, request);
+    draw_summary(&mut canvas, &request.labels, &totals);
+    draw_status_and_priority(&mut canvas, &request.labels, &totals, &rows);
+    draw_daily(&mut canvas, request, &rows);
+    draw_table(&mut canvas, request, &rows);
+    draw_footers(&mut canvas, request);
+
+    let mut doc = Document::with_version("1.7");
+    let pages_id = doc.new_object_id();
+    let font_id = canvas.font.embed(&mut doc)?;
+    let resources_id = doc.add_object(dictionary! {
+        "Font" => dictionary! { "F1" => font_id },
+    });
+    let mut kids = Vec::new();
+    for ops in std::mem::take(&mut canvas.pages) {
+        let content = Content { operations: ops }
+            .encode()
+            .map_err(|e| format!("生成 PDF 失败: {}", e))?;
+        let content_id = doc.add_object(Stream::new(dictionary! {}, content));
+        kids.push(
+            doc.add_object(dictionary! {
+                "Type" => "Page",
+                "Parent" => pages_id,
+                "Contents" => content_id,
+            })
+            .into(),
+        );
+    }
+    let page_count = kids.len();
+    doc.objects.insert(
+        pages_id,
+        Object::Dictionary(dictionary! {
+            "Type" => "Pages",
+            "Kids" => kids,
+            "Count" => page_count as i64,
+            "Resources" => resources_id,
+            "MediaBox" => vec![0.into(), 0.into(), real(PAGE_WIDTH), real(PAGE_HEIGHT)],
+        }),
+    );
+    let catalog_id = doc.add_object(dictionary! {
+        "Type" => "Catalog",
+        "Pages" => pages_id,
+    });
+    let info_id = doc.add_object(dictionary! {
+        "Title" => lopdf::text_string(&request.title),
+        "Producer" => Object::string_literal("WorkPlan"),
+    });
+    doc.trailer.set("Root", catalog_id);
+    doc.trailer.set("Info", info_id);
+    doc.compress();
+
+    let mut bytes = Vec::new();
+    doc.save_to(&mut bytes)
+        .map_err(|e| format!("生成 PDF 失败: {}", e))?;
+    Ok((bytes, page_count, source.path.to_string_lossy().to_string()))
+}
+
+/// Builds a PDF work report for `request.tasks` and saves it, to the
+/// downloads directory unless a save dialog is asked for. `None` means the
+/// dialog was cancelled.
+#[tauri::command]
+pub async fn export_task_report(
+    app: AppHandle,
+    mut request: ReportRequest,
+) -> Result<Option<ReportSummary>, String> {
+    let filename = match request.filename.trim() {
+        "" => format!("work-report_{}_{}.pdf", request.start, request.end),
+        name => name.to_string(),
+    };
+    let mut save = std::mem::take(&mut request.save);
+    save.mime = "application/pdf".to_string();
+    let tasks = request.tasks.len();
+    let (bytes, pages, font) =
+        tauri::async_runtime::spawn_blocking(move || render_report(&request))
+            .await
+            .map_err(|e| e.to_string())??;
+    let Some(saved) = downloads::save_bytes(&app, &filename, &bytes, &save).await? else {
+        return Ok(None);
+    };
+    Ok(Some(ReportSummary {
+        path: saved.path,
+        pages,
+        tasks,
+        font,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// Wraps with ASCII one unit wide and everything else two, like a
+    /// monospaced CJK font.
+    fn wrap(text: &str, max_width: f32) -> Vec<String> {
+        wrap_text(text, max_width, |line| {
+            line.chars()
+                .map(|c| if c.is_ascii() { 1.0 } else { 2.0 })
+                .sum()
+        })
+    }
+
+    fn bfchars(cmap: &[u8]) -> Vec<String> {
+        String::from_utf8(cmap.to_vec())
+            .unwrap()
+            .lines()
+            .filter(|line| line.starts_with('<') && !line.contains("FFFF>"))
+            .map(str::to_string)
+            .collect()
+    }
+
+    fn task(status: &str, deadline: &str, completed: &str) -> Value {
+        json!({
+            "title": format!("{} task", status),
+            "status": status,
+            "priority": "normal",
+            "date": "2024-03-01",
+            "deadline": deadline,
+            "completedDate": completed,
+        })
+    }
+
+    #[test]
+    fn ascii_wraps_at_spaces() {
+        assert_eq!(wrap("hello world foo", 11.0), ["hello world", "foo"]);
+        assert_eq!(wrap("abcd efg", 4.0), ["abcd", "efg"]);
+        // A word longer than the line is broken anywhere.
+        assert_eq!(wrap("abcdefghij", 4.0), ["abcd", "efgh", "ij"]);
+        assert_eq!(wrap("one\ntwo", 10.0), ["one", "two"]);
+        assert_eq!(wrap("", 10.0), [""]);
+    }
+
+    #[test]
+    fn cjk_wraps_between_characters() {
+        assert_eq!(wrap("一二三四五", 6.0), ["一二三", "四五"]);
+        assert_eq!(
+            wrap("计划 review meeting", 12.0),
+            ["计划 review", "meeting"]
+        );
+        assert_eq!(wrap("ab 中文字", 5.0), ["ab 中", "文字"]);
+        // A glyph wider than the line still gets a line of its own.
+        assert_eq!(wrap("中文", 1.0), ["中", "文"]);
+    }
+
+    #[test]
+    fn to_unicode_maps_drawn_glyphs_back_to_text() {
+        let used = BTreeMap::from([(0, '?'), (5, '中'), (7, 'A'), (9, '😀')]);
+        let cmap = to_unicode(&used);
+        let text = String::from_utf8(cmap.clone()).unwrap();
+        assert!(text.contains("1 begincodespacerange\n<0000> <FFFF>\nendcodespacerange"));
+        assert!(text.contains("3 beginbfchar\n"));
+        // The missing glyph maps to nothing; astral characters take a surrogate pair.
+        assert_eq!(
+            bfchars(&cmap),
+            ["<0005> <4E2D>", "<0007> <0041>", "<0009> <D83DDE00>"]
+        );
+
+        let used: BTreeMap<u16, char> = (1..=150).map(|gid| (gid, 'x')).collect();
+        let text = String::from_utf8(to_unicode(&used)).unwrap();
+        assert!(text.contains("100 beginbfchar\n") && text.contains("50 beginbfchar\n"));
+        assert_eq!(text.matches("endbfchar").count(), 2);
+    }
+
+    #[test]
+    fn totals_count_overdue_and_late_tasks() {
+        let rows: Vec<TaskRow> = [
+            task("done", "2024-03-05T18:00", "2024-03-04T10:00"),
+            task("done", "2024-03-05T18:00", "2024-03-06T09:00"),
+            task("doing", "2024-03-09T18:00", ""),
+            task("todo", "2024-03-01T18:00", ""),
+            task("todo", "", ""),
+        ]
+        .iter()
+        .map(TaskRow::from_value)
+        .collect();
+        let totals = Totals::of(&rows, "2024-03-08T12:00");
+        assert_eq!(
+            [
+                totals.total,
+                totals.done,
+                totals.doing,
+                totals.todo,
+                totals.overdue,
+                totals.late
+            ],
+            [5, 2, 1, 2, 1, 1]
+        );
+        assert_eq!(totals.rate(), 40.0);
+        assert_eq!(Totals::of(&[], "").rate(), 0.0);
+    }
+
+    #[test]
+    fn renders_a_loadable_pdf() {
+        // Needs a system font covering Chinese; skipped where none is installed.
+        if load_font(None).is_err() {
+            return;
+        }
+        let tasks: Vec<Value> = (0..80)
+            .map(|day| {
+                let mut task = task("todo", "2024-03-20T18:00", "");
+                task["title"] = json!(format!("第 {} 项任务 with a longer English title", day));
+                task["date"] = json!(format!("2024-03-{:02}", day % 28 + 1));
+                task
+            })
+            .collect();
+        let request: ReportRequest = serde_json::from_value(json!({
+            "title": "三月工作报告",
+            "start": "2024-03-01",
+            "end": "2024-03-31",
+            "now": "2024-03-31T12:00",
+            "tasks": tasks,
+        }))
+        .unwrap();
+        let (bytes, pages, _) = render_report(&request).unwrap_or_else(|e| panic!("{}", e));
+        assert!(pages > 1);
+        let doc = Document::load_mem(&bytes).unwrap();
+        assert_eq!(doc.get_pages().len(), pages);
+        let font = doc
+            .objects
+            .values()
+            .filter_map(|object| object.as_dict().ok())
+            .find(|dict| dict.get(b"Subtype").and_then(Object::as_name).ok() == Some(b"Type0"))
+            .expect("embedded Type0 font");
+        assert!(font.get(b"ToUnicode").is_ok());
+    }
+}
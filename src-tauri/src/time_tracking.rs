@@ -0,0 +1,1270 @@
+//! Time spent on tasks: a single active timer (start, pause, resume, stop),
+//! manual entries and per day/week/project/tag/task totals for timesheets.
+//! Every stretch the timer runs becomes one entry, so the log can be edited
+//! and exported without knowing about the timer.
+//!
+//! Idle time is left out: while a timer runs, a watcher polls how long the
+//! system has seen no keyboard or mouse input and, after `idle_minutes`, cuts
+//! the running stretch where the input stopped. A machine that slept, or a
+//! timer still running when the app starts again, is cut the same way. Other
+//! detectors can report idle time through [`mark_timer_idle`].
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tauri::async_runtime::JoinHandle;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::downloads::{self, SaveOptions};
+
+const LOG_FILE: &str = "time_tracking.json";
+const DAY_MS: i64 = 86_400_000;
+/// Stretches shorter than this (a start followed by an immediate pause) are
+/// not worth an entry.
+const MIN_ENTRY_MS: u64 = 1000;
+const DEFAULT_IDLE_MINUTES: u64 = 10;
+const IDLE_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+/// How far the saved last activity may lag behind; it is only read back when
+/// the app starts with a timer still running.
+const ACTIVITY_SAVE_MS: u64 = 5 * 60_000;
+
+static ENTRY_SEQ: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Default)]
+pub struct TimeTrackingState {
+    log: Mutex<Option<TimeLog>>,
+    watcher: Mutex<Option<JoinHandle<()>>>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase", default)]
+struct TimeLog {
+    /// Minutes without activity after which a running timer is cut; 0 turns
+    /// idle detection off.
+    idle_minutes: u64,
+    active: Option<ActiveTimer>,
+    entries: Vec<TimeEntry>,
+}
+
+impl Default for TimeLog {
+    fn default() -> Self {
+        TimeLog {
+            idle_minutes: DEFAULT_IDLE_MINUTES,
+            active: None,
+            entries: Vec::new(),
+        }
+    }
+}
+
+/// What an entry or the timer is booked on.
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct TaskRef {
+    task_id: String,
+    task_title: String,
+    project: String,
+    tags: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct ActiveTimer {
+    #[serde(flatten)]
+    task: TaskRef,
+    started_at: u64,
+    /// Start of the stretch being timed; `None` while paused.
+    running_since: Option<u64>,
+    /// Time already booked as entries since `started_at`.
+    logged_ms: u64,
+    last_activity: u64,
+    /// Set when the timer was paused by idle detection rather than the user.
+    #[serde(default)]
+    idle_paused: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TimeEntry {
+    id: String,
+    #[serde(flatten)]
+    task: TaskRef,
+    start: u64,
+    end: u64,
+    #[serde(default)]
+    note: String,
+    /// `timer` or `manual`.
+    source: String,
+}
+
+impl TimeEntry {
+    fn duration(&self) -> u64 {
+        self.end.saturating_sub(self.start)
+    }
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TimerView {
+    #[serde(flatten)]
+    task: TaskRef,
+    started_at: u64,
+    running: bool,
+    running_since: Option<u64>,
+    /// Total for this timer including the stretch still running.
+    elapsed_ms: u64,
+    idle_paused: bool,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TimeTrackingStatus {
+    active: Option<TimerView>,
+    idle_minutes: u64,
+    now: u64,
+}
+
+/// A manual entry, or the new values of an existing one.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EntryInput {
+    #[serde(flatten)]
+    task: TaskRef,
+    start: u64,
+    end: u64,
+    #[serde(default)]
+    note: String,
+}
+
+/// Entries overlapping the local dates `from..=to`, optionally for one task.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TimeQuery {
+    from: String,
+    to: String,
+    #[serde(default)]
+    task_id: Option<String>,
+    /// `day`, `week`, `project`, `tag` or `task`.
+    #[serde(default)]
+    group_by: String,
+    #[serde(default)]
+    utc_offset_minutes: i64,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TimeGroup {
+    key: String,
+    label: String,
+    total_ms: u64,
+    entries: usize,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TimeSummary {
+    group_by: String,
+    groups: Vec<TimeGroup>,
+    /// Sum over entries; with `tag` grouping an entry counts for each tag, so
+    /// the groups can add up to more than this.
+    total_ms: u64,
+}
+
+/// Column titles of the CSV export, localized by the frontend.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct TimesheetLabels {
+    date: String,
+    start: String,
+    end: String,
+    hours: String,
+    task: String,
+    project: String,
+    tags: String,
+    note: String,
+    source: String,
+    group: String,
+    entries: String,
+    none: String,
+}
+
+impl Default for TimesheetLabels {
+    fn default() -> Self {
+        let text = |s: &str| s.to_string();
+        TimesheetLabels {
+            date: text("日期"),
+            start: text("开始"),
+            end: text("结束"),
+            hours: text("工时(小时)"),
+            task: text("任务"),
+            project: text("项目"),
+            tags: text("标签"),
+            note: text("备注"),
+            source: text("来源"),
+            group: text("分组"),
+            entries: text("记录数"),
+            none: text("未分类"),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TimesheetExport {
+    #[serde(flatten)]
+    query: TimeQuery,
+    /// Writes one row per entry instead of per group.
+    #[serde(default)]
+    detailed: bool,
+    #[serde(default)]
+    labels: TimesheetLabels,
+    #[serde(default)]
+    filename: String,
+    #[serde(default)]
+    save: SaveOptions,
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+fn entry_id(task_id: &str, start: u64) -> String {
+    let seq = ENTRY_SEQ.fetch_add(1, Ordering::SeqCst);
+    let digest = Sha256::digest(format!("{}#{}#{}#{}", task_id, start, now_millis(), seq));
+    digest[..8]
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+fn log_path(app: &AppHandle) -> Result<PathBuf, String> {
+    crate::app_data_file(app, LOG_FILE)
+}
+
+impl TimeLog {
+    /// Books the running stretch up to `end` as an entry and pauses the timer.
+    fn close_stretch(&mut self, end: u64) {
+        let Some(active) = self.active.as_mut() else {
+            return;
+        };
+        let Some(since) = active.running_since.take() else {
+            return;
+        };
+        let end = end.max(since);
+        if end - since >= MIN_ENTRY_MS {
+            active.logged_ms += end - since;
+            self.entries.push(TimeEntry {
+                id: entry_id(&active.task.task_id, since),
+                task: active.task.clone(),
+                start: since,
+                end,
+                note: String::new(),
+                source: "timer".to_string(),
+            });
+        }
+    }
+
+    /// Cuts a running timer at its last activity when that is more than
+    /// `idle_minutes` ago, e.g. after the app was closed or the machine slept.
+    fn cut_stale_timer(&mut self, now: u64) -> bool {
+        let Some(active) = self.active.as_ref() else {
+            return false;
+        };
+        let threshold = self.idle_minutes * 60_000;
+        if threshold == 0
+            || active.running_since.is_none()
+            || now.saturating_sub(active.last_activity) <= threshold
+        {
+            return false;
+        }
+        let last_activity = active.last_activity;
+        self.close_stretch(last_activity);
+        if let Some(active) = self.active.as_mut() {
+            active.idle_paused = true;
+        }
+        true
+    }
+
+    /// Ends the running stretch at `idle_since` and marks the timer as paused
+    /// by idle detection.
+    fn pause_idle(&mut self, idle_since: u64, now: u64) {
+        let Some(since) = self.active.as_ref().and_then(|active| active.running_since) else {
+            return;
+        };
+        self.close_stretch(idle_since.clamp(since, now));
+        if let Some(active) = self.active.as_mut() {
+            active.idle_paused = true;
+        }
+    }
+
+    fn status(&self, now: u64) -> TimeTrackingStatus {
+        TimeTrackingStatus {
+            active: self.active.as_ref().map(|active| TimerView {
+                task: active.task.clone(),
+                started_at: active.started_at,
+                running: active.running_since.is_some(),
+                running_since: active.running_since,
+                elapsed_ms: active.logged_ms
+                    + active
+                        .running_since
+                        .map(|since| now.saturating_sub(since))
+                        .unwrap_or(0),
+                idle_paused: active.idle_paused,
+            }),
+            idle_minutes: self.idle_minutes,
+            now,
+        }
+    }
+
+    /// Entries plus the stretch still running, as if it ended at `now`.
+    fn entries_with_running(&self, now: u64) -> Vec<TimeEntry> {
+        let mut entries = self.entries.clone();
+        if let Some(active) = &self.active {
+            if let Some(since) = active.running_since {
+                entries.push(TimeEntry {
+                    id: String::new(),
+                    task: active.task.clone(),
+                    start: since,
+                    end: now.max(since),
+                    note: String::new(),
+                    source: "running".to_string(),
+                });
+            }
+        }
+        entries
+    }
+}
+
+/// Loads the log, lets `change` edit it and writes it back. Listeners are told
+/// through `time-tracking-changed`.
+fn update_log<T>(
+    app: &AppHandle,
+    state: &TimeTrackingState,
+    change: impl FnOnce(&mut TimeLog, u64) -> Result<T, String>,
+) -> Result<T, String> {
+    let mut guard = state.log.lock().map_err(|e| e.to_string())?;
+    let now = now_millis();
+    let log = load_log(app, &mut guard, now)?;
+    let result = change(log, now)?;
+    save_log(app, log)?;
+    let _ = app.emit("time-tracking-changed", log.status(now));
+    Ok(result)
+}
+
+fn save_log(app: &AppHandle, log: &TimeLog) -> Result<(), String> {
+    let data = serde_json::to_string_pretty(log).map_err(|e| e.to_string())?;
+    fs::write(log_path(app)?, data).map_err(|e| format!("保存工时记录失败: {}", e))
+}
+
+fn read_log<T>(
+    app: &AppHandle,
+    state: &TimeTrackingState,
+    read: impl FnOnce(&TimeLog, u64) -> T,
+) -> Result<T, String> {
+    let mut guard = state.log.lock().map_err(|e| e.to_string())?;
+    let now = now_millis();
+    let log = load_log(app, &mut guard, now)?;
+    Ok(read(log, now))
+}
+
+/// The cached log, read from disk on first use. A timer left running since
+/// the last session is cut at its last activity right away.
+fn load_log<'a>(
+    app: &AppHandle,
+    slot: &'a mut Option<TimeLog>,
+    now: u64,
+) -> Result<&'a mut TimeLog, String> {
+    if slot.is_none() {
+        let mut log: TimeLog = fs::read_to_string(log_path(app)?)
+            .ok()
+            .and_then(|raw| serde_json::from_str(&raw).ok())
+            .unwrap_or_default();
+        if log.cut_stale_timer(now) {
+            save_log(app, &log)?;
+        }
+        *slot = Some(log);
+    }
+    Ok(slot.get_or_insert_with(TimeLog::default))
+}
+
+/// Milliseconds since the last keyboard or mouse input anywhere on the
+/// system, where the platform offers a way to ask.
+#[cfg(windows)]
+fn system_idle_ms() -> Option<u64> {
+    #[repr(C)]
+    struct LastInputInfo {
+        cb_size: u32,
+        dw_time: u32,
+    }
+    #[link(name = "user32")]
+    extern "system" {
+        fn GetLastInputInfo(info: *mut LastInputInfo) -> i32;
+    }
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn GetTickCount() -> u32;
+    }
+
+    let mut info = LastInputInfo {
+        cb_size: std::mem::size_of::<LastInputInfo>() as u32,
+        dw_time: 0,
+    };
+    // SAFETY: both calls only write to the correctly sized struct passed in.
+    unsafe {
+        if GetLastInputInfo(&mut info) == 0 {
+            return None;
+        }
+        Some(GetTickCount().wrapping_sub(info.dw_time) as u64)
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn system_idle_ms() -> Option<u64> {
+    let output = std::process::Command::new("ioreg")
+        .args(["-c", "IOHIDSystem", "-d", "4"])
+        .output()
+        .ok()?;
+    let text = String::from_utf8_lossy(&output.stdout);
+    // `"HIDIdleTime" = 1234567890`, in nanoseconds.
+    let line = text.lines().find(|line| line.contains("\"HIDIdleTime\""))?;
+    let nanos: u64 = line.rsplit('=').next()?.trim().parse().ok()?;
+    Some(nanos / 1_000_000)
+}
+
+/// X11 through `xprintidle`, GNOME on Wayland through Mutter's idle monitor.
+#[cfg(target_os = "linux")]
+fn system_idle_ms() -> Option<u64> {
+    let run = |program: &str, args: &[&str]| -> Option<String> {
+        let output = std::process::Command::new(program)
+            .args(args)
+            .output()
+            .ok()?;
+        output
+            .status
+            .success()
+            .then(|| String::from_utf8_lossy(&output.stdout).to_string())
+    };
+    if let Some(millis) = run("xprintidle", &[]).and_then(|out| out.trim().parse().ok()) {
+        return Some(millis);
+    }
+    // `(uint64 12345,)`
+    let out = run(
+        "gdbus",
+        &[
+            "call",
+            "--session",
+            "--dest",
+            "org.gnome.Mutter.IdleMonitor",
+            "--object-path",
+            "/org/gnome/Mutter/IdleMonitor/Core",
+            "--method",
+            "org.gnome.Mutter.IdleMonitor.GetIdletime",
+        ],
+    )?;
+    out.split_whitespace()
+        .nth(1)?
+        .trim_end_matches([',', ')'])
+        .parse()
+        .ok()
+}
+
+#[cfg(not(any(windows, target_os = "macos", target_os = "linux")))]
+fn system_idle_ms() -> Option<u64> {
+    None
+}
+
+/// One watcher tick. A running timer is cut when the system has seen no input
+/// for `idle_minutes`, or when the previous tick is that long ago because the
+/// machine slept. Otherwise its last activity moves forward.
+fn check_idle(app: &AppHandle, last_check: u64, now: u64, idle_ms: Option<u64>) {
+    let state = app.state::<TimeTrackingState>();
+    let Ok(mut guard) = state.log.lock() else {
+        return;
+    };
+    let Ok(log) = load_log(app, &mut guard, now) else {
+        return;
+    };
+    let threshold = log.idle_minutes * 60_000;
+    let Some(active) = log.active.as_mut().filter(|a| a.running_since.is_some()) else {
+        return;
+    };
+    let idle_since = if threshold == 0 {
+        None
+    } else if now.saturating_sub(last_check) > threshold {
+        Some(last_check)
+    } else {
+        idle_ms
+            .filter(|idle| *idle >= threshold)
+            .map(|idle| now.saturating_sub(idle))
+    };
+
+    match idle_since {
+        Some(idle_since) => {
+            log.pause_idle(idle_since, now);
+            if save_log(app, log).is_ok() {
+                let _ = app.emit("time-tracking-changed", log.status(now));
+            }
+        }
+        None => {
+            let activity = now.saturating_sub(idle_ms.unwrap_or(0));
+            if activity >= active.last_activity + ACTIVITY_SAVE_MS {
+                active.last_activity = activity;
+                let _ = save_log(app, log);
+            }
+        }
+    }
+}
+
+/// Starts the idle watcher unless it is already running. It keeps going for
+/// the rest of the session; ticks without a running timer cost nothing.
+fn watch_idle(app: &AppHandle, state: &TimeTrackingState) -> Result<(), String> {
+    let mut watcher = state.watcher.lock().map_err(|e| e.to_string())?;
+    if watcher.is_none() {
+        let app = app.clone();
+        *watcher = Some(tauri::async_runtime::spawn(async move {
+            let mut last_check = now_millis();
+            loop {
+                tokio::time::sleep(IDLE_CHECK_INTERVAL).await;
+                let running = app
+                    .state::<TimeTrackingState>()
+                    .log
+                    .lock()
+                    .is_ok_and(|log| {
+                        log.as_ref()
+                            .and_then(|log| log.active.as_ref())
+                            .is_some_and(|active| active.running_since.is_some())
+                    });
+                let idle_ms = if running {
+                    tauri::async_runtime::spawn_blocking(system_idle_ms)
+                        .await
+                        .ok()
+                        .flatten()
+                } else {
+                    None
+                };
+                let now = now_millis();
+                check_idle(&app, last_check, now, idle_ms);
+                last_check = now;
+            }
+        }));
+    }
+    Ok(())
+}
+
+fn validate_input(input: &EntryInput) -> Result<(), String> {
+    if input.task.task_id.trim().is_empty() && input.task.task_title.trim().is_empty() {
+        return Err("工时记录需要关联任务".to_string());
+    }
+    if input.end <= input.start {
+        return Err("结束时间必须晚于开始时间".to_string());
+    }
+    if input.end > now_millis() + DAY_MS as u64 {
+        return Err("不能记录未来的工时".to_string());
+    }
+    Ok(())
+}
+
+/// Local midnight (as a UTC timestamp) of the day `date` names.
+fn local_day_start(date: &str, offset_ms: i64) -> Option<i64> {
+    crate::date_days(date).map(|days| days * DAY_MS - offset_ms)
+}
+
+fn local_date(millis: i64, offset_ms: i64) -> String {
+    let (year, month, day, ..) = crate::utc_date_time((millis + offset_ms).max(0) as u64);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+fn local_time(millis: u64, offset_ms: i64) -> String {
+    let (year, month, day, hour, minute, _) =
+        crate::utc_date_time((millis as i64 + offset_ms).max(0) as u64);
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}",
+        year, month, day, hour, minute
+    )
+}
+
+/// The query range as UTC timestamps `[from, to)`.
+fn range_bounds(query: &TimeQuery) -> Result<(u64, u64), String> {
+    let offset = query.utc_offset_minutes * 60_000;
+    let from = local_day_start(&query.from, offset).ok_or("开始日期格式无效")?;
+    let to = local_day_start(&query.to, offset).ok_or("结束日期格式无效")? + DAY_MS;
+    Ok((from.max(0) as u64, to.max(0) as u64))
+}
+
+fn matches_query(entry: &TimeEntry, query: &TimeQuery, (from, to): (u64, u64)) -> bool {
+    let task_id = query.task_id.as_deref().filter(|id| !id.is_empty());
+    task_id.is_none_or(|id| entry.task.task_id == id) && entry.end > from && entry.start < to
+}
+
+/// Entries overlapping the query range, clipped to it, oldest first.
+fn entries_in_range(entries: Vec<TimeEntry>, query: &TimeQuery) -> Result<Vec<TimeEntry>, String> {
+    let bounds = range_bounds(query)?;
+    let mut selected: Vec<TimeEntry> = entries
+        .into_iter()
+        .filter(|entry| matches_query(entry, query, bounds))
+        .map(|mut entry| {
+            entry.start = entry.start.max(bounds.0);
+            entry.end = entry.end.min(bounds.1);
+            entry
+        })
+        .collect();
+    selected.sort_by_key(|entry| entry.start);
+    Ok(selected)
+}
+
+/// Splits an entry at local midnights into (date, milliseconds) pieces.
+fn split_by_day(entry: &TimeEntry, offset_ms: i64) -> Vec<(String, u64)> {
+    let mut pieces = Vec::new();
+    let mut start = entry.start as i64;
+    let end = entry.end as i64;
+    while start < end {
+        let local = start + offset_ms;
+        let next_midnight = (local.div_euclid(DAY_MS) + 1) * DAY_MS - offset_ms;
+        let piece_end = next_midnight.min(end);
+        pieces.push((local_date(start, offset_ms), (piece_end - start) as u64));
+        start = piece_end;
+    }
+    pieces
+}
+
+/// Monday of the week `date` falls in.
+fn week_start(date: &str) -> String {
+    let Some(days) = crate::date_days(date) else {
+        return date.to_string();
+    };
+    // 1970-01-01 was a Thursday.
+    let monday = days - (days + 3).rem_euclid(7);
+    local_date(monday * DAY_MS, 0)
+}
+
+fn summarize(entries: &[TimeEntry], query: &TimeQuery) -> Result<TimeSummary, String> {
+    let offset = query.utc_offset_minutes * 60_000;
+    let group_by = match query.group_by.as_str() {
+        "" => "day",
+        "day" | "week" | "project" | "tag" | "task" => query.group_by.as_str(),
+        other => return Err(format!("不支持的分组方式: {}", other)),
+    };
+    // key -> (label, total, entry ids counted)
+    let mut groups: BTreeMap<String, (String, u64, usize)> = BTreeMap::new();
+    let mut add = |key: String, label: String, millis: u64, first_piece: bool| {
+        let group = groups.entry(key).or_insert((label, 0, 0));
+        group.1 += millis;
+        if first_piece {
+            group.2 += 1;
+        }
+    };
+    for entry in entries {
+        match group_by {
+            "day" | "week" => {
+                let mut seen = Vec::new();
+                for (date, millis) in split_by_day(entry, offset) {
+                    let key = if group_by == "week" {
+                        week_start(&date)
+                    } else {
+                        date
+                    };
+                    let first = !seen.contains(&key);
+                    seen.push(key.clone());
+                    add(key.clone(), key, millis, first);
+                }
+            }
+            "project" => {
+                let project = entry.task.project.trim().to_string();
+                add(project.clone(), project, entry.duration(), true);
+            }
+            "tag" => {
+                if entry.task.tags.is_empty() {
+                    add(String::new(), String::new(), entry.duration(), true);
+                }
+                for tag in &entry.task.tags {
+                    add(tag.clone(), tag.clone(), entry.duration(), true);
+                }
+            }
+            _ => {
+                let key = if entry.task.task_id.is_empty() {
+                    entry.task.task_title.clone()
+                } else {
+                    entry.task.task_id.clone()
+                };
+                add(key, entry.task.task_title.clone(), entry.duration(), true);
+            }
+        }
+    }
+
+    let mut groups: Vec<TimeGroup> = groups
+        .into_iter()
+        .map(|(key, (label, total_ms, entries))| TimeGroup {
+            key,
+            label,
+            total_ms,
+            entries,
+        })
+        .collect();
+    // Dates read best in order; everything else by time spent.
+    if !matches!(group_by, "day" | "week") {
+        groups.sort_by(|a, b| b.total_ms.cmp(&a.total_ms).then(a.key.cmp(&b.key)));
+    }
+    Ok(TimeSummary {
+        group_by: group_by.to_string(),
+        groups,
+        total_ms: entries.iter().map(TimeEntry::duration).sum(),
+    })
+}
+
+fn hours(millis: u64) -> String {
+    format!("{:.2}", millis as f64 / 3_600_000.0)
+}
+
+/// Text as a CSV cell that spreadsheets will not evaluate: a leading `=`, `+`,
+/// `-` or `@` (or a tab or carriage return) would start a formula.
+fn text_cell(text: &str) -> Cow<'_, str> {
+    if text.starts_with(['=', '+', '-', '@', '\t', '\r']) {
+        Cow::Owned(format!("'{}", text))
+    } else {
+        Cow::Borrowed(text)
+    }
+}
+
+fn timesheet_csv(entries: &[TimeEntry], export: &TimesheetExport) -> Result<Vec<u8>, String> {
+    let labels = &export.labels;
+    let offset = export.query.utc_offset_minutes * 60_000;
+    // Excel only detects UTF-8 with a byte order mark.
+    let mut writer = csv::Writer::from_writer(b"\xEF\xBB\xBF".to_vec());
+    let csv_error = |e: csv::Error| format!("生成 CSV 失败: {}", e);
+    if export.detailed {
+        writer
+            .write_record([
+                &labels.date,
+                &labels.start,
+                &labels.end,
+                &labels.hours,
+                &labels.task,
+                &labels.project,
+                &labels.tags,
+                &labels.note,
+                &labels.source,
+            ])
+            .map_err(csv_error)?;
+        for entry in entries {
+            let start = local_time(entry.start, offset);
+            let end = local_time(entry.end, offset);
+            writer
+                .write_record([
+                    &start[..10],
+                    &start[11..],
+                    &end[11..],
+                    &hours(entry.duration()),
+                    &text_cell(&entry.task.task_title),
+                    &text_cell(&entry.task.project),
+                    &text_cell(&entry.task.tags.join(" ")),
+                    &text_cell(&entry.note),
+                    &entry.source,
+                ])
+                .map_err(csv_error)?;
+        }
+    } else {
+        let summary = summarize(entries, &export.query)?;
+        writer
+            .write_record([&labels.group, &labels.hours, &labels.entries])
+            .map_err(csv_error)?;
+        for group in &summary.groups {
+            let label = if group.label.is_empty() {
+                &labels.none
+            } else {
+                &group.label
+            };
+            writer
+                .write_record([
+                    text_cell(label).as_ref(),
+                    &hours(group.total_ms),
+                    &group.entries.to_string(),
+                ])
+                .map_err(csv_error)?;
+        }
+    }
+    writer
+        .into_inner()
+        .map_err(|e| format!("生成 CSV 失败: {}", e))
+}
+
+#[tauri::command]
+pub fn get_time_tracking(
+    app: AppHandle,
+    state: State<'_, TimeTrackingState>,
+) -> Result<TimeTrackingStatus, String> {
+    watch_idle(&app, &state)?;
+    read_log(&app, &state, |log, now| log.status(now))
+}
+
+/// Starts timing `task`. Another task's timer is stopped first; the same
+/// task's paused timer is resumed.
+#[tauri::command]
+pub fn start_timer(
+    app: AppHandle,
+    state: State<'_, TimeTrackingState>,
+    task: TaskRef,
+) -> Result<TimeTrackingStatus, String> {
+    if task.task_id.trim().is_empty() {
+        return Err("缺少任务 ID".to_string());
+    }
+    watch_idle(&app, &state)?;
+    update_log(&app, &state, |log, now| {
+        let same_task = log
+            .active
+            .as_ref()
+            .is_some_and(|active| active.task.task_id == task.task_id);
+        if same_task {
+            if let Some(active) = log.active.as_mut() {
+                active.task = task;
+                active.running_since.get_or_insert(now);
+                active.last_activity = now;
+                active.idle_paused = false;
+            }
+        } else {
+            log.close_stretch(now);
+            log.active = Some(ActiveTimer {
+                task,
+                started_at: now,
+                running_since: Some(now),
+                logged_ms: 0,
+                last_activity: now,
+                idle_paused: false,
+            });
+        }
+        Ok(log.status(now))
+    })
+}
+
+#[tauri::command]
+pub fn pause_timer(
+    app: AppHandle,
+    state: State<'_, TimeTrackingState>,
+) -> Result<TimeTrackingStatus, String> {
+    update_log(&app, &state, |log, now| {
+        log.close_stretch(now);
+        Ok(log.status(now))
+    })
+}
+
+#[tauri::command]
+pub fn resume_timer(
+    app: AppHandle,
+    state: State<'_, TimeTrackingState>,
+) -> Result<TimeTrackingStatus, String> {
+    watch_idle(&app, &state)?;
+    update_log(&app, &state, |log, now| {
+        let active = log.active.as_mut().ok_or("当前没有计时中的任务")?;
+        active.running_since.get_or_insert(now);
+        active.last_activity = now;
+        active.idle_paused = false;
+        Ok(log.status(now))
+    })
+}
+
+#[tauri::command]
+pub fn stop_timer(
+    app: AppHandle,
+    state: State<'_, TimeTrackingState>,
+) -> Result<TimeTrackingStatus, String> {
+    update_log(&app, &state, |log, now| {
+        log.close_stretch(now);
+        log.active = None;
+        Ok(log.status(now))
+    })
+}
+
+/// Idle detection hook: the user has been away since `idle_since`, so the
+/// running stretch ends there and the timer pauses until resumed.
+#[tauri::command]
+pub fn mark_timer_idle(
+    app: AppHandle,
+    state: State<'_, TimeTrackingState>,
+    idle_since: u64,
+) -> Result<TimeTrackingStatus, String> {
+    update_log(&app, &state, |log, now| {
+        log.pause_idle(idle_since, now);
+        Ok(log.status(now))
+    })
+}
+
+#[tauri::command]
+pub fn set_idle_minutes(
+    app: AppHandle,
+    state: State<'_, TimeTrackingState>,
+    minutes: u64,
+) -> Result<TimeTrackingStatus, String> {
+    update_log(&app, &state, |log, now| {
+        log.idle_minutes = minutes.min(24 * 60);
+        Ok(log.status(now))
+    })
+}
+
+#[tauri::command]
+pub fn add_time_entry(
+    app: AppHandle,
+    state: State<'_, TimeTrackingState>,
+    entry: EntryInput,
+) -> Result<TimeEntry, String> {
+    validate_input(&entry)?;
+    update_log(&app, &state, |log, _| {
+        let entry = TimeEntry {
+            id: entry_id(&entry.task.task_id, entry.start),
+            task: entry.task,
+            start: entry.start,
+            end: entry.end,
+            note: entry.note.trim().to_string(),
+            source: "manual".to_string(),
+        };
+        log.entries.push(entry.clone());
+        Ok(entry)
+    })
+}
+
+#[tauri::command]
+pub fn update_time_entry(
+    app: AppHandle,
+    state: State<'_, TimeTrackingState>,
+    id: String,
+    entry: EntryInput,
+) -> Result<TimeEntry, String> {
+    validate_input(&entry)?;
+    update_log(&app, &state, |log, _| {
+        let existing = log
+            .entries
+            .iter_mut()
+            .find(|existing| existing.id == id)
+            .ok_or("工时记录不存在")?;
+        existing.task = entry.task;
+        existing.start = entry.start;
+        existing.end = entry.end;
+        existing.note = entry.note.trim().to_string();
+        Ok(existing.clone())
+    })
+}
+
+#[tauri::command]
+pub fn delete_time_entry(
+    app: AppHandle,
+    state: State<'_, TimeTrackingState>,
+    id: String,
+) -> Result<(), String> {
+    update_log(&app, &state, |log, _| {
+        let before = log.entries.len();
+        log.entries.retain(|entry| entry.id != id);
+        if log.entries.len() == before {
+            return Err("工时记录不存在".to_string());
+        }
+        Ok(())
+    })
+}
+
+/// Entries in the range, newest first; unlike the totals they are not clipped.
+#[tauri::command]
+pub fn list_time_entries(
+    app: AppHandle,
+    state: State<'_, TimeTrackingState>,
+    query: TimeQuery,
+) -> Result<Vec<TimeEntry>, String> {
+    let bounds = range_bounds(&query)?;
+    let mut listed = read_log(&app, &state, |log, _| {
+        log.entries
+            .iter()
+            .filter(|entry| matches_query(entry, &query, bounds))
+            .cloned()
+            .collect::<Vec<_>>()
+    })?;
+    listed.sort_by_key(|entry| std::cmp::Reverse(entry.start));
+    Ok(listed)
+}
+
+/// Time per day, week (keyed by its Monday), project, tag or task within the
+/// range, counting the running timer up to now.
+#[tauri::command]
+pub fn summarize_time(
+    app: AppHandle,
+    state: State<'_, TimeTrackingState>,
+    query: TimeQuery,
+) -> Result<TimeSummary, String> {
+    let entries = read_log(&app, &state, |log, now| log.entries_with_running(now))?;
+    summarize(&entries_in_range(entries, &query)?, &query)
+}
+
+/// Writes the range as CSV, grouped like `summarize_time` or one row per
+/// entry, and saves it. `None` means the save dialog was cancelled.
+#[tauri::command]
+pub async fn export_timesheet_csv(
+    app: AppHandle,
+    state: State<'_, TimeTrackingState>,
+    export: TimesheetExport,
+) -> Result<Option<downloads::SavedFile>, String> {
+    let entries = read_log(&app, &state, |log, now| log.entries_with_running(now))?;
+    let entries = entries_in_range(entries, &export.query)?;
+    let bytes = timesheet_csv(&entries, &export)?;
+    let filename = match export.filename.trim() {
+        "" => format!("timesheet_{}_{}.csv", export.query.from, export.query.to),
+        name => name.to_string(),
+    };
+    let options = SaveOptions {
+        mime: "text/csv".to_string(),
+        dialog: export.save.dialog,
+        reveal: export.save.reveal,
+    };
+    downloads::save_bytes(&app, &filename, &bytes, &options).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const HOUR: u64 = 3_600_000;
+    const MINUTE: u64 = 60_000;
+
+    /// Milliseconds of `hour`:00 UTC on `date`.
+    fn at(date: &str, hour: u64) -> u64 {
+        crate::date_days(date).unwrap() as u64 * DAY_MS as u64 + hour * HOUR
+    }
+
+    fn entry(title: &str, project: &str, tags: &[&str], start: u64, end: u64) -> TimeEntry {
+        TimeEntry {
+            id: entry_id(title, start),
+            task: TaskRef {
+                task_id: title.to_lowercase(),
+                task_title: title.to_string(),
+                project: project.to_string(),
+                tags: tags.iter().map(|tag| tag.to_string()).collect(),
+            },
+            start,
+            end,
+            note: String::new(),
+            source: "manual".to_string(),
+        }
+    }
+
+    fn query(group_by: &str, offset_minutes: i64) -> TimeQuery {
+        serde_json::from_value(json!({
+            "from": "2024-03-01",
+            "to": "2024-03-31",
+            "groupBy": group_by,
+            "utcOffsetMinutes": offset_minutes,
+        }))
+        .unwrap()
+    }
+
+    fn groups(summary: &TimeSummary) -> Vec<(&str, u64, usize)> {
+        summary
+            .groups
+            .iter()
+            .map(|group| (group.key.as_str(), group.total_ms / MINUTE, group.entries))
+            .collect()
+    }
+
+    fn running_log(since: u64, last_activity: u64) -> TimeLog {
+        TimeLog {
+            active: Some(ActiveTimer {
+                task: TaskRef::default(),
+                started_at: since,
+                running_since: Some(since),
+                logged_ms: 0,
+                last_activity,
+                idle_paused: false,
+            }),
+            ..Default::default()
+        }
+    }
+
+    fn csv_text(entries: &[TimeEntry], detailed: bool) -> String {
+        let export: TimesheetExport = serde_json::from_value(json!({
+            "from": "2024-03-01",
+            "to": "2024-03-31",
+            "groupBy": "project",
+            "utcOffsetMinutes": 480,
+            "detailed": detailed,
+        }))
+        .unwrap();
+        let bytes = timesheet_csv(entries, &export).unwrap();
+        assert!(bytes.starts_with(b"\xEF\xBB\xBF"));
+        String::from_utf8(bytes[3..].to_vec()).unwrap()
+    }
+
+    #[test]
+    fn entries_split_at_local_midnight() {
+        // Sunday 22:00 to Monday 02:00 UTC.
+        let late = entry("Deploy", "", &[], at("2024-03-10", 22), at("2024-03-11", 2));
+        assert_eq!(
+            split_by_day(&late, 0),
+            [
+                ("2024-03-10".to_string(), 2 * HOUR),
+                ("2024-03-11".to_string(), 2 * HOUR)
+            ]
+        );
+        // 06:00 to 10:00 in UTC+8 stays on one day.
+        assert_eq!(
+            split_by_day(&late, 480 * MINUTE as i64),
+            [("2024-03-11".to_string(), 4 * HOUR)]
+        );
+        // 04:00 to 06:00 UTC is 23:00 to 01:00 in UTC-5.
+        let early = entry("Deploy", "", &[], at("2024-03-10", 4), at("2024-03-10", 6));
+        assert_eq!(
+            split_by_day(&early, -300 * MINUTE as i64),
+            [
+                ("2024-03-09".to_string(), HOUR),
+                ("2024-03-10".to_string(), HOUR)
+            ]
+        );
+    }
+
+    #[test]
+    fn day_and_week_totals_follow_the_utc_offset() {
+        let entries = [entry(
+            "Deploy",
+            "",
+            &[],
+            at("2024-03-10", 22),
+            at("2024-03-11", 2),
+        )];
+        let days = summarize(&entries, &query("day", 0)).unwrap();
+        assert_eq!(
+            groups(&days),
+            [("2024-03-10", 120, 1), ("2024-03-11", 120, 1)]
+        );
+        // The Sunday evening belongs to the week starting Monday 4 March.
+        let weeks = summarize(&entries, &query("week", 0)).unwrap();
+        assert_eq!(
+            groups(&weeks),
+            [("2024-03-04", 120, 1), ("2024-03-11", 120, 1)]
+        );
+        assert_eq!(weeks.total_ms, 4 * HOUR);
+        let weeks = summarize(&entries, &query("week", 480)).unwrap();
+        assert_eq!(groups(&weeks), [("2024-03-11", 240, 1)]);
+    }
+
+    #[test]
+    fn project_tag_and_task_totals() {
+        let entries = [
+            entry("Spec", "alpha", &["docs", "review"], 0, HOUR),
+            entry("Build", "beta", &["code"], HOUR, 4 * HOUR),
+            entry("Spec", "alpha", &[], 4 * HOUR, 5 * HOUR),
+        ];
+        let projects = summarize(&entries, &query("project", 0)).unwrap();
+        assert_eq!(groups(&projects), [("beta", 180, 1), ("alpha", 120, 2)]);
+        let tags = summarize(&entries, &query("tag", 0)).unwrap();
+        assert_eq!(
+            groups(&tags),
+            [
+                ("code", 180, 1),
+                ("", 60, 1),
+                ("docs", 60, 1),
+                ("review", 60, 1)
+            ]
+        );
+        assert_eq!(tags.total_ms, 5 * HOUR);
+        let tasks = summarize(&entries, &query("task", 0)).unwrap();
+        assert_eq!(groups(&tasks), [("build", 180, 1), ("spec", 120, 2)]);
+        assert!(summarize(&entries, &query("month", 0)).is_err());
+    }
+
+    #[test]
+    fn ranges_are_clipped_to_local_days() {
+        let query: TimeQuery = serde_json::from_value(json!({
+            "from": "2024-03-11",
+            "to": "2024-03-11",
+            "utcOffsetMinutes": 480,
+        }))
+        .unwrap();
+        // Local 2024-03-11 in UTC+8 runs from 16:00 UTC the day before.
+        let entries = vec![
+            entry("Early", "", &[], at("2024-03-10", 15), at("2024-03-10", 17)),
+            entry("Late", "", &[], at("2024-03-11", 15), at("2024-03-11", 18)),
+            entry(
+                "Before",
+                "",
+                &[],
+                at("2024-03-10", 10),
+                at("2024-03-10", 16),
+            ),
+        ];
+        let selected = entries_in_range(entries, &query).unwrap();
+        let spans: Vec<_> = selected
+            .iter()
+            .map(|entry| (entry.task.task_title.as_str(), entry.start, entry.end))
+            .collect();
+        assert_eq!(
+            spans,
+            [
+                ("Early", at("2024-03-10", 16), at("2024-03-10", 17)),
+                ("Late", at("2024-03-11", 15), at("2024-03-11", 16)),
+            ]
+        );
+    }
+
+    #[test]
+    fn a_stale_timer_is_cut_at_its_last_activity() {
+        let start = at("2024-03-10", 9);
+        let mut log = running_log(start, start + 5 * MINUTE);
+        assert!(!log.cut_stale_timer(start + 15 * MINUTE));
+        assert!(log.cut_stale_timer(start + 16 * MINUTE));
+        let active = log.active.as_ref().unwrap();
+        assert!(active.running_since.is_none() && active.idle_paused);
+        assert_eq!(active.logged_ms, 5 * MINUTE);
+        assert_eq!(log.entries.len(), 1);
+        assert_eq!(
+            (log.entries[0].start, log.entries[0].end),
+            (start, start + 5 * MINUTE)
+        );
+        // Already paused: nothing more to cut.
+        assert!(!log.cut_stale_timer(start + HOUR));
+
+        let mut log = running_log(start, start);
+        log.idle_minutes = 0;
+        assert!(!log.cut_stale_timer(start + HOUR));
+    }
+
+    #[test]
+    fn idle_pauses_end_the_stretch_where_input_stopped() {
+        let start = at("2024-03-10", 9);
+        let mut log = running_log(start, start);
+        log.pause_idle(start + 20 * MINUTE, start + 30 * MINUTE);
+        assert_eq!(log.entries[0].end, start + 20 * MINUTE);
+        assert!(log.active.as_ref().unwrap().idle_paused);
+
+        // Idle since before the stretch started: too short to book.
+        let mut log = running_log(start, start);
+        log.pause_idle(start - HOUR, start + 500);
+        assert!(log.entries.is_empty());
+        assert!(log.active.as_ref().unwrap().running_since.is_none());
+    }
+
+    #[test]
+    fn timesheet_rows_are_local_and_formula_safe() {
+        let mut spec = entry(
+            "=1+2",
+            "+alpha",
+            &["-x", "y"],
+            at("2024-03-10", 22),
+            at("2024-03-10", 23) + 30 * MINUTE,
+        );
+        spec.note = "@ref, see spec".to_string();
+        let plain = entry("Build", "", &[], at("2024-03-11", 1), at("2024-03-11", 2));
+        let entries = [spec, plain];
+
+        assert_eq!(
+            csv_text(&entries, true),
+            "日期,开始,结束,工时(小时),任务,项目,标签,备注,来源\n\
+             2024-03-11,06:00,07:30,1.50,'=1+2,'+alpha,'-x y,\"'@ref, see spec\",manual\n\
+             2024-03-11,09:00,10:00,1.00,Build,,,,manual\n"
+        );
+        assert_eq!(
+            csv_text(&entries, false),
+            "分组,工时(小时),记录数\n'+alpha,1.50,1\n未分类,1.00,1\n"
+        );
+    }
+}
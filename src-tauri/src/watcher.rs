@@ -0,0 +1,634 @@
+use notify::event::ModifyKind;
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Emitter, State};
+
+const LINKS_FILE: &str = "file_task_links.json";
+const IGNORED_SEGMENTS: [&str; 4] = [".git", "node_modules", "target", ".svelte-kit"];
+
+#[derive(Default)]
+pub struct FileWatcherState {
+    active: Mutex<Option<ActiveWatcher>>,
+    links: Arc<Mutex<Vec<FileTaskLink>>>,
+    links_loaded: AtomicBool,
+}
+
+struct ActiveWatcher {
+    _watcher: RecommendedWatcher,
+    roots: Vec<PathBuf>,
+    debounce_ms: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct FileTaskLink {
+    task_id: String,
+    path: String,
+    action: String,
+    created_at: u64,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileWatcherStatus {
+    running: bool,
+    roots: Vec<String>,
+    debounce_ms: u64,
+    link_count: usize,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct FileChangeEvent {
+    path: String,
+    root: String,
+    kind: String,
+    timestamp: u64,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct FileTaskTriggerEvent {
+    task_id: String,
+    path: String,
+    action: String,
+    kind: String,
+    timestamp: u64,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum ChangeKind {
+    Created,
+    Modified,
+    Removed,
+}
+
+impl ChangeKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            ChangeKind::Created => "create",
+            ChangeKind::Modified => "modify",
+            ChangeKind::Removed => "delete",
+        }
+    }
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+fn is_ignored(path: &Path) -> bool {
+    path.components().any(|component| {
+        let segment = component.as_os_str().to_string_lossy();
+        IGNORED_SEGMENTS.contains(&segment.as_ref())
+    })
+}
+
+fn classify(kind: &EventKind, path: &Path) -> Option<ChangeKind> {
+    match kind {
+        EventKind::Create(_) => Some(ChangeKind::Created),
+        EventKind::Remove(_) => Some(ChangeKind::Removed),
+        // Renames arrive as separate from/to events; the file's presence tells which side this is.
+        EventKind::Modify(ModifyKind::Name(_)) => Some(if path.exists() {
+            ChangeKind::Created
+        } else {
+            ChangeKind::Removed
+        }),
+        EventKind::Modify(ModifyKind::Metadata(_)) => None,
+        EventKind::Modify(_) | EventKind::Any => Some(ChangeKind::Modified),
+        EventKind::Access(_) | EventKind::Other => None,
+    }
+}
+
+fn merge_change(pending: &mut HashMap<PathBuf, ChangeKind>, path: PathBuf, next: ChangeKind) {
+    match (pending.get(&path).copied(), next) {
+        (Some(ChangeKind::Created), ChangeKind::Modified) => {}
+        (Some(ChangeKind::Created), ChangeKind::Removed) => {
+            pending.remove(&path);
+        }
+        (Some(ChangeKind::Removed), ChangeKind::Created) => {
+            pending.insert(path, ChangeKind::Modified);
+        }
+        _ => {
+            pending.insert(path, next);
+        }
+    }
+}
+
+fn links_path(app: &AppHandle) -> Result<PathBuf, String> {
+    crate::app_data_file(app, LINKS_FILE)
+}
+
+fn read_links(path: &Path) -> Vec<FileTaskLink> {
+    match fs::read_to_string(path) {
+        Ok(raw) => serde_json::from_str(&raw).unwrap_or_default(),
+        Err(_) => Vec::new(),
+    }
+}
+
+fn write_links(path: &Path, links: &[FileTaskLink]) -> Result<(), String> {
+    let data = serde_json::to_string_pretty(links).map_err(|e| e.to_string())?;
+    fs::write(path, data).map_err(|e| format!("保存文件关联失败: {}", e))
+}
+
+fn persist_links(app: &AppHandle, links: &[FileTaskLink]) -> Result<(), String> {
+    write_links(&links_path(app)?, links)
+}
+
+fn ensure_links_loaded(app: &AppHandle, state: &FileWatcherState) -> Result<(), String> {
+    if state.links_loaded.load(Ordering::SeqCst) {
+        return Ok(());
+    }
+    let loaded = read_links(&links_path(app)?);
+    *state.links.lock().map_err(|e| e.to_string())? = loaded;
+    state.links_loaded.store(true, Ordering::SeqCst);
+    Ok(())
+}
+
+/// The triggers for the tasks linked to a changed file. Returns whether a
+/// `complete` link was consumed, in which case the links need saving.
+fn trigger_links(
+    links: &mut Vec<FileTaskLink>,
+    path: &str,
+    kind: ChangeKind,
+    timestamp: u64,
+) -> (Vec<FileTaskTriggerEvent>, bool) {
+    let mut triggers = Vec::new();
+    let mut consumed = false;
+    if kind == ChangeKind::Removed {
+        return (triggers, consumed);
+    }
+    links.retain(|link| {
+        if link.path != path {
+            return true;
+        }
+        triggers.push(FileTaskTriggerEvent {
+            task_id: link.task_id.clone(),
+            path: path.to_string(),
+            action: link.action.clone(),
+            kind: kind.as_str().to_string(),
+            timestamp,
+        });
+        // A completed task has nothing left to wait for, so its link is consumed.
+        if link.action == "complete" {
+            consumed = true;
+            return false;
+        }
+        true
+    });
+    (triggers, consumed)
+}
+
+fn dispatch_changes(
+    app: &AppHandle,
+    roots: &[PathBuf],
+    links: &Arc<Mutex<Vec<FileTaskLink>>>,
+    pending: HashMap<PathBuf, ChangeKind>,
+) {
+    let timestamp = now_millis();
+    let mut fired_completions = false;
+
+    for (path, kind) in pending {
+        let path_text = path.to_string_lossy().to_string();
+        let root = roots
+            .iter()
+            .find(|root| path.starts_with(root))
+            .map(|root| root.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let _ = app.emit(
+            "file-watcher-change",
+            FileChangeEvent {
+                path: path_text.clone(),
+                root,
+                kind: kind.as_str().to_string(),
+                timestamp,
+            },
+        );
+
+        let Ok(mut guard) = links.lock() else {
+            continue;
+        };
+        let (triggers, consumed) = trigger_links(&mut guard, &path_text, kind, timestamp);
+        for trigger in triggers {
+            let _ = app.emit("file-watcher-task-trigger", trigger);
+        }
+        fired_completions |= consumed;
+    }
+
+    if fired_completions {
+        if let Ok(guard) = links.lock() {
+            let _ = persist_links(app, &guard);
+        }
+    }
+}
+
+/// Collects events until none has arrived for `debounce`, then hands the merged
+/// changes to `flush`. Returns once the watcher is dropped.
+fn debounce_events(
+    rx: Receiver<notify::Result<notify::Event>>,
+    debounce: Duration,
+    mut flush: impl FnMut(HashMap<PathBuf, ChangeKind>),
+) {
+    let mut pending: HashMap<PathBuf, ChangeKind> = HashMap::new();
+    let mut last_event = Instant::now();
+
+    loop {
+        let received = if pending.is_empty() {
+            rx.recv().map_err(|_| RecvTimeoutError::Disconnected)
+        } else {
+            rx.recv_timeout(debounce.saturating_sub(last_event.elapsed()))
+        };
+
+        match received {
+            Ok(Ok(event)) => {
+                for path in event.paths {
+                    if is_ignored(&path) {
+                        continue;
+                    }
+                    if let Some(kind) = classify(&event.kind, &path) {
+                        merge_change(&mut pending, path, kind);
+                    }
+                }
+                last_event = Instant::now();
+            }
+            Ok(Err(_)) => {}
+            Err(RecvTimeoutError::Timeout) => flush(std::mem::take(&mut pending)),
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+    }
+}
+
+fn run_debounce_loop(
+    app: AppHandle,
+    rx: Receiver<notify::Result<notify::Event>>,
+    roots: Vec<PathBuf>,
+    links: Arc<Mutex<Vec<FileTaskLink>>>,
+    debounce: Duration,
+) {
+    debounce_events(rx, debounce, |pending| {
+        dispatch_changes(&app, &roots, &links, pending)
+    });
+}
+
+fn build_status(state: &FileWatcherState) -> Result<FileWatcherStatus, String> {
+    let active = state.active.lock().map_err(|e| e.to_string())?;
+    let link_count = state.links.lock().map_err(|e| e.to_string())?.len();
+    Ok(match active.as_ref() {
+        Some(watcher) => FileWatcherStatus {
+            running: true,
+            roots: watcher
+                .roots
+                .iter()
+                .map(|root| root.to_string_lossy().to_string())
+                .collect(),
+            debounce_ms: watcher.debounce_ms,
+            link_count,
+        },
+        None => FileWatcherStatus {
+            running: false,
+            roots: Vec::new(),
+            debounce_ms: 0,
+            link_count,
+        },
+    })
+}
+
+#[tauri::command]
+pub fn start_file_watcher(
+    app: AppHandle,
+    state: State<'_, FileWatcherState>,
+    trusted_dirs: Vec<String>,
+    debounce_ms: Option<u64>,
+) -> Result<FileWatcherStatus, String> {
+    ensure_links_loaded(&app, &state)?;
+
+    let debounce_ms = debounce_ms.unwrap_or(800).clamp(100, 10_000);
+    let mut roots = crate::allowed_write_roots(&trusted_dirs)?;
+    roots.sort();
+    roots.dedup();
+
+    let (tx, rx) = mpsc::channel();
+    let mut watcher =
+        notify::recommended_watcher(tx).map_err(|e| format!("创建文件监听失败: {}", e))?;
+    for root in &roots {
+        if !root.exists() {
+            continue;
+        }
+        watcher
+            .watch(root, RecursiveMode::Recursive)
+            .map_err(|e| format!("监听目录失败 {}: {}", root.to_string_lossy(), e))?;
+    }
+
+    let worker_app = app.clone();
+    let worker_roots = roots.clone();
+    let worker_links = state.links.clone();
+    std::thread::spawn(move || {
+        run_debounce_loop(
+            worker_app,
+            rx,
+            worker_roots,
+            worker_links,
+            Duration::from_millis(debounce_ms),
+        )
+    });
+
+    // Replacing the previous watcher drops its sender, which ends the old worker thread.
+    *state.active.lock().map_err(|e| e.to_string())? = Some(ActiveWatcher {
+        _watcher: watcher,
+        roots,
+        debounce_ms,
+    });
+
+    build_status(&state)
+}
+
+#[tauri::command]
+pub fn stop_file_watcher(state: State<'_, FileWatcherState>) -> Result<FileWatcherStatus, String> {
+    state.active.lock().map_err(|e| e.to_string())?.take();
+    build_status(&state)
+}
+
+#[tauri::command]
+pub fn get_file_watcher_status(
+    app: AppHandle,
+    state: State<'_, FileWatcherState>,
+) -> Result<FileWatcherStatus, String> {
+    ensure_links_loaded(&app, &state)?;
+    build_status(&state)
+}
+
+#[tauri::command]
+pub fn attach_task_to_file(
+    app: AppHandle,
+    state: State<'_, FileWatcherState>,
+    task_id: String,
+    path: String,
+    action: Option<String>,
+    trusted_dirs: Vec<String>,
+) -> Result<FileTaskLink, String> {
+    ensure_links_loaded(&app, &state)?;
+
+    let task_id = task_id.trim().to_string();
+    if task_id.is_empty() {
+        return Err("任务 ID 不能为空".to_string());
+    }
+    let action = match action.as_deref().map(str::trim) {
+        None | Some("") | Some("annotate") => "annotate",
+        Some("complete") => "complete",
+        Some(other) => return Err(format!("不支持的关联动作: {}", other)),
+    };
+
+    let normalized = crate::normalize_path(&path)?;
+    let roots = crate::allowed_write_roots(&trusted_dirs)?;
+    if !roots.iter().any(|root| normalized.starts_with(root)) {
+        return Err(format!(
+            "只能关联工作目录或授权目录中的文件：{}",
+            normalized.to_string_lossy()
+        ));
+    }
+
+    let link = FileTaskLink {
+        task_id,
+        path: normalized.to_string_lossy().to_string(),
+        action: action.to_string(),
+        created_at: now_millis(),
+    };
+
+    let mut links = state.links.lock().map_err(|e| e.to_string())?;
+    links.retain(|item| !(item.task_id == link.task_id && item.path == link.path));
+    links.push(link.clone());
+    persist_links(&app, &links)?;
+
+    Ok(link)
+}
+
+#[tauri::command]
+pub fn detach_task_from_file(
+    app: AppHandle,
+    state: State<'_, FileWatcherState>,
+    task_id: String,
+    path: Option<String>,
+) -> Result<usize, String> {
+    ensure_links_loaded(&app, &state)?;
+
+    let target_path = match path {
+        Some(value) if !value.trim().is_empty() => {
+            Some(crate::normalize_path(&value)?.to_string_lossy().to_string())
+        }
+        _ => None,
+    };
+
+    let mut links = state.links.lock().map_err(|e| e.to_string())?;
+    let before = links.len();
+    links.retain(|item| {
+        item.task_id != task_id
+            || target_path
+                .as_ref()
+                .is_some_and(|target| &item.path != target)
+    });
+    let removed = before - links.len();
+    if removed > 0 {
+        persist_links(&app, &links)?;
+    }
+
+    Ok(removed)
+}
+
+#[tauri::command]
+pub fn list_file_task_links(
+    app: AppHandle,
+    state: State<'_, FileWatcherState>,
+) -> Result<Vec<FileTaskLink>, String> {
+    ensure_links_loaded(&app, &state)?;
+    let links = state.links.lock().map_err(|e| e.to_string())?;
+    Ok(links.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+    use notify::event::{AccessKind, CreateKind, DataChange, MetadataKind, RemoveKind, RenameMode};
+
+    fn event(kind: EventKind, path: &str) -> notify::Result<notify::Event> {
+        Ok(notify::Event::new(kind).add_path(PathBuf::from(path)))
+    }
+
+    fn link(task_id: &str, path: &str, action: &str) -> FileTaskLink {
+        FileTaskLink {
+            task_id: task_id.to_string(),
+            path: path.to_string(),
+            action: action.to_string(),
+            created_at: 1,
+        }
+    }
+
+    fn kinds(pending: &HashMap<PathBuf, ChangeKind>) -> Vec<(String, &'static str)> {
+        let mut kinds: Vec<_> = pending
+            .iter()
+            .map(|(path, kind)| (path.to_string_lossy().to_string(), kind.as_str()))
+            .collect();
+        kinds.sort();
+        kinds
+    }
+
+    #[test]
+    fn classify_maps_notify_events_to_changes() {
+        let path = Path::new("/no/such/file.md");
+        let kind = |kind: EventKind| classify(&kind, path).map(ChangeKind::as_str);
+        assert_eq!(kind(EventKind::Create(CreateKind::File)), Some("create"));
+        assert_eq!(kind(EventKind::Remove(RemoveKind::Any)), Some("delete"));
+        assert_eq!(
+            kind(EventKind::Modify(ModifyKind::Data(DataChange::Content))),
+            Some("modify")
+        );
+        assert_eq!(kind(EventKind::Any), Some("modify"));
+        assert_eq!(
+            kind(EventKind::Modify(ModifyKind::Metadata(MetadataKind::Any))),
+            None
+        );
+        assert_eq!(kind(EventKind::Access(AccessKind::Any)), None);
+
+        // A rename counts as a creation where the file now exists.
+        let dir = TempDir::new("watcher-classify");
+        let renamed = dir.path().join("renamed.md");
+        fs::write(&renamed, "").unwrap();
+        let rename = EventKind::Modify(ModifyKind::Name(RenameMode::Any));
+        assert_eq!(
+            classify(&rename, &renamed).map(ChangeKind::as_str),
+            Some("create")
+        );
+        assert_eq!(
+            classify(&rename, path).map(ChangeKind::as_str),
+            Some("delete")
+        );
+    }
+
+    #[test]
+    fn merge_change_folds_changes_within_the_window() {
+        let mut pending = HashMap::new();
+        let (a, b, c) = (
+            PathBuf::from("/a"),
+            PathBuf::from("/b"),
+            PathBuf::from("/c"),
+        );
+        // Created, edited and removed again: nothing to report.
+        merge_change(&mut pending, a.clone(), ChangeKind::Created);
+        merge_change(&mut pending, a.clone(), ChangeKind::Modified);
+        assert_eq!(pending.get(&a).map(|kind| kind.as_str()), Some("create"));
+        merge_change(&mut pending, a.clone(), ChangeKind::Removed);
+        assert!(!pending.contains_key(&a));
+        // Replaced by an editor's save-as-rename: a modification.
+        merge_change(&mut pending, b.clone(), ChangeKind::Removed);
+        merge_change(&mut pending, b.clone(), ChangeKind::Created);
+        merge_change(&mut pending, c.clone(), ChangeKind::Modified);
+        merge_change(&mut pending, c.clone(), ChangeKind::Removed);
+        assert_eq!(
+            kinds(&pending),
+            vec![("/b".to_string(), "modify"), ("/c".to_string(), "delete")]
+        );
+    }
+
+    #[test]
+    fn debounce_flushes_merged_changes_once_events_settle() {
+        let (tx, rx) = mpsc::channel();
+        let (batches, received) = mpsc::channel();
+        let worker = std::thread::spawn(move || {
+            debounce_events(rx, Duration::from_millis(150), |pending| {
+                batches.send(kinds(&pending)).unwrap();
+            })
+        });
+
+        let modify = EventKind::Modify(ModifyKind::Data(DataChange::Content));
+        tx.send(event(EventKind::Create(CreateKind::File), "/w/a.md"))
+            .unwrap();
+        tx.send(event(modify, "/w/a.md")).unwrap();
+        tx.send(event(EventKind::Remove(RemoveKind::File), "/w/a.md"))
+            .unwrap();
+        tx.send(event(EventKind::Create(CreateKind::File), "/w/b.md"))
+            .unwrap();
+        tx.send(event(modify, "/w/node_modules/pkg/index.md"))
+            .unwrap();
+        tx.send(Err(notify::Error::generic("overflow"))).unwrap();
+        let first = received.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(first, vec![("/w/b.md".to_string(), "create")]);
+
+        tx.send(event(modify, "/w/b.md")).unwrap();
+        let second = received.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(second, vec![("/w/b.md".to_string(), "modify")]);
+
+        drop(tx);
+        worker.join().unwrap();
+        assert!(received.try_recv().is_err());
+    }
+
+    #[test]
+    fn complete_links_fire_once_and_are_consumed() {
+        let mut links = vec![
+            link("t1", "/w/report.md", "complete"),
+            link("t2", "/w/report.md", "annotate"),
+            link("t3", "/w/other.md", "complete"),
+        ];
+        let (triggers, consumed) =
+            trigger_links(&mut links, "/w/report.md", ChangeKind::Removed, 5);
+        assert!(triggers.is_empty() && !consumed);
+        assert_eq!(links.len(), 3);
+
+        let (triggers, consumed) =
+            trigger_links(&mut links, "/w/report.md", ChangeKind::Modified, 5);
+        let fired: Vec<_> = triggers
+            .iter()
+            .map(|trigger| {
+                (
+                    trigger.task_id.as_str(),
+                    trigger.action.as_str(),
+                    trigger.kind.as_str(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            fired,
+            vec![("t1", "complete", "modify"), ("t2", "annotate", "modify")]
+        );
+        assert!(consumed);
+        let remaining: Vec<_> = links.iter().map(|link| link.task_id.as_str()).collect();
+        assert_eq!(remaining, vec!["t2", "t3"]);
+
+        // The annotation keeps firing; nothing is left to consume.
+        let (triggers, consumed) =
+            trigger_links(&mut links, "/w/report.md", ChangeKind::Created, 6);
+        assert_eq!(triggers.len(), 1);
+        assert!(!consumed);
+    }
+
+    #[test]
+    fn links_survive_a_restart() {
+        let dir = TempDir::new("watcher-links");
+        let path = dir.path().join(LINKS_FILE);
+        assert!(read_links(&path).is_empty());
+
+        let mut links = vec![
+            link("t1", "/w/report.md", "complete"),
+            link("t2", "/w/notes.md", "annotate"),
+        ];
+        trigger_links(&mut links, "/w/report.md", ChangeKind::Modified, 5);
+        write_links(&path, &links).unwrap();
+        let loaded = read_links(&path);
+        assert_eq!(loaded.len(), 1);
+        assert_eq!(loaded[0].task_id, "t2");
+        assert_eq!(loaded[0].action, "annotate");
+
+        fs::write(&path, "not json").unwrap();
+        assert!(read_links(&path).is_empty());
+    }
+}
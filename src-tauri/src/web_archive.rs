@@ -0,0 +1,814 @@
+use base64::Engine;
+use scraper::{Html, Node, Selector, StrTendril};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::{AppHandle, State};
+use tokio::sync::Semaphore;
+use url::Url;
+
+use crate::http_cache::HttpCacheState;
+use crate::network::{NetworkConfig, NetworkState};
+use crate::web_content;
+
+const ARCHIVE_DIR: &str = "web_archive";
+const INDEX_FILE: &str = "index.json";
+const MAX_ASSETS: usize = 80;
+const MAX_ASSET_BYTES: usize = 3 * 1024 * 1024;
+const MAX_TOTAL_ASSET_BYTES: usize = 20 * 1024 * 1024;
+/// Assets downloaded at once; they bypass the page cache's per-host spacing.
+const ASSET_CONCURRENCY: usize = 6;
+const ASSET_TIMEOUT: Duration = Duration::from_secs(20);
+const REMOVED_ELEMENTS: &str = "script, noscript, iframe, frame, object, embed, base";
+
+#[derive(Default)]
+pub struct WebArchiveState {
+    entries: Mutex<Option<Vec<WebArchiveEntry>>>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct WebArchiveEntry {
+    id: String,
+    url: String,
+    final_url: String,
+    title: String,
+    format: String,
+    owner_kind: String,
+    owner_id: String,
+    created_at: u64,
+    size: u64,
+    pub(crate) file: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WebArchiveContent {
+    entry: WebArchiveEntry,
+    content: String,
+}
+
+fn now_millis() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+fn archive_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    let dir = crate::app_data_file(app, ARCHIVE_DIR)?;
+    fs::create_dir_all(&dir).map_err(|e| format!("创建存档目录失败: {}", e))?;
+    Ok(dir)
+}
+
+fn read_index(dir: &Path) -> Vec<WebArchiveEntry> {
+    fs::read_to_string(dir.join(INDEX_FILE))
+        .ok()
+        .and_then(|raw| serde_json::from_str(&raw).ok())
+        .unwrap_or_default()
+}
+
+fn load_entries(dir: &Path, state: &WebArchiveState) -> Result<Vec<WebArchiveEntry>, String> {
+    let mut guard = state.entries.lock().map_err(|e| e.to_string())?;
+    Ok(guard.get_or_insert_with(|| read_index(dir)).clone())
+}
+
+/// Applies `change` to the index and saves it without releasing the lock in
+/// between, so that concurrent commands cannot drop each other's entries.
+fn update_entries<T>(
+    dir: &Path,
+    state: &WebArchiveState,
+    change: impl FnOnce(&mut Vec<WebArchiveEntry>) -> Result<T, String>,
+) -> Result<T, String> {
+    let mut guard = state.entries.lock().map_err(|e| e.to_string())?;
+    let mut entries = guard.get_or_insert_with(|| read_index(dir)).clone();
+    let result = change(&mut entries)?;
+    let data = serde_json::to_string_pretty(&entries).map_err(|e| e.to_string())?;
+    fs::write(dir.join(INDEX_FILE), data).map_err(|e| format!("保存存档索引失败: {}", e))?;
+    *guard = Some(entries);
+    Ok(result)
+}
+
+/// Archived pages with their file contents, for the data export.
+pub fn export_archives(
+    app: &AppHandle,
+    state: &WebArchiveState,
+) -> Result<Vec<(WebArchiveEntry, Vec<u8>)>, String> {
+    let dir = archive_dir(app)?;
+    Ok(load_entries(&dir, state)?
+        .into_iter()
+        .filter_map(|entry| {
+            let data = fs::read(dir.join(&entry.file)).ok()?;
+            Some((entry, data))
+        })
+        .collect())
+}
+
+/// Adds imported pages, replacing any with the same id; `replace` drops the
+/// existing pages first. File names are rebuilt from the id so an imported index
+/// cannot point outside the archive directory.
+pub fn import_archives(
+    app: &AppHandle,
+    state: &WebArchiveState,
+    archives: Vec<(WebArchiveEntry, Vec<u8>)>,
+    replace: bool,
+) -> Result<usize, String> {
+    let dir = archive_dir(app)?;
+    update_entries(&dir, state, |entries| {
+        if replace {
+            for entry in entries.drain(..) {
+                let _ = fs::remove_file(dir.join(&entry.file));
+            }
+        }
+        let mut imported = 0;
+        for (mut entry, data) in archives {
+            if entry.id.is_empty() || !entry.id.chars().all(|c| c.is_ascii_hexdigit()) {
+                continue;
+            }
+            let extension = if entry.format == "html" { "html" } else { "md" };
+            entry.file = format!("{}.{}", entry.id, extension);
+            entry.size = data.len() as u64;
+            fs::write(dir.join(&entry.file), &data)
+                .map_err(|e| format!("保存网页存档失败: {}", e))?;
+            entries.retain(|existing| existing.id != entry.id);
+            entries.push(entry);
+            imported += 1;
+        }
+        Ok(imported)
+    })
+}
+
+fn archive_id(url: &str, created_at: u64) -> String {
+    let digest = Sha256::digest(format!("{}#{}", url, created_at).as_bytes());
+    digest[..8]
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+fn data_uri(content_type: &str, body: &[u8]) -> String {
+    let mime = content_type
+        .split(';')
+        .next()
+        .map(str::trim)
+        .filter(|mime| !mime.is_empty())
+        .unwrap_or("application/octet-stream");
+    format!(
+        "data:{};base64,{}",
+        mime,
+        base64::engine::general_purpose::STANDARD.encode(body)
+    )
+}
+
+/// Downloads one asset with the fetch client. Assets skip the HTTP cache and its
+/// per-host spacing, which would take over a minute for a page with many
+/// images, but are checked against the fetch policy like pages.
+async fn fetch_asset(
+    client: reqwest::Client,
+    config: NetworkConfig,
+    url: String,
+    downloaded: Arc<AtomicUsize>,
+) -> Option<(String, Vec<u8>)> {
+    crate::check_fetch_target(&config, &url).await.ok()?;
+    let response = client
+        .get(&url)
+        .timeout(ASSET_TIMEOUT)
+        .send()
+        .await
+        .ok()
+        .filter(|response| response.status().is_success())?;
+    let content_type = response
+        .headers()
+        .get("content-type")
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default()
+        .to_lowercase();
+    let body = crate::network::read_limited(response, MAX_ASSET_BYTES as u64)
+        .await
+        .ok()?;
+    // Bounds what finished downloads hold in memory before they are used.
+    if downloaded.fetch_add(body.len(), Ordering::Relaxed) + body.len() > MAX_TOTAL_ASSET_BYTES {
+        return None;
+    }
+    Some((content_type, body))
+}
+
+/// Replaces each `url(...)` reference in a stylesheet, resolved against `base`,
+/// with what `replace` returns for it; `None` keeps the reference.
+fn rewrite_css_urls(
+    css: &str,
+    base: &Url,
+    mut replace: impl FnMut(&Url) -> Option<String>,
+) -> String {
+    let mut output = String::with_capacity(css.len());
+    let mut rest = css;
+    while let Some(start) = rest.find("url(") {
+        output.push_str(&rest[..start + 4]);
+        rest = &rest[start + 4..];
+        let Some(end) = rest.find(')') else {
+            break;
+        };
+        let raw = rest[..end].trim().trim_matches(|c| c == '"' || c == '\'');
+        let replacement = match base.join(raw) {
+            Ok(resolved) if !raw.starts_with("data:") => {
+                replace(&resolved).map(|uri| format!("\"{}\"", uri))
+            }
+            _ => None,
+        };
+        output.push_str(&replacement.unwrap_or_else(|| rest[..end].to_string()));
+        rest = &rest[end..];
+    }
+    output.push_str(rest);
+    output
+}
+
+fn css_urls(css: &str, base: &Url) -> Vec<String> {
+    let mut urls = Vec::new();
+    rewrite_css_urls(css, base, |url| {
+        urls.push(url.to_string());
+        None
+    });
+    urls
+}
+
+/// Downloads assets a few at a time, then hands them out under shared count
+/// and size budgets; anything over budget keeps its absolute URL.
+struct AssetFetcher {
+    client: reqwest::Client,
+    config: NetworkConfig,
+    fetched: HashMap<String, Option<(String, Vec<u8>)>>,
+    downloaded: Arc<AtomicUsize>,
+    inlined: HashMap<String, Option<String>>,
+    total_bytes: usize,
+}
+
+impl AssetFetcher {
+    fn new(client: reqwest::Client, config: NetworkConfig) -> Self {
+        AssetFetcher {
+            client,
+            config,
+            fetched: HashMap::new(),
+            downloaded: Arc::new(AtomicUsize::new(0)),
+            inlined: HashMap::new(),
+            total_bytes: 0,
+        }
+    }
+
+    /// Downloads the `urls` not seen yet, up to `MAX_ASSETS` in total.
+    async fn prefetch(&mut self, urls: Vec<String>) {
+        let slots = Arc::new(Semaphore::new(ASSET_CONCURRENCY));
+        let mut queued = HashSet::new();
+        let mut tasks = Vec::new();
+        for url in urls {
+            if self.fetched.len() + tasks.len() >= MAX_ASSETS {
+                break;
+            }
+            if self.fetched.contains_key(&url) || !queued.insert(url.clone()) {
+                continue;
+            }
+            let (client, config, downloaded, slots) = (
+                self.client.clone(),
+                self.config.clone(),
+                self.downloaded.clone(),
+                slots.clone(),
+            );
+            let target = url.clone();
+            let task = tauri::async_runtime::spawn(async move {
+                let _slot = slots.acquire_owned().await.ok()?;
+                fetch_asset(client, config, target, downloaded).await
+            });
+            tasks.push((url, task));
+        }
+        for (url, task) in tasks {
+            self.fetched.insert(url, task.await.ok().flatten());
+        }
+    }
+
+    fn take(&mut self, url: &str) -> Option<(String, Vec<u8>)> {
+        let (content_type, body) = self.fetched.get(url)?.clone()?;
+        if self.total_bytes + body.len() > MAX_TOTAL_ASSET_BYTES {
+            return None;
+        }
+        self.total_bytes += body.len();
+        Some((content_type, body))
+    }
+
+    fn inline(&mut self, url: &str) -> Option<String> {
+        if let Some(cached) = self.inlined.get(url) {
+            return cached.clone();
+        }
+        let uri = self
+            .take(url)
+            .map(|(content_type, body)| data_uri(&content_type, &body));
+        self.inlined.insert(url.to_string(), uri.clone());
+        uri
+    }
+
+    /// Inlines `url(...)` references inside a stylesheet, resolved against `base`.
+    fn inline_css(&mut self, css: &str, base: &Url) -> String {
+        rewrite_css_urls(css, base, |url| self.inline(url.as_str()))
+    }
+}
+
+fn attr<'a>(node: &'a Node, name: &str) -> Option<&'a str> {
+    match node {
+        Node::Element(element) => element.attr(name),
+        _ => None,
+    }
+}
+
+fn set_attr(node: &mut Node, name: &str, value: &str) {
+    if let Node::Element(element) = node {
+        for (key, current) in element.attrs.iter_mut() {
+            if &*key.local == name {
+                *current = StrTendril::from(value);
+            }
+        }
+    }
+}
+
+fn strip_attrs(node: &mut Node, predicate: impl Fn(&str) -> bool) {
+    if let Node::Element(element) = node {
+        element.attrs.retain(|(key, _)| !predicate(&key.local));
+    }
+}
+
+/// The attribute of an element that points at an asset to inline, the asset's
+/// absolute URL, and whether it is a stylesheet.
+fn asset_link(node: &Node, base: &Url) -> Option<(&'static str, Url, bool)> {
+    let name = node.as_element()?.name();
+    let rel = attr(node, "rel").unwrap_or("").to_ascii_lowercase();
+    let (attribute, stylesheet) = match name {
+        "img" | "source" | "input" => ("src", false),
+        "link" if rel.contains("stylesheet") => ("href", true),
+        "link" if rel.contains("icon") => ("href", false),
+        _ => return None,
+    };
+    let url = base.join(attr(node, attribute)?).ok()?;
+    Some((attribute, url, stylesheet))
+}
+
+fn style_texts(document: &Html) -> Vec<(ego_tree::NodeId, String)> {
+    let Ok(selector) = Selector::parse("style") else {
+        return Vec::new();
+    };
+    document
+        .select(&selector)
+        .flat_map(|style| style.children())
+        .filter_map(|child| match child.value() {
+            Node::Text(text) => Some((child.id(), text.text.to_string())),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Produces a self-contained copy of the page: scripts and frames removed, images,
+/// stylesheets and icons inlined as data URIs, remaining links made absolute.
+async fn snapshot_html(
+    client: reqwest::Client,
+    config: NetworkConfig,
+    html: &str,
+    page_url: &str,
+) -> String {
+    let mut document = Html::parse_document(html);
+    let Ok(base) = Url::parse(page_url) else {
+        return document.html();
+    };
+    let mut fetcher = AssetFetcher::new(client, config);
+
+    let removed: Vec<_> = Selector::parse(REMOVED_ELEMENTS)
+        .map(|selector| document.select(&selector).map(|el| el.id()).collect())
+        .unwrap_or_default();
+    for id in removed {
+        if let Some(mut node) = document.tree.get_mut(id) {
+            node.detach();
+        }
+    }
+
+    let element_ids: Vec<_> = document
+        .tree
+        .nodes()
+        .filter(|node| node.value().is_element())
+        .map(|node| node.id())
+        .collect();
+    let styles = style_texts(&document);
+
+    // Download everything up front, then what the stylesheets refer to.
+    let links: Vec<_> = element_ids
+        .iter()
+        .filter_map(|id| asset_link(document.tree.get(*id)?.value(), &base))
+        .collect();
+    let mut urls: Vec<String> = links.iter().map(|(_, url, _)| url.to_string()).collect();
+    urls.extend(styles.iter().flat_map(|(_, css)| css_urls(css, &base)));
+    fetcher.prefetch(urls).await;
+    let nested = links
+        .iter()
+        .filter(|(_, _, stylesheet)| *stylesheet)
+        .filter_map(|(_, url, _)| {
+            let (_, body) = fetcher.fetched.get(url.as_str())?.as_ref()?;
+            Some(css_urls(&String::from_utf8_lossy(body), url))
+        })
+        .flatten()
+        .collect();
+    fetcher.prefetch(nested).await;
+
+    for id in element_ids {
+        let Some(node) = document.tree.get(id) else {
+            continue;
+        };
+        let value = node.value();
+        let name = value
+            .as_element()
+            .map(|el| el.name().to_string())
+            .unwrap_or_default();
+
+        let (attribute, replacement) = match asset_link(value, &base) {
+            Some((attribute, url, true)) => {
+                let uri = fetcher.take(url.as_str()).map(|(_, body)| {
+                    let css = fetcher.inline_css(&String::from_utf8_lossy(&body), &url);
+                    data_uri("text/css", css.as_bytes())
+                });
+                (attribute, uri.unwrap_or_else(|| url.to_string()))
+            }
+            Some((attribute, url, false)) => {
+                let uri = fetcher.inline(url.as_str());
+                (attribute, uri.unwrap_or_else(|| url.to_string()))
+            }
+            None if name == "a" || name == "area" => {
+                match attr(value, "href")
+                    .filter(|href| !href.starts_with('#'))
+                    .and_then(|href| base.join(href).ok())
+                {
+                    Some(absolute) => ("href", absolute.to_string()),
+                    None => ("", String::new()),
+                }
+            }
+            None => ("", String::new()),
+        };
+
+        let Some(mut node) = document.tree.get_mut(id) else {
+            continue;
+        };
+        let value = node.value();
+        if !attribute.is_empty() {
+            set_attr(value, attribute, &replacement);
+        }
+        strip_attrs(value, |key| {
+            key.starts_with("on") || key == "srcset" || key == "integrity"
+        });
+    }
+
+    for (text_id, css) in styles {
+        let inlined = fetcher.inline_css(&css, &base);
+        if let Some(mut node) = document.tree.get_mut(text_id) {
+            if let Node::Text(text) = node.value() {
+                text.text = StrTendril::from(inlined.as_str());
+            }
+        }
+    }
+
+    document.html()
+}
+
+fn markdown_document(result: &web_content::WebContentResult, created_at: u64) -> String {
+    let archived_at = format_utc_millis(created_at);
+    let title = if result.title.is_empty() {
+        result.final_url.as_str()
+    } else {
+        result.title.as_str()
+    };
+    let mut header = format!(
+        "# {}\n\n> 来源: {}\n> 存档时间: {}\n",
+        title, result.final_url, archived_at
+    );
+    if let Some(published) = &result.published_at {
+        header.push_str(&format!("> 发布时间: {}\n", published));
+    }
+    format!("{}\n{}\n", header, result.content)
+}
+
+fn format_utc_millis(millis: u64) -> String {
+    let (year, month, day, hour, minute, _) = crate::utc_date_time(millis);
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02} UTC",
+        year, month, day, hour, minute
+    )
+}
+
+/// Saves a page for offline reading, either as a self-contained HTML snapshot or as
+/// extracted Markdown, and records it against a task or note.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn archive_web_page(
+    app: AppHandle,
+    state: State<'_, WebArchiveState>,
+    cache: State<'_, HttpCacheState>,
+    network: State<'_, NetworkState>,
+    url: String,
+    format: Option<String>,
+    owner_kind: String,
+    owner_id: String,
+) -> Result<WebArchiveEntry, String> {
+    let url = url.trim().to_string();
+    if url.is_empty() {
+        return Err("URL 不能为空".to_string());
+    }
+    if !matches!(owner_kind.as_str(), "task" | "note") || owner_id.trim().is_empty() {
+        return Err("存档必须关联任务或笔记".to_string());
+    }
+    let format = match format.as_deref().unwrap_or("markdown") {
+        "html" => "html",
+        "markdown" | "md" => "markdown",
+        other => return Err(format!("不支持的存档格式: {}", other)),
+    };
+
+    let response = crate::fetch_web_bytes(&app, &cache, &network, &url).await?;
+    if !response.is_success() {
+        return Err(format!("HTTP {}", response.status));
+    }
+    let result = web_content::build_result(
+        url.clone(),
+        response.final_url.clone(),
+        &response.content_type,
+        &response.body,
+        usize::MAX,
+    )?;
+
+    let created_at = now_millis();
+    let id = archive_id(&url, created_at);
+    let (file, data) = if format == "html" && result.content_type == "html" {
+        let (client, config) = network.fetch_client(&app)?;
+        let html = snapshot_html(
+            client,
+            config,
+            &String::from_utf8_lossy(&response.body),
+            &response.final_url,
+        )
+        .await;
+        (format!("{}.html", id), html)
+    } else {
+        (format!("{}.md", id), markdown_document(&result, created_at))
+    };
+    let dir = archive_dir(&app)?;
+    fs::write(dir.join(&file), &data).map_err(|e| format!("保存网页存档失败: {}", e))?;
+
+    let entry = WebArchiveEntry {
+        id,
+        url,
+        final_url: result.final_url.clone(),
+        title: result.title.clone(),
+        format: if file.ends_with(".html") {
+            "html"
+        } else {
+            "markdown"
+        }
+        .to_string(),
+        owner_kind,
+        owner_id: owner_id.trim().to_string(),
+        created_at,
+        size: data.len() as u64,
+        file,
+    };
+    update_entries(&dir, &state, |entries| {
+        entries.push(entry.clone());
+        Ok(())
+    })?;
+    Ok(entry)
+}
+
+#[tauri::command]
+pub fn list_web_archives(
+    app: AppHandle,
+    state: State<'_, WebArchiveState>,
+    owner_kind: Option<String>,
+    owner_id: Option<String>,
+) -> Result<Vec<WebArchiveEntry>, String> {
+    let mut entries: Vec<WebArchiveEntry> = load_entries(&archive_dir(&app)?, &state)?
+        .into_iter()
+        .filter(|entry| {
+            owner_kind
+                .as_ref()
+                .is_none_or(|kind| &entry.owner_kind == kind)
+        })
+        .filter(|entry| owner_id.as_ref().is_none_or(|id| &entry.owner_id == id))
+        .collect();
+    entries.sort_by_key(|entry| std::cmp::Reverse(entry.created_at));
+    Ok(entries)
+}
+
+#[tauri::command]
+pub fn read_web_archive(
+    app: AppHandle,
+    state: State<'_, WebArchiveState>,
+    id: String,
+) -> Result<WebArchiveContent, String> {
+    let dir = archive_dir(&app)?;
+    let entry = load_entries(&dir, &state)?
+        .into_iter()
+        .find(|entry| entry.id == id)
+        .ok_or_else(|| "存档不存在".to_string())?;
+    let content = fs::read_to_string(dir.join(&entry.file))
+        .map_err(|e| format!("读取网页存档失败: {}", e))?;
+    Ok(WebArchiveContent { entry, content })
+}
+
+#[tauri::command]
+pub fn delete_web_archive(
+    app: AppHandle,
+    state: State<'_, WebArchiveState>,
+    id: String,
+) -> Result<bool, String> {
+    let dir = archive_dir(&app)?;
+    let removed = update_entries(&dir, &state, |entries| {
+        let index = entries.iter().position(|entry| entry.id == id);
+        Ok(index.map(|index| entries.remove(index)))
+    })?;
+    let Some(entry) = removed else {
+        return Ok(false);
+    };
+    let _ = fs::remove_file(dir.join(&entry.file));
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::{FetchPolicy, ProxyConfig, ProxyMode};
+    use crate::test_support::{serve, Response, TempDir};
+    use base64::engine::general_purpose::STANDARD as BASE64;
+    use std::time::Instant;
+
+    fn config(allow_private_networks: bool) -> NetworkConfig {
+        NetworkConfig {
+            fetch_policy: FetchPolicy {
+                allow_private_networks,
+                ..FetchPolicy::default()
+            },
+            proxy: ProxyConfig {
+                mode: ProxyMode::None,
+                ..ProxyConfig::default()
+            },
+            ..NetworkConfig::default()
+        }
+    }
+
+    async fn snapshot(config: NetworkConfig, html: &str, page_url: &str) -> String {
+        let client = config.fetch_client().unwrap();
+        snapshot_html(client, config, html, page_url).await
+    }
+
+    fn counted_assets() -> (String, Arc<AtomicUsize>) {
+        let hits = Arc::new(AtomicUsize::new(0));
+        let counter = hits.clone();
+        let base = serve(move |request| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            match request.path() {
+                "/style.css" => Response::new(200, "body { background: url('bg.png') }")
+                    .header("Content-Type", "text/css"),
+                "/huge.png" => Response::new(200, vec![0u8; MAX_ASSET_BYTES + 1]),
+                path if path.ends_with("/missing.png") => Response::new(404, ""),
+                path if path.ends_with(".png") => {
+                    Response::new(200, path.as_bytes()).header("Content-Type", "image/png")
+                }
+                _ => Response::new(200, "icon").header("Content-Type", "image/x-icon"),
+            }
+        });
+        (base, hits)
+    }
+
+    fn png_uri(path: &str) -> String {
+        format!("data:image/png;base64,{}", BASE64.encode(path))
+    }
+
+    #[tokio::test]
+    async fn snapshot_inlines_assets_and_drops_active_content() {
+        let (base, _) = counted_assets();
+        let html = r##"<html><head>
+            <base href="https://elsewhere.example/">
+            <link rel="stylesheet" href="/style.css" integrity="sha384-x">
+            <link rel="icon" href="/favicon.ico">
+            <style>h1 { background: url("/header.png") }</style>
+            <script>alert(1)</script>
+        </head><body onload="track()">
+            <img src="photo.png" srcset="photo-2x.png 2x" onerror="steal()">
+            <img src="missing.png">
+            <iframe src="https://ads.example/"></iframe>
+            <a href="../about">About</a> <a href="#top">Top</a>
+        </body></html>"##;
+        let output = snapshot(config(true), html, &format!("{}/articles/", base)).await;
+
+        for removed in [
+            "<script",
+            "<base",
+            "<iframe",
+            "onload",
+            "onerror",
+            "srcset",
+            "integrity",
+        ] {
+            assert!(!output.contains(removed), "{}", removed);
+        }
+        assert!(output.contains(&png_uri("/articles/photo.png")));
+        assert!(output.contains(&png_uri("/header.png")));
+        assert!(output.contains(&format!(
+            "data:image/x-icon;base64,{}",
+            BASE64.encode("icon")
+        )));
+        // A failed download keeps the absolute URL.
+        assert!(output.contains(&format!(r#"src="{}/articles/missing.png""#, base)));
+        assert!(output.contains(&format!(r#"href="{}/about""#, base)));
+        assert!(output.contains(r##"href="#top""##));
+
+        // The stylesheet is inlined with its own references resolved against it.
+        let css = BASE64.encode(format!(
+            "body {{ background: url(\"{}\") }}",
+            png_uri("/bg.png")
+        ));
+        assert!(output.contains(&format!("data:text/css;base64,{}", css)));
+    }
+
+    #[tokio::test]
+    async fn assets_beyond_the_budget_keep_their_urls() {
+        let (base, hits) = counted_assets();
+        let images: String = (0..MAX_ASSETS + 5)
+            .map(|n| format!(r#"<img src="/{}.png">"#, n))
+            .collect();
+        let html = format!(r#"<img src="/huge.png">{}"#, images);
+        let output = snapshot(config(true), &html, &base).await;
+
+        assert_eq!(hits.load(Ordering::SeqCst), MAX_ASSETS);
+        assert!(output.contains(&format!(r#"src="{}/huge.png""#, base)));
+        assert!(output.contains(&png_uri(&format!("/{}.png", MAX_ASSETS - 2))));
+        assert!(output.contains(&format!(r#"src="{}/{}.png""#, base, MAX_ASSETS - 1)));
+        assert!(!output.contains(&png_uri(&format!("/{}.png", MAX_ASSETS - 1))));
+    }
+
+    #[tokio::test]
+    async fn assets_are_checked_against_the_fetch_policy() {
+        let (base, hits) = counted_assets();
+        let output = snapshot(config(false), r#"<img src="/photo.png">"#, &base).await;
+        assert_eq!(hits.load(Ordering::SeqCst), 0);
+        assert!(output.contains(&format!(r#"src="{}/photo.png""#, base)));
+    }
+
+    #[tokio::test]
+    async fn assets_download_concurrently() {
+        let base = serve(|request| {
+            std::thread::sleep(Duration::from_millis(300));
+            Response::new(200, request.path().as_bytes()).header("Content-Type", "image/png")
+        });
+        let html: String = (0..12)
+            .map(|n| format!(r#"<img src="/{}.png">"#, n))
+            .collect();
+        let started = Instant::now();
+        let output = snapshot(config(true), &html, &base).await;
+        assert!(output.contains(&png_uri("/11.png")));
+        // One at a time, or spaced like page fetches, this takes 3.6 s or more.
+        assert!(
+            started.elapsed() < Duration::from_secs(2),
+            "{:?}",
+            started.elapsed()
+        );
+    }
+
+    #[test]
+    fn concurrent_index_updates_keep_every_entry() {
+        let dir = TempDir::new("web-archive-index");
+        let state = Arc::new(WebArchiveState::default());
+        let threads: Vec<_> = (0..8)
+            .map(|thread| {
+                let (path, state) = (dir.path().to_path_buf(), state.clone());
+                std::thread::spawn(move || {
+                    for n in 0..10 {
+                        update_entries(&path, &state, |entries| {
+                            entries.push(WebArchiveEntry {
+                                id: format!("{:02x}{:02x}", thread, n),
+                                url: String::new(),
+                                final_url: String::new(),
+                                title: String::new(),
+                                format: "markdown".to_string(),
+                                owner_kind: "task".to_string(),
+                                owner_id: "t".to_string(),
+                                created_at: 0,
+                                size: 0,
+                                file: String::new(),
+                            });
+                            Ok(())
+                        })
+                        .unwrap();
+                    }
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+        assert_eq!(read_index(dir.path()).len(), 80);
+        assert_eq!(load_entries(dir.path(), &state).unwrap().len(), 80);
+    }
+}
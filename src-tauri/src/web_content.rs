@@ -0,0 +1,1079 @@
+use ego_tree::NodeRef;
+use scraper::{ElementRef, Html, Node, Selector};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use url::Url;
+
+const SKIP_TAGS: [&str; 14] = [
+    "script", "style", "nav", "footer", "header", "aside", "noscript", "svg", "form", "iframe",
+    "button", "select", "template", "canvas",
+];
+const POSITIVE_HINTS: [&str; 8] = [
+    "article", "content", "entry", "main", "post", "story", "text", "body",
+];
+const NEGATIVE_HINTS: [&str; 14] = [
+    "comment", "sidebar", "footer", "nav", "menu", "share", "social", "related", "promo",
+    "advert", "banner", "sponsor", "popup", "cookie",
+];
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WebContentResult {
+    pub url: String,
+    pub final_url: String,
+    pub content_type: String,
+    pub title: String,
+    pub canonical_url: Option<String>,
+    pub published_at: Option<String>,
+    pub content: String,
+    pub truncated: bool,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub items: Vec<FeedItem>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FeedItem {
+    pub title: String,
+    pub url: String,
+    pub published_at: Option<String>,
+    pub summary: String,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum ContentKind {
+    Html,
+    Text,
+    Pdf,
+    Json,
+    Feed,
+    Xml,
+}
+
+impl ContentKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            ContentKind::Html => "html",
+            ContentKind::Text => "text",
+            ContentKind::Pdf => "pdf",
+            ContentKind::Json => "json",
+            ContentKind::Feed => "feed",
+            ContentKind::Xml => "xml",
+        }
+    }
+}
+
+pub struct ExtractedPage {
+    pub title: String,
+    pub canonical_url: Option<String>,
+    pub published_at: Option<String>,
+    pub markdown: String,
+}
+
+fn selector(css: &str) -> Selector {
+    Selector::parse(css).expect("static selector")
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn resolve_url(base: Option<&Url>, raw: &str) -> Option<String> {
+    let raw = raw.trim();
+    if raw.is_empty() || raw.starts_with('#') || raw.starts_with("javascript:") {
+        return None;
+    }
+    let resolved = match base {
+        Some(base) => base.join(raw).ok()?,
+        None => Url::parse(raw).ok()?,
+    };
+    matches!(resolved.scheme(), "http" | "https" | "mailto").then(|| resolved.to_string())
+}
+
+fn meta_content(document: &Html, css: &str) -> Option<String> {
+    document
+        .select(&selector(css))
+        .filter_map(|el| el.value().attr("content"))
+        .map(str::trim)
+        .find(|value| !value.is_empty())
+        .map(str::to_string)
+}
+
+fn find_json_ld_date(value: &Value) -> Option<String> {
+    match value {
+        Value::Object(map) => {
+            if let Some(Value::String(date)) = map.get("datePublished") {
+                return Some(date.clone());
+            }
+            map.values().find_map(find_json_ld_date)
+        }
+        Value::Array(items) => items.iter().find_map(find_json_ld_date),
+        _ => None,
+    }
+}
+
+fn extract_title(document: &Html) -> String {
+    meta_content(document, r#"meta[property="og:title"]"#)
+        .or_else(|| {
+            document
+                .select(&selector("title"))
+                .next()
+                .map(|el| collapse_whitespace(&el.text().collect::<String>()))
+        })
+        .or_else(|| {
+            document
+                .select(&selector("h1"))
+                .next()
+                .map(|el| collapse_whitespace(&el.text().collect::<String>()))
+        })
+        .unwrap_or_default()
+}
+
+fn extract_canonical(document: &Html, base: Option<&Url>) -> Option<String> {
+    document
+        .select(&selector(r#"link[rel="canonical"]"#))
+        .filter_map(|el| el.value().attr("href"))
+        .find_map(|href| resolve_url(base, href))
+        .or_else(|| {
+            meta_content(document, r#"meta[property="og:url"]"#)
+                .and_then(|href| resolve_url(base, &href))
+        })
+}
+
+fn extract_published(document: &Html) -> Option<String> {
+    let meta_selectors = [
+        r#"meta[property="article:published_time"]"#,
+        r#"meta[itemprop="datePublished"]"#,
+        r#"meta[name="pubdate"]"#,
+        r#"meta[name="publishdate"]"#,
+        r#"meta[name="date"]"#,
+        r#"meta[name="DC.date.issued"]"#,
+    ];
+    meta_selectors
+        .iter()
+        .find_map(|css| meta_content(document, css))
+        .or_else(|| {
+            document
+                .select(&selector(r#"script[type="application/ld+json"]"#))
+                .filter_map(|el| serde_json::from_str::<Value>(&el.text().collect::<String>()).ok())
+                .find_map(|value| find_json_ld_date(&value))
+        })
+        .or_else(|| {
+            document
+                .select(&selector("time[datetime]"))
+                .filter_map(|el| el.value().attr("datetime"))
+                .map(str::trim)
+                .find(|value| !value.is_empty())
+                .map(str::to_string)
+        })
+}
+
+fn is_hidden(el: &scraper::node::Element) -> bool {
+    el.attr("hidden").is_some()
+        || el.attr("aria-hidden") == Some("true")
+        || el
+            .attr("style")
+            .is_some_and(|style| style.replace(' ', "").contains("display:none"))
+}
+
+fn class_weight(el: &scraper::node::Element) -> f64 {
+    let hints = format!(
+        "{} {}",
+        el.attr("class").unwrap_or(""),
+        el.attr("id").unwrap_or("")
+    )
+    .to_lowercase();
+    let mut weight = 0.0;
+    if POSITIVE_HINTS.iter().any(|hint| hints.contains(hint)) {
+        weight += 25.0;
+    }
+    if NEGATIVE_HINTS.iter().any(|hint| hints.contains(hint)) {
+        weight -= 25.0;
+    }
+    weight
+}
+
+fn text_length(el: ElementRef) -> usize {
+    el.text().map(|t| t.trim().chars().count()).sum()
+}
+
+fn link_density(el: ElementRef) -> f64 {
+    let total = text_length(el);
+    if total == 0 {
+        return 0.0;
+    }
+    let links: usize = el.select(&selector("a")).map(text_length).sum();
+    links as f64 / total as f64
+}
+
+/// Readability-style scoring: every substantial paragraph votes for its parent
+/// and grandparent, and the best-scoring container is taken as the main content.
+fn find_main_content(document: &Html) -> Option<ElementRef<'_>> {
+    let mut scores: HashMap<ego_tree::NodeId, f64> = HashMap::new();
+
+    for paragraph in document.select(&selector("p, pre, td, blockquote")) {
+        let text = paragraph.text().collect::<String>();
+        let length = text.trim().chars().count();
+        if length < 25 {
+            continue;
+        }
+        let commas = text.matches([',', '，', '、', '。']).count() as f64;
+        let score = 1.0 + commas + (length as f64 / 100.0).min(3.0);
+
+        let mut ancestors = paragraph.ancestors().filter_map(ElementRef::wrap);
+        if let Some(parent) = ancestors.next() {
+            *scores.entry(parent.id()).or_insert(0.0) += score;
+        }
+        if let Some(grandparent) = ancestors.next() {
+            *scores.entry(grandparent.id()).or_insert(0.0) += score / 2.0;
+        }
+    }
+
+    scores
+        .into_iter()
+        .filter_map(|(id, score)| {
+            let el = ElementRef::wrap(document.tree.get(id)?)?;
+            if SKIP_TAGS.contains(&el.value().name()) {
+                return None;
+            }
+            let adjusted = (score + class_weight(el.value())) * (1.0 - link_density(el));
+            Some((el, adjusted))
+        })
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(el, _)| el)
+        .or_else(|| document.select(&selector("article, main, [role=main]")).next())
+        .or_else(|| document.select(&selector("body")).next())
+}
+
+struct MarkdownWriter<'a> {
+    out: String,
+    base: Option<&'a Url>,
+}
+
+impl<'a> MarkdownWriter<'a> {
+    fn new(base: Option<&'a Url>) -> Self {
+        Self {
+            out: String::new(),
+            base,
+        }
+    }
+
+    fn child(&self) -> Self {
+        Self::new(self.base)
+    }
+
+    fn at_line_start(&self) -> bool {
+        self.out.is_empty() || self.out.ends_with('\n')
+    }
+
+    fn push_text(&mut self, text: &str) {
+        let starts_with_space = text.starts_with(char::is_whitespace);
+        let ends_with_space = text.ends_with(char::is_whitespace);
+        let collapsed = collapse_whitespace(text);
+        if collapsed.is_empty() {
+            if starts_with_space && !self.at_line_start() && !self.out.ends_with(' ') {
+                self.out.push(' ');
+            }
+            return;
+        }
+        if starts_with_space && !self.at_line_start() && !self.out.ends_with(' ') {
+            self.out.push(' ');
+        }
+        self.out.push_str(&collapsed);
+        if ends_with_space {
+            self.out.push(' ');
+        }
+    }
+
+    fn push_inline(&mut self, text: &str) {
+        if !text.is_empty() {
+            self.out.push_str(text);
+        }
+    }
+
+    fn block_break(&mut self) {
+        let trimmed_len = self.out.trim_end_matches([' ', '\t']).len();
+        self.out.truncate(trimmed_len);
+        if self.out.is_empty() {
+            return;
+        }
+        while !self.out.ends_with("\n\n") {
+            self.out.push('\n');
+        }
+    }
+
+    fn push_block(&mut self, block: &str) {
+        if block.trim().is_empty() {
+            return;
+        }
+        self.block_break();
+        self.out.push_str(block.trim_end());
+        self.block_break();
+    }
+
+    fn render_children(&mut self, node: NodeRef<Node>) {
+        for child in node.children() {
+            self.render_node(child);
+        }
+    }
+
+    fn inline_of(&self, node: NodeRef<Node>) -> String {
+        let mut writer = self.child();
+        writer.render_children(node);
+        collapse_whitespace(&writer.out)
+    }
+
+    fn block_of(&self, node: NodeRef<Node>) -> String {
+        let mut writer = self.child();
+        writer.render_children(node);
+        writer.finish()
+    }
+
+    fn finish(self) -> String {
+        let mut lines: Vec<&str> = Vec::new();
+        let mut blank_run = 0;
+        for line in self.out.lines() {
+            if line.trim().is_empty() {
+                blank_run += 1;
+                if blank_run > 1 {
+                    continue;
+                }
+                lines.push("");
+            } else {
+                blank_run = 0;
+                lines.push(line.trim_end());
+            }
+        }
+        lines.join("\n").trim().to_string()
+    }
+
+    fn render_list(&mut self, node: NodeRef<Node>, ordered: bool) {
+        let mut items = Vec::new();
+        let mut counter = 1;
+        for child in node.children() {
+            let Some(el) = ElementRef::wrap(child) else {
+                continue;
+            };
+            if el.value().name() != "li" {
+                continue;
+            }
+            let marker = if ordered {
+                format!("{}. ", counter)
+            } else {
+                "- ".to_string()
+            };
+            counter += 1;
+            let body = self.block_of(child);
+            if body.is_empty() {
+                continue;
+            }
+            let indent = " ".repeat(marker.len());
+            let mut item = String::new();
+            for (index, line) in body.lines().enumerate() {
+                if index == 0 {
+                    item.push_str(&marker);
+                } else {
+                    item.push('\n');
+                    if !line.is_empty() {
+                        item.push_str(&indent);
+                    }
+                }
+                item.push_str(line);
+            }
+            items.push(item);
+        }
+        self.push_block(&items.join("\n"));
+    }
+
+    fn render_table(&mut self, node: NodeRef<Node>) {
+        let table = ElementRef::wrap(node).expect("table element");
+        let mut rows: Vec<Vec<String>> = Vec::new();
+        for row in table.select(&selector("tr")) {
+            // Skip rows belonging to nested tables.
+            let owner = row
+                .ancestors()
+                .filter_map(ElementRef::wrap)
+                .find(|el| el.value().name() == "table");
+            if owner.map(|el| el.id()) != Some(table.id()) {
+                continue;
+            }
+            let cells: Vec<String> = row
+                .children()
+                .filter_map(ElementRef::wrap)
+                .filter(|cell| matches!(cell.value().name(), "td" | "th"))
+                .map(|cell| self.inline_of(*cell).replace('|', "\\|"))
+                .collect();
+            if !cells.is_empty() {
+                rows.push(cells);
+            }
+        }
+        if rows.is_empty() {
+            return;
+        }
+        let columns = rows.iter().map(Vec::len).max().unwrap_or(1);
+        let format_row = |cells: &[String]| {
+            let mut padded = cells.to_vec();
+            padded.resize(columns, String::new());
+            format!("| {} |", padded.join(" | "))
+        };
+        let mut lines = vec![
+            format_row(&rows[0]),
+            format!("|{}", " --- |".repeat(columns)),
+        ];
+        lines.extend(rows[1..].iter().map(|row| format_row(row)));
+        self.push_block(&lines.join("\n"));
+    }
+
+    fn render_node(&mut self, node: NodeRef<Node>) {
+        let el = match node.value() {
+            Node::Text(text) => {
+                self.push_text(text);
+                return;
+            }
+            Node::Element(el) => el,
+            Node::Document | Node::Fragment => {
+                self.render_children(node);
+                return;
+            }
+            _ => return,
+        };
+        let name = el.name();
+        if SKIP_TAGS.contains(&name) || is_hidden(el) {
+            return;
+        }
+
+        match name {
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                let level = name[1..].parse::<usize>().unwrap_or(1);
+                let text = self.inline_of(node);
+                if !text.is_empty() {
+                    self.push_block(&format!("{} {}", "#".repeat(level), text));
+                }
+            }
+            "br" => self.out.push('\n'),
+            "hr" => self.push_block("---"),
+            "ul" | "menu" => self.render_list(node, false),
+            "ol" => self.render_list(node, true),
+            "table" => self.render_table(node),
+            "pre" => {
+                let code = ElementRef::wrap(node)
+                    .map(|pre| pre.text().collect::<String>())
+                    .unwrap_or_default();
+                let language = ElementRef::wrap(node)
+                    .and_then(|pre| {
+                        std::iter::once(pre)
+                            .chain(pre.select(&selector("code")))
+                            .filter_map(|el| el.value().attr("class"))
+                            .flat_map(str::split_whitespace)
+                            .find_map(|class| {
+                                class
+                                    .strip_prefix("language-")
+                                    .or_else(|| class.strip_prefix("lang-"))
+                                    .map(str::to_string)
+                            })
+                    })
+                    .unwrap_or_default();
+                let fence = if code.contains("```") { "````" } else { "```" };
+                self.push_block(&format!(
+                    "{}{}\n{}\n{}",
+                    fence,
+                    language,
+                    code.trim_end_matches('\n'),
+                    fence
+                ));
+            }
+            "code" | "kbd" | "samp" => {
+                let code = ElementRef::wrap(node)
+                    .map(|el| el.text().collect::<String>())
+                    .unwrap_or_default();
+                let code = code.trim();
+                if !code.is_empty() {
+                    let tick = if code.contains('`') { "``" } else { "`" };
+                    self.push_text(" ");
+                    self.push_inline(&format!("{}{}{}", tick, code, tick));
+                }
+            }
+            "a" => {
+                let text = self.inline_of(node);
+                let href = el.attr("href").and_then(|href| resolve_url(self.base, href));
+                match href {
+                    Some(href) if !text.is_empty() => {
+                        self.push_text(" ");
+                        self.push_inline(&format!("[{}]({})", text.replace(']', "\\]"), href));
+                    }
+                    _ => self.push_text(&format!(" {} ", text)),
+                }
+            }
+            "img" => {
+                let alt = collapse_whitespace(el.attr("alt").unwrap_or(""));
+                if let Some(src) = el.attr("src").and_then(|src| resolve_url(self.base, src)) {
+                    self.push_text(" ");
+                    self.push_inline(&format!("![{}]({})", alt, src));
+                }
+            }
+            "strong" | "b" => {
+                let text = self.inline_of(node);
+                if !text.is_empty() {
+                    self.push_text(" ");
+                    self.push_inline(&format!("**{}**", text));
+                }
+            }
+            "em" | "i" => {
+                let text = self.inline_of(node);
+                if !text.is_empty() {
+                    self.push_text(" ");
+                    self.push_inline(&format!("*{}*", text));
+                }
+            }
+            "blockquote" => {
+                let body = self.block_of(node);
+                let quoted = body
+                    .lines()
+                    .map(|line| {
+                        if line.is_empty() {
+                            ">".to_string()
+                        } else {
+                            format!("> {}", line)
+                        }
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                self.push_block(&quoted);
+            }
+            "dt" => {
+                let text = self.inline_of(node);
+                if !text.is_empty() {
+                    self.push_block(&format!("**{}**", text));
+                }
+            }
+            "p" | "div" | "section" | "article" | "main" | "figure" | "figcaption" | "dd"
+            | "dl" | "address" | "details" | "summary" | "li" => {
+                self.block_break();
+                self.render_children(node);
+                self.block_break();
+            }
+            _ => self.render_children(node),
+        }
+    }
+}
+
+/// Converts the main content of an HTML page into Markdown and collects page metadata.
+pub fn extract_page(html: &str, page_url: &str) -> ExtractedPage {
+    let document = Html::parse_document(html);
+    let base = Url::parse(page_url).ok();
+
+    let markdown = match find_main_content(&document) {
+        Some(main) => {
+            let mut writer = MarkdownWriter::new(base.as_ref());
+            writer.render_node(*main);
+            writer.finish()
+        }
+        None => String::new(),
+    };
+
+    ExtractedPage {
+        title: extract_title(&document),
+        canonical_url: extract_canonical(&document, base.as_ref()),
+        published_at: extract_published(&document),
+        markdown,
+    }
+}
+
+pub struct PageLinks {
+    pub links: Vec<String>,
+    pub nofollow: bool,
+    pub noindex: bool,
+}
+
+/// Collects absolute http(s) links from a page, honouring `<base href>` and the
+/// robots meta tag.
+pub fn extract_links(html: &str, page_url: &str) -> PageLinks {
+    let document = Html::parse_document(html);
+    let page = Url::parse(page_url).ok();
+    let base = document
+        .select(&selector("base[href]"))
+        .filter_map(|el| el.value().attr("href"))
+        .find_map(|href| match &page {
+            Some(page) => page.join(href).ok(),
+            None => Url::parse(href).ok(),
+        })
+        .or(page);
+
+    let robots = meta_content(&document, r#"meta[name="robots"]"#)
+        .unwrap_or_default()
+        .to_lowercase();
+    let links = document
+        .select(&selector("a[href]"))
+        .filter(|el| {
+            !el.value()
+                .attr("rel")
+                .is_some_and(|rel| rel.to_lowercase().contains("nofollow"))
+        })
+        .filter_map(|el| el.value().attr("href"))
+        .filter_map(|href| resolve_url(base.as_ref(), href))
+        .filter(|href| !href.starts_with("mailto:"))
+        .collect();
+
+    PageLinks {
+        links,
+        nofollow: robots.contains("nofollow") || robots.contains("none"),
+        noindex: robots.contains("noindex") || robots.contains("none"),
+    }
+}
+
+/// Cuts text to `limit` characters, returning whether anything was dropped.
+pub fn truncate_content(text: &str, limit: usize) -> (String, bool) {
+    if text.chars().count() <= limit {
+        return (text.to_string(), false);
+    }
+    let truncated: String = text.chars().take(limit).collect();
+    (format!("{}...[已截断]", truncated), true)
+}
+
+fn looks_like_feed(head: &str) -> bool {
+    head.contains("<rss") || head.contains("<feed") || head.contains("<rdf:RDF")
+}
+
+fn detect_content_kind(content_type: &str, bytes: &[u8]) -> Option<ContentKind> {
+    let head = String::from_utf8_lossy(&bytes[..bytes.len().min(1024)]).to_lowercase();
+    let head = head.trim_start_matches('\u{feff}').trim_start();
+
+    if content_type.contains("application/pdf") || bytes.starts_with(b"%PDF-") {
+        return Some(ContentKind::Pdf);
+    }
+    if content_type.contains("rss+xml") || content_type.contains("atom+xml") {
+        return Some(ContentKind::Feed);
+    }
+    if content_type.contains("json") {
+        return Some(ContentKind::Json);
+    }
+    if content_type.contains("text/html") || content_type.contains("xhtml") {
+        return Some(ContentKind::Html);
+    }
+    if content_type.contains("xml") {
+        return Some(if looks_like_feed(head) {
+            ContentKind::Feed
+        } else {
+            ContentKind::Xml
+        });
+    }
+    if content_type.starts_with("text/") {
+        return Some(ContentKind::Text);
+    }
+
+    // Servers often label everything `application/octet-stream`; fall back to sniffing.
+    if !content_type.is_empty()
+        && !content_type.contains("octet-stream")
+        && !content_type.contains("binary")
+    {
+        return None;
+    }
+    if head.starts_with("<!doctype html") || head.starts_with("<html") {
+        Some(ContentKind::Html)
+    } else if head.starts_with("<?xml") || head.starts_with('<') {
+        Some(if looks_like_feed(head) {
+            ContentKind::Feed
+        } else {
+            ContentKind::Xml
+        })
+    } else if (head.starts_with('{') || head.starts_with('['))
+        && serde_json::from_slice::<Value>(bytes).is_ok()
+    {
+        Some(ContentKind::Json)
+    } else if std::str::from_utf8(bytes).is_ok() {
+        Some(ContentKind::Text)
+    } else {
+        None
+    }
+}
+
+fn title_from_url(url: &str) -> String {
+    Url::parse(url)
+        .ok()
+        .and_then(|parsed| {
+            parsed
+                .path_segments()
+                .and_then(|mut segments| segments.next_back().map(str::to_string))
+        })
+        .filter(|segment| !segment.is_empty())
+        .unwrap_or_default()
+}
+
+fn render_pdf(bytes: &[u8]) -> Result<String, String> {
+    let document =
+        lopdf::Document::load_mem(bytes).map_err(|e| format!("解析 PDF 失败: {}", e))?;
+    let pages: Vec<u32> = document.get_pages().keys().copied().collect();
+    let text = document
+        .extract_text_chunks(&pages)
+        .into_iter()
+        .filter_map(Result::ok)
+        .collect::<Vec<_>>()
+        .join("\n");
+    let text = text
+        .lines()
+        .map(str::trim_end)
+        .filter(|line| !line.trim().is_empty())
+        .collect::<Vec<_>>()
+        .join("\n");
+    if text.is_empty() {
+        return Err("PDF 中没有可提取的文本（可能是扫描件）".to_string());
+    }
+    Ok(text)
+}
+
+fn render_json(bytes: &[u8]) -> Result<String, String> {
+    let value: Value =
+        serde_json::from_slice(bytes).map_err(|e| format!("解析 JSON 失败: {}", e))?;
+    serde_json::to_string_pretty(&value).map_err(|e| e.to_string())
+}
+
+fn render_feed(bytes: &[u8]) -> Result<(String, Vec<FeedItem>), String> {
+    let feed = feed_rs::parser::parse(bytes).map_err(|e| format!("解析订阅源失败: {}", e))?;
+    let title = feed.title.map(|text| text.content).unwrap_or_default();
+    let items = feed
+        .entries
+        .into_iter()
+        .map(|entry| {
+            let summary = entry
+                .summary
+                .map(|text| text.content)
+                .or_else(|| entry.content.and_then(|content| content.body))
+                .unwrap_or_default();
+            let summary = if summary.contains('<') {
+                Html::parse_fragment(&summary)
+                    .root_element()
+                    .text()
+                    .collect::<String>()
+            } else {
+                summary
+            };
+            FeedItem {
+                title: entry
+                    .title
+                    .map(|text| collapse_whitespace(&text.content))
+                    .unwrap_or_default(),
+                url: entry
+                    .links
+                    .first()
+                    .map(|link| link.href.clone())
+                    .unwrap_or_default(),
+                published_at: entry.published.or(entry.updated).map(|date| date.to_rfc3339()),
+                summary: collapse_whitespace(&summary).chars().take(280).collect(),
+            }
+        })
+        .collect();
+    Ok((title, items))
+}
+
+/// Keeps feed items while their titles and summaries fit in `limit` characters.
+/// The item that spends the budget keeps a cut summary; later items are dropped.
+fn fit_feed_items(items: Vec<FeedItem>, limit: usize) -> (Vec<FeedItem>, bool) {
+    let mut remaining = limit;
+    let mut kept = Vec::new();
+    let total = items.len();
+    for mut item in items {
+        let title_len = item.title.chars().count();
+        if title_len > remaining {
+            break;
+        }
+        remaining -= title_len;
+        let (summary, cut) = truncate_content(&item.summary, remaining);
+        remaining = remaining.saturating_sub(item.summary.chars().count());
+        item.summary = summary;
+        kept.push(item);
+        if cut {
+            return (kept, true);
+        }
+    }
+    let truncated = kept.len() < total;
+    (kept, truncated)
+}
+
+fn feed_markdown(title: &str, items: &[FeedItem]) -> String {
+    let mut lines = Vec::new();
+    if !title.is_empty() {
+        lines.push(format!("# {}", title));
+        lines.push(String::new());
+    }
+    for (index, item) in items.iter().enumerate() {
+        let heading = if item.url.is_empty() {
+            item.title.clone()
+        } else {
+            format!("[{}]({})", item.title, item.url)
+        };
+        let date = item
+            .published_at
+            .as_deref()
+            .map(|date| format!(" — {}", date))
+            .unwrap_or_default();
+        lines.push(format!("{}. {}{}", index + 1, heading, date));
+        if !item.summary.is_empty() {
+            lines.push(format!("   {}", item.summary));
+        }
+    }
+    lines.join("\n")
+}
+
+/// Renders arbitrary XML as an indented outline of element names and text values.
+fn render_xml(bytes: &[u8]) -> Result<String, String> {
+    use quick_xml::events::Event;
+
+    let mut reader = quick_xml::Reader::from_reader(bytes);
+    let decoder = reader.decoder();
+    let mut buf = Vec::new();
+    let mut lines: Vec<String> = Vec::new();
+    let mut depth = 0usize;
+    let mut pending: Option<String> = None;
+    let mut text = String::new();
+
+    let flush = |lines: &mut Vec<String>, pending: &mut Option<String>, text: &mut String, depth: usize| {
+        if let Some(label) = pending.take() {
+            let indent = "  ".repeat(depth.saturating_sub(1));
+            let value = collapse_whitespace(text);
+            if value.is_empty() {
+                lines.push(format!("{}{}", indent, label));
+            } else {
+                lines.push(format!("{}{}: {}", indent, label, value));
+            }
+        }
+        text.clear();
+    };
+    let label_of = |start: &quick_xml::events::BytesStart| {
+        let name = String::from_utf8_lossy(start.name().as_ref()).to_string();
+        let attrs: Vec<String> = start
+            .attributes()
+            .filter_map(Result::ok)
+            .map(|attr| {
+                format!(
+                    "{}={}",
+                    String::from_utf8_lossy(attr.key.as_ref()),
+                    attr.decoded_and_normalized_value(quick_xml::XmlVersion::Implicit1_0, decoder)
+                        .unwrap_or_default()
+                )
+            })
+            .collect();
+        if attrs.is_empty() {
+            name
+        } else {
+            format!("{} [{}]", name, attrs.join(", "))
+        }
+    };
+
+    loop {
+        match reader.read_event_into(&mut buf) {
+            Ok(Event::Start(start)) => {
+                flush(&mut lines, &mut pending, &mut text, depth);
+                depth += 1;
+                pending = Some(label_of(&start));
+            }
+            Ok(Event::Empty(start)) => {
+                flush(&mut lines, &mut pending, &mut text, depth);
+                lines.push(format!("{}{}", "  ".repeat(depth), label_of(&start)));
+            }
+            Ok(Event::Text(content)) => {
+                text.push_str(&content.decode().unwrap_or_default());
+            }
+            Ok(Event::CData(content)) => {
+                text.push_str(&String::from_utf8_lossy(&content));
+            }
+            Ok(Event::GeneralRef(reference)) => {
+                let name = reference.decode().unwrap_or_default();
+                if let Ok(resolved) = quick_xml::escape::unescape(&format!("&{};", name)) {
+                    text.push_str(&resolved);
+                }
+            }
+            Ok(Event::End(_)) => {
+                flush(&mut lines, &mut pending, &mut text, depth);
+                depth = depth.saturating_sub(1);
+            }
+            Ok(Event::Eof) => break,
+            Ok(_) => {}
+            Err(e) => return Err(format!("解析 XML 失败: {}", e)),
+        }
+        buf.clear();
+    }
+
+    Ok(lines.join("\n"))
+}
+
+/// Turns a fetched response body into the structured result returned to the assistant.
+pub fn build_result(
+    url: String,
+    final_url: String,
+    content_type: &str,
+    bytes: &[u8],
+    limit: usize,
+) -> Result<WebContentResult, String> {
+    let kind = detect_content_kind(content_type, bytes)
+        .ok_or_else(|| format!("不支持的内容类型: {}", content_type))?;
+
+    let mut title = String::new();
+    let mut canonical_url = None;
+    let mut published_at = None;
+    let mut items = Vec::new();
+    let mut items_truncated = false;
+    let body = match kind {
+        ContentKind::Html => {
+            let page = extract_page(&String::from_utf8_lossy(bytes), &final_url);
+            title = page.title;
+            canonical_url = page.canonical_url;
+            published_at = page.published_at;
+            page.markdown
+        }
+        ContentKind::Text => String::from_utf8_lossy(bytes).trim().to_string(),
+        ContentKind::Pdf => {
+            title = title_from_url(&final_url);
+            render_pdf(bytes)?
+        }
+        ContentKind::Json => render_json(bytes)?,
+        ContentKind::Feed => {
+            let (feed_title, feed_items) = render_feed(bytes)?;
+            title = feed_title;
+            (items, items_truncated) = fit_feed_items(feed_items, limit);
+            feed_markdown(&title, &items)
+        }
+        ContentKind::Xml => render_xml(bytes)?,
+    };
+
+    let (content, content_truncated) = truncate_content(&body, limit);
+    let truncated = content_truncated || items_truncated;
+    Ok(WebContentResult {
+        url,
+        final_url,
+        content_type: kind.as_str().to_string(),
+        title,
+        canonical_url,
+        published_at,
+        content,
+        truncated,
+        items,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    macro_rules! fixture {
+        ($name:literal) => {
+            include_str!(concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/tests/fixtures/web_content/",
+                $name
+            ))
+        };
+    }
+
+    fn rss(count: usize) -> String {
+        let items: String = (1..=count)
+            .map(|n| {
+                format!(
+                    "<item><title>Item {n}</title><link>https://example.com/{n}</link>\
+                     <description>Summary number {n} of the feed.</description></item>"
+                )
+            })
+            .collect();
+        format!(
+            r#"<?xml version="1.0"?><rss version="2.0"><channel><title>Feed</title>{items}</channel></rss>"#
+        )
+    }
+
+    fn feed(count: usize, limit: usize) -> WebContentResult {
+        build_result(
+            "https://example.com/feed".to_string(),
+            "https://example.com/feed".to_string(),
+            "application/rss+xml",
+            rss(count).as_bytes(),
+            limit,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn feed_within_the_budget_is_complete() {
+        let result = feed(3, 10_000);
+        assert_eq!(result.items.len(), 3);
+        assert!(!result.truncated);
+        assert_eq!(result.items[2].summary, "Summary number 3 of the feed.");
+    }
+
+    #[test]
+    fn feed_items_share_the_character_budget() {
+        // Each item costs 6 title + 29 summary characters.
+        let result = feed(5, 80);
+        assert!(result.truncated);
+        assert_eq!(result.items.len(), 3);
+        assert_eq!(result.items[1].summary, "Summary number 2 of the feed.");
+        assert_eq!(result.items[2].summary, "Summ...[已截断]");
+        assert!(!result.content.contains("Item 4"));
+
+        // A title that no longer fits drops the item entirely.
+        let result = feed(5, 72);
+        assert_eq!(result.items.len(), 2);
+        assert!(result.truncated);
+    }
+
+    #[test]
+    fn article_renders_as_markdown() {
+        let page = extract_page(
+            fixture!("article.html"),
+            "https://example.com/blog/posts/release.html",
+        );
+        assert_eq!(page.markdown, fixture!("article.md").trim_end());
+    }
+
+    #[test]
+    fn article_keeps_the_main_content_only() {
+        let page = extract_page(
+            fixture!("article.html"),
+            "https://example.com/blog/posts/release.html",
+        );
+        for boilerplate in [
+            "Home",
+            "Subscribe",
+            "Copyright",
+            "analytics",
+            "font-family",
+            "Hidden tracking",
+        ] {
+            assert!(!page.markdown.contains(boilerplate), "{}", boilerplate);
+        }
+        // Links resolve against the page; fragment-only links become plain text.
+        assert!(page
+            .markdown
+            .contains("[sync guide](https://example.com/blog/docs/sync.html)"));
+        assert!(page
+            .markdown
+            .contains("![The new editor](https://example.com/blog/posts/images/editor.png)"));
+        assert!(page.markdown.contains("then comment or mail"));
+    }
+
+    #[test]
+    fn article_metadata_prefers_open_graph_and_canonical_links() {
+        let page = extract_page(
+            fixture!("article.html"),
+            "https://example.com/blog/posts/release.html",
+        );
+        assert_eq!(page.title, "Release notes for 2.0");
+        assert_eq!(
+            page.canonical_url.as_deref(),
+            Some("https://example.com/blog/release-2-0")
+        );
+        assert_eq!(page.published_at.as_deref(), Some("2024-03-05T09:30:00Z"));
+    }
+
+    #[test]
+    fn metadata_falls_back_to_title_og_url_and_json_ld() {
+        let page = extract_page(fixture!("metadata.html"), "https://example.org/x");
+        assert_eq!(page.title, "Fallback title");
+        assert_eq!(
+            page.canonical_url.as_deref(),
+            Some("https://example.org/posts/42?ref=feed")
+        );
+        // JSON-LD wins over `<time>` and is found inside `@graph`.
+        assert_eq!(page.published_at.as_deref(), Some("2023-11-20"));
+        // Without a substantial paragraph, `<main>` is taken as the content.
+        assert_eq!(page.markdown, "Only a short note.\n\nLong ago");
+    }
+}
@@ -0,0 +1,753 @@
+use scraper::{Html, Selector};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::{AppHandle, State};
+use url::Url;
+
+use crate::http_cache::{self, HttpCacheState};
+use crate::network::NetworkState;
+
+const SEARCH_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(15);
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct WebSearchEntry {
+    title: String,
+    url: String,
+    snippet: String,
+    source: String,
+}
+
+#[derive(Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SearchProviderConfig {
+    id: String,
+    enabled: Option<bool>,
+    api_key: String,
+    base_url: String,
+    results_path: String,
+    title_field: String,
+    url_field: String,
+    snippet_field: String,
+}
+
+/// A web search backend. Request building and response parsing are kept separate
+/// so every backend shares the same HTTP handling and fallback logic.
+pub trait SearchProvider: Send + Sync {
+    fn name(&self) -> &str;
+
+    fn build_request(
+        &self,
+        client: &reqwest::Client,
+        query: &str,
+        limit: usize,
+    ) -> Result<reqwest::RequestBuilder, String>;
+
+    fn parse_response(&self, body: &str, limit: usize) -> Result<Vec<WebSearchEntry>, String>;
+}
+
+fn entry(title: &str, url: &str, snippet: &str, source: &str) -> Option<WebSearchEntry> {
+    let title = title.trim();
+    let url = url.trim();
+    if title.is_empty() || !(url.starts_with("http://") || url.starts_with("https://")) {
+        return None;
+    }
+    Some(WebSearchEntry {
+        title: title.to_string(),
+        url: url.to_string(),
+        snippet: snippet.split_whitespace().collect::<Vec<_>>().join(" "),
+        source: source.to_string(),
+    })
+}
+
+fn parse_json(body: &str, provider: &str) -> Result<Value, String> {
+    serde_json::from_str(body).map_err(|e| format!("解析 {} 搜索结果失败: {}", provider, e))
+}
+
+fn value_at<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.')
+        .filter(|segment| !segment.is_empty())
+        .try_fold(value, |current, segment| match current {
+            Value::Array(items) => items.get(segment.parse::<usize>().ok()?),
+            _ => current.get(segment),
+        })
+}
+
+fn str_at<'a>(value: &'a Value, path: &str) -> &'a str {
+    value_at(value, path).and_then(Value::as_str).unwrap_or("")
+}
+
+fn entries_from_array(
+    items: Option<&Value>,
+    limit: usize,
+    source: &str,
+    fields: (&str, &str, &str),
+) -> Vec<WebSearchEntry> {
+    items
+        .and_then(Value::as_array)
+        .map(|items| {
+            items
+                .iter()
+                .filter_map(|item| {
+                    entry(
+                        str_at(item, fields.0),
+                        str_at(item, fields.1),
+                        str_at(item, fields.2),
+                        source,
+                    )
+                })
+                .take(limit)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn require_key<'a>(config: &'a SearchProviderConfig, provider: &str) -> Result<&'a str, String> {
+    let key = config.api_key.trim();
+    if key.is_empty() {
+        return Err(format!("{} 需要配置 API Key", provider));
+    }
+    Ok(key)
+}
+
+pub(crate) fn extract_duckduckgo_url(raw_url: &str) -> String {
+    let prefixed = if raw_url.starts_with("http://") || raw_url.starts_with("https://") {
+        raw_url.to_string()
+    } else if raw_url.starts_with('/') {
+        format!("https://html.duckduckgo.com{}", raw_url)
+    } else {
+        raw_url.to_string()
+    };
+
+    if let Ok(parsed) = Url::parse(&prefixed) {
+        if let Some(decoded) = parsed
+            .query_pairs()
+            .find_map(|(key, value)| (key == "uddg").then(|| value.to_string()))
+        {
+            if let Ok(decoded_url) = Url::parse(&decoded) {
+                if matches!(decoded_url.scheme(), "http" | "https") {
+                    return decoded;
+                }
+            }
+            return String::new();
+        }
+
+        if matches!(parsed.scheme(), "http" | "https") {
+            return prefixed;
+        }
+    }
+
+    String::new()
+}
+
+pub struct DuckDuckGo;
+
+impl SearchProvider for DuckDuckGo {
+    fn name(&self) -> &str {
+        "DuckDuckGo"
+    }
+
+    fn build_request(
+        &self,
+        client: &reqwest::Client,
+        query: &str,
+        _limit: usize,
+    ) -> Result<reqwest::RequestBuilder, String> {
+        Ok(client
+            .get("https://html.duckduckgo.com/html/")
+            .query(&[("q", query)]))
+    }
+
+    fn parse_response(&self, body: &str, limit: usize) -> Result<Vec<WebSearchEntry>, String> {
+        let document = Html::parse_document(body);
+        let result_selector = Selector::parse(".result").map_err(|e| e.to_string())?;
+        let title_selector = Selector::parse("a.result__a").map_err(|e| e.to_string())?;
+        let snippet_selector = Selector::parse(".result__snippet").map_err(|e| e.to_string())?;
+
+        let mut entries = Vec::new();
+        for result in document.select(&result_selector) {
+            if entries.len() >= limit {
+                break;
+            }
+            let Some(title_link) = result.select(&title_selector).next() else {
+                continue;
+            };
+            let title = title_link.text().collect::<String>();
+            let raw_url = title_link.value().attr("href").unwrap_or("").trim();
+            let url = extract_duckduckgo_url(raw_url);
+            let snippet = result
+                .select(&snippet_selector)
+                .next()
+                .map(|item| item.text().collect::<String>())
+                .unwrap_or_default();
+            if let Some(item) = entry(&title, &url, &snippet, self.name()) {
+                entries.push(item);
+            }
+        }
+        Ok(entries)
+    }
+}
+
+/// Self-hosted SearXNG instance; the JSON format must be enabled in its `settings.yml`.
+pub struct SearXng {
+    base_url: String,
+}
+
+impl SearchProvider for SearXng {
+    fn name(&self) -> &str {
+        "SearXNG"
+    }
+
+    fn build_request(
+        &self,
+        client: &reqwest::Client,
+        query: &str,
+        _limit: usize,
+    ) -> Result<reqwest::RequestBuilder, String> {
+        let base = self.base_url.trim().trim_end_matches('/');
+        if base.is_empty() {
+            return Err("SearXNG 需要配置实例地址".to_string());
+        }
+        let endpoint = if base.ends_with("/search") {
+            base.to_string()
+        } else {
+            format!("{}/search", base)
+        };
+        Ok(client
+            .get(endpoint)
+            .query(&[("q", query), ("format", "json")]))
+    }
+
+    fn parse_response(&self, body: &str, limit: usize) -> Result<Vec<WebSearchEntry>, String> {
+        let payload = parse_json(body, self.name())?;
+        Ok(entries_from_array(
+            payload.get("results"),
+            limit,
+            self.name(),
+            ("title", "url", "content"),
+        ))
+    }
+}
+
+pub struct Brave {
+    api_key: String,
+}
+
+impl SearchProvider for Brave {
+    fn name(&self) -> &str {
+        "Brave"
+    }
+
+    fn build_request(
+        &self,
+        client: &reqwest::Client,
+        query: &str,
+        limit: usize,
+    ) -> Result<reqwest::RequestBuilder, String> {
+        Ok(client
+            .get("https://api.search.brave.com/res/v1/web/search")
+            .query(&[("q", query), ("count", &limit.to_string())])
+            .header("Accept", "application/json")
+            .header("X-Subscription-Token", &self.api_key))
+    }
+
+    fn parse_response(&self, body: &str, limit: usize) -> Result<Vec<WebSearchEntry>, String> {
+        let payload = parse_json(body, self.name())?;
+        Ok(entries_from_array(
+            value_at(&payload, "web.results"),
+            limit,
+            self.name(),
+            ("title", "url", "description"),
+        ))
+    }
+}
+
+pub struct Bing {
+    api_key: String,
+}
+
+impl SearchProvider for Bing {
+    fn name(&self) -> &str {
+        "Bing"
+    }
+
+    fn build_request(
+        &self,
+        client: &reqwest::Client,
+        query: &str,
+        limit: usize,
+    ) -> Result<reqwest::RequestBuilder, String> {
+        Ok(client
+            .get("https://api.bing.microsoft.com/v7.0/search")
+            .query(&[("q", query), ("count", &limit.to_string())])
+            .header("Ocp-Apim-Subscription-Key", &self.api_key))
+    }
+
+    fn parse_response(&self, body: &str, limit: usize) -> Result<Vec<WebSearchEntry>, String> {
+        let payload = parse_json(body, self.name())?;
+        Ok(entries_from_array(
+            value_at(&payload, "webPages.value"),
+            limit,
+            self.name(),
+            ("name", "url", "snippet"),
+        ))
+    }
+}
+
+pub struct Tavily {
+    api_key: String,
+}
+
+impl SearchProvider for Tavily {
+    fn name(&self) -> &str {
+        "Tavily"
+    }
+
+    fn build_request(
+        &self,
+        client: &reqwest::Client,
+        query: &str,
+        limit: usize,
+    ) -> Result<reqwest::RequestBuilder, String> {
+        Ok(client
+            .post("https://api.tavily.com/search")
+            .bearer_auth(&self.api_key)
+            .json(&serde_json::json!({
+                "query": query,
+                "max_results": limit,
+            })))
+    }
+
+    fn parse_response(&self, body: &str, limit: usize) -> Result<Vec<WebSearchEntry>, String> {
+        let payload = parse_json(body, self.name())?;
+        Ok(entries_from_array(
+            payload.get("results"),
+            limit,
+            self.name(),
+            ("title", "url", "content"),
+        ))
+    }
+}
+
+/// Any JSON search API: `{query}` and `{limit}` in the URL are substituted, and
+/// results are read from dotted paths such as `data.items` / `link`.
+pub struct GenericJson {
+    url: String,
+    api_key: String,
+    results_path: String,
+    title_field: String,
+    url_field: String,
+    snippet_field: String,
+}
+
+impl SearchProvider for GenericJson {
+    fn name(&self) -> &str {
+        "Custom"
+    }
+
+    fn build_request(
+        &self,
+        client: &reqwest::Client,
+        query: &str,
+        limit: usize,
+    ) -> Result<reqwest::RequestBuilder, String> {
+        let template = self.url.trim();
+        if template.is_empty() {
+            return Err("自定义搜索需要配置接口地址".to_string());
+        }
+        let encoded: String = url::form_urlencoded::byte_serialize(query.as_bytes()).collect();
+        let endpoint = if template.contains("{query}") {
+            template
+                .replace("{query}", &encoded)
+                .replace("{limit}", &limit.to_string())
+        } else {
+            let separator = if template.contains('?') { '&' } else { '?' };
+            format!("{}{}q={}", template, separator, encoded)
+        };
+        let mut request = client.get(endpoint).header("Accept", "application/json");
+        if !self.api_key.trim().is_empty() {
+            request = request.bearer_auth(self.api_key.trim());
+        }
+        Ok(request)
+    }
+
+    fn parse_response(&self, body: &str, limit: usize) -> Result<Vec<WebSearchEntry>, String> {
+        let payload = parse_json(body, self.name())?;
+        let items = if self.results_path.trim().is_empty() {
+            Some(&payload)
+        } else {
+            value_at(&payload, self.results_path.trim())
+        };
+        Ok(entries_from_array(
+            items,
+            limit,
+            self.name(),
+            (&self.title_field, &self.url_field, &self.snippet_field),
+        ))
+    }
+}
+
+fn or_default(value: &str, fallback: &str) -> String {
+    if value.trim().is_empty() {
+        fallback.to_string()
+    } else {
+        value.trim().to_string()
+    }
+}
+
+pub fn build_provider(config: &SearchProviderConfig) -> Result<Box<dyn SearchProvider>, String> {
+    Ok(match config.id.as_str() {
+        "duckduckgo" => Box::new(DuckDuckGo),
+        "searxng" => Box::new(SearXng {
+            base_url: config.base_url.clone(),
+        }),
+        "brave" => Box::new(Brave {
+            api_key: require_key(config, "Brave")?.to_string(),
+        }),
+        "bing" => Box::new(Bing {
+            api_key: require_key(config, "Bing")?.to_string(),
+        }),
+        "tavily" => Box::new(Tavily {
+            api_key: require_key(config, "Tavily")?.to_string(),
+        }),
+        "generic" => Box::new(GenericJson {
+            url: config.base_url.clone(),
+            api_key: config.api_key.clone(),
+            results_path: or_default(&config.results_path, "results"),
+            title_field: or_default(&config.title_field, "title"),
+            url_field: or_default(&config.url_field, "url"),
+            snippet_field: or_default(&config.snippet_field, "snippet"),
+        }),
+        other => return Err(format!("未知的搜索服务: {}", other)),
+    })
+}
+
+async fn run_provider(
+    app: &AppHandle,
+    cache: &HttpCacheState,
+    client: &reqwest::Client,
+    config: &SearchProviderConfig,
+    provider: &dyn SearchProvider,
+    query: &str,
+    limit: usize,
+) -> Result<Vec<WebSearchEntry>, String> {
+    let key = format!(
+        "search|{}|{}|{}|{}",
+        config.id,
+        config.base_url.trim(),
+        limit,
+        http_cache::normalize_query(query)
+    );
+    let request = provider
+        .build_request(client, query, limit)?
+        .timeout(SEARCH_TIMEOUT);
+    let response = cache
+        .fetch(
+            app,
+            &key,
+            http_cache::SEARCH_TTL,
+            crate::network::DEFAULT_MAX_RESPONSE_BYTES,
+            request,
+        )
+        .await?;
+
+    if !response.is_success() {
+        return Err(format!("网页搜索失败: HTTP {}", response.status));
+    }
+
+    let body = String::from_utf8_lossy(&response.body);
+    provider.parse_response(&body, limit)
+}
+
+#[tauri::command]
+pub async fn search_web(
+    app: AppHandle,
+    cache: State<'_, HttpCacheState>,
+    network: State<'_, NetworkState>,
+    query: String,
+    max_results: Option<usize>,
+    providers: Option<Vec<SearchProviderConfig>>,
+) -> Result<Vec<WebSearchEntry>, String> {
+    let needle = query.trim();
+    if needle.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let limit = max_results.unwrap_or(6).clamp(1, 10);
+    let client = network.client(&app)?;
+
+    let mut configs: Vec<SearchProviderConfig> = providers
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|config| config.enabled != Some(false))
+        .collect();
+    if configs.is_empty() {
+        configs.push(SearchProviderConfig {
+            id: "duckduckgo".to_string(),
+            ..Default::default()
+        });
+    }
+
+    let app = &app;
+    let cache = cache.inner();
+    let client = &client;
+    search_with_fallback(&configs, |config, provider| async move {
+        run_provider(app, cache, client, config, provider.as_ref(), needle, limit).await
+    })
+    .await
+}
+
+/// Tries providers in the configured order; the first one with results wins.
+/// Fails only when every provider failed, otherwise an empty list means no hits.
+async fn search_with_fallback<'a, F, Fut>(
+    configs: &'a [SearchProviderConfig],
+    mut run: F,
+) -> Result<Vec<WebSearchEntry>, String>
+where
+    F: FnMut(&'a SearchProviderConfig, Box<dyn SearchProvider>) -> Fut,
+    Fut: std::future::Future<Output = Result<Vec<WebSearchEntry>, String>>,
+{
+    let mut errors = Vec::new();
+    for config in configs {
+        let outcome = match build_provider(config) {
+            Ok(provider) => {
+                let name = provider.name().to_string();
+                run(config, provider)
+                    .await
+                    .map_err(|e| format!("{}: {}", name, e))
+            }
+            Err(e) => Err(e),
+        };
+        match outcome {
+            Ok(entries) if !entries.is_empty() => return Ok(entries),
+            Ok(_) => {}
+            Err(e) => errors.push(e),
+        }
+    }
+
+    if errors.len() == configs.len() {
+        return Err(errors.join("；"));
+    }
+    Ok(Vec::new())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    macro_rules! fixture {
+        ($name:literal) => {
+            include_str!(concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/tests/fixtures/web_search/",
+                $name
+            ))
+        };
+    }
+
+    fn config(id: &str) -> SearchProviderConfig {
+        SearchProviderConfig {
+            id: id.to_string(),
+            api_key: "key".to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn urls(entries: &[WebSearchEntry]) -> Vec<&str> {
+        entries.iter().map(|entry| entry.url.as_str()).collect()
+    }
+
+    fn hit(url: &str) -> WebSearchEntry {
+        entry("Result", url, "", "test").unwrap()
+    }
+
+    #[test]
+    fn duckduckgo_html_results_are_unwrapped() {
+        let entries = DuckDuckGo
+            .parse_response(fixture!("duckduckgo.html"), 10)
+            .unwrap();
+        assert_eq!(
+            urls(&entries),
+            [
+                "https://rust-lang.github.io/async-book/",
+                "https://tokio.rs/tokio/tutorial",
+                "https://docs.rs/futures/latest/futures/",
+                "https://without.snippet.example/",
+            ]
+        );
+        assert_eq!(entries[0].title, "Asynchronous Programming in Rust");
+        assert_eq!(
+            entries[0].snippet,
+            "Getting started with async/.await in Rust."
+        );
+        assert_eq!(entries[0].source, "DuckDuckGo");
+        assert_eq!(entries[3].snippet, "");
+
+        let limited = DuckDuckGo
+            .parse_response(fixture!("duckduckgo.html"), 2)
+            .unwrap();
+        assert_eq!(limited.len(), 2);
+    }
+
+    #[test]
+    fn searxng_results_skip_non_web_urls() {
+        let entries = SearXng {
+            base_url: String::new(),
+        }
+        .parse_response(fixture!("searxng.json"), 10)
+        .unwrap();
+        assert_eq!(
+            urls(&entries),
+            [
+                "https://rust-lang.github.io/async-book/",
+                "https://tokio.rs/tokio/tutorial"
+            ]
+        );
+        assert_eq!(
+            entries[0].snippet,
+            "Getting started with async/.await in Rust."
+        );
+        assert_eq!(entries[1].snippet, "");
+        assert_eq!(entries[1].source, "SearXNG");
+    }
+
+    #[test]
+    fn brave_results_are_read_from_web_results() {
+        let entries = Brave {
+            api_key: String::new(),
+        }
+        .parse_response(fixture!("brave.json"), 10)
+        .unwrap();
+        assert_eq!(
+            urls(&entries),
+            [
+                "https://rust-lang.github.io/async-book/",
+                "https://tokio.rs/tokio/tutorial"
+            ]
+        );
+        assert_eq!(entries[1].title, "Tutorial | Tokio");
+        assert!(entries[0].snippet.starts_with("Getting started with"));
+    }
+
+    #[test]
+    fn bing_results_are_read_from_web_pages() {
+        let entries = Bing {
+            api_key: String::new(),
+        }
+        .parse_response(fixture!("bing.json"), 1)
+        .unwrap();
+        assert_eq!(urls(&entries), ["https://rust-lang.github.io/async-book/"]);
+        assert_eq!(entries[0].title, "Asynchronous Programming in Rust");
+        assert_eq!(
+            entries[0].snippet,
+            "Getting started with async/.await in Rust."
+        );
+        assert_eq!(entries[0].source, "Bing");
+    }
+
+    #[test]
+    fn tavily_snippets_are_collapsed_to_one_line() {
+        let entries = Tavily {
+            api_key: String::new(),
+        }
+        .parse_response(fixture!("tavily.json"), 10)
+        .unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(
+            entries[1].snippet,
+            "Tokio is an asynchronous runtime for the Rust programming language."
+        );
+    }
+
+    #[test]
+    fn generic_json_follows_dotted_paths() {
+        let provider = GenericJson {
+            url: String::new(),
+            api_key: String::new(),
+            results_path: "data.items".to_string(),
+            title_field: "headline".to_string(),
+            url_field: "link.href".to_string(),
+            snippet_field: "summary".to_string(),
+        };
+        let entries = provider
+            .parse_response(fixture!("generic.json"), 10)
+            .unwrap();
+        assert_eq!(
+            urls(&entries),
+            [
+                "https://rust-lang.github.io/async-book/",
+                "https://tokio.rs/tokio/tutorial"
+            ]
+        );
+        assert_eq!(entries[0].source, "Custom");
+
+        let missing = GenericJson {
+            results_path: "data.missing".to_string(),
+            ..provider
+        };
+        assert!(missing
+            .parse_response(fixture!("generic.json"), 10)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn malformed_json_is_an_error() {
+        let error = Bing {
+            api_key: String::new(),
+        }
+        .parse_response("<html>rate limited</html>", 10)
+        .unwrap_err();
+        assert!(error.contains("解析 Bing 搜索结果失败"), "{}", error);
+    }
+
+    #[tokio::test]
+    async fn fallback_skips_errors_and_empty_results_in_order() {
+        let configs = [
+            config("brave"),
+            config("bing"),
+            config("tavily"),
+            config("searxng"),
+        ];
+        let calls = RefCell::new(Vec::new());
+        let entries = search_with_fallback(&configs, |config, _| {
+            calls.borrow_mut().push(config.id.clone());
+            let outcome = match config.id.as_str() {
+                "brave" => Err("HTTP 429".to_string()),
+                "bing" => Ok(Vec::new()),
+                "tavily" => Ok(vec![hit("https://tavily.example/")]),
+                _ => Ok(vec![hit("https://searxng.example/")]),
+            };
+            async move { outcome }
+        })
+        .await
+        .unwrap();
+        assert_eq!(urls(&entries), ["https://tavily.example/"]);
+        assert_eq!(*calls.borrow(), ["brave", "bing", "tavily"]);
+    }
+
+    #[tokio::test]
+    async fn fallback_fails_only_when_every_provider_fails() {
+        let mut missing_key = config("bing");
+        missing_key.api_key.clear();
+        let configs = [config("brave"), missing_key];
+        let error = search_with_fallback(&configs, |_, _| async { Err("HTTP 503".to_string()) })
+            .await
+            .unwrap_err();
+        assert_eq!(error, "Brave: HTTP 503；Bing 需要配置 API Key");
+
+        let configs = [config("brave"), config("bing")];
+        let entries = search_with_fallback(&configs, |config, _| {
+            let outcome = if config.id == "brave" {
+                Err("HTTP 503".to_string())
+            } else {
+                Ok(Vec::new())
+            };
+            async move { outcome }
+        })
+        .await
+        .unwrap();
+        assert!(entries.is_empty());
+    }
+}
@@ -9,9 +9,12 @@ import {
 } from '../utils/local-file-tools.js';
 import {
     looksLikeWebSearchIntent,
+    looksLikeCrawlIntent,
     searchWeb,
-    fetchWebContent
+    fetchWebContent,
+    crawlSite
 } from '../utils/web-search.js';
+import { semanticSearch, buildSemanticContext } from '../utils/semantic-search.js';
 import { settingsStore } from './settings.js';
 import { notesStore } from './notes.js';
 
@@ -1285,6 +1288,57 @@ function filterTasksByTimeScope(tasks, timeScope, dateInfo) {
     });
 }
 
+const PROMPT_TASK_LIMIT = 40;
+const SEMANTIC_MIN_SCORE = 0.2;
+
+async function searchSemanticIndex(query, kinds, limit) {
+    if (!query?.trim()) return [];
+    try {
+        const hits = await semanticSearch({
+            config: get(settingsStore).embeddingConfig,
+            query,
+            kinds,
+            limit
+        });
+        return hits.filter(hit => hit.score >= SEMANTIC_MIN_SCORE);
+    } catch (error) {
+        console.warn('Semantic search unavailable:', error);
+        return [];
+    }
+}
+
+/**
+ * Tasks to put in a prompt about `text`. Short lists go in whole; longer ones
+ * are cut to the semantic index hits, then `preferred` (e.g. the tasks in the
+ * time range the user mentioned), then the tasks dated closest to now.
+ */
+async function selectPromptTasks(text, tasks = [], preferred = []) {
+    if (tasks.length <= PROMPT_TASK_LIMIT) return tasks;
+    const byId = new Map(tasks.map(task => [String(task.id), task]));
+    const selected = new Map();
+    for (const hit of await searchSemanticIndex(text, ['task'], PROMPT_TASK_LIMIT)) {
+        const task = byId.get(hit.id);
+        if (task) selected.set(hit.id, task);
+    }
+    const now = Date.now();
+    const distance = (task) => {
+        const time = new Date(task.date || '').getTime();
+        return Number.isNaN(time) ? Infinity : Math.abs(time - now);
+    };
+    const rest = [...tasks].sort((a, b) => distance(a) - distance(b));
+    for (const task of [...preferred, ...rest]) {
+        if (selected.size >= PROMPT_TASK_LIMIT) break;
+        if (byId.has(String(task.id))) selected.set(String(task.id), task);
+    }
+    return [...selected.values()];
+}
+
+function promptTaskHeading(title, promptTasks, allTasks) {
+    return promptTasks.length < allTasks.length
+        ? `【${title}（仅列出与请求最相关的 ${promptTasks.length} 个，共 ${allTasks.length} 个）】`
+        : `【${title}】`;
+}
+
 function normalizeAssistantPayload(payload = []) {
     if (Array.isArray(payload)) {
         return {
@@ -1535,7 +1589,7 @@ function formatNoteContextForAI(context = {}) {
 async function buildContextualAssistantResponse(userText, assistantContext, config, options = {}) {
     const { callAIWithMessages } = await import('../utils/ai-providers.js');
     const nowStr = getFormattedDateTime();
-    const projectContext = await getProjectContextSummary();
+    const projectContext = await getProjectContextSummary(userText);
     const scopedItems = formatContextItemsForAI(assistantContext.items, assistantContext);
     const noteContext = formatNoteContextForAI(assistantContext);
     const allowActions = options.allowActions ?? true;
@@ -1694,7 +1748,7 @@ function formatWebSearchResult(entries = []) {
 }
 
 async function analyzeWebSearchIntent(userText, config, callAI, intentHint = null) {
-    if (!looksLikeWebSearchIntent(userText) && intentHint !== 'web_search') {
+    if (!looksLikeWebSearchIntent(userText) && !looksLikeCrawlIntent(userText) && intentHint !== 'web_search') {
         return null;
     }
     const fallbackPlan = buildFallbackWebSearchIntent(userText);
@@ -1706,6 +1760,9 @@ async function analyzeWebSearchIntent(userText, config, callAI, intentHint = nul
 2. 用户的问题依赖实时信息，例如天气、股价、汇率、新闻、产品最新版本
 3. 用户要求给出网页结果、链接或在线资料
 
+【什么时候抓取整个站点】
+用户给出一个文档站/网站链接，并要求通读、总结或据此拆解任务计划时，使用 "crawl"，并在 url 中填写起始链接
+
 【什么时候不需要搜索】
 1. 只是项目内任务、模板、定时任务或笔记操作
 2. 只是让 AI 做一般性解释、写作或总结
@@ -1713,8 +1770,9 @@ async function analyzeWebSearchIntent(userText, config, callAI, intentHint = nul
 【输出格式】
 只返回 JSON：
 {
-  "mode": "reply|web",
+  "mode": "reply|web|crawl",
   "query": "精简后的搜索关键词",
+  "url": "crawl 模式下的起始链接",
   "response_goal": "基于搜索结果应如何回答用户",
   "message": "给用户的简短提示"
 }`;
@@ -1723,6 +1781,9 @@ async function analyzeWebSearchIntent(userText, config, callAI, intentHint = nul
         const aiResponse = await callAI(config, userText, systemPrompt);
         if (!aiResponse) return fallbackPlan;
         const parsed = extractJsonPayload(aiResponse);
+        if (parsed.mode === 'crawl' && /^https?:\/\//i.test(parsed.url || '')) {
+            return { ...fallbackPlan, ...parsed, query: parsed.query || userText };
+        }
         if (parsed.mode !== 'web' || !parsed.query) {
             return fallbackPlan;
         }
@@ -1848,10 +1909,47 @@ async function runLocalFilePlan(plan, userText, config, requireConfirmation = tr
     };
 }
 
+async function runCrawlPlan(plan, userText, config, onProgress = null) {
+    if (onProgress) onProgress('fetching');
+    const crawl = await crawlSite({ url: plan.url });
+    const entries = crawl.pages.map(page => ({
+        title: page.title || page.url,
+        url: page.url,
+        snippet: page.content.slice(0, 160),
+        source: 'crawl'
+    }));
+    const pageContents = crawl.pages
+        .map(page => `[${page.title || page.url}](${page.url})\n${page.content}`)
+        .join('\n\n---\n\n');
+
+    if (onProgress) onProgress('generating');
+    const summarized = await finalizeToolAnswer(
+        userText,
+        {
+            operation: 'crawl_site',
+            response_goal: plan.response_goal || '通读站点内容并总结要点；如用户需要，按模块拆解为可执行的任务计划。'
+        },
+        `共抓取 ${crawl.pages.length} 个页面${crawl.budgetExhausted ? '（已达页面上限）' : ''}：\n\n${pageContents}`,
+        config
+    );
+    return {
+        role: 'assistant',
+        type: 'web_search_result',
+        query: plan.url,
+        summary: summarized || `已抓取 ${crawl.pages.length} 个页面。`,
+        entries,
+        message: plan.message || '已完成站点抓取。'
+    };
+}
+
 async function runWebSearchPlan(plan, userText, config, onProgress = null) {
+    if (plan.mode === 'crawl' && plan.url) {
+        return await runCrawlPlan(plan, userText, config, onProgress);
+    }
     const results = await searchWeb({
         query: plan.query || userText,
-        maxResults: plan.maxResults || 6
+        maxResults: plan.maxResults || 6,
+        providers: get(settingsStore).webSearchConfig?.providers
     });
 
     // Fetch page content for top results to give AI richer context
@@ -2268,7 +2366,8 @@ async function analyzeScheduledUpdateIntent(userText, allTasks, relevantTasks, d
         };
     }
 
-    const taskList = allTasks.map(task => formatFullTaskForAI(task)).join('\n');
+    const promptTasks = await selectPromptTasks(userText, allTasks, relevantTasks);
+    const taskList = promptTasks.map(task => formatFullTaskForAI(task)).join('\n');
     const systemPrompt = `你是 WorkPlan 的定时任务助手。用户想修改定时任务。
 
 【现有定时任务】
@@ -2840,7 +2939,9 @@ async function analyzeSubtaskIntent(userText, allTasks, dateInfo, config, callAI
     }
 
     const nowStr = getFormattedDateTime();
-    const taskList = allTasks.map(t => formatFullTaskForAI(t)).join('\n');
+    const promptTasks = await selectPromptTasks(userText, allTasks);
+    const taskList = promptTasks.map(t => formatFullTaskForAI(t)).join('\n');
+    const taskHeading = promptTaskHeading('所有任务列表（含子任务详情）', promptTasks, allTasks);
 
     const systemPrompt = `你是一个智能任务管理助手。用户想要对任务的子任务进行操作。
 
@@ -2848,7 +2949,7 @@ async function analyzeSubtaskIntent(userText, allTasks, dateInfo, config, callAI
 【今天】${formatDateForAI(dateInfo.today)}
 【明天】${formatDateForAI(dateInfo.tomorrow)}
 
-【所有任务列表（含子任务详情）】
+${taskHeading}
 ${taskList}
 
 【子任务操作类型】
@@ -2933,7 +3034,8 @@ async function analyzeDeleteIntent(userText, allTasks, relevantTasks, dateInfo,
         };
     }
     const nowStr = getFormattedDateTime();
-    const taskList = relevantTasks.map(t => formatFullTaskForAI(t)).join('\n');
+    const promptTasks = await selectPromptTasks(userText, relevantTasks);
+    const taskList = promptTasks.map(t => formatFullTaskForAI(t)).join('\n');
     const systemPrompt = `你是一个智能任务管理助手。用户想要删除任务。
 【当前时间】${nowStr}
 【今天】${formatDateForAI(dateInfo.today)}
@@ -2997,7 +3099,9 @@ async function analyzeUpdateIntent(userText, allTasks, relevantTasks, dateInfo,
         };
     }
     const nowStr = getFormattedDateTime();
-    const taskList = allTasks.map(t => formatFullTaskForAI(t)).join('\n');
+    const promptTasks = await selectPromptTasks(userText, allTasks, relevantTasks);
+    const taskList = promptTasks.map(t => formatFullTaskForAI(t)).join('\n');
+    const taskHeading = promptTaskHeading('所有任务列表', promptTasks, allTasks);
     const lowerText = userText.toLowerCase();
     const statusKeywords = {
         done: ['完成', '搞定', '做完', '已完成', 'complete', 'done', 'finish'],
@@ -3015,7 +3119,7 @@ async function analyzeUpdateIntent(userText, allTasks, relevantTasks, dateInfo,
         const statusPrompt = `你是一个智能任务管理助手。用户想要将任务状态修改为 ${detectedStatus}。
 【当前时间】${nowStr}
 【今天】${formatDateForAI(dateInfo.today)}
-【所有任务列表】
+${taskHeading}
 ${taskList}
 【状态说明】
 - todo: 未开始
@@ -3089,7 +3193,7 @@ ${taskList}
 【今天】${formatDateForAI(dateInfo.today)}
 【明天】${formatDateForAI(dateInfo.tomorrow)}
 【后天】${formatDateForAI(dateInfo.dayAfterTomorrow)}
-【所有任务列表】
+${taskHeading}
 ${taskList}
 【重要规则】
 1. task_id 必须是完整的任务ID
@@ -3201,13 +3305,15 @@ async function analyzeMixedIntent(userText, allTasks, relevantTasks, dateInfo, c
         };
     }
     const nowStr = getFormattedDateTime();
-    const taskList = allTasks.map(t => formatFullTaskForAI(t)).join('\n');
+    const promptTasks = await selectPromptTasks(userText, allTasks, relevantTasks);
+    const taskList = promptTasks.map(t => formatFullTaskForAI(t)).join('\n');
+    const taskHeading = promptTaskHeading('所有任务列表', promptTasks, allTasks);
     const systemPrompt = `你是一个智能任务管理助手。用户想要修改任务。
 【当前时间】${nowStr}
 【今天】${formatDateForAI(dateInfo.today)}
 【明天】${formatDateForAI(dateInfo.tomorrow)}
 【后天】${formatDateForAI(dateInfo.dayAfterTomorrow)}
-【所有任务列表】
+${taskHeading}
 ${taskList}
 【优先级说明】
 - normal: 普通任务
@@ -3308,14 +3414,16 @@ async function analyzeQueryIntent(userText, allTasks, relevantTasks, dateInfo, c
         };
     }
     const nowStr = getFormattedDateTime();
-    const taskList = allTasks.map(t => formatFullTaskForAI(t)).join('\n');
+    const promptTasks = await selectPromptTasks(userText, allTasks, relevantTasks);
+    const taskList = promptTasks.map(t => formatFullTaskForAI(t)).join('\n');
+    const taskHeading = promptTaskHeading('所有任务列表', promptTasks, allTasks);
     const systemPrompt = `你是一个智能任务管理助手。用户想要查询任务。
 【当前时间】${nowStr}
 【今天】${formatDateForAI(dateInfo.today)}
 【明天】${formatDateForAI(dateInfo.tomorrow)}
 【后天】${formatDateForAI(dateInfo.dayAfterTomorrow)}
 【本周】${formatDateForAI(dateInfo.thisWeek.start)} 至 ${formatDateForAI(dateInfo.thisWeek.end)}
-【所有任务列表】
+${taskHeading}
 ${taskList}
 【查询类型】
 - 按时间: "今天的任务"、"明天有什么"、"本周的任务"
@@ -3359,7 +3467,7 @@ ${taskList}
     }
 }
 
-async function getProjectContextSummary() {
+async function getProjectContextSummary(query = '') {
     const { taskStore } = await import('./tasks.js');
 
     const taskState = get(taskStore);
@@ -3383,18 +3491,22 @@ async function getProjectContextSummary() {
         .map(note => `- [笔记] ${note.title} | ${note.category || '未分类'}`)
         .join('\n');
 
+    const related = buildSemanticContext(await searchSemanticIndex(query, ['note', 'file'], 6));
+
     return [
         '【项目上下文】',
         taskLines || '- [任务] 暂无',
         templateLines || '- [模板] 暂无',
         scheduledLines || '- [定时] 暂无',
-        noteLines || '- [笔记] 暂无'
+        noteLines || '- [笔记] 暂无',
+        ...(related ? ['', '【相关笔记与文件】', related] : [])
     ].join('\n');
 }
 
 async function buildContextMessages(history, chatStyle) {
     const nowStr = getFormattedDateTime();
-    const projectContext = await getProjectContextSummary();
+    const lastUserMessage = [...history].reverse().find(msg => msg.role === 'user');
+    const projectContext = await getProjectContextSummary(lastUserMessage?.content || '');
     const stylePrompts = {
         default: `你是一个智能助手。当前时间：${nowStr}。请用友好、专业的方式回答用户问题。\n${projectContext}`,
         fun: `你是 Grok，一个由 xAI 打造的 AI 助手。当前时间：${nowStr}。
@@ -127,6 +127,24 @@ function createNotesStore() {
             save(newState);
             return newState;
         }),
+        // Applies notes added, edited or deleted in the Markdown mirror folder.
+        applyMirrorChanges: ({ upserts = [], deleted = [] }) => update(s => {
+            const incoming = new Map(upserts.map(note => [note.id, note]));
+            const removed = new Set(deleted);
+            const notes = s.notes
+                .filter(n => !removed.has(n.id))
+                .map(n => incoming.has(n.id) ? incoming.get(n.id) : n);
+            const known = new Set(notes.map(n => n.id));
+            const added = upserts.filter(note => !known.has(note.id));
+            const categories = [...s.categories];
+            for (const note of upserts) {
+                if (note.category && !categories.includes(note.category)) categories.push(note.category);
+            }
+            const activeNoteId = removed.has(s.activeNoteId) ? null : s.activeNoteId;
+            const newState = { ...s, notes: [...added, ...notes], categories, activeNoteId };
+            save(newState);
+            return newState;
+        }),
         exportNotes: () => {
             const state = get({ subscribe });
             return JSON.stringify(state.notes, null, 2);
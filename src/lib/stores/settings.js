@@ -1,9 +1,28 @@
 import { writable, get } from 'svelte/store';
 import { _ as i18n } from 'svelte-i18n';
-import { getDefaultDatabaseConfig } from '../utils/database-providers.js';
+import { getDefaultDatabaseConfig, getDefaultSyncEncryption } from '../utils/database-providers.js';
 import { getDefaultLocalFileConfig, getWorkspaceRoot } from '../utils/local-file-tools.js';
+import { getDefaultWebSearchConfig, normalizeWebSearchConfig } from '../utils/web-search.js';
+import { getDefaultLanSyncConfig } from '../utils/sync.js';
+import { getDefaultBackupConfig } from '../utils/backup.js';
+import { getDefaultNotesMirrorConfig } from '../utils/notes-mirror.js';
+import { getDefaultEmbeddingConfig } from '../utils/semantic-search.js';
 
 const DARK_THEMES = new Set(['dark', 'graphite']);
+// Preferences that travel in a data archive. Credentials and device-specific
+// paths (database, web search, local files, LAN sync, backups, notes mirror)
+// stay behind.
+const PORTABLE_SETTINGS = [
+    'enableNotification',
+    'enableAiSummary',
+    'enableCharts',
+    'enableAiChatTools',
+    'closeToQuit',
+    'dailyReportPrompt',
+    'weeklyReportPrompt',
+    'theme',
+    'markdownEditor'
+];
 const NOTIFICATION_CHANNEL_ID = 'workplan-important';
 let themeTransitionTimer = null;
 let notificationChannelPromise = null;
@@ -55,6 +74,12 @@ function getInitialSettings() {
             markdownEditor: 'vditor',
             databaseConfig: getDefaultDatabaseConfig(),
             localFileConfig: getDefaultLocalFileConfig(),
+            webSearchConfig: getDefaultWebSearchConfig(),
+            lanSyncConfig: getDefaultLanSyncConfig(),
+            backupConfig: getDefaultBackupConfig(),
+            notesMirrorConfig: getDefaultNotesMirrorConfig(),
+            embeddingConfig: getDefaultEmbeddingConfig(),
+            reportFontPath: '',
             workspaceRoot: ''
         };
     }
@@ -87,6 +112,24 @@ function getInitialSettings() {
                     ...getDefaultLocalFileConfig(),
                     ...(parsed.localFileConfig || {})
                 },
+                webSearchConfig: normalizeWebSearchConfig(parsed.webSearchConfig),
+                lanSyncConfig: {
+                    ...getDefaultLanSyncConfig(),
+                    ...(parsed.lanSyncConfig || {})
+                },
+                backupConfig: {
+                    ...getDefaultBackupConfig(),
+                    ...(parsed.backupConfig || {})
+                },
+                notesMirrorConfig: {
+                    ...getDefaultNotesMirrorConfig(),
+                    ...(parsed.notesMirrorConfig || {})
+                },
+                embeddingConfig: {
+                    ...getDefaultEmbeddingConfig(),
+                    ...(parsed.embeddingConfig || {})
+                },
+                reportFontPath: parsed.reportFontPath || '',
                 workspaceRoot: ''
             };
         } catch {
@@ -116,6 +159,12 @@ function getDefaultSettings() {
         markdownEditor: 'vditor',
         databaseConfig: getDefaultDatabaseConfig(),
         localFileConfig: getDefaultLocalFileConfig(),
+        webSearchConfig: getDefaultWebSearchConfig(),
+        lanSyncConfig: getDefaultLanSyncConfig(),
+        backupConfig: getDefaultBackupConfig(),
+        notesMirrorConfig: getDefaultNotesMirrorConfig(),
+        embeddingConfig: getDefaultEmbeddingConfig(),
+        reportFontPath: '',
         workspaceRoot: ''
     };
 }
@@ -138,7 +187,13 @@ function createSettingsStore() {
             theme: state.theme,
             markdownEditor: state.markdownEditor,
             databaseConfig: state.databaseConfig,
-            localFileConfig: state.localFileConfig
+            localFileConfig: state.localFileConfig,
+            webSearchConfig: state.webSearchConfig,
+            lanSyncConfig: state.lanSyncConfig,
+            backupConfig: state.backupConfig,
+            notesMirrorConfig: state.notesMirrorConfig,
+            embeddingConfig: state.embeddingConfig,
+            reportFontPath: state.reportFontPath
         }));
     }
 
@@ -360,6 +415,23 @@ function createSettingsStore() {
             save(newState);
             return newState;
         }),
+        exportSettings: () => {
+            const state = get({ subscribe });
+            return Object.fromEntries(PORTABLE_SETTINGS.map(key => [key, state[key]]));
+        },
+        importSettings: (values) => {
+            const current = get({ subscribe });
+            const picked = Object.fromEntries(
+                PORTABLE_SETTINGS.filter(key => values?.[key] !== undefined).map(key => [key, values[key]])
+            );
+            const newState = { ...current, ...picked };
+            if (newState.closeToQuit !== current.closeToQuit) {
+                syncCloseToQuit(newState.closeToQuit);
+            }
+            applyTheme(newState.theme, { animate: true });
+            save(newState);
+            set(newState);
+        },
         updateDatabaseConfig: (updates) => update(s => {
             const newState = {
                 ...s,
@@ -372,6 +444,24 @@ function createSettingsStore() {
             save(newState);
             return newState;
         }),
+        // Changing the passphrase keeps the old one for decryption until the next
+        // successful sync re-encrypts the cloud copy with the new key.
+        updateSyncEncryption: (updates) => update(s => {
+            const databaseConfig = { ...getDefaultDatabaseConfig(), ...(s.databaseConfig || {}) };
+            const current = { ...getDefaultSyncEncryption(), ...(databaseConfig.encryption || {}) };
+            const next = { ...current, ...updates };
+            if (updates.passphrase !== undefined && updates.passphrase !== current.passphrase) {
+                next.previousPassphrases = [current.passphrase, ...current.previousPassphrases]
+                    .filter((value, index, list) => value !== next.passphrase && list.indexOf(value) === index)
+                    .slice(0, 5);
+            }
+            const newState = {
+                ...s,
+                databaseConfig: { ...databaseConfig, encryption: next }
+            };
+            save(newState);
+            return newState;
+        }),
         updateLocalFileConfig: (updates) => update(s => {
             const newState = {
                 ...s,
@@ -384,6 +474,79 @@ function createSettingsStore() {
             save(newState);
             return newState;
         }),
+        updateLanSyncConfig: (updates) => update(s => {
+            const newState = {
+                ...s,
+                lanSyncConfig: {
+                    ...getDefaultLanSyncConfig(),
+                    ...(s.lanSyncConfig || {}),
+                    ...updates
+                }
+            };
+            save(newState);
+            return newState;
+        }),
+        updateBackupConfig: (updates) => update(s => {
+            const newState = {
+                ...s,
+                backupConfig: {
+                    ...getDefaultBackupConfig(),
+                    ...(s.backupConfig || {}),
+                    ...updates
+                }
+            };
+            save(newState);
+            return newState;
+        }),
+        updateNotesMirrorConfig: (updates) => update(s => {
+            const newState = {
+                ...s,
+                notesMirrorConfig: {
+                    ...getDefaultNotesMirrorConfig(),
+                    ...(s.notesMirrorConfig || {}),
+                    ...updates
+                }
+            };
+            save(newState);
+            return newState;
+        }),
+        updateEmbeddingConfig: (updates) => update(s => {
+            const newState = {
+                ...s,
+                embeddingConfig: {
+                    ...getDefaultEmbeddingConfig(),
+                    ...(s.embeddingConfig || {}),
+                    ...updates
+                }
+            };
+            save(newState);
+            return newState;
+        }),
+        updateWebSearchProvider: (id, updates) => update(s => {
+            const config = normalizeWebSearchConfig(s.webSearchConfig);
+            const newState = {
+                ...s,
+                webSearchConfig: {
+                    ...config,
+                    providers: config.providers.map(provider =>
+                        provider.id === id ? { ...provider, ...updates } : provider
+                    )
+                }
+            };
+            save(newState);
+            return newState;
+        }),
+        moveWebSearchProvider: (id, offset) => update(s => {
+            const config = normalizeWebSearchConfig(s.webSearchConfig);
+            const providers = [...config.providers];
+            const from = providers.findIndex(provider => provider.id === id);
+            const to = from + offset;
+            if (from < 0 || to < 0 || to >= providers.length) return s;
+            [providers[from], providers[to]] = [providers[to], providers[from]];
+            const newState = { ...s, webSearchConfig: { ...config, providers } };
+            save(newState);
+            return newState;
+        }),
         addTrustedDirectory: (directory) => update(s => {
             const value = String(directory || '').trim();
             if (!value) return s;
@@ -457,6 +620,11 @@ function createSettingsStore() {
             save(newState);
             return newState;
         }),
+        setReportFontPath: (path) => update(s => {
+            const newState = { ...s, reportFontPath: path };
+            save(newState);
+            return newState;
+        }),
         acceptAgreement: () => update(s => {
             const newState = { ...s, agreementAccepted: true, showAgreement: false };
             save(newState);
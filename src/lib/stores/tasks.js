@@ -1,5 +1,6 @@
-import { writable, derived } from 'svelte/store';
-import { getDefaultDatabaseConfig } from '../utils/database-providers.js';
+import { writable, derived, get } from 'svelte/store';
+import { getDefaultDatabaseConfig, isNativeOnlyService } from '../utils/database-providers.js';
+import { isNativeSyncAvailable, syncNow, clearSyncData, retryPendingSync, syncLanPeer, respondLanSync } from '../utils/sync.js';
 
 const DEFAULT_SUPABASE_URL = import.meta.env.VITE_SUPABASE_URL || '';
 const DEFAULT_SUPABASE_KEY = import.meta.env.VITE_SUPABASE_KEY || '';
@@ -36,7 +37,7 @@ function readDatabaseConfig() {
             ...savedConfig
         };
         if (!useCustomConfig) {
-            return fallback;
+            return { ...fallback, encryption: savedConfig.encryption || fallback.encryption };
         }
         return {
             ...databaseConfig,
@@ -54,6 +55,14 @@ function shouldUseCustomHttpAdapter(databaseConfig) {
     return Boolean(databaseConfig?.useCustomConfig);
 }
 
+function isWebSyncUnsupported(databaseConfig) {
+    if (!shouldUseCustomHttpAdapter(databaseConfig) || !isNativeOnlyService(databaseConfig.service)) {
+        return false;
+    }
+    console.warn(`${databaseConfig.service} sync is only available in the desktop and mobile apps.`);
+    return true;
+}
+
 function trimTrailingSlash(value = '') {
     return String(value || '').trim().replace(/\/+$/, '');
 }
@@ -181,6 +190,10 @@ async function loadCloudRecord(accessKey) {
         return null;
     }
 
+    if (isWebSyncUnsupported(databaseConfig)) {
+        return null;
+    }
+
     if (shouldUseCustomHttpAdapter(databaseConfig)) {
         return await requestRestRecord(databaseConfig, accessKey);
     }
@@ -206,6 +219,10 @@ async function saveCloudRecord(accessKey, content, updatedAt) {
         return;
     }
 
+    if (isWebSyncUnsupported(databaseConfig)) {
+        return;
+    }
+
     if (shouldUseCustomHttpAdapter(databaseConfig)) {
         await upsertRestRecord(databaseConfig, accessKey, content, updatedAt);
         return;
@@ -229,6 +246,10 @@ async function deleteCloudRecord(accessKey) {
         return;
     }
 
+    if (isWebSyncUnsupported(databaseConfig)) {
+        return;
+    }
+
     if (shouldUseCustomHttpAdapter(databaseConfig)) {
         await deleteRestRecord(databaseConfig, accessKey);
         return;
@@ -266,6 +287,9 @@ function createTaskStore() {
     });
 
     let saveTimer = null;
+    // Set when the cloud copy is end-to-end encrypted, which only the native sync
+    // engine can read; writing plaintext over it would discard it.
+    let encryptedRemote = false;
 
     function getTableName() {
         return readDatabaseConfig().tableName || DEFAULT_TABLE_NAME;
@@ -284,6 +308,59 @@ function createTaskStore() {
         return JSON.stringify(copy);
     }
 
+    function keepLocalFields(current, next) {
+        const expanded = new Map(current.map(item => [item.id, item.expanded]));
+        return next.map(item => expanded.has(item.id) ? { ...item, expanded: expanded.get(item.id) } : item);
+    }
+
+    // Adopts the merged result of a native sync unless the user edited in the
+    // meantime; the pending save will sync those edits again.
+    // A queued result still carries the local replica; the background replay
+    // reports back through `applyRemoteSync`.
+    function applySyncedData(data, expectedStr = null, queued = false) {
+        update(s => {
+            if (expectedStr !== null && getPureDataString({
+                tasks: s.tasks,
+                templates: s.templates,
+                scheduledTasks: s.scheduledTasks
+            }) !== expectedStr) {
+                return s;
+            }
+            const merged = {
+                tasks: keepLocalFields(s.tasks, data?.tasks || []),
+                templates: keepLocalFields(s.templates, data?.templates || []),
+                scheduledTasks: keepLocalFields(s.scheduledTasks, data?.scheduledTasks || [])
+            };
+            return { ...s, ...merged, syncStatus: queued ? 'queued' : 'done', lastCloudStr: getPureDataString(merged) };
+        });
+        if (queued) return;
+        setTimeout(() => update(s => s.syncStatus === 'done' ? { ...s, syncStatus: 'idle' } : s), 3000);
+    }
+
+    function currentPureData(state) {
+        return getPureDataString({
+            tasks: state.tasks,
+            templates: state.templates,
+            scheduledTasks: state.scheduledTasks
+        });
+    }
+
+    // A LAN result comes from another device rather than the cloud, so it is
+    // saved like a local edit and still reaches the configured database.
+    function applyLanData(data, expectedStr) {
+        update(s => {
+            if (currentPureData(s) !== expectedStr) return s;
+            const newState = {
+                ...s,
+                tasks: keepLocalFields(s.tasks, data?.tasks || []),
+                templates: keepLocalFields(s.templates, data?.templates || []),
+                scheduledTasks: keepLocalFields(s.scheduledTasks, data?.scheduledTasks || [])
+            };
+            if (currentPureData(newState) !== expectedStr) saveData(newState);
+            return newState;
+        });
+    }
+
     async function loadData(accessKey) {
         const databaseConfig = readDatabaseConfig();
         if (!databaseConfig.enabled || !databaseConfig.url || !databaseConfig.apiKey) {
@@ -291,9 +368,26 @@ function createTaskStore() {
             return;
         }
         update(s => ({ ...s, syncStatus: 'syncing' }));
+        if (isNativeSyncAvailable()) {
+            try {
+                const result = await syncNow({ config: databaseConfig, accessKey });
+                applySyncedData(result.data, null, result.queued);
+            } catch (e) {
+                console.error('Sync error:', e);
+                update(s => ({ ...s, syncStatus: 'error' }));
+            }
+            return;
+        }
+        encryptedRemote = false;
         try {
             const data = await loadCloudRecord(accessKey);
 
+            if (data?.content?._encrypted) {
+                encryptedRemote = true;
+                console.warn('Cloud data is end-to-end encrypted and can only be synced by the desktop or mobile app.');
+                update(s => ({ ...s, syncStatus: 'error' }));
+                return;
+            }
             if (data && data.content) {
                 const json = data.content;
                 const cloudStr = getPureDataString({
@@ -329,7 +423,7 @@ function createTaskStore() {
     }
 
     async function saveData(state) {
-        if (!state.accessKey) return;
+        if (!state.accessKey || encryptedRemote) return;
 
         const currentPureStr = getPureDataString({
             tasks: state.tasks,
@@ -346,6 +440,20 @@ function createTaskStore() {
             try {
                 const nowTimestamp = Date.now();
                 const rawData = JSON.parse(currentPureStr);
+                if (isNativeSyncAvailable()) {
+                    const databaseConfig = readDatabaseConfig();
+                    if (!databaseConfig.enabled || !databaseConfig.url || !databaseConfig.apiKey) {
+                        update(s => ({ ...s, syncStatus: 'idle' }));
+                        return;
+                    }
+                    const result = await syncNow({ config: databaseConfig, accessKey: state.accessKey, data: rawData });
+                    applySyncedData(result.data, currentPureStr, result.queued);
+                    return;
+                }
+                if (isWebSyncUnsupported(readDatabaseConfig())) {
+                    update(s => ({ ...s, syncStatus: 'idle' }));
+                    return;
+                }
                 await saveCloudRecord(state.accessKey, rawData, nowTimestamp);
                 update(s => ({ ...s, syncStatus: 'done', lastCloudStr: currentPureStr }));
                 setTimeout(() => update(s => s.syncStatus === 'done' ? { ...s, syncStatus: 'idle' } : s), 3000);
@@ -466,13 +574,28 @@ function createTaskStore() {
             saveData(newState);
             return newState;
         }),
+        // Appends tasks converted from another app's export.
+        importTasks: ({ tasks = [], scheduledTasks = [] }) => update(s => {
+            const newState = {
+                ...s,
+                tasks: [...s.tasks, ...tasks],
+                scheduledTasks: [...s.scheduledTasks, ...scheduledTasks]
+            };
+            saveData(newState);
+            return newState;
+        }),
         checkScheduled: () => update(s => {
             checkScheduledTasks(s);
             return s;
         }),
         clearAllData: async (accessKey) => {
             try {
-                await deleteCloudRecord(accessKey);
+                const databaseConfig = readDatabaseConfig();
+                if (isNativeSyncAvailable() && databaseConfig.enabled && databaseConfig.url && databaseConfig.apiKey) {
+                    await clearSyncData({ config: databaseConfig, accessKey });
+                } else {
+                    await deleteCloudRecord(accessKey);
+                }
             } catch (e) {
                 console.error('Delete error:', e);
             }
@@ -481,6 +604,36 @@ function createTaskStore() {
             }
             set({ tasks: [], templates: [], scheduledTasks: [], accessKey: null, syncStatus: 'idle', lastCloudStr: '' });
         },
+        applyRemoteSync: ({ accessKey, outcome }) => {
+            if (get({ subscribe }).accessKey !== accessKey) return;
+            applySyncedData(outcome.data, null, outcome.queued);
+        },
+        syncWithLanPeer: async (peerId) => {
+            const state = get({ subscribe });
+            const pureStr = currentPureData(state);
+            const result = await syncLanPeer({ peerId, accessKey: state.accessKey || '', data: JSON.parse(pureStr) });
+            applyLanData(result.data, pureStr);
+            return result;
+        },
+        // Hands the current data to a sync started by a paired device; declined
+        // when not logged in.
+        answerLanSync: async (requestId) => {
+            const state = get({ subscribe });
+            const pureStr = currentPureData(state);
+            const result = await respondLanSync({ requestId, accessKey: state.accessKey || '', data: JSON.parse(pureStr) });
+            applyLanData(result.data, pureStr);
+            return result;
+        },
+        // Replays a sync queued while offline with the current credentials.
+        retryQueuedSync: async () => {
+            const { accessKey } = get({ subscribe });
+            const databaseConfig = readDatabaseConfig();
+            if (!accessKey || !isNativeSyncAvailable() || !databaseConfig.enabled) return 0;
+            return await retryPendingSync({ config: databaseConfig, accessKey });
+        },
+        markSyncFailed: (accessKey) => {
+            update(s => s.accessKey === accessKey ? { ...s, syncStatus: 'error' } : s);
+        },
         exportData: (state) => {
             return JSON.stringify({
                 tasks: state.tasks,
@@ -0,0 +1,27 @@
+import { writable } from 'svelte/store';
+import { getTimeTracking } from '../utils/time-tracking.js';
+
+/** Latest `{ active, idleMinutes, now }` from the backend. */
+export const timerStatus = writable({ active: null, idleMinutes: 10, now: 0 });
+
+/**
+ * Loads the timer and follows `time-tracking-changed`. `onIdlePaused` runs
+ * when idle detection pauses a running timer. Resolves to an unlisten function.
+ */
+export async function initTimeTracking({ onIdlePaused = () => {} } = {}) {
+    const { listen } = await import('@tauri-apps/api/event');
+    let previous = null;
+    const apply = (status) => {
+        if (status.active?.idlePaused && previous?.active?.running) {
+            onIdlePaused(status.active);
+        }
+        previous = status;
+        timerStatus.set(status);
+    };
+    const unlisten = await listen('time-tracking-changed', ({ payload }) => apply(payload));
+    const initial = await getTimeTracking();
+    // A timer cut while the app was closed is reported like one cut just now.
+    if (initial.active?.idlePaused) onIdlePaused(initial.active);
+    apply(initial);
+    return unlisten;
+}
@@ -0,0 +1,77 @@
+import { get } from 'svelte/store';
+import { taskStore } from '../stores/tasks.js';
+import { notesStore } from '../stores/notes.js';
+import { passwordsStore } from '../stores/passwords.js';
+import { loadAiConfig } from '../stores/ai.js';
+
+const NOTES_KEY = 'planpro_notes';
+const PASSWORDS_KEY = 'planpro_passwords';
+const MASTER_KEY = 'planpro_master_hash';
+const PASSWORDS_SETTINGS_KEY = 'planpro_passwords_settings';
+const CHATS_KEY = 'planpro_ai_chat_sessions';
+
+export const BACKUP_SECTIONS = ['tasks', 'notes', 'vault', 'chats'];
+
+function readJson(key, fallback) {
+    const saved = localStorage.getItem(key);
+    if (!saved) return fallback;
+    try {
+        return JSON.parse(saved);
+    } catch {
+        return fallback;
+    }
+}
+
+/**
+ * Gathers everything a backup covers. Tasks are only loaded once an access key
+ * is entered, so they are left out before that rather than saved as empty.
+ * Vault entries stay encrypted with the master password, so a restored vault
+ * opens with the password it was saved under.
+ */
+export function collectBackupData() {
+    const tasks = get(taskStore);
+    const data = {
+        notes: readJson(NOTES_KEY, { notes: [], categories: [], aiPrompts: [] }),
+        vault: {
+            passwords: readJson(PASSWORDS_KEY, []),
+            masterHash: localStorage.getItem(MASTER_KEY) || '',
+            settings: readJson(PASSWORDS_SETTINGS_KEY, {})
+        },
+        chats: readJson(CHATS_KEY, { activeSessionId: null, sessions: [] })
+    };
+    if (tasks.accessKey) {
+        data.tasks = {
+            tasks: tasks.tasks || [],
+            templates: tasks.templates || [],
+            scheduledTasks: tasks.scheduledTasks || []
+        };
+    }
+    return data;
+}
+
+/** Adopts restored sections and reloads the stores that read them. */
+export function applyBackupData(data) {
+    if (data?.tasks) {
+        const result = taskStore.importData(JSON.stringify(data.tasks));
+        if (!result.success) throw new Error(result.error);
+    }
+    if (data?.notes) {
+        localStorage.setItem(NOTES_KEY, JSON.stringify(data.notes));
+        notesStore.load();
+    }
+    if (data?.vault) {
+        localStorage.setItem(PASSWORDS_KEY, JSON.stringify(data.vault.passwords || []));
+        if (data.vault.masterHash) {
+            localStorage.setItem(MASTER_KEY, data.vault.masterHash);
+        } else {
+            localStorage.removeItem(MASTER_KEY);
+        }
+        localStorage.setItem(PASSWORDS_SETTINGS_KEY, JSON.stringify(data.vault.settings || {}));
+        passwordsStore.lock();
+        passwordsStore.load();
+    }
+    if (data?.chats) {
+        localStorage.setItem(CHATS_KEY, JSON.stringify(data.chats));
+        loadAiConfig();
+    }
+}
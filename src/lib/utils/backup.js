@@ -0,0 +1,38 @@
+import { invoke } from '@tauri-apps/api/core';
+
+export const BACKUP_INTERVALS = [1, 6, 24, 168];
+
+export function getDefaultBackupConfig() {
+    return {
+        enabled: false,
+        directory: '',
+        intervalHours: 24,
+        keep: 7,
+        encrypt: false,
+        passphrase: ''
+    };
+}
+
+export async function configureBackups(config) {
+    return await invoke('configure_backups', { config: { ...getDefaultBackupConfig(), ...config } });
+}
+
+export async function getBackupStatus() {
+    return await invoke('get_backup_status');
+}
+
+export async function createBackup(data, reason = 'manual') {
+    return await invoke('create_backup', { data, reason });
+}
+
+export async function listBackups() {
+    return await invoke('list_backups');
+}
+
+/**
+ * Compares a backup with `current`; unless `dryRun`, the result also carries the
+ * backup's data and the pre-restore snapshot taken of `current`.
+ */
+export async function restoreBackup({ id, current, dryRun = true, passphrase = '', sections = null }) {
+    return await invoke('restore_backup', { id, current, dryRun, passphrase, sections });
+}
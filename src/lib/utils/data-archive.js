@@ -0,0 +1,42 @@
+import { invoke } from '@tauri-apps/api/core';
+import { settingsStore } from '../stores/settings.js';
+import { collectBackupData, applyBackupData } from './backup-data.js';
+
+export const ARCHIVE_EXTENSION = 'workplan';
+
+/** The sections a `.workplan` archive carries, flattened the way it stores them. */
+export function collectArchiveData() {
+    const { tasks, notes, vault } = collectBackupData();
+    return { ...(tasks || {}), notes, vault, settings: settingsStore.exportSettings() };
+}
+
+export function applyArchiveData(data) {
+    const { tasks, templates, scheduledTasks, settings, ...rest } = data || {};
+    const taskSection = tasks || templates || scheduledTasks ? { tasks, templates, scheduledTasks } : null;
+    applyBackupData({ ...rest, tasks: taskSection });
+    if (settings) settingsStore.importSettings(settings);
+}
+
+export async function exportArchive({ path = null, includeAttachments = true, includeVault = false, passphrase = '' } = {}) {
+    return await invoke('export_workplan_archive', {
+        path,
+        data: collectArchiveData(),
+        options: { includeAttachments, includeVault, passphrase }
+    });
+}
+
+export async function inspectArchive(path, passphrase = '') {
+    return await invoke('inspect_workplan_archive', { path, passphrase });
+}
+
+/** Imports with `mode` 'merge' or 'replace' and adopts the resulting data. */
+export async function importArchive(path, { mode = 'merge', passphrase = '' } = {}) {
+    const result = await invoke('import_workplan_archive', {
+        path,
+        mode,
+        current: collectArchiveData(),
+        passphrase
+    });
+    applyArchiveData(result.data);
+    return result;
+}
@@ -41,8 +41,9 @@ export const DATABASE_PROVIDER_CATALOG = [
         databaseType: 'SQLite (libSQL)',
         freeTier: '5 GB',
         paidTier: 'Developer 9 GB 起（然后 $0.50–$0.75/GB）',
-        notes: '免费层很慷慨，适合多数据库场景；当前设置页可保存接入信息，后续可切换 libSQL 适配。',
-        directSync: false
+        notes: '免费层很慷慨，适合多数据库场景。地址填数据库 URL（libsql:// 或 https://），API Key 填数据库令牌；首次同步会自动建表。仅桌面端和移动端可用。',
+        directSync: true,
+        nativeOnly: true
     },
     {
         id: 'pocketbase',
@@ -50,8 +51,9 @@ export const DATABASE_PROVIDER_CATALOG = [
         databaseType: 'SQLite',
         freeTier: '无限制（自托管）',
         paidTier: '无（自托管）',
-        notes: '容量取决于你的 VPS / 服务器磁盘大小；适合单文件数据库场景。',
-        directSync: false
+        notes: '容量取决于你的 VPS / 服务器磁盘大小；适合单文件数据库场景。需先创建 planpro_data 集合（字段 user_key 文本且唯一、content JSON、updated_at 数字），填写账号邮箱与密码，或留空用户名并在 API Key 中填写令牌。仅桌面端和移动端可用。',
+        directSync: true,
+        nativeOnly: true
     },
     {
         id: 'appwrite',
@@ -71,6 +73,26 @@ export const DATABASE_PROVIDER_CATALOG = [
         notes: '单文档最大 1 MiB；总存储可扩展，但数据模型与 SQL 差异较大。',
         directSync: false
     },
+    {
+        id: 'webdav',
+        name: 'WebDAV',
+        databaseType: '文件 (JSON)',
+        freeTier: '取决于网盘 / 服务器',
+        paidTier: '取决于网盘 / 服务器',
+        notes: '支持 Nextcloud、Synology、坚果云等 WebDAV 服务：地址填同步目录（如 https://cloud.example.com/remote.php/dav/files/用户名/WorkPlan），并填写用户名与应用密码。仅桌面端和移动端可用，建议同时开启端到端加密。',
+        directSync: true,
+        nativeOnly: true
+    },
+    {
+        id: 's3',
+        name: 'S3 兼容对象存储',
+        databaseType: '对象存储 (JSON 快照)',
+        freeTier: 'R2 10 GB / B2 10 GB / MinIO 自托管',
+        paidTier: '按存储量计费，约 $0.006–$0.023/GB/月',
+        notes: '支持 AWS S3、MinIO、Cloudflare R2、Backblaze B2：地址填 Endpoint（如 https://<account>.r2.cloudflarestorage.com），并填写存储桶、区域与访问密钥。每次同步保存一个带版本的快照（保留最近 10 份），依赖条件写入检测冲突。仅桌面端和移动端可用，建议同时开启端到端加密。',
+        directSync: true,
+        nativeOnly: true
+    },
     {
         id: 'self-hosted',
         name: '自托管',
@@ -90,13 +112,29 @@ export function getDefaultDatabaseConfig() {
         url: '',
         apiKey: '',
         tableName: 'planpro_data',
+        username: '',
+        bucket: '',
         databaseName: '',
         projectId: '',
         region: '',
-        notes: ''
+        notes: '',
+        encryption: getDefaultSyncEncryption()
+    };
+}
+
+export function getDefaultSyncEncryption() {
+    return {
+        enabled: false,
+        passphrase: '',
+        previousPassphrases: []
     };
 }
 
 export function getDatabaseProviderMeta(service) {
     return DATABASE_PROVIDER_CATALOG.find((item) => item.id === service) || DATABASE_PROVIDER_CATALOG[0];
 }
+
+// Services synced only by the Rust sync layer; the browser build cannot reach them.
+export function isNativeOnlyService(service) {
+    return DATABASE_PROVIDER_CATALOG.some((item) => item.id === service && item.nativeOnly);
+}
@@ -30,6 +30,42 @@ async function downloadFile(content, filename) {
     return { success: true };
 }
 
+/**
+ * Binary counterpart of downloadFile for PDFs, images and archives: the bytes go
+ * to the backend as the raw invoke body, so nothing is re-encoded as text.
+ * `dialog` asks for the location with a native save dialog (desktop),
+ * `reveal` shows the saved file in its folder.
+ * @returns {{ success: boolean, path?: string, cancelled?: boolean }}
+ */
+export async function saveBinaryFile(data, filename, options = {}) {
+    const { mime = 'application/octet-stream', dialog = false, reveal = false } = options;
+    const bytes = data instanceof Uint8Array ? data : new Uint8Array(data);
+    try {
+        if (window.__TAURI__) {
+            const saved = await invoke('save_bytes_to_downloads', bytes, {
+                headers: {
+                    'x-filename': encodeURIComponent(filename),
+                    'x-mime-type': mime,
+                    'x-save-dialog': dialog ? '1' : '0',
+                    'x-reveal': reveal ? '1' : '0'
+                }
+            });
+            return saved ? { success: true, path: saved.path } : { success: false, cancelled: true };
+        }
+    } catch (e) {
+        console.warn('Tauri save failed, falling back to browser download:', e);
+    }
+
+    const blob = new Blob([bytes], { type: mime });
+    const url = URL.createObjectURL(blob);
+    const a = document.createElement('a');
+    a.href = url;
+    a.download = filename;
+    a.click();
+    URL.revokeObjectURL(url);
+    return { success: true };
+}
+
 function cleanOklchColors(element) {
     const clone = element.cloneNode(true);
     const allElements = [clone, ...clone.querySelectorAll('*')];
@@ -50,7 +86,7 @@ function cleanOklchColors(element) {
 }
 
 export async function exportToPDF(element, filename = 'export.pdf', options = {}) {
-    const { width = 'a4', showToast } = options;
+    const { width = 'a4', showToast, dialog = false, reveal = false } = options;
 
     try {
         if (showToast) showToast({ message: '正在生成PDF...', type: 'info', duration: 2000 });
@@ -128,12 +164,15 @@ export async function exportToPDF(element, filename = 'export.pdf', options = {}
             }
         }
 
-        // PDF uses binary output — jsPDF.save() works on desktop but may not on Android WebView
-        // For now, use jsPDF.save() which handles blob download internally
-        pdf.save(filename);
+        const result = await saveBinaryFile(pdf.output('arraybuffer'), filename, {
+            mime: 'application/pdf',
+            dialog,
+            reveal
+        });
+        if (result.cancelled) return result;
 
         if (showToast) showToast({ message: 'PDF导出成功', type: 'success' });
-        return { success: true };
+        return result;
     } catch (e) {
         console.error('PDF export failed:', e);
         if (showToast) showToast({ message: 'PDF导出失败: ' + e.message, type: 'error' });
@@ -0,0 +1,67 @@
+import { invoke } from '@tauri-apps/api/core';
+
+export async function startFileWatcher({
+    trustedDirectories = [],
+    debounceMs = 800
+} = {}) {
+    return await invoke('start_file_watcher', {
+        trustedDirs: trustedDirectories,
+        debounceMs
+    });
+}
+
+export async function stopFileWatcher() {
+    return await invoke('stop_file_watcher');
+}
+
+export async function getFileWatcherStatus() {
+    return await invoke('get_file_watcher_status');
+}
+
+export async function attachTaskToFile({
+    taskId,
+    path,
+    action = 'annotate',
+    trustedDirectories = []
+} = {}) {
+    return await invoke('attach_task_to_file', {
+        taskId: String(taskId),
+        path,
+        action,
+        trustedDirs: trustedDirectories
+    });
+}
+
+export async function detachTaskFromFile({
+    taskId,
+    path = ''
+} = {}) {
+    return await invoke('detach_task_from_file', {
+        taskId: String(taskId),
+        path: path || null
+    });
+}
+
+export async function listFileTaskLinks() {
+    return await invoke('list_file_task_links');
+}
+
+function localTimestamp(millis) {
+    const date = new Date(millis || Date.now());
+    return new Date(date.getTime() - date.getTimezoneOffset() * 60000).toISOString().slice(0, 16);
+}
+
+export function buildFileTriggerUpdates(task, event) {
+    const at = localTimestamp(event.timestamp);
+    const kindLabel = event.kind === 'create' ? '已创建' : '已更新';
+    const line = `[${at.replace('T', ' ')}] 关联文件${kindLabel}：${event.path}`;
+    const updates = {
+        note: task.note ? `${task.note}\n${line}` : line
+    };
+    if (event.action === 'complete' && task.status !== 'done') {
+        updates.status = 'done';
+        updates.completedDate = at;
+        updates.subtasks = task.subtasks?.map(s => ({ ...s, status: 'done' }));
+    }
+    return updates;
+}
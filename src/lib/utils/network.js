@@ -0,0 +1,27 @@
+import { invoke } from '@tauri-apps/api/core';
+
+export function getDefaultNetworkConfig() {
+    return {
+        fetchPolicy: {
+            allowPrivateNetworks: false,
+            allowList: [],
+            maxResponseBytes: 5 * 1024 * 1024
+        },
+        proxy: {
+            mode: 'system',
+            url: '',
+            noProxy: ''
+        },
+        caCertificates: [],
+        connectTimeoutSecs: 10,
+        timeoutSecs: 30
+    };
+}
+
+export async function getNetworkConfig() {
+    return await invoke('get_network_config');
+}
+
+export async function setNetworkConfig(config) {
+    return await invoke('set_network_config', { config });
+}
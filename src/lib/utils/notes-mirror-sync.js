@@ -0,0 +1,82 @@
+import { get } from 'svelte/store';
+import { notesStore } from '../stores/notes.js';
+import { settingsStore } from '../stores/settings.js';
+import { syncNotesMirror, startNotesMirror, stopNotesMirror } from './notes-mirror.js';
+
+// Edits in the app are synced once typing pauses.
+const EDIT_DEBOUNCE_MS = 1500;
+
+let running = null;
+let rerun = false;
+let stopCurrent = () => {};
+
+/**
+ * Syncs the notes with the mirror folder and applies what changed on disk.
+ * Calls made while a sync runs are folded into one more run after it.
+ */
+export async function runNotesMirrorSync() {
+    const { enabled, folder } = get(settingsStore).notesMirrorConfig || {};
+    if (!enabled || !folder) return null;
+    if (running) {
+        rerun = true;
+        return running;
+    }
+    running = (async () => {
+        let outcome = null;
+        do {
+            rerun = false;
+            outcome = await syncNotesMirror(folder, get(notesStore).notes);
+            if (outcome.upserts.length || outcome.deleted.length) {
+                notesStore.applyMirrorChanges(outcome);
+            }
+        } while (rerun);
+        return outcome;
+    })();
+    try {
+        return await running;
+    } finally {
+        running = null;
+    }
+}
+
+function logFailure(e) {
+    console.warn('Notes mirror sync failed:', e);
+}
+
+/**
+ * Watches the folder and the notes, syncing on changes on either side.
+ * Resolves to the outcome of the first sync.
+ */
+export async function startNotesMirrorSync() {
+    stopNotesMirrorSync();
+    const { enabled, folder } = get(settingsStore).notesMirrorConfig || {};
+    if (!enabled || !folder) return null;
+
+    await startNotesMirror(folder);
+    const { listen } = await import('@tauri-apps/api/event');
+    const unlisten = await listen('notes-mirror-changed', () => {
+        runNotesMirrorSync().catch(logFailure);
+    });
+    let timer = null;
+    let lastNotes = get(notesStore).notes;
+    const unsubscribe = notesStore.subscribe(state => {
+        // Selecting a note updates the store too; only edits matter here.
+        if (state.notes === lastNotes) return;
+        lastNotes = state.notes;
+        clearTimeout(timer);
+        timer = setTimeout(() => runNotesMirrorSync().catch(logFailure), EDIT_DEBOUNCE_MS);
+    });
+    stopCurrent = () => {
+        clearTimeout(timer);
+        unsubscribe();
+        unlisten();
+    };
+
+    return await runNotesMirrorSync();
+}
+
+export function stopNotesMirrorSync() {
+    stopCurrent();
+    stopCurrent = () => {};
+    return stopNotesMirror().catch(logFailure);
+}
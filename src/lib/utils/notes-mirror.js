@@ -0,0 +1,33 @@
+import { invoke } from '@tauri-apps/api/core';
+
+export function getDefaultNotesMirrorConfig() {
+    return {
+        enabled: false,
+        folder: ''
+    };
+}
+
+/**
+ * Mirrors `notes` to `folder` and resolves to the changes to apply:
+ * `{ upserts, deleted, written, imported, updated, removed, conflicts, warnings, syncedAt }`.
+ */
+export async function syncNotesMirror(folder, notes) {
+    return await invoke('sync_notes_mirror', { folder, notes });
+}
+
+/** Reads a Markdown folder (an Obsidian vault, a Logseq graph) into new notes once. */
+export async function importMarkdownNotes(folder, existingIds = []) {
+    return await invoke('import_markdown_notes', { folder, existingIds });
+}
+
+export async function startNotesMirror(folder, debounceMs = 1000) {
+    return await invoke('start_notes_mirror', { folder, debounceMs });
+}
+
+export async function stopNotesMirror() {
+    return await invoke('stop_notes_mirror');
+}
+
+export async function getNotesMirrorStatus() {
+    return await invoke('get_notes_mirror_status');
+}
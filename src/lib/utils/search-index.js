@@ -0,0 +1,39 @@
+import { invoke } from '@tauri-apps/api/core';
+
+let syncTimer = null;
+
+export async function syncSearchIndex({
+    tasks = [],
+    notes = [],
+    chatSessions = []
+} = {}) {
+    return await invoke('sync_search_index', {
+        tasks,
+        notes,
+        chatSessions
+    });
+}
+
+export function scheduleSearchIndexSync(getPayload, delay = 3000) {
+    if (syncTimer) clearTimeout(syncTimer);
+    syncTimer = setTimeout(async () => {
+        syncTimer = null;
+        try {
+            await syncSearchIndex(getPayload());
+        } catch (error) {
+            console.warn('Failed to sync search index:', error);
+        }
+    }, delay);
+}
+
+export async function searchEverything({
+    query,
+    kinds = [],
+    limit = 20
+} = {}) {
+    return await invoke('search_everything', {
+        query,
+        kinds: kinds.length > 0 ? kinds : null,
+        limit
+    });
+}
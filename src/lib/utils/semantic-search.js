@@ -0,0 +1,64 @@
+import { invoke } from '@tauri-apps/api/core';
+
+let syncTimer = null;
+
+export function getDefaultEmbeddingConfig() {
+    return {
+        provider: 'local',
+        apiUrl: '',
+        apiKey: '',
+        model: ''
+    };
+}
+
+export async function syncSemanticIndex({
+    config = getDefaultEmbeddingConfig(),
+    tasks = [],
+    notes = [],
+    trustedDirectories = []
+} = {}) {
+    return await invoke('sync_semantic_index', {
+        config,
+        tasks,
+        notes,
+        trustedDirs: trustedDirectories
+    });
+}
+
+/**
+ * Debounced `syncSemanticIndex`. Unchanged documents keep their vectors, so a
+ * sync only embeds what was edited since the last one.
+ */
+export function scheduleSemanticIndexSync(getPayload, delay = 10000) {
+    if (syncTimer) clearTimeout(syncTimer);
+    syncTimer = setTimeout(async () => {
+        syncTimer = null;
+        try {
+            await syncSemanticIndex(getPayload());
+        } catch (error) {
+            console.warn('Failed to sync semantic index:', error);
+        }
+    }, delay);
+}
+
+export async function semanticSearch({
+    config = getDefaultEmbeddingConfig(),
+    query,
+    kinds = [],
+    limit = 8
+} = {}) {
+    return await invoke('semantic_search', {
+        config,
+        query,
+        kinds: kinds.length > 0 ? kinds : null,
+        limit
+    });
+}
+
+export function buildSemanticContext(hits = [], minScore = 0.2) {
+    const kindLabels = { task: '任务', note: '笔记', file: '文件' };
+    return hits
+        .filter(hit => hit.score >= minScore)
+        .map(hit => `[${kindLabels[hit.kind] || hit.kind}:${hit.id}] ${hit.title}\n${hit.excerpt}`)
+        .join('\n\n');
+}
@@ -0,0 +1,69 @@
+import { invoke } from '@tauri-apps/api/core';
+
+export function isNativeSyncAvailable() {
+    return typeof window !== 'undefined' && !!window.__TAURI__;
+}
+
+export async function syncNow({ config, accessKey, data = null }) {
+    return await invoke('sync_now', { config, accessKey, data });
+}
+
+export async function clearSyncData({ config, accessKey }) {
+    return await invoke('clear_sync_data', { config, accessKey });
+}
+
+export async function getSyncQueue() {
+    return await invoke('get_sync_queue');
+}
+
+/**
+ * Replays the account's queued sync now. Credentials are never written to the
+ * queue, so the current config and access key are passed in every time.
+ */
+export async function retryPendingSync({ config, accessKey }) {
+    return await invoke('retry_pending_sync', { config, accessKey });
+}
+
+export async function listSyncConflicts() {
+    return await invoke('list_sync_conflicts');
+}
+
+export async function dismissSyncConflicts(ids = null) {
+    return await invoke('dismiss_sync_conflicts', { ids });
+}
+
+export function getDefaultLanSyncConfig() {
+    return { enabled: false, deviceName: '' };
+}
+
+export async function startLanSync(deviceName = '') {
+    return await invoke('start_lan_sync', { deviceName });
+}
+
+export async function stopLanSync() {
+    return await invoke('stop_lan_sync');
+}
+
+export async function getLanStatus() {
+    return await invoke('get_lan_status');
+}
+
+export async function beginLanPairing() {
+    return await invoke('begin_lan_pairing');
+}
+
+export async function pairLanPeer({ address, code }) {
+    return await invoke('pair_lan_peer', { address, code });
+}
+
+export async function forgetLanPeer(id) {
+    return await invoke('forget_lan_peer', { id });
+}
+
+export async function syncLanPeer({ peerId, accessKey, data }) {
+    return await invoke('sync_lan_peer', { peerId, accessKey, data });
+}
+
+export async function respondLanSync({ requestId, accessKey, data }) {
+    return await invoke('lan_sync_respond', { requestId, accessKey, data });
+}
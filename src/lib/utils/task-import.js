@@ -0,0 +1,18 @@
+import { invoke } from '@tauri-apps/api/core';
+
+export const TASK_IMPORT_FORMATS = ['auto', 'todoist', 'microsoft-todo', 'ticktick', 'taskwarrior', 'todotxt'];
+
+/** File types the import picker offers: CSV and JSON exports plus todo.txt. */
+export const TASK_IMPORT_ACCEPT = '.csv,.json,.txt';
+
+/**
+ * Converts another app's export into WorkPlan tasks without adding them.
+ * Resolves to `{ report, tasks, scheduledTasks }`.
+ */
+export async function previewTaskImport(content, format = 'auto') {
+    return await invoke('preview_task_import', {
+        content,
+        format,
+        utcOffsetMinutes: -new Date().getTimezoneOffset()
+    });
+}
@@ -0,0 +1,31 @@
+import { invoke } from '@tauri-apps/api/core';
+
+/** Error the backend returns when no font with Chinese glyphs can be embedded. */
+export const REPORT_FONT_MISSING = '未找到支持中文的字体';
+
+function localNow() {
+    const date = new Date();
+    return new Date(date.getTime() - date.getTimezoneOffset() * 60000).toISOString().slice(0, 16);
+}
+
+/**
+ * Renders a PDF work report for `tasks` (summary, charts and a task table) and
+ * saves it to the downloads folder, or where the user picks when `save.dialog`
+ * is set. Resolves to `{ path, pages, tasks, font }`, or `null` if the save
+ * dialog was cancelled.
+ */
+export async function exportTaskReport({ title, start, end, tasks, labels = {}, fontPath = '', filename = '', save = {} }) {
+    return await invoke('export_task_report', {
+        request: {
+            title,
+            start,
+            end,
+            now: localNow(),
+            tasks,
+            labels,
+            fontPath: fontPath || null,
+            filename,
+            save
+        }
+    });
+}
@@ -0,0 +1,104 @@
+import { invoke } from '@tauri-apps/api/core';
+
+const HASHTAG = /(?:^|\s)#([^\s#]+)/g;
+
+function utcOffsetMinutes() {
+    return -new Date().getTimezoneOffset();
+}
+
+/**
+ * What an entry is booked on. Tasks have no project field; as with imported
+ * tasks, the first `#tag` in the note names the project.
+ */
+export function taskRefFor(task) {
+    const tags = [...new Set([...(task.note || '').matchAll(HASHTAG)].map(match => match[1]))];
+    return {
+        taskId: task.id,
+        taskTitle: task.title || '',
+        project: tags[0] || '',
+        tags
+    };
+}
+
+/** `{ active, idleMinutes, now }`; `active` is `null` when no timer exists. */
+export async function getTimeTracking() {
+    return await invoke('get_time_tracking');
+}
+
+/** Starts timing `task`, stopping another task's timer first. */
+export async function startTimer(task) {
+    return await invoke('start_timer', { task: taskRefFor(task) });
+}
+
+export async function pauseTimer() {
+    return await invoke('pause_timer');
+}
+
+export async function resumeTimer() {
+    return await invoke('resume_timer');
+}
+
+export async function stopTimer() {
+    return await invoke('stop_timer');
+}
+
+/** Reports that the user has been away since `idleSince` (epoch millis). */
+export async function markTimerIdle(idleSince) {
+    return await invoke('mark_timer_idle', { idleSince });
+}
+
+export async function setIdleMinutes(minutes) {
+    return await invoke('set_idle_minutes', { minutes });
+}
+
+export async function addTimeEntry(task, { start, end, note = '' }) {
+    return await invoke('add_time_entry', { entry: { ...taskRefFor(task), start, end, note } });
+}
+
+export async function updateTimeEntry(id, entry) {
+    return await invoke('update_time_entry', { id, entry });
+}
+
+export async function deleteTimeEntry(id) {
+    return await invoke('delete_time_entry', { id });
+}
+
+/** Entries overlapping the local dates `from..=to`, newest first. */
+export async function listTimeEntries({ from, to, taskId = null }) {
+    return await invoke('list_time_entries', {
+        query: { from, to, taskId, utcOffsetMinutes: utcOffsetMinutes() }
+    });
+}
+
+/** Totals grouped by `day`, `week`, `project`, `tag` or `task`. */
+export async function summarizeTime({ from, to, taskId = null, groupBy = 'day' }) {
+    return await invoke('summarize_time', {
+        query: { from, to, taskId, groupBy, utcOffsetMinutes: utcOffsetMinutes() }
+    });
+}
+
+/**
+ * Saves the range as a CSV timesheet, grouped or one row per entry. Resolves
+ * to `{ path, size }`, or `null` if the save dialog was cancelled.
+ */
+export async function exportTimesheetCsv({ from, to, taskId = null, groupBy = 'day', detailed = false, labels = {}, filename = '', save = {} }) {
+    return await invoke('export_timesheet_csv', {
+        export: {
+            from,
+            to,
+            taskId,
+            groupBy,
+            utcOffsetMinutes: utcOffsetMinutes(),
+            detailed,
+            labels,
+            filename,
+            save
+        }
+    });
+}
+
+export function formatDuration(millis) {
+    const minutes = Math.floor(millis / 60000);
+    const hours = Math.floor(minutes / 60);
+    return hours > 0 ? `${hours}h ${String(minutes % 60).padStart(2, '0')}m` : `${minutes}m`;
+}
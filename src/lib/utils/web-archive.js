@@ -0,0 +1,17 @@
+import { invoke } from '@tauri-apps/api/core';
+
+export async function archiveWebPage({ url, format = 'markdown', ownerKind, ownerId }) {
+    return await invoke('archive_web_page', { url, format, ownerKind, ownerId });
+}
+
+export async function listWebArchives({ ownerKind = null, ownerId = null } = {}) {
+    return await invoke('list_web_archives', { ownerKind, ownerId });
+}
+
+export async function readWebArchive(id) {
+    return await invoke('read_web_archive', { id });
+}
+
+export async function deleteWebArchive(id) {
+    return await invoke('delete_web_archive', { id });
+}
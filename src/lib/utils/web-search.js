@@ -1,15 +1,119 @@
 import { invoke } from '@tauri-apps/api/core';
 
+export const WEB_SEARCH_PROVIDERS = [
+    { id: 'duckduckgo', label: 'DuckDuckGo', needsKey: false, needsUrl: false },
+    { id: 'searxng', label: 'SearXNG', needsKey: false, needsUrl: true },
+    { id: 'brave', label: 'Brave Search', needsKey: true, needsUrl: false },
+    { id: 'bing', label: 'Bing', needsKey: true, needsUrl: false },
+    { id: 'tavily', label: 'Tavily', needsKey: true, needsUrl: false },
+    { id: 'generic', label: 'Custom JSON', needsKey: true, needsUrl: true }
+];
+
+function getDefaultProviderConfig(id) {
+    return {
+        id,
+        enabled: id === 'duckduckgo',
+        apiKey: '',
+        baseUrl: '',
+        resultsPath: id === 'generic' ? 'results' : '',
+        titleField: id === 'generic' ? 'title' : '',
+        urlField: id === 'generic' ? 'url' : '',
+        snippetField: id === 'generic' ? 'snippet' : ''
+    };
+}
+
+export function getDefaultWebSearchConfig() {
+    return {
+        providers: WEB_SEARCH_PROVIDERS.map(provider => getDefaultProviderConfig(provider.id))
+    };
+}
+
+// Keeps the saved order and appends providers added in newer versions.
+export function normalizeWebSearchConfig(config = {}) {
+    const known = new Set(WEB_SEARCH_PROVIDERS.map(provider => provider.id));
+    const saved = (config.providers || []).filter(provider => known.has(provider?.id));
+    const savedIds = new Set(saved.map(provider => provider.id));
+    return {
+        ...config,
+        providers: [
+            ...saved.map(provider => ({ ...getDefaultProviderConfig(provider.id), ...provider })),
+            ...WEB_SEARCH_PROVIDERS
+                .filter(provider => !savedIds.has(provider.id))
+                .map(provider => getDefaultProviderConfig(provider.id))
+        ]
+    };
+}
+
 export async function searchWeb({
     query,
-    maxResults = 6
+    maxResults = 6,
+    providers = null
 } = {}) {
+    const enabled = (providers || []).filter(provider => provider.enabled);
     return await invoke('search_web', {
         query,
-        maxResults
+        maxResults,
+        providers: enabled.length > 0 ? enabled : null
+    });
+}
+
+export async function crawlSite({
+    url,
+    maxDepth = 2,
+    maxPages = 15,
+    maxCharsPerPage = 2000,
+    pathPrefix = null
+} = {}) {
+    return await invoke('crawl_site', {
+        url,
+        maxDepth,
+        maxPages,
+        maxCharsPerPage,
+        pathPrefix
     });
 }
 
+export async function inspectHttpCache() {
+    return await invoke('inspect_http_cache');
+}
+
+export async function clearHttpCache(pattern = null) {
+    return await invoke('clear_http_cache', { pattern });
+}
+
+/**
+ * Whether the user hands over a link and asks for the site behind it to be read
+ * through. A link alone is not enough, since links are pasted into tasks and notes.
+ */
+export function looksLikeCrawlIntent(text = '') {
+    const links = /https?:\/\/\S+/gi;
+    if (!links.test(text)) return false;
+    // Keywords inside the link itself, e.g. `/documentation/`, do not count.
+    const prose = String(text).replace(links, ' ').toLowerCase();
+    const keywords = [
+        '爬取',
+        '抓取',
+        '通读',
+        '读完',
+        '整个站',
+        '整个网站',
+        '全站',
+        '文档站',
+        '站点',
+        '所有页面',
+        '整套文档',
+        'crawl',
+        'scrape',
+        'read through',
+        'whole site',
+        'entire site',
+        'all pages',
+        'the docs',
+        'documentation'
+    ];
+    return keywords.some((keyword) => prose.includes(keyword));
+}
+
 export function looksLikeWebSearchIntent(text = '') {
     const lowerText = String(text).toLowerCase();
     const keywords = [
@@ -69,9 +173,14 @@ export function looksLikeWebSearchIntent(text = '') {
     return searchPatterns.some((pattern) => pattern.test(lowerText));
 }
 
+export async function fetchWebPage(url, maxChars = 4000) {
+    return await invoke('fetch_web_content', { url, maxChars });
+}
+
 export async function fetchWebContent(url, maxChars = 4000) {
     try {
-        return await invoke('fetch_web_content', { url, maxChars });
+        const page = await fetchWebPage(url, maxChars);
+        return page?.content || null;
     } catch (error) {
         console.warn('fetchWebContent failed:', url, error);
         return null;